@@ -268,7 +268,7 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { Client, Connection, ConnectionInfo, Schema, Field, RowIterator, RowIteratorExt, RowOrProgress, Row, QueryProgress } = nativeBinding
+const { Client, Connection, ConnectionInfo, Schema, Field, RowIterator, RowIteratorExt, RowOrProgress, Row, QueryProgress, QueryStats } = nativeBinding
 
 module.exports.Client = Client
 module.exports.Connection = Connection
@@ -280,3 +280,4 @@ module.exports.RowIteratorExt = RowIteratorExt
 module.exports.RowOrProgress = RowOrProgress
 module.exports.Row = Row
 module.exports.QueryProgress = QueryProgress
+module.exports.QueryStats = QueryStats
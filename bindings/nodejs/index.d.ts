@@ -34,6 +34,8 @@ export class Connection {
   exec(sql: string): Promise<number>
   /** Execute a SQL query, and only return the first row. */
   queryRow(sql: string): Promise<Row | null>
+  /** Execute a SQL query, and fetch all rows into memory. */
+  queryAll(sql: string): Promise<Array<Row>>
   /** Execute a SQL query, and return all rows. */
   queryIter(sql: string): Promise<RowIterator>
   /** Execute a SQL query, and return all rows with schema and progress. */
@@ -43,6 +45,14 @@ export class Connection {
    * The SQL can be `INSERT INTO tbl VALUES` or `REPLACE INTO tbl VALUES`.
    */
   streamLoad(sql: string, data: Array<Array<string>>): Promise<QueryProgress>
+  /** Upload data to the stage location, like `@stage/path/to/file.csv`. */
+  uploadToStage(stage: string, data: Buffer): Promise<void>
+  /** Upload a local file to the stage location. */
+  uploadFileToStage(stage: string, path: string): Promise<void>
+  /** Kill a running query by id. */
+  killQuery(queryId: string): Promise<void>
+  /** Drop temporary objects created by the connection. */
+  close(): Promise<void>
 }
 export class ConnectionInfo {
   get handler(): string
@@ -65,6 +75,8 @@ export class RowIterator {
    * Returns `None` if there are no more rows.
    */
   next(): Promise<Error | Row | null>
+  schema(): Schema
+  [Symbol.asyncIterator](): AsyncIterator<Row>
 }
 export class RowIteratorExt {
   /**
@@ -73,11 +85,13 @@ export class RowIteratorExt {
    */
   next(): Promise<Error | RowOrProgress | null>
   schema(): Schema
+  [Symbol.asyncIterator](): AsyncIterator<RowOrProgress>
 }
-/** Must contain either row or progress. */
+/** Must contain either row, progress or stats, stats is the last one. */
 export class RowOrProgress {
   get row(): Row | null
   get progress(): QueryProgress | null
+  get stats(): QueryStats | null
 }
 export class Row {
   values(): Array<any>
@@ -90,3 +104,13 @@ export class QueryProgress {
   get writeRows(): bigint
   get writeBytes(): bigint
 }
+export class QueryStats {
+  get scanRows(): bigint
+  get scanBytes(): bigint
+  get writeRows(): bigint
+  get writeBytes(): bigint
+  get resultRows(): bigint
+  get resultBytes(): bigint
+  /** Running time in milliseconds. */
+  get runningTimeMs(): number
+}
@@ -16,6 +16,28 @@
 
 /// <reference types="node" />
 
-const { Client } = require("./generated.js");
+const { Client, RowIterator, RowIteratorExt } = require("./generated.js");
+
+// errors are returned as values by `next()`, thrown while iterating
+async function* iterate(iterator) {
+  for (;;) {
+    const item = await iterator.next();
+    if (item instanceof Error) {
+      throw item;
+    }
+    if (item === null || item === undefined) {
+      return;
+    }
+    yield item;
+  }
+}
+
+RowIterator.prototype[Symbol.asyncIterator] = function () {
+  return iterate(this);
+};
+
+RowIteratorExt.prototype[Symbol.asyncIterator] = function () {
+  return iterate(this);
+};
 
 module.exports.Client = Client;
@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#![allow(clippy::missing_safety_doc)]
+
 #[macro_use]
 extern crate napi_derive;
 
@@ -19,6 +21,8 @@ use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
 use napi::bindgen_prelude::*;
 use tokio_stream::StreamExt;
 
+use databend_driver::ConnectionExt;
+
 #[napi]
 pub struct Client(databend_driver::Client);
 
@@ -81,6 +85,25 @@ impl ToNapiValue for Value {
                     NaiveDateTime::new(v, NaiveTime::from_hms_opt(0, 0, 0).unwrap()),
                 )
             }
+            databend_driver::Value::Array(vals) | databend_driver::Value::Tuple(vals) => {
+                Vec::to_napi_value(env, vals.into_iter().map(Value).collect())
+            }
+            // map keys are converted to strings as object keys
+            databend_driver::Value::Map(entries) => {
+                let mut obj = Env::from(env).create_object()?;
+                for (k, v) in entries {
+                    obj.set(k.to_string(), Value(v))?;
+                }
+                Object::to_napi_value(env, obj)
+            }
+            // JSONB in hex is returned as a string
+            databend_driver::Value::Variant(s) => match serde_json::from_str(&s) {
+                Ok(v) => serde_json::Value::to_napi_value(env, v),
+                Err(_) => String::to_napi_value(env, s),
+            },
+            databend_driver::Value::Bitmap(s) | databend_driver::Value::Geometry(s) => {
+                String::to_napi_value(env, s)
+            }
         }
     }
 }
@@ -151,6 +174,11 @@ impl RowIterator {
             .await
             .map(|row| row.map(Row).map_err(format_napi_error))
     }
+
+    #[napi]
+    pub fn schema(&self) -> Schema {
+        Schema(self.0.schema().as_ref().clone())
+    }
 }
 
 #[napi]
@@ -165,23 +193,27 @@ impl RowIteratorExt {
     /// Returns `None` if there are no more rows.
     #[napi]
     pub async unsafe fn next(&mut self) -> Option<Result<RowOrProgress>> {
-        match self.iterator.next().await {
-            None => None,
-            Some(r0) => match r0 {
-                Ok(r1) => match r1 {
-                    databend_driver::RowWithProgress::Row(row) => Some(Ok(RowOrProgress {
-                        row: Some(Row(row)),
-                        progress: None,
-                    })),
-                    databend_driver::RowWithProgress::Progress(progress) => {
-                        Some(Ok(RowOrProgress {
-                            row: None,
-                            progress: Some(QueryProgress(progress)),
-                        }))
-                    }
-                },
-                Err(e) => Some(Err(format_napi_error(e))),
-            },
+        loop {
+            let item = match self.iterator.next().await? {
+                Ok(item) => item,
+                Err(e) => return Some(Err(format_napi_error(e))),
+            };
+            let (row, progress, stats) = match item {
+                databend_driver::RowWithProgress::Row(row) => (Some(Row(row)), None, None),
+                databend_driver::RowWithProgress::Progress(progress) => {
+                    (None, Some(QueryProgress(progress)), None)
+                }
+                databend_driver::RowWithProgress::Stats(stats) => {
+                    (None, None, Some(QueryStats(stats)))
+                }
+                // skip items not known to the binding
+                _ => continue,
+            };
+            return Some(Ok(RowOrProgress {
+                row,
+                progress,
+                stats,
+            }));
         }
     }
 
@@ -191,11 +223,12 @@ impl RowIteratorExt {
     }
 }
 
-/// Must contain either row or progress.
+/// Must contain either row, progress or stats, stats is the last one.
 #[napi]
 pub struct RowOrProgress {
     row: Option<Row>,
     progress: Option<QueryProgress>,
+    stats: Option<QueryStats>,
 }
 
 #[napi]
@@ -209,6 +242,11 @@ impl RowOrProgress {
     pub fn progress(&self) -> Option<QueryProgress> {
         self.progress.clone()
     }
+
+    #[napi(getter)]
+    pub fn stats(&self) -> Option<QueryStats> {
+        self.stats.clone()
+    }
 }
 
 #[napi]
@@ -261,6 +299,49 @@ impl QueryProgress {
     }
 }
 
+#[napi]
+#[derive(Clone)]
+pub struct QueryStats(databend_driver::QueryStats);
+
+#[napi]
+impl QueryStats {
+    #[napi(getter)]
+    pub fn scan_rows(&self) -> usize {
+        self.0.scan_rows
+    }
+
+    #[napi(getter)]
+    pub fn scan_bytes(&self) -> usize {
+        self.0.scan_bytes
+    }
+
+    #[napi(getter)]
+    pub fn write_rows(&self) -> usize {
+        self.0.write_rows
+    }
+
+    #[napi(getter)]
+    pub fn write_bytes(&self) -> usize {
+        self.0.write_bytes
+    }
+
+    #[napi(getter)]
+    pub fn result_rows(&self) -> usize {
+        self.0.result_rows
+    }
+
+    #[napi(getter)]
+    pub fn result_bytes(&self) -> usize {
+        self.0.result_bytes
+    }
+
+    /// Running time in milliseconds.
+    #[napi(getter)]
+    pub fn running_time_ms(&self) -> f64 {
+        self.0.running_time.as_secs_f64() * 1000.0
+    }
+}
+
 #[napi]
 impl Client {
     /// Create a new databend client with a given DSN.
@@ -276,7 +357,7 @@ impl Client {
         self.0
             .get_conn()
             .await
-            .map(|conn| Connection(conn))
+            .map(Connection)
             .map_err(format_napi_error)
     }
 }
@@ -311,13 +392,23 @@ impl Connection {
             .map_err(format_napi_error)
     }
 
+    /// Execute a SQL query, and fetch all rows into memory.
+    #[napi]
+    pub async fn query_all(&self, sql: String) -> Result<Vec<Row>> {
+        let rows = self.0.query_iter(&sql).await.map_err(format_napi_error)?;
+        rows.map(|row| row.map(Row))
+            .collect::<databend_driver::Result<Vec<_>>>()
+            .await
+            .map_err(format_napi_error)
+    }
+
     /// Execute a SQL query, and return all rows.
     #[napi]
     pub async fn query_iter(&self, sql: String) -> Result<RowIterator> {
         self.0
             .query_iter(&sql)
             .await
-            .map(|iter| RowIterator(iter))
+            .map(RowIterator)
             .map_err(format_napi_error)
     }
 
@@ -353,6 +444,50 @@ impl Connection {
             .map_err(format_napi_error)?;
         Ok(QueryProgress(progress))
     }
+
+    /// Upload data to the stage location, like `@stage/path/to/file.csv`.
+    #[napi]
+    pub async fn upload_to_stage(&self, stage: String, data: Buffer) -> Result<()> {
+        let bytes: Vec<u8> = data.into();
+        let size = bytes.len() as u64;
+        let reader = Box::new(std::io::Cursor::new(bytes));
+        self.0
+            .upload_to_stage(&stage, reader, size)
+            .await
+            .map_err(format_napi_error)
+    }
+
+    /// Upload a local file to the stage location.
+    #[napi]
+    pub async fn upload_file_to_stage(&self, stage: String, path: String) -> Result<()> {
+        let file = tokio::fs::File::open(&path)
+            .await
+            .map_err(|e| Error::from_reason(format!("{}", e)))?;
+        let size = file
+            .metadata()
+            .await
+            .map_err(|e| Error::from_reason(format!("{}", e)))?
+            .len();
+        self.0
+            .upload_file_to_stage(&stage, file, size)
+            .await
+            .map_err(format_napi_error)
+    }
+
+    /// Kill a running query by id.
+    #[napi]
+    pub async fn kill_query(&self, query_id: String) -> Result<()> {
+        self.0
+            .kill_query(&query_id)
+            .await
+            .map_err(format_napi_error)
+    }
+
+    /// Drop temporary objects created by the connection.
+    #[napi]
+    pub async fn close(&self) -> Result<()> {
+        self.0.close().await.map_err(format_napi_error)
+    }
 }
 
 fn format_napi_error(err: databend_driver::Error) -> Error {
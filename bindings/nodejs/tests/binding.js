@@ -45,6 +45,28 @@ Then("Select numbers should iterate all rows", async function () {
   assert.deepEqual(ret, expected);
 });
 
+Then("Select numbers should iterate all rows asynchronously", async function () {
+  const rows = await this.conn.queryIter("SELECT number FROM numbers(5)");
+  assert.equal(rows.schema().fields[0].name, "number");
+  const ret = [];
+  for await (const row of rows) {
+    ret.push(row.values()[0]);
+  }
+  assert.deepEqual(ret, [0, 1, 2, 3, 4]);
+
+  const all = await this.conn.queryAll("SELECT number FROM numbers(5)");
+  assert.deepEqual(all.map((row) => row.values()[0]), [0, 1, 2, 3, 4]);
+});
+
+Then("Upload to stage and Select should be equal", async function () {
+  await this.conn.exec("CREATE STAGE IF NOT EXISTS test_upload");
+  const data = Buffer.from("-1,1,1.0,1,1,2011-03-06,2011-03-06T06:20:00Z\n");
+  await this.conn.uploadToStage("@test_upload/test.csv", data);
+  await this.conn.exec("COPY INTO test FROM @test_upload/test.csv FILE_FORMAT = (type = CSV) PURGE = true");
+  const row = await this.conn.queryRow("SELECT i64, s FROM test");
+  assert.deepEqual(row.values(), [-1, "1"]);
+});
+
 When("Create a test table", async function () {
   await this.conn.exec("DROP TABLE IF EXISTS test");
   await this.conn.exec(`CREATE TABLE test (
@@ -12,6 +12,51 @@
 # See the License for the specific language governing permissions and
 # limitations under the License.
 
+from typing import Any, List, Optional, Sequence, Tuple
+
+apilevel: str
+threadsafety: int
+paramstyle: str
+
+class Warning(Exception): ...  # NOQA
+class Error(Exception): ...  # NOQA
+class InterfaceError(Error): ...  # NOQA
+class DatabaseError(Error): ...  # NOQA
+class DataError(DatabaseError): ...  # NOQA
+class OperationalError(DatabaseError): ...  # NOQA
+class IntegrityError(DatabaseError): ...  # NOQA
+class InternalError(DatabaseError): ...  # NOQA
+class ProgrammingError(DatabaseError): ...  # NOQA
+class NotSupportedError(DatabaseError): ...  # NOQA
+
+def connect(dsn: str) -> Connection: ...  # NOQA
+
+class Connection:
+    def cursor(self) -> Cursor: ...  # NOQA
+    def close(self) -> None: ...  # NOQA
+    def commit(self) -> None: ...  # NOQA
+    def rollback(self) -> None: ...  # NOQA
+
+class Cursor:
+    arraysize: int
+    @property
+    def description(self) -> Optional[List[Tuple[Any, ...]]]: ...  # NOQA
+    @property
+    def rowcount(self) -> int: ...  # NOQA
+    def execute(self, sql: str, params: Optional[Sequence[Any]] = None) -> None: ...  # NOQA
+    def fetchone(self) -> Optional[Tuple[Any, ...]]: ...  # NOQA
+    def fetchmany(self, size: Optional[int] = None) -> List[Tuple[Any, ...]]: ...  # NOQA
+    def fetchall(self) -> List[Tuple[Any, ...]]: ...  # NOQA
+    def close(self) -> None: ...  # NOQA
+    def __iter__(self) -> Cursor: ...  # NOQA
+    def __next__(self) -> Tuple[Any, ...]: ...  # NOQA
+
 class AsyncDatabendDriver:
     def __init__(self, dsn: str): ...  # NOQA
-    async def exec(self, sql: str) -> int: ...  # NOQA
+    async def exec(self, sql: str, params: Optional[Sequence[Any]] = None) -> int: ...  # NOQA
+    async def query_row(
+        self, sql: str, params: Optional[Sequence[Any]] = None
+    ) -> Optional[Tuple[Any, ...]]: ...  # NOQA
+    async def query_all(
+        self, sql: str, params: Optional[Sequence[Any]] = None
+    ) -> List[Tuple[Any, ...]]: ...  # NOQA
@@ -16,7 +16,11 @@ use pyo3::prelude::*;
 
 use pyo3_asyncio::tokio::future_into_py;
 
+use crate::exceptions::to_py_err;
+use crate::types::{params_from_py, row_to_py};
 use crate::{build_connector, Connector};
+use databend_driver::builder;
+use futures::StreamExt;
 
 /// `AsyncDatabendDriver` is the entry for all public async API
 #[pyclass(module = "databend_driver")]
@@ -26,26 +30,66 @@ pub struct AsyncDatabendDriver(Connector);
 impl AsyncDatabendDriver {
     #[new]
     #[pyo3(signature = (dsn))]
-    pub fn new(dsn: &str) -> PyResult<Self> {
-        Ok(AsyncDatabendDriver(build_connector(dsn)?))
+    pub fn new(py: Python, dsn: &str) -> PyResult<Self> {
+        Ok(AsyncDatabendDriver(build_connector(py, dsn)?))
     }
 
     /// exec
-    pub fn exec<'p>(&'p self, py: Python<'p>, sql: String) -> PyResult<&'p PyAny> {
+    #[pyo3(signature = (sql, params = None))]
+    pub fn exec<'p>(
+        &'p self,
+        py: Python<'p>,
+        sql: &str,
+        params: Option<&PyAny>,
+    ) -> PyResult<&'p PyAny> {
         let this = self.0.clone();
+        let sql = builder::bind(sql, &params_from_py(params)?).map_err(to_py_err)?;
         future_into_py(py, async move {
-            let res = this.connector.exec(&sql).await.unwrap();
+            let res = this.connector.exec(&sql).await.map_err(to_py_err)?;
             Ok(res)
         })
     }
 
-    pub fn query_row<'p>(&'p self, py: Python<'p>, sql: String) -> PyResult<&'p PyAny> {
+    /// query_row returns the first row as a tuple, or `None` without rows
+    #[pyo3(signature = (sql, params = None))]
+    pub fn query_row<'p>(
+        &'p self,
+        py: Python<'p>,
+        sql: &str,
+        params: Option<&PyAny>,
+    ) -> PyResult<&'p PyAny> {
         let this = self.0.clone();
+        let sql = builder::bind(sql, &params_from_py(params)?).map_err(to_py_err)?;
         future_into_py(py, async move {
-            let row = this.connector.query_row(&sql).await.unwrap();
-            let row = row.unwrap();
-            let res = row.is_empty();
-            Ok(res)
+            let row = this.connector.query_row(&sql).await.map_err(to_py_err)?;
+            Python::with_gil(|py| match row {
+                Some(row) => row_to_py(py, row),
+                None => Ok(py.None()),
+            })
+        })
+    }
+
+    /// query_all returns all rows as a list of tuples
+    #[pyo3(signature = (sql, params = None))]
+    pub fn query_all<'p>(
+        &'p self,
+        py: Python<'p>,
+        sql: &str,
+        params: Option<&PyAny>,
+    ) -> PyResult<&'p PyAny> {
+        let this = self.0.clone();
+        let sql = builder::bind(sql, &params_from_py(params)?).map_err(to_py_err)?;
+        future_into_py(py, async move {
+            let mut rows = this.connector.query_iter(&sql).await.map_err(to_py_err)?;
+            let mut all = Vec::new();
+            while let Some(row) = rows.next().await {
+                all.push(row.map_err(to_py_err)?);
+            }
+            Python::with_gil(|py| {
+                all.into_iter()
+                    .map(|row| row_to_py(py, row))
+                    .collect::<PyResult<Vec<_>>>()
+            })
         })
     }
 }
@@ -0,0 +1,215 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use databend_driver::{builder, RowIterator, SchemaRef};
+use futures::StreamExt;
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+
+use crate::exceptions::{to_py_err, InterfaceError, NotSupportedError};
+use crate::types::{params_from_py, row_to_py};
+use crate::{block_on, build_connector, Connector};
+
+/// Open a connection with the DSN, following DB-API 2.0.
+#[pyfunction]
+#[pyo3(signature = (dsn))]
+pub fn connect(py: Python, dsn: &str) -> PyResult<BlockingConnection> {
+    Ok(BlockingConnection(Some(build_connector(py, dsn)?)))
+}
+
+/// `Connection` returned by `connect`, `None` after closed.
+#[pyclass(name = "Connection", module = "databend_driver")]
+pub struct BlockingConnection(Option<Connector>);
+
+impl BlockingConnection {
+    fn connector(&self) -> PyResult<&Connector> {
+        self.0
+            .as_ref()
+            .ok_or_else(|| InterfaceError::new_err("connection already closed"))
+    }
+}
+
+#[pymethods]
+impl BlockingConnection {
+    pub fn cursor(&self) -> PyResult<BlockingCursor> {
+        Ok(BlockingCursor::new(self.connector()?.clone()))
+    }
+
+    pub fn close(&mut self, py: Python) -> PyResult<()> {
+        if let Some(conn) = self.0.take() {
+            block_on(py, conn.connector.close()).map_err(to_py_err)?;
+        }
+        Ok(())
+    }
+
+    /// Statements are auto committed, so there is nothing to commit.
+    pub fn commit(&self) -> PyResult<()> {
+        self.connector()?;
+        Ok(())
+    }
+
+    pub fn rollback(&self) -> PyResult<()> {
+        Err(NotSupportedError::new_err("transactions are not supported"))
+    }
+}
+
+/// `Cursor` created by `Connection.cursor`, rows are fetched from the server
+/// page by page while iterating.
+#[pyclass(name = "Cursor", module = "databend_driver")]
+pub struct BlockingCursor {
+    conn: Option<Connector>,
+    rows: Option<RowIterator>,
+    schema: Option<SchemaRef>,
+    fetched: i64,
+    done: bool,
+    /// Number of rows to fetch at a time with `fetchmany`.
+    #[pyo3(get, set)]
+    arraysize: usize,
+}
+
+impl BlockingCursor {
+    fn new(conn: Connector) -> Self {
+        Self {
+            conn: Some(conn),
+            rows: None,
+            schema: None,
+            fetched: 0,
+            done: false,
+            arraysize: 1,
+        }
+    }
+
+    fn rows(&mut self) -> PyResult<&mut RowIterator> {
+        if self.conn.is_none() {
+            return Err(InterfaceError::new_err("cursor already closed"));
+        }
+        self.rows
+            .as_mut()
+            .ok_or_else(|| InterfaceError::new_err("no results, call execute first"))
+    }
+
+    fn next_row(&mut self, py: Python) -> PyResult<Option<PyObject>> {
+        let rows = self.rows()?;
+        match block_on(py, rows.next()) {
+            Some(row) => {
+                let row = row.map_err(to_py_err)?;
+                self.fetched += 1;
+                Ok(Some(row_to_py(py, row)?))
+            }
+            None => {
+                self.done = true;
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[pymethods]
+impl BlockingCursor {
+    /// Execute the statement, with parameters bound to `?` placeholders.
+    #[pyo3(signature = (sql, params = None))]
+    pub fn execute(&mut self, py: Python, sql: &str, params: Option<&PyAny>) -> PyResult<()> {
+        let conn = self
+            .conn
+            .clone()
+            .ok_or_else(|| InterfaceError::new_err("cursor already closed"))?;
+        let sql = builder::bind(sql, &params_from_py(params)?).map_err(to_py_err)?;
+        self.rows = None;
+        self.schema = None;
+        self.fetched = 0;
+        self.done = false;
+        let rows = block_on(py, async move { conn.connector.query_iter(&sql).await })
+            .map_err(to_py_err)?;
+        self.schema = Some(rows.schema());
+        self.rows = Some(rows);
+        Ok(())
+    }
+
+    pub fn fetchone(&mut self, py: Python) -> PyResult<Option<PyObject>> {
+        self.next_row(py)
+    }
+
+    #[pyo3(signature = (size = None))]
+    pub fn fetchmany(&mut self, py: Python, size: Option<usize>) -> PyResult<Vec<PyObject>> {
+        let size = size.unwrap_or(self.arraysize);
+        let mut rows = Vec::with_capacity(size);
+        while rows.len() < size {
+            match self.next_row(py)? {
+                Some(row) => rows.push(row),
+                None => break,
+            }
+        }
+        Ok(rows)
+    }
+
+    pub fn fetchall(&mut self, py: Python) -> PyResult<Vec<PyObject>> {
+        let mut rows = Vec::new();
+        while let Some(row) = self.next_row(py)? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    /// Sequence of 7-item tuples for the result columns, only name,
+    /// type_code and null_ok are set, `None` without results.
+    #[getter]
+    pub fn description(&self, py: Python) -> Option<Vec<PyObject>> {
+        let schema = self.schema.as_ref()?;
+        if schema.fields().is_empty() {
+            return None;
+        }
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|field| {
+                let none = py.None();
+                let item: [PyObject; 7] = [
+                    field.name.clone().into_py(py),
+                    field.data_type.to_string().into_py(py),
+                    none.clone_ref(py),
+                    none.clone_ref(py),
+                    none.clone_ref(py),
+                    none,
+                    field.is_nullable().into_py(py),
+                ];
+                PyTuple::new(py, item).into_py(py)
+            })
+            .collect();
+        Some(columns)
+    }
+
+    /// Number of rows in the result, -1 until all rows are fetched.
+    #[getter]
+    pub fn rowcount(&self) -> i64 {
+        if self.done {
+            self.fetched
+        } else {
+            -1
+        }
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python) -> PyResult<Option<PyObject>> {
+        self.next_row(py)
+    }
+
+    pub fn close(&mut self) {
+        self.rows = None;
+        self.schema = None;
+        self.conn = None;
+    }
+}
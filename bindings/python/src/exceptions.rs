@@ -0,0 +1,134 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Exceptions following the hierarchy of DB-API 2.0 (PEP 249).
+
+use databend_driver::{ApiError, Error as DriverError, ErrorKind};
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+
+create_exception!(
+    databend_driver,
+    Warning,
+    PyException,
+    "Important warnings like data truncations while inserting"
+);
+create_exception!(
+    databend_driver,
+    Error,
+    PyException,
+    "Base class of all other error exceptions"
+);
+create_exception!(
+    databend_driver,
+    InterfaceError,
+    Error,
+    "Errors related to the driver rather than the database"
+);
+create_exception!(
+    databend_driver,
+    DatabaseError,
+    Error,
+    "Errors related to the database"
+);
+create_exception!(
+    databend_driver,
+    DataError,
+    DatabaseError,
+    "Errors due to problems with the processed data"
+);
+create_exception!(
+    databend_driver,
+    OperationalError,
+    DatabaseError,
+    "Errors related to the operation of the database, e.g. lost connections"
+);
+create_exception!(
+    databend_driver,
+    IntegrityError,
+    DatabaseError,
+    "Errors when the relational integrity of the database is affected"
+);
+create_exception!(
+    databend_driver,
+    InternalError,
+    DatabaseError,
+    "Errors when the database encounters an internal error"
+);
+create_exception!(
+    databend_driver,
+    ProgrammingError,
+    DatabaseError,
+    "Programming errors, e.g. wrong number of parameters"
+);
+create_exception!(
+    databend_driver,
+    NotSupportedError,
+    DatabaseError,
+    "Errors when a method or API is not supported by the database"
+);
+
+pub(crate) fn register(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add("Warning", py.get_type::<Warning>())?;
+    m.add("Error", py.get_type::<Error>())?;
+    m.add("InterfaceError", py.get_type::<InterfaceError>())?;
+    m.add("DatabaseError", py.get_type::<DatabaseError>())?;
+    m.add("DataError", py.get_type::<DataError>())?;
+    m.add("OperationalError", py.get_type::<OperationalError>())?;
+    m.add("IntegrityError", py.get_type::<IntegrityError>())?;
+    m.add("InternalError", py.get_type::<InternalError>())?;
+    m.add("ProgrammingError", py.get_type::<ProgrammingError>())?;
+    m.add("NotSupportedError", py.get_type::<NotSupportedError>())?;
+    Ok(())
+}
+
+/// Convert driver errors to the closest DB-API exception.
+pub(crate) fn to_py_err(err: DriverError) -> PyErr {
+    let msg = err.to_string();
+    match err {
+        DriverError::Parsing(_) | DriverError::Convert(_) => DataError::new_err(msg),
+        DriverError::BadArgument(_) => ProgrammingError::new_err(msg),
+        DriverError::Protocol(_) | DriverError::Transport(_) | DriverError::IO(_) => {
+            OperationalError::new_err(msg)
+        }
+        DriverError::InvalidResponse(_) | DriverError::Arrow(_) => DatabaseError::new_err(msg),
+        DriverError::Api(e) => match e {
+            ApiError::Parsing(_) => InterfaceError::new_err(msg),
+            ApiError::BadArgument(_) => ProgrammingError::new_err(msg),
+            ApiError::Request(_) | ApiError::IO(_) | ApiError::Integrity(_) => {
+                OperationalError::new_err(msg)
+            }
+            ApiError::AuthFailure(_) | ApiError::Timeout(_) | ApiError::QueryTimeout(_) => {
+                OperationalError::new_err(msg)
+            }
+            ApiError::PermissionDenied(_) => ProgrammingError::new_err(msg),
+            ApiError::InvalidResponse(_) | ApiError::InvalidPage(_) | ApiError::HttpStatus(_) => {
+                match e.kind() {
+                    ErrorKind::Syntax
+                    | ErrorKind::UnknownDatabase
+                    | ErrorKind::UnknownTable
+                    | ErrorKind::UnknownColumn
+                    | ErrorKind::AlreadyExists
+                    | ErrorKind::PermissionDenied => ProgrammingError::new_err(msg),
+                    ErrorKind::Aborted
+                    | ErrorKind::Timeout
+                    | ErrorKind::RateLimited
+                    | ErrorKind::Unavailable => OperationalError::new_err(msg),
+                    _ => DatabaseError::new_err(msg),
+                }
+            }
+        },
+    }
+}
@@ -12,22 +12,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+// pyo3 macros expand to code triggering these lints on newer toolchains
+#![allow(unexpected_cfgs, non_local_definitions)]
+
 mod asyncio;
+mod blocking;
+mod exceptions;
+mod types;
 
 use crate::asyncio::*;
+use crate::blocking::*;
+use crate::exceptions::to_py_err;
 
 use databend_driver::{Client, Connection};
 
-use pyo3::create_exception;
-use pyo3::exceptions::PyException;
 use pyo3::prelude::*;
+use std::future::Future;
 use std::sync::Arc;
-create_exception!(
-    databend_client,
-    Error,
-    PyException,
-    "databend_client related errors"
-);
 
 #[derive(Clone)]
 pub struct Connector {
@@ -38,23 +39,37 @@ pub type FusedConnector = Arc<dyn Connection>;
 
 // For bindings
 impl Connector {
-    pub fn new_connector(dsn: &str) -> Result<Box<Self>, Error> {
+    pub fn new_connector(py: Python, dsn: &str) -> PyResult<Self> {
         let client = Client::new(dsn.to_string());
-        let conn = futures::executor::block_on(client.get_conn()).unwrap();
-        let r = Self {
+        let conn = block_on(py, client.get_conn()).map_err(to_py_err)?;
+        Ok(Self {
             connector: FusedConnector::from(conn),
-        };
-        Ok(Box::new(r))
+        })
     }
 }
 
-fn build_connector(dsn: &str) -> PyResult<Connector> {
-    let conn = Connector::new_connector(dsn).unwrap();
-    Ok(*conn)
+fn build_connector(py: Python, dsn: &str) -> PyResult<Connector> {
+    Connector::new_connector(py, dsn)
+}
+
+/// Run the future on the shared tokio runtime, with the GIL released.
+fn block_on<F>(py: Python, fut: F) -> F::Output
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    py.allow_threads(|| pyo3_asyncio::tokio::get_runtime().block_on(fut))
 }
 
 #[pymodule]
-fn _databend_driver(_py: Python, m: &PyModule) -> PyResult<()> {
+fn _databend_driver(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add("apilevel", "2.0")?;
+    m.add("threadsafety", 1)?;
+    m.add("paramstyle", "qmark")?;
+    m.add_function(wrap_pyfunction!(connect, m)?)?;
+    m.add_class::<BlockingConnection>()?;
+    m.add_class::<BlockingCursor>()?;
     m.add_class::<AsyncDatabendDriver>()?;
+    exceptions::register(py, m)?;
     Ok(())
 }
@@ -0,0 +1,123 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use databend_driver::{NumberValue, Row, Value};
+use pyo3::prelude::*;
+use pyo3::types::{IntoPyDict, PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+
+/// Convert a value to the python object, decimals as `decimal.Decimal`,
+/// timestamps as naive `datetime.datetime` in UTC and dates as `datetime.date`,
+/// arrays as lists, tuples as tuples, maps as dicts and variants as parsed json.
+pub(crate) fn value_to_py(py: Python, value: Value) -> PyResult<PyObject> {
+    let obj = match value {
+        Value::Null => py.None(),
+        Value::Boolean(b) => b.into_py(py),
+        Value::String(s) => s.into_py(py),
+        Value::Number(n) => match n {
+            NumberValue::Int8(i) => i.into_py(py),
+            NumberValue::Int16(i) => i.into_py(py),
+            NumberValue::Int32(i) => i.into_py(py),
+            NumberValue::Int64(i) => i.into_py(py),
+            NumberValue::UInt8(i) => i.into_py(py),
+            NumberValue::UInt16(i) => i.into_py(py),
+            NumberValue::UInt32(i) => i.into_py(py),
+            NumberValue::UInt64(i) => i.into_py(py),
+            NumberValue::Float32(f) => f.into_py(py),
+            NumberValue::Float64(f) => f.into_py(py),
+            NumberValue::Decimal128(..) | NumberValue::Decimal256(..) => py
+                .import("decimal")?
+                .getattr("Decimal")?
+                .call1((n.to_string(),))?
+                .into_py(py),
+        },
+        Value::Timestamp(us) => {
+            let datetime = py.import("datetime")?;
+            let epoch = datetime.getattr("datetime")?.call1((1970, 1, 1))?;
+            let delta = datetime
+                .getattr("timedelta")?
+                .call((), Some([("microseconds", us)].into_py_dict(py)))?;
+            epoch.call_method1("__add__", (delta,))?.into_py(py)
+        }
+        Value::Date(days) => {
+            let datetime = py.import("datetime")?;
+            let epoch = datetime.getattr("date")?.call1((1970, 1, 1))?;
+            let delta = datetime
+                .getattr("timedelta")?
+                .call((), Some([("days", days)].into_py_dict(py)))?;
+            epoch.call_method1("__add__", (delta,))?.into_py(py)
+        }
+        Value::Array(vals) => {
+            let vals = vals
+                .into_iter()
+                .map(|v| value_to_py(py, v))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, vals).into_py(py)
+        }
+        Value::Tuple(vals) => {
+            let vals = vals
+                .into_iter()
+                .map(|v| value_to_py(py, v))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyTuple::new(py, vals).into_py(py)
+        }
+        Value::Map(entries) => {
+            let dict = PyDict::new(py);
+            for (k, v) in entries {
+                dict.set_item(value_to_py(py, k)?, value_to_py(py, v)?)?;
+            }
+            dict.into_py(py)
+        }
+        Value::Variant(v) => py.import("json")?.call_method1("loads", (v,))?.into_py(py),
+        Value::Bitmap(s) | Value::Geometry(s) => s.into_py(py),
+    };
+    Ok(obj)
+}
+
+/// Convert a row to a python tuple.
+pub(crate) fn row_to_py(py: Python, row: Row) -> PyResult<PyObject> {
+    let values = row
+        .into_iter()
+        .map(|v| value_to_py(py, v))
+        .collect::<PyResult<Vec<_>>>()?;
+    Ok(PyTuple::new(py, values).into_py(py))
+}
+
+/// Convert a sequence of python objects to values bound to `?` placeholders,
+/// objects other than `None`, `bool`, `int`, `float` and `str` are bound
+/// with their string representation, e.g. `datetime` and `Decimal`.
+pub(crate) fn params_from_py(params: Option<&PyAny>) -> PyResult<Vec<Value>> {
+    let params = match params {
+        Some(params) if !params.is_none() => params,
+        _ => return Ok(vec![]),
+    };
+    let mut values = Vec::new();
+    for param in params.iter()? {
+        let param = param?;
+        let value = if param.is_none() {
+            Value::Null
+        } else if param.is_instance_of::<PyBool>() {
+            Value::Boolean(param.extract()?)
+        } else if param.is_instance_of::<PyInt>() {
+            Value::Number(NumberValue::Int64(param.extract()?))
+        } else if param.is_instance_of::<PyFloat>() {
+            Value::Number(NumberValue::Float64(param.extract()?))
+        } else if param.is_instance_of::<PyString>() {
+            Value::String(param.extract()?)
+        } else {
+            Value::String(param.str()?.to_string())
+        };
+        values.push(value);
+    }
+    Ok(values)
+}
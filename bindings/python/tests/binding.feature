@@ -20,3 +20,9 @@ Feature: Databend-Driver Binding
         When Async exec "CREATE TABLE if not exists test_data (x Int32,y VARCHAR)"
         When Async exec "INSERT INTO test_data(x,y) VALUES(1,'xx')"
         Then The select "SELECT * FROM test_data" should run
+
+    Scenario: Databend-Driver Blocking Cursor
+        Given A new Databend-Driver Connection
+        When Cursor execute "CREATE TABLE if not exists test_cursor (x Int32,y VARCHAR)"
+        When Cursor execute "INSERT INTO test_cursor(x,y) VALUES(1,'xx')"
+        Then Cursor fetchall "SELECT * FROM test_cursor" should return rows
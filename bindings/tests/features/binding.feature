@@ -22,6 +22,10 @@ Feature: Databend Driver
         Given A new Databend Driver Client
         Then Select numbers should iterate all rows
 
+    Scenario: Select Async Iter
+        Given A new Databend Driver Client
+        Then Select numbers should iterate all rows asynchronously
+
     Scenario: Insert and Select
         Given A new Databend Driver Client
         When Create a test table
@@ -31,3 +35,8 @@ Feature: Databend Driver
         Given A new Databend Driver Client
         When Create a test table
         Then Stream load and Select should be equal
+
+    Scenario: Upload To Stage
+        Given A new Databend Driver Client
+        When Create a test table
+        Then Upload to stage and Select should be equal
@@ -21,7 +21,8 @@ use crate::session::QueryKind;
 
 pub fn format_query(query: &str) -> String {
     let kind = QueryKind::from(query);
-    if kind == QueryKind::Put || kind == QueryKind::Get {
+    // keep scripting blocks as is
+    if kind == QueryKind::Put || kind == QueryKind::Get || query.contains("$$") {
         return query.to_owned();
     }
     let options = sqlformat::FormatOptions {
@@ -21,7 +21,7 @@ use strum_macros::EnumIter;
 
 type Result<T> = std::result::Result<T, String>;
 
-pub fn tokenize_sql(sql: &str) -> Result<Vec<Token>> {
+pub fn tokenize_sql(sql: &str) -> Result<Vec<Token<'_>>> {
     Tokenizer::new(sql).collect::<Result<Vec<_>>>()
 }
 
@@ -111,6 +111,13 @@ pub enum TokenKind {
     #[regex(r"[\n]+")]
     Newline,
 
+    // body of scripting block like `EXECUTE IMMEDIATE $$ ... $$`
+    #[regex(r"\$\$([^\$]|\$[^\$])*\$\$")]
+    DollarQuotedString,
+    // start of a multi-line `$$` body
+    #[regex(r"\$\$")]
+    DollarQuoteStart,
+
     #[regex(r#"[_a-zA-Z][_$a-zA-Z0-9]*"#)]
     Ident,
 
@@ -402,6 +409,8 @@ pub enum TokenKind {
     EXCEPT,
     #[token("EXCLUDE", ignore(ascii_case))]
     EXCLUDE,
+    #[token("EXECUTE", ignore(ascii_case))]
+    EXECUTE,
     #[token("ELSE", ignore(ascii_case))]
     ELSE,
     #[token("ENABLE_VIRTUAL_HOST_STYLE", ignore(ascii_case))]
@@ -901,7 +910,13 @@ impl TokenKind {
     pub fn is_literal(&self) -> bool {
         matches!(
             self,
-            LiteralInteger | LiteralFloat | QuotedString | PGLiteralHex | MySQLLiteralHex
+            LiteralInteger
+                | LiteralFloat
+                | QuotedString
+                | DollarQuotedString
+                | DollarQuoteStart
+                | PGLiteralHex
+                | MySQLLiteralHex
         )
     }
 
@@ -910,6 +925,8 @@ impl TokenKind {
             self,
             Ident
                 | QuotedString
+                | DollarQuotedString
+                | DollarQuoteStart
                 | PGLiteralHex
                 | MySQLLiteralHex
                 | LiteralInteger
@@ -0,0 +1,157 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Backslash commands in REPL like those of psql, translated to queries
+//! of `system.*` tables.
+
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use databend_driver::builder;
+
+use crate::{InputFormat, LoadOptions};
+
+pub const HELP: &str = r"\d               list tables of the current database
+\d <table>       describe columns of the table
+\dt [<database>] list tables of the database
+\l               list databases
+\du              list users
+\c <database>    change the current database
+\copy <table> <file> [<format>] [delimiter=,] [compression=gzip] [skip_header=1] [<option>=<value>]
+                 load the local file into the table, CSV by default
+\x               toggle expanded output
+\timing          toggle timing of queries
+\format <fmt>    set output format: table, csv, tsv, json, ndjson, vertical, null
+\?               show this help";
+
+pub enum Command {
+    ListTables(Option<String>),
+    Describe(String),
+    ListDatabases,
+    ListUsers,
+    Connect(String),
+    Copy {
+        table: String,
+        file: String,
+        format: InputFormat,
+        load: LoadOptions,
+        // other format options passed as is
+        options: Vec<(String, String)>,
+    },
+    ToggleExpanded,
+    ToggleTiming,
+    Format(String),
+    Help,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self> {
+        let line = line.trim().trim_end_matches(';');
+        let (name, arg) = match line.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, Some(arg.trim().to_string())),
+            None => (line, None),
+        };
+        let required = |arg: Option<String>, usage: &str| {
+            arg.filter(|a| !a.is_empty())
+                .ok_or_else(|| anyhow!("Usage: {}", usage))
+        };
+        let cmd = match name {
+            "\\d" => match arg {
+                Some(table) if !table.is_empty() => Command::Describe(table),
+                _ => Command::ListTables(None),
+            },
+            "\\dt" => Command::ListTables(arg.filter(|a| !a.is_empty())),
+            "\\l" => Command::ListDatabases,
+            "\\du" => Command::ListUsers,
+            "\\c" => Command::Connect(required(arg, "\\c <database>")?),
+            "\\copy" => parse_copy(&required(arg, COPY_USAGE)?)?,
+            "\\x" => Command::ToggleExpanded,
+            "\\timing" => Command::ToggleTiming,
+            "\\format" => Command::Format(required(arg, "\\format <fmt>")?),
+            "\\?" => Command::Help,
+            _ => return Err(anyhow!("Unknown command: {}, try \\?", name)),
+        };
+        Ok(cmd)
+    }
+
+    /// The query of commands listing objects, names without database are
+    /// resolved in the current one.
+    pub fn to_sql(&self, current_database: &str) -> Result<Option<String>> {
+        let sql = match self {
+            Command::ListTables(database) => builder::select(&["name", "engine", "created_on"])
+                .from("system.tables")
+                .where_(
+                    "database",
+                    "=",
+                    database.as_deref().unwrap_or(current_database),
+                )
+                .order_by("name", true)
+                .build()?,
+            Command::Describe(table) => {
+                let (database, table) = match table.split_once('.') {
+                    Some((database, table)) => (database, table),
+                    None => (current_database, table.as_str()),
+                };
+                builder::select(&["name", "type", "is_nullable", "default_expression"])
+                    .from("system.columns")
+                    .where_("database", "=", database)
+                    .where_("table", "=", table)
+                    .build()?
+            }
+            Command::ListDatabases => builder::select(&["name"])
+                .from("system.databases")
+                .order_by("name", true)
+                .build()?,
+            Command::ListUsers => builder::select(&["name", "hostname", "auth_type"])
+                .from("system.users")
+                .order_by("name", true)
+                .build()?,
+            _ => return Ok(None),
+        };
+        Ok(Some(sql))
+    }
+}
+
+const COPY_USAGE: &str = "\\copy <table> <file> [<format>] [<option>=<value> ...]";
+
+fn parse_copy(args: &str) -> Result<Command> {
+    let usage = || anyhow!("Usage: {}", COPY_USAGE);
+    let mut parts = args.split_whitespace();
+    let table = parts.next().ok_or_else(usage)?.to_string();
+    let file = parts.next().ok_or_else(usage)?.to_string();
+    let mut format = InputFormat::CSV;
+    let mut load = LoadOptions::default();
+    let mut options = Vec::new();
+    for (i, part) in parts.enumerate() {
+        match part.split_once('=') {
+            Some(("delimiter", v)) => load.delimiter = Some(v.to_string()),
+            Some(("compression", v)) => load.compression = Some(v.to_string()),
+            Some(("skip_header", v)) => {
+                load.skip_header = Some(v.parse().map_err(|_| usage())?);
+            }
+            Some((k, v)) => options.push((k.to_string(), v.to_string())),
+            None if i == 0 => {
+                format = InputFormat::from_str(part, true)
+                    .map_err(|_| anyhow!("Unknown format: {}", part))?;
+            }
+            None => return Err(usage()),
+        }
+    }
+    Ok(Command::Copy {
+        table,
+        file,
+        format,
+        load,
+        options,
+    })
+}
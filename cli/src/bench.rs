@@ -0,0 +1,173 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use databend_driver::Client;
+use tokio::time::Instant;
+use tokio_stream::StreamExt;
+
+pub struct BenchOptions {
+    pub count: usize,
+    pub concurrency: usize,
+}
+
+impl BenchOptions {
+    /// Parse arguments of `.bench <count> [<concurrency>] <sql>`.
+    pub fn parse(args: &str) -> Result<(Self, String)> {
+        let usage = || anyhow!("Usage: .bench <count> [<concurrency>] <sql>");
+        let (count, rest) = args
+            .trim()
+            .split_once(char::is_whitespace)
+            .ok_or_else(usage)?;
+        let count = count.parse().map_err(|_| usage())?;
+        let rest = rest.trim_start();
+        let (concurrency, sql) = match rest.split_once(char::is_whitespace) {
+            Some((c, sql)) if c.chars().all(|c| c.is_ascii_digit()) => {
+                (c.parse().map_err(|_| usage())?, sql.trim())
+            }
+            _ => (1, rest),
+        };
+        if sql.is_empty() {
+            return Err(usage());
+        }
+        Ok((Self { count, concurrency }, sql.to_string()))
+    }
+}
+
+pub struct BenchReport {
+    count: usize,
+    concurrency: usize,
+    errors: usize,
+    elapsed: Duration,
+    // sorted latencies of successful queries
+    latencies: Vec<Duration>,
+}
+
+impl BenchReport {
+    fn percentile(&self, p: usize) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::ZERO;
+        }
+        let rank = (self.latencies.len() * p).div_ceil(100).max(1);
+        self.latencies[rank - 1]
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        let elapsed = self.elapsed.as_secs_f64();
+        writeln!(
+            f,
+            "{} queries with concurrency {}, {} errors",
+            self.count, self.concurrency, self.errors
+        )?;
+        writeln!(
+            f,
+            "elapsed {:.3} sec, {:.2} queries/sec",
+            elapsed,
+            self.latencies.len() as f64 / elapsed
+        )?;
+        if self.latencies.is_empty() {
+            return Ok(());
+        }
+        let total: Duration = self.latencies.iter().sum();
+        write!(
+            f,
+            "latency(ms): min {:.3}, avg {:.3}, p50 {:.3}, p90 {:.3}, p99 {:.3}, max {:.3}",
+            ms(self.latencies[0]),
+            ms(total / self.latencies.len() as u32),
+            ms(self.percentile(50)),
+            ms(self.percentile(90)),
+            ms(self.percentile(99)),
+            ms(self.latencies[self.latencies.len() - 1]),
+        )
+    }
+}
+
+/// Run the query `count` times over `concurrency` connections,
+/// results are fully fetched and discarded.
+pub async fn run(client: &Client, sql: &str, opts: &BenchOptions) -> Result<BenchReport> {
+    if opts.count == 0 || opts.concurrency == 0 {
+        return Err(anyhow!("count and concurrency must be greater than 0"));
+    }
+    let concurrency = opts.concurrency.min(opts.count);
+    let next = Arc::new(AtomicUsize::new(0));
+    let sql: Arc<str> = sql.into();
+    let mut workers = Vec::with_capacity(concurrency);
+    for _ in 0..concurrency {
+        workers.push(client.get_conn().await?);
+    }
+
+    let start = Instant::now();
+    let handles = workers
+        .into_iter()
+        .map(|conn| {
+            let next = next.clone();
+            let sql = sql.clone();
+            let count = opts.count;
+            tokio::spawn(async move {
+                let mut latencies = Vec::new();
+                let mut errors = 0;
+                while next.fetch_add(1, Ordering::Relaxed) < count {
+                    let start = Instant::now();
+                    let result = match conn.query_iter(&sql).await {
+                        Ok(mut rows) => {
+                            let mut result = Ok(());
+                            while let Some(row) = rows.next().await {
+                                if let Err(e) = row {
+                                    result = Err(e);
+                                    break;
+                                }
+                            }
+                            result
+                        }
+                        Err(e) => Err(e),
+                    };
+                    match result {
+                        Ok(()) => latencies.push(start.elapsed()),
+                        Err(e) => {
+                            if errors == 0 {
+                                eprintln!("error: {}", e);
+                            }
+                            errors += 1;
+                        }
+                    }
+                }
+                (latencies, errors)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut report = BenchReport {
+        count: opts.count,
+        concurrency,
+        errors: 0,
+        elapsed: Duration::ZERO,
+        latencies: Vec::with_capacity(opts.count),
+    };
+    for handle in handles {
+        let (latencies, errors) = handle.await?;
+        report.latencies.extend(latencies);
+        report.errors += errors;
+    }
+    report.elapsed = start.elapsed();
+    report.latencies.sort();
+    Ok(report)
+}
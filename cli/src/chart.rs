@@ -0,0 +1,150 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Render small numeric results as unicode charts with `.chart bar|line`,
+//! values are taken from the last column and labels from the first one.
+
+use std::fmt::Write;
+
+use anyhow::{anyhow, Result};
+use databend_driver::{DataType, Row, SchemaRef, Value};
+use terminal_size::{terminal_size, Width};
+
+use crate::config::ChartMode;
+
+const MAX_CHART_ROWS: usize = 100;
+const LINE_HEIGHT: usize = 10;
+const BAR_BLOCKS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+
+pub fn render(mode: &ChartMode, schema: SchemaRef, rows: &[Row]) -> Result<String> {
+    let (labels, values) = extract(schema, rows)?;
+    match mode {
+        ChartMode::Bar => Ok(render_bar(&labels, &values, chart_width())),
+        ChartMode::Line => Ok(render_line(&labels, &values)),
+        ChartMode::Off => Err(anyhow!("chart is off")),
+    }
+}
+
+fn chart_width() -> usize {
+    match terminal_size() {
+        Some((Width(w), _)) => w as usize,
+        None => 80,
+    }
+}
+
+fn extract(schema: SchemaRef, rows: &[Row]) -> Result<(Vec<String>, Vec<f64>)> {
+    let fields = schema.fields();
+    let last = fields
+        .last()
+        .ok_or_else(|| anyhow!("no columns to chart"))?;
+    let data_type = match &last.data_type {
+        DataType::Nullable(inner) => inner.as_ref(),
+        dt => dt,
+    };
+    if !(data_type.is_numeric() || matches!(data_type, DataType::Decimal(_))) {
+        return Err(anyhow!(
+            "last column {} of type {} is not numeric",
+            last.name,
+            last.data_type
+        ));
+    }
+    if rows.len() > MAX_CHART_ROWS {
+        return Err(anyhow!(
+            "too many rows to chart: {}, at most {}",
+            rows.len(),
+            MAX_CHART_ROWS
+        ));
+    }
+    let mut labels = Vec::with_capacity(rows.len());
+    let mut values = Vec::with_capacity(rows.len());
+    for (i, row) in rows.iter().enumerate() {
+        let row = row.values();
+        let label = if row.len() > 1 {
+            row[0].to_string()
+        } else {
+            (i + 1).to_string()
+        };
+        let value = match &row[row.len() - 1] {
+            Value::Null => 0.0,
+            v => v.to_string().parse()?,
+        };
+        labels.push(label);
+        values.push(value);
+    }
+    Ok((labels, values))
+}
+
+/// One bar per row, negative values are shown without bars.
+fn render_bar(labels: &[String], values: &[f64], width: usize) -> String {
+    let label_width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    let texts: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+    let text_width = texts.iter().map(|t| t.len()).max().unwrap_or(0);
+    let bar_width = width.saturating_sub(label_width + text_width + 4).max(10);
+    let max = values.iter().cloned().fold(0.0, f64::max);
+
+    let mut out = String::new();
+    for ((label, value), text) in labels.iter().zip(values).zip(&texts) {
+        // in eighths of a cell
+        let eighths = if max > 0.0 && *value > 0.0 {
+            (value / max * (bar_width * 8) as f64).round() as usize
+        } else {
+            0
+        };
+        let mut bar = BAR_BLOCKS[7].to_string().repeat(eighths / 8);
+        if eighths % 8 > 0 {
+            bar.push(BAR_BLOCKS[eighths % 8 - 1]);
+        }
+        let _ = writeln!(out, "{:>label_width$} │{} {}", label, bar, text);
+    }
+    out
+}
+
+/// One point per row from left to right, scaled between the min and max.
+fn render_line(labels: &[String], values: &[f64]) -> String {
+    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let levels: Vec<usize> = values
+        .iter()
+        .map(|v| {
+            if max > min {
+                ((v - min) / (max - min) * (LINE_HEIGHT - 1) as f64).round() as usize
+            } else {
+                0
+            }
+        })
+        .collect();
+    let (top, bottom) = (max.to_string(), min.to_string());
+    let axis_width = top.len().max(bottom.len());
+
+    let mut out = String::new();
+    for level in (0..LINE_HEIGHT).rev() {
+        let (axis, tick) = if level == LINE_HEIGHT - 1 {
+            (top.as_str(), '┤')
+        } else if level == 0 {
+            (bottom.as_str(), '┤')
+        } else {
+            ("", '│')
+        };
+        let points: String = levels
+            .iter()
+            .map(|l| if *l == level { '●' } else { ' ' })
+            .collect();
+        let _ = writeln!(out, "{:>axis_width$} {}{}", axis, tick, points.trim_end());
+    }
+    let _ = writeln!(out, "{:>axis_width$} └{}", "", "─".repeat(values.len()));
+    if let (Some(first), Some(last)) = (labels.first(), labels.last()) {
+        let _ = writeln!(out, "{:>axis_width$}  {} .. {}", "", first, last);
+    }
+    out
+}
@@ -0,0 +1,225 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use databend_driver::{escape_literal, Connection};
+use tokio_stream::StreamExt;
+
+// a slow server should not freeze the prompt for long
+const FETCH_TIMEOUT: Duration = Duration::from_secs(3);
+
+// keywords followed by a table name
+const TABLE_KEYWORDS: &[&str] = &[
+    "FROM", "JOIN", "INTO", "UPDATE", "TABLE", "DESC", "DESCRIBE",
+];
+// keywords followed by a database name
+const DATABASE_KEYWORDS: &[&str] = &["USE", "DATABASE"];
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Names {
+    Databases,
+    Tables(String),
+    Columns(String, String),
+    Functions,
+}
+
+impl Names {
+    fn sql(&self) -> String {
+        match self {
+            Names::Databases => "SELECT name FROM system.databases".to_string(),
+            Names::Tables(database) => format!(
+                "SELECT name FROM system.tables WHERE database = {}",
+                escape_literal(database)
+            ),
+            Names::Columns(database, table) => format!(
+                "SELECT name FROM system.columns WHERE database = {} AND table = {}",
+                escape_literal(database),
+                escape_literal(table)
+            ),
+            Names::Functions => "SELECT name FROM system.functions".to_string(),
+        }
+    }
+}
+
+struct Cache {
+    conn: Box<dyn Connection>,
+    database: String,
+    names: HashMap<Names, Arc<Vec<String>>>,
+}
+
+/// Names of databases, tables and columns for completion in REPL, fetched
+/// from the server the first time they are needed and cached for the
+/// session until the current database or the schema changes.
+#[derive(Clone)]
+pub struct Metadata {
+    cache: Arc<Mutex<Cache>>,
+}
+
+impl Metadata {
+    pub fn new(conn: Box<dyn Connection>, database: &str) -> Self {
+        Self {
+            cache: Arc::new(Mutex::new(Cache {
+                conn,
+                database: database.to_string(),
+                names: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Fetch names with the new connection after reconnecting.
+    pub fn set_conn(&self, conn: Box<dyn Connection>) {
+        self.cache.lock().unwrap().conn = conn;
+    }
+
+    /// Tables without a database are completed in the new one after `USE`.
+    pub fn set_database(&self, database: &str) {
+        self.cache.lock().unwrap().database = database.to_string();
+    }
+
+    /// Forget all names after DDL, they are fetched again when needed.
+    pub fn invalidate(&self) {
+        self.cache.lock().unwrap().names.clear();
+    }
+
+    /// Candidates for the word ending at `pos`, with the start of the word
+    /// to replace. Names not cached yet are fetched only if `fetch`, which
+    /// blocks the prompt, so hints while typing only come from the cache.
+    pub fn complete(&self, line: &str, pos: usize, fetch: bool) -> (usize, Vec<String>) {
+        let line = &line[..pos];
+        let word_start = line.rfind(is_separator).map(|i| i + 1).unwrap_or(0);
+        let word = &line[word_start..];
+        let words = line[..word_start]
+            .split(is_separator)
+            .filter(|w| !w.is_empty())
+            .collect::<Vec<_>>();
+        let previous = words
+            .last()
+            .map(|w| w.to_ascii_uppercase())
+            .unwrap_or_default();
+        let database = self.cache.lock().unwrap().database.clone();
+
+        // `db.table` or `table.column`
+        if let Some((prefix, partial)) = word.rsplit_once('.') {
+            let prefix = unquote(prefix);
+            let start = pos - partial.len();
+            let mut candidates = vec![];
+            if self.names(Names::Databases, fetch).contains(&prefix) {
+                candidates.extend(self.names(Names::Tables(prefix.clone()), fetch).to_vec());
+            }
+            if self
+                .names(Names::Tables(database.clone()), fetch)
+                .contains(&prefix)
+            {
+                candidates.extend(self.names(Names::Columns(database, prefix), fetch).to_vec());
+            }
+            return (start, filter(candidates, partial));
+        }
+
+        let start = pos - word.len();
+        if DATABASE_KEYWORDS.contains(&previous.as_str()) {
+            let candidates = self.names(Names::Databases, fetch).to_vec();
+            return (start, filter(candidates, word));
+        }
+        if TABLE_KEYWORDS.contains(&previous.as_str()) {
+            let mut candidates = self.names(Names::Tables(database), fetch).to_vec();
+            candidates.extend(self.names(Names::Databases, fetch).iter().cloned());
+            return (start, filter(candidates, word));
+        }
+
+        // columns of the tables in the statement and functions
+        let mut candidates = vec![];
+        for pair in words.windows(2) {
+            if TABLE_KEYWORDS.contains(&pair[0].to_ascii_uppercase().as_str()) {
+                let (db, table) = match pair[1].split_once('.') {
+                    Some((db, table)) => (unquote(db), unquote(table)),
+                    None => (database.clone(), unquote(pair[1])),
+                };
+                candidates.extend(self.names(Names::Columns(db, table), fetch).to_vec());
+            }
+        }
+        candidates.extend(self.names(Names::Functions, fetch).iter().cloned());
+        (start, filter(candidates, word))
+    }
+
+    fn names(&self, names: Names, fetch: bool) -> Arc<Vec<String>> {
+        let conn = {
+            let cache = self.cache.lock().unwrap();
+            if let Some(cached) = cache.names.get(&names) {
+                return cached.clone();
+            }
+            if !fetch {
+                return Arc::default();
+            }
+            cache.conn.clone()
+        };
+        // failures are cached as empty to not wait for the server on every tab
+        let fetched = Arc::new(fetch_names(conn, &names.sql()).unwrap_or_default());
+        self.cache
+            .lock()
+            .unwrap()
+            .names
+            .insert(names, fetched.clone());
+        fetched
+    }
+}
+
+fn fetch_names(conn: Box<dyn Connection>, sql: &str) -> Option<Vec<String>> {
+    // completion is called synchronously by the line editor in the runtime
+    let handle = tokio::runtime::Handle::try_current().ok()?;
+    tokio::task::block_in_place(|| {
+        handle.block_on(async {
+            let fetch = async {
+                let mut rows = conn.query_iter(sql).await?;
+                let mut names = vec![];
+                while let Some(row) = rows.next().await {
+                    let (name,): (String,) =
+                        row?.try_into().map_err(databend_driver::Error::Parsing)?;
+                    names.push(name);
+                }
+                Ok::<_, databend_driver::Error>(names)
+            };
+            match tokio::time::timeout(FETCH_TIMEOUT, fetch).await {
+                Ok(Ok(names)) => Some(names),
+                _ => None,
+            }
+        })
+    })
+}
+
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || matches!(c, ',' | '(' | ')' | '=')
+}
+
+/// Start of the name being typed at the end of line, after `.` of a
+/// qualified name.
+pub fn word_start(line: &str) -> usize {
+    line.rfind(|c| is_separator(c) || c == '.')
+        .map(|i| i + 1)
+        .unwrap_or(0)
+}
+
+fn unquote(name: &str) -> String {
+    name.trim_matches(|c| c == '`' || c == '"').to_string()
+}
+
+fn filter(mut candidates: Vec<String>, word: &str) -> Vec<String> {
+    let word = unquote(word).to_lowercase();
+    candidates.retain(|c| c.to_lowercase().starts_with(&word));
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
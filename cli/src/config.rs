@@ -18,7 +18,7 @@ use std::{collections::BTreeMap, path::Path};
 
 use anyhow::anyhow;
 use anyhow::Result;
-use clap::ValueEnum;
+use clap::{Subcommand, ValueEnum};
 use serde::Deserialize;
 
 #[derive(Clone, Debug, Default, Deserialize)]
@@ -27,6 +27,9 @@ pub struct Config {
     pub connection: ConnectionConfig,
     #[serde(default)]
     pub settings: SettingsConfig,
+    /// Named connections selected with `--profile`.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ConnectionConfig>,
 }
 
 #[derive(Clone, Debug, Deserialize, Default)]
@@ -42,6 +45,7 @@ pub struct SettingsConfig {
     pub max_display_rows: Option<usize>,
     pub max_col_width: Option<usize>,
     pub max_width: Option<usize>,
+    pub safe_mode: Option<bool>,
 }
 
 #[derive(Clone, Debug)]
@@ -62,6 +66,27 @@ impl From<&str> for ExpandMode {
     }
 }
 
+/// Render results as charts instead of tables, see `chart.rs`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChartMode {
+    Off,
+    Bar,
+    Line,
+}
+
+impl std::str::FromStr for ChartMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" => Ok(ChartMode::Off),
+            "bar" => Ok(ChartMode::Bar),
+            "line" => Ok(ChartMode::Line),
+            _ => Err(anyhow!("Unknown chart mode: {}, expect bar/line/off", s)),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Settings {
     pub display_pretty_sql: bool,
@@ -86,6 +111,9 @@ pub struct Settings {
     /// Expand table format display, default off, could be on/off/auto.
     /// only works with output format `table`.
     pub expand: ExpandMode,
+    /// Chart mode, default off, could be bar/line/off.
+    /// only works with output format `table`.
+    pub chart: ChartMode,
 
     /// Show time elapsed when executing queries.
     /// only works with output format `null`.
@@ -95,6 +123,8 @@ pub struct Settings {
     pub multi_line: bool,
     /// whether replace '\n' with '\\n', default true.
     pub replace_newline: bool,
+    /// Confirm before destructive statements in REPL, default false.
+    pub safe_mode: bool,
 }
 
 #[derive(ValueEnum, Clone, Debug, PartialEq, Deserialize)]
@@ -102,6 +132,12 @@ pub enum OutputFormat {
     Table,
     CSV,
     TSV,
+    /// Array of objects keyed by column names.
+    JSON,
+    /// One object per line.
+    NDJSON,
+    /// One line per column for each row, for wide rows.
+    Vertical,
     Null,
 }
 
@@ -122,6 +158,7 @@ impl Settings {
         self.max_width = cfg.max_width.unwrap_or(self.max_width);
         self.max_col_width = cfg.max_col_width.unwrap_or(self.max_col_width);
         self.max_display_rows = cfg.max_display_rows.unwrap_or(self.max_display_rows);
+        self.safe_mode = cfg.safe_mode.unwrap_or(self.safe_mode);
     }
 
     pub fn inject_ctrl_cmd(&mut self, cmd_name: &str, cmd_value: &str) -> Result<()> {
@@ -136,17 +173,22 @@ impl Settings {
                     "table" => OutputFormat::Table,
                     "csv" => OutputFormat::CSV,
                     "tsv" => OutputFormat::TSV,
+                    "json" => OutputFormat::JSON,
+                    "ndjson" => OutputFormat::NDJSON,
+                    "vertical" => OutputFormat::Vertical,
                     "null" => OutputFormat::Null,
                     _ => return Err(anyhow!("Unknown output format: {}", cmd_value)),
                 }
             }
             "expand" => self.expand = cmd_value.into(),
+            "chart" => self.chart = cmd_value.parse()?,
             "time" => self.time = cmd_value.parse()?,
             "multi_line" => self.multi_line = cmd_value.parse()?,
             "max_display_rows" => self.max_display_rows = cmd_value.parse()?,
             "max_width" => self.max_width = cmd_value.parse()?,
             "max_col_width" => self.max_col_width = cmd_value.parse()?,
             "replace_newline" => self.replace_newline = cmd_value.parse()?,
+            "safe_mode" => self.safe_mode = cmd_value.parse()?,
             _ => return Err(anyhow!("Unknown command: {}", cmd_name)),
         }
         Ok(())
@@ -164,8 +206,8 @@ pub struct ConnectionConfig {
 }
 
 impl Config {
-    pub fn load() -> Self {
-        let paths = [
+    fn paths() -> [String; 2] {
+        [
             format!(
                 "{}/.bendsql/config.toml",
                 std::env::var("HOME").unwrap_or_else(|_| ".".to_string())
@@ -174,14 +216,36 @@ impl Config {
                 "{}/.config/bendsql/config.toml",
                 std::env::var("HOME").unwrap_or_else(|_| ".".to_string())
             ),
-        ];
-        let path = paths.iter().find(|p| Path::new(p).exists());
+        ]
+    }
+
+    /// The config file in use, or the one to create.
+    fn path() -> String {
+        let [legacy, path] = Self::paths();
+        if Path::new(&legacy).exists() {
+            legacy
+        } else {
+            path
+        }
+    }
+
+    pub fn load() -> Self {
+        let path = Self::paths().into_iter().find(|p| Path::new(p).exists());
         match path {
-            Some(path) => Self::load_from_file(path),
+            Some(path) => Self::load_from_file(&path),
             None => Self::default(),
         }
     }
 
+    /// Use the connection of the profile instead of the default one.
+    pub fn use_profile(&mut self, name: &str) -> Result<()> {
+        self.connection = self
+            .profiles
+            .remove(name)
+            .ok_or_else(|| anyhow!("profile not found in config: {}", name))?;
+        Ok(())
+    }
+
     fn load_from_file(path: &str) -> Self {
         match toml::from_str(&std::fs::read_to_string(path).unwrap()) {
             Ok(config) => config,
@@ -201,6 +265,7 @@ impl Default for Settings {
             prompt: "{user}@{warehouse}/{database}> ".to_string(),
             output_format: OutputFormat::Table,
             expand: ExpandMode::Off,
+            chart: ChartMode::Off,
             show_progress: false,
             max_display_rows: 40,
             max_col_width: 1024 * 1024,
@@ -209,6 +274,7 @@ impl Default for Settings {
             time: false,
             multi_line: true,
             replace_newline: true,
+            safe_mode: false,
         }
     }
 }
@@ -224,3 +290,158 @@ impl Default for ConnectionConfig {
         }
     }
 }
+
+/// `bendsql config`, keys are dotted paths into the config file
+/// like `connection.host` or `profiles.<name>.host`.
+#[derive(Debug, Subcommand, PartialEq)]
+pub enum ConfigAction {
+    /// Print all keys and values, or dump the whole config in the format
+    List {
+        #[clap(long, value_enum)]
+        format: Option<DumpFormat>,
+    },
+    /// Print the value of the key
+    Get { key: String },
+    /// Set the value of the key, numbers and booleans are stored as is
+    Set { key: String, value: String },
+    /// Remove the key, a whole profile with `profiles.<name>`
+    Unset { key: String },
+}
+
+#[derive(ValueEnum, Clone, Debug, PartialEq)]
+pub enum DumpFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+pub fn run_config_action(action: ConfigAction) -> Result<()> {
+    let path = Config::path();
+    let mut doc = match std::fs::read_to_string(&path) {
+        Ok(content) => toml::from_str(&content)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml::Table::new(),
+        Err(e) => return Err(e.into()),
+    };
+    match action {
+        ConfigAction::List { format } => match format {
+            None => {
+                let mut lines = Vec::new();
+                flatten("", &doc, &mut lines);
+                for line in lines {
+                    println!("{}", line);
+                }
+            }
+            Some(DumpFormat::Toml) => print!("{}", toml::to_string_pretty(&doc)?),
+            Some(DumpFormat::Json) => println!("{}", serde_json::to_string_pretty(&doc)?),
+            Some(DumpFormat::Yaml) => {
+                let mut out = String::new();
+                to_yaml(&doc, 0, &mut out)?;
+                print!("{}", out);
+            }
+        },
+        ConfigAction::Get { key } => {
+            let value = get_key(&doc, &key).ok_or_else(|| anyhow!("key not found: {}", key))?;
+            match value {
+                toml::Value::String(s) => println!("{}", s),
+                toml::Value::Table(t) => print!("{}", toml::to_string_pretty(t)?),
+                v => println!("{}", v),
+            }
+        }
+        ConfigAction::Set { key, value } => {
+            let value = if let Ok(v) = value.parse::<i64>() {
+                toml::Value::Integer(v)
+            } else if let Ok(v) = value.parse::<bool>() {
+                toml::Value::Boolean(v)
+            } else {
+                toml::Value::String(value)
+            };
+            set_key(&mut doc, &key, Some(value))?;
+            save(&path, doc)?;
+        }
+        ConfigAction::Unset { key } => {
+            set_key(&mut doc, &key, None)?;
+            save(&path, doc)?;
+        }
+    }
+    Ok(())
+}
+
+fn save(path: &str, doc: toml::Table) -> Result<()> {
+    // reject changes breaking the config, like a string for a port
+    let _: Config = toml::Value::Table(doc.clone()).try_into()?;
+    if let Some(dir) = Path::new(path).parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, toml::to_string_pretty(&doc)?)?;
+    Ok(())
+}
+
+fn get_key<'a>(doc: &'a toml::Table, key: &str) -> Option<&'a toml::Value> {
+    let mut parts = key.split('.');
+    let mut value = doc.get(parts.next()?)?;
+    for part in parts {
+        value = value.as_table()?.get(part)?;
+    }
+    Some(value)
+}
+
+/// Set or remove the key, tables on the path are created if missing.
+fn set_key(doc: &mut toml::Table, key: &str, value: Option<toml::Value>) -> Result<()> {
+    let parts: Vec<&str> = key.split('.').collect();
+    if parts.iter().any(|p| p.is_empty()) {
+        return Err(anyhow!("invalid key: {}", key));
+    }
+    let (last, parents) = parts.split_last().unwrap();
+    let mut table = doc;
+    for part in parents {
+        table = table
+            .entry(part.to_string())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| anyhow!("{} in key {} is not a table", part, key))?;
+    }
+    match value {
+        Some(value) => {
+            table.insert(last.to_string(), value);
+        }
+        None => {
+            table
+                .remove(*last)
+                .ok_or_else(|| anyhow!("key not found: {}", key))?;
+        }
+    }
+    Ok(())
+}
+
+fn flatten(prefix: &str, table: &toml::Table, lines: &mut Vec<String>) {
+    for (k, v) in table {
+        let key = if prefix.is_empty() {
+            k.clone()
+        } else {
+            format!("{}.{}", prefix, k)
+        };
+        match v {
+            toml::Value::Table(t) => flatten(&key, t, lines),
+            v => lines.push(format!("{} = {}", key, v)),
+        }
+    }
+}
+
+/// Values are written in JSON, which is also valid YAML.
+fn to_yaml(table: &toml::Table, indent: usize, out: &mut String) -> Result<()> {
+    for (k, v) in table {
+        match v {
+            toml::Value::Table(t) => {
+                out.push_str(&format!("{:indent$}{}:\n", "", k));
+                to_yaml(t, indent + 2, out)?;
+            }
+            v => out.push_str(&format!(
+                "{:indent$}{}: {}\n",
+                "",
+                k,
+                serde_json::to_string(v)?
+            )),
+        }
+    }
+    Ok(())
+}
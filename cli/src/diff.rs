@@ -0,0 +1,217 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compare results of two queries with `.diff [--key <cols>] "<sql1>" "<sql2>"`,
+//! rows are matched by the key columns if given, otherwise by position.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{anyhow, Result};
+use databend_driver::Connection;
+use tokio_stream::StreamExt;
+
+pub struct DiffArgs {
+    pub key: Vec<String>,
+    pub left: String,
+    pub right: String,
+}
+
+impl DiffArgs {
+    pub fn parse(args: &str) -> Result<Self> {
+        let usage = || anyhow!(r#"Usage: .diff [--key <col1,col2>] "<sql1>" "<sql2>""#);
+        let mut args = split_args(args)?.into_iter();
+        let mut key = Vec::new();
+        let mut queries = Vec::new();
+        while let Some(arg) = args.next() {
+            if arg == "--key" {
+                let cols = args.next().ok_or_else(usage)?;
+                key = cols.split(',').map(|c| c.trim().to_string()).collect();
+            } else {
+                queries.push(arg);
+            }
+        }
+        match <[String; 2]>::try_from(queries) {
+            Ok([left, right]) => Ok(Self { key, left, right }),
+            Err(_) => Err(usage()),
+        }
+    }
+}
+
+struct QueryResult {
+    columns: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+async fn fetch(conn: &dyn Connection, sql: &str) -> Result<QueryResult> {
+    let (schema, mut rows) = conn.query_iter_ext(sql).await?;
+    let columns = schema.fields().iter().map(|f| f.name.clone()).collect();
+    let mut result = QueryResult {
+        columns,
+        rows: Vec::new(),
+    };
+    while let Some(row) = rows.next().await {
+        if let databend_driver::RowWithProgress::Row(row) = row? {
+            result
+                .rows
+                .push(row.values().iter().map(|v| v.to_string()).collect());
+        }
+    }
+    Ok(result)
+}
+
+#[derive(Default)]
+pub struct DiffReport {
+    columns: Vec<String>,
+    added: Vec<Vec<String>>,
+    removed: Vec<Vec<String>>,
+    changed: Vec<(Vec<String>, Vec<String>)>,
+}
+
+impl DiffReport {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    pub fn print(&self) {
+        println!("  {}", self.columns.join("\t"));
+        for row in &self.removed {
+            println!("- {}", row.join("\t"));
+        }
+        for row in &self.added {
+            println!("+ {}", row.join("\t"));
+        }
+        for (old, new) in &self.changed {
+            println!("~ {}", old.join("\t"));
+            println!("> {}", new.join("\t"));
+        }
+        eprintln!(
+            "{} added, {} removed, {} changed",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        );
+    }
+}
+
+pub async fn run(conn: &dyn Connection, args: &DiffArgs) -> Result<DiffReport> {
+    let left = fetch(conn, &args.left).await?;
+    let right = fetch(conn, &args.right).await?;
+    if left.columns.len() != right.columns.len() {
+        return Err(anyhow!(
+            "column count mismatch: {} vs {}",
+            left.columns.len(),
+            right.columns.len()
+        ));
+    }
+    if left.columns != right.columns {
+        eprintln!(
+            "warning: column names differ, comparing by position: {:?} vs {:?}",
+            left.columns, right.columns
+        );
+    }
+    let mut report = DiffReport {
+        columns: left.columns.clone(),
+        ..Default::default()
+    };
+
+    if args.key.is_empty() {
+        let mut l = left.rows.into_iter();
+        let mut r = right.rows.into_iter();
+        loop {
+            match (l.next(), r.next()) {
+                (Some(a), Some(b)) if a != b => report.changed.push((a, b)),
+                (Some(_), Some(_)) => {}
+                (Some(a), None) => report.removed.push(a),
+                (None, Some(b)) => report.added.push(b),
+                (None, None) => break,
+            }
+        }
+        return Ok(report);
+    }
+
+    let key_index = args
+        .key
+        .iter()
+        .map(|k| {
+            left.columns
+                .iter()
+                .position(|c| c == k)
+                .ok_or_else(|| anyhow!("key column `{}` not found", k))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let key_of = |row: &[String]| {
+        key_index
+            .iter()
+            .map(|i| row[*i].clone())
+            .collect::<Vec<_>>()
+    };
+    let mut right_rows = BTreeMap::new();
+    for row in right.rows {
+        if right_rows.insert(key_of(&row), row).is_some() {
+            return Err(anyhow!("duplicated key in the second query"));
+        }
+    }
+    let mut seen = BTreeSet::new();
+    for row in left.rows {
+        let key = key_of(&row);
+        if !seen.insert(key.clone()) {
+            return Err(anyhow!("duplicated key in the first query"));
+        }
+        match right_rows.remove(&key) {
+            Some(other) if other != row => report.changed.push((row, other)),
+            Some(_) => {}
+            None => report.removed.push(row),
+        }
+    }
+    report.added.extend(right_rows.into_values());
+    Ok(report)
+}
+
+// split arguments by whitespace, double quoted ones could contain spaces
+// and escaped quotes like `\"`
+fn split_args(s: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut arg = String::new();
+        if c == '"' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('\\') if chars.peek() == Some(&'"') => {
+                        arg.push('"');
+                        chars.next();
+                    }
+                    Some('"') => break,
+                    Some(c) => arg.push(c),
+                    None => return Err(anyhow!("unterminated quote")),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                arg.push(c);
+                chars.next();
+            }
+        }
+        args.push(arg);
+    }
+    Ok(args)
+}
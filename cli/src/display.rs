@@ -14,6 +14,7 @@
 
 use std::collections::HashSet;
 use std::fmt::Write;
+use std::time::Duration;
 use unicode_segmentation::UnicodeSegmentation;
 
 use anyhow::Result;
@@ -29,7 +30,8 @@ use indicatif::{HumanBytes, ProgressBar, ProgressState, ProgressStyle};
 
 use crate::{
     ast::format_query,
-    config::{ExpandMode, OutputFormat, Settings},
+    chart,
+    config::{ChartMode, ExpandMode, OutputFormat, Settings},
     helper::CliHelper,
     session::QueryKind,
 };
@@ -37,7 +39,6 @@ use crate::{
 #[async_trait::async_trait]
 pub trait ChunkDisplay {
     async fn display(&mut self) -> Result<()>;
-    fn total_rows(&self) -> usize;
 }
 
 pub struct FormatDisplay<'a> {
@@ -54,6 +55,8 @@ pub struct FormatDisplay<'a> {
     progress: Option<ProgressBar>,
     start: Instant,
     stats: Option<QueryProgress>,
+    // results printed, kept for `.record --output`
+    captured: Option<String>,
 }
 
 impl<'a> FormatDisplay<'a> {
@@ -76,6 +79,25 @@ impl<'a> FormatDisplay<'a> {
             progress: None,
             start,
             stats: None,
+            captured: None,
+        }
+    }
+
+    /// Keep the results printed in table format.
+    pub fn with_capture(mut self) -> Self {
+        self.captured = Some(String::new());
+        self
+    }
+
+    pub fn take_captured(&mut self) -> Option<String> {
+        self.captured.take()
+    }
+
+    fn emit(&mut self, text: &str) {
+        println!("{}", text);
+        if let Some(captured) = self.captured.as_mut() {
+            captured.push_str(text);
+            captured.push('\n');
         }
     }
 }
@@ -84,12 +106,13 @@ impl<'a> FormatDisplay<'a> {
     async fn display_progress(&mut self, pg: &QueryProgress) {
         if self.settings.show_progress {
             let pgo = self.progress.take();
+            let color = &self.settings.progress_color;
             match self.kind {
                 QueryKind::Get | QueryKind::Query => {
-                    self.progress = Some(display_progress(pgo, pg, "read"));
+                    self.progress = Some(display_progress(pgo, pg, "read", color));
                 }
                 QueryKind::Put | QueryKind::Update => {
-                    self.progress = Some(display_progress(pgo, pg, "write"));
+                    self.progress = Some(display_progress(pgo, pg, "write", color));
                 }
                 _ => {}
             }
@@ -114,6 +137,7 @@ impl<'a> FormatDisplay<'a> {
                     self.display_progress(&pg).await;
                     self.stats = Some(pg);
                 }
+                Ok(_) => {}
                 Err(err) => {
                     error = Some(err);
                     break;
@@ -131,46 +155,42 @@ impl<'a> FormatDisplay<'a> {
         }
 
         if self.kind == QueryKind::Explain {
-            print_explain(&rows)?;
+            self.emit(&format_explain(&rows));
             return Ok(());
         }
 
-        match self.settings.expand {
-            ExpandMode::On => {
-                print_expanded(self.schema.clone(), &rows)?;
-            }
-            ExpandMode::Off => {
-                println!(
-                    "{}",
-                    create_table(
-                        self.schema.clone(),
-                        &rows,
-                        self.replace_newline,
-                        self.settings.max_display_rows,
-                        self.settings.max_width,
-                        self.settings.max_col_width
-                    )?
-                );
-            }
-            ExpandMode::Auto => {
-                if rows.len() > 1 {
-                    println!(
-                        "{}",
-                        create_table(
-                            self.schema.clone(),
-                            &rows,
-                            self.replace_newline,
-                            self.settings.max_display_rows,
-                            self.settings.max_width,
-                            self.settings.max_col_width
-                        )?
-                    );
-                } else {
-                    print_expanded(self.schema.clone(), &rows)?;
+        if self.settings.chart != ChartMode::Off {
+            match chart::render(&self.settings.chart, self.schema.clone(), &rows) {
+                Ok(chart) => {
+                    self.emit(&chart);
+                    return Ok(());
                 }
+                // fallback to table
+                Err(err) => eprintln!("cannot render chart: {}", err),
             }
         }
 
+        let expanded = self.settings.output_format == OutputFormat::Vertical
+            || match self.settings.expand {
+                ExpandMode::On => true,
+                ExpandMode::Off => false,
+                ExpandMode::Auto => rows.len() <= 1,
+            };
+        let text = if expanded {
+            format_expanded(self.schema.clone(), &rows)
+        } else {
+            create_table(
+                self.schema.clone(),
+                &rows,
+                self.replace_newline,
+                self.settings.max_display_rows,
+                self.settings.max_width,
+                self.settings.max_col_width,
+            )?
+            .to_string()
+        };
+        self.emit(&text);
+
         Ok(())
     }
 
@@ -188,6 +208,7 @@ impl<'a> FormatDisplay<'a> {
                 Ok(RowWithProgress::Progress(pg)) => {
                     self.stats = Some(pg);
                 }
+                Ok(_) => {}
                 Err(err) => {
                     eprintln!("error: {}", err);
                     break;
@@ -212,6 +233,7 @@ impl<'a> FormatDisplay<'a> {
                 Ok(RowWithProgress::Progress(pg)) => {
                     self.stats = Some(pg);
                 }
+                Ok(_) => {}
                 Err(err) => {
                     eprintln!("error: {}", err);
                     break;
@@ -221,6 +243,47 @@ impl<'a> FormatDisplay<'a> {
         Ok(())
     }
 
+    /// An array of objects, or one object per line for NDJSON.
+    async fn display_json(&mut self, lines: bool) -> Result<()> {
+        let names = self
+            .schema
+            .fields()
+            .iter()
+            .map(|f| f.name.clone())
+            .collect::<Vec<_>>();
+        while let Some(line) = self.data.next().await {
+            match line {
+                Ok(RowWithProgress::Row(row)) => {
+                    let object = json_object(&names, &row)?;
+                    if lines {
+                        println!("{}", object);
+                    } else if self.rows == 0 {
+                        print!("[\n  {}", object);
+                    } else {
+                        print!(",\n  {}", object);
+                    }
+                    self.rows += 1;
+                }
+                Ok(RowWithProgress::Progress(pg)) => {
+                    self.stats = Some(pg);
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    eprintln!("error: {}", err);
+                    break;
+                }
+            }
+        }
+        if !lines {
+            if self.rows == 0 {
+                println!("[]");
+            } else {
+                println!("\n]");
+            }
+        }
+        Ok(())
+    }
+
     async fn display_null(&mut self) -> Result<()> {
         while let Some(line) = self.data.next().await {
             match line {
@@ -231,6 +294,7 @@ impl<'a> FormatDisplay<'a> {
                     self.display_progress(&pg).await;
                     self.stats = Some(pg);
                 }
+                Ok(_) => {}
                 Err(err) => {
                     eprintln!("error: {}", err);
                     break;
@@ -301,7 +365,7 @@ impl<'a> FormatDisplay<'a> {
 impl<'a> ChunkDisplay for FormatDisplay<'a> {
     async fn display(&mut self) -> Result<()> {
         match self.settings.output_format {
-            OutputFormat::Table => {
+            OutputFormat::Table | OutputFormat::Vertical => {
                 self.display_table().await?;
             }
             OutputFormat::CSV => {
@@ -310,6 +374,12 @@ impl<'a> ChunkDisplay for FormatDisplay<'a> {
             OutputFormat::TSV => {
                 self.display_tsv().await?;
             }
+            OutputFormat::JSON => {
+                self.display_json(false).await?;
+            }
+            OutputFormat::NDJSON => {
+                self.display_json(true).await?;
+            }
             OutputFormat::Null => {
                 self.display_null().await?;
             }
@@ -317,10 +387,6 @@ impl<'a> ChunkDisplay for FormatDisplay<'a> {
         self.display_stats().await;
         Ok(())
     }
-
-    fn total_rows(&self) -> usize {
-        self.rows
-    }
 }
 
 fn format_read_progress(progress: &QueryProgress, elapsed: f64) -> String {
@@ -345,10 +411,14 @@ pub fn format_write_progress(progress: &QueryProgress, elapsed: f64) -> String {
     )
 }
 
-fn display_progress(pb: Option<ProgressBar>, current: &QueryProgress, kind: &str) -> ProgressBar {
+fn display_progress(
+    pb: Option<ProgressBar>,
+    current: &QueryProgress,
+    kind: &str,
+    progress_color: &str,
+) -> ProgressBar {
     let pb = pb.unwrap_or_else(|| {
         let pbn = ProgressBar::new(current.total_bytes as u64);
-        let progress_color = "green";
         let template = "{spinner:.${progress_color}} [{elapsed_precise}] {msg} {wide_bar:.${progress_color}/blue} ({eta})".replace("${progress_color}", progress_color);
         pbn.set_style(
             ProgressStyle::with_template(&template)
@@ -358,13 +428,24 @@ fn display_progress(pb: Option<ProgressBar>, current: &QueryProgress, kind: &str
                 })
                 .progress_chars("█▓▒░ "),
         );
+        // keep the spinner and elapsed time moving between updates, which
+        // could be seconds apart for slow queries
+        pbn.enable_steady_tick(Duration::from_millis(100));
         pbn
     });
 
-    pb.set_position(current.read_bytes as u64);
+    // total is unknown until the query is planned, and grows for some sources
     match kind {
-        "read" => pb.set_message(format_read_progress(current, pb.elapsed().as_secs_f64())),
-        "write" => pb.set_message(format_write_progress(current, pb.elapsed().as_secs_f64())),
+        "read" => {
+            pb.set_length(current.total_bytes.max(current.read_bytes) as u64);
+            pb.set_position(current.read_bytes as u64);
+            pb.set_message(format_read_progress(current, pb.elapsed().as_secs_f64()));
+        }
+        "write" => {
+            pb.set_length(current.write_bytes as u64);
+            pb.set_position(current.write_bytes as u64);
+            pb.set_message(format_write_progress(current, pb.elapsed().as_secs_f64()));
+        }
         _ => {}
     }
     pb
@@ -501,7 +582,7 @@ fn create_table(
     let (top_rows, bottom_rows) = if rows_to_render == row_count {
         (row_count, 0usize)
     } else {
-        let top_rows = rows_to_render / 2 + (rows_to_render % 2 != 0) as usize;
+        let top_rows = rows_to_render / 2 + !rows_to_render.is_multiple_of(2) as usize;
         (top_rows, rows_to_render - top_rows)
     };
 
@@ -573,7 +654,7 @@ fn create_table(
                 } else {
                     let mut value = values[*col_index as usize].clone();
                     if value.len() + 3 > widths[idx] {
-                        let element_size = if widths[idx] >= 6 { widths[idx] - 6 } else { 0 };
+                        let element_size = widths[idx].saturating_sub(6);
                         value = String::from_utf8(
                             value
                                 .graphemes(true)
@@ -626,7 +707,7 @@ fn create_table(
                     } else {
                         let mut value = values[*col_index as usize].clone();
                         if value.len() + 3 > widths[idx] {
-                            let element_size = if widths[idx] >= 6 { widths[idx] - 6 } else { 0 };
+                            let element_size = widths[idx].saturating_sub(6);
                             value = String::from_utf8(
                                 value
                                     .graphemes(true)
@@ -658,7 +739,7 @@ fn create_table(
 fn render_head(
     schema: SchemaRef,
     widths: &mut [usize],
-    column_map: &mut Vec<i32>,
+    column_map: &mut [i32],
     header: &mut Vec<Cell>,
     aligns: &mut Vec<CellAlignment>,
 ) {
@@ -687,7 +768,7 @@ fn render_head(
                 let width = widths[idx];
                 let mut field_name = field.name.to_string();
                 let mut field_data_type = field.data_type.to_string();
-                let element_size = if width >= 6 { width - 6 } else { 0 };
+                let element_size = width.saturating_sub(6);
 
                 if field_name.len() + 3 > width {
                     field_name = String::from_utf8(
@@ -729,30 +810,53 @@ fn render_head(
     }
 }
 
-fn print_expanded(schema: SchemaRef, results: &[Row]) -> Result<()> {
+fn format_expanded(schema: SchemaRef, results: &[Row]) -> String {
     let mut head_width = 0;
     for field in schema.fields() {
         if field.name.len() > head_width {
             head_width = field.name.len();
         }
     }
+    let mut out = String::new();
     for (row, result) in results.iter().enumerate() {
-        println!("-[ RECORD {} ]-----------------------------------", row + 1);
+        let _ = writeln!(
+            out,
+            "-[ RECORD {} ]-----------------------------------",
+            row + 1
+        );
         for (idx, field) in schema.fields().iter().enumerate() {
-            println!("{: >head_width$}: {}", field.name, result.values()[idx]);
+            let _ = writeln!(
+                out,
+                "{: >head_width$}: {}",
+                field.name,
+                result.values()[idx]
+            );
+        }
+    }
+    out
+}
+
+/// Keys are written in the order of columns, which is not kept by `serde_json::Map`.
+fn json_object(names: &[String], row: &Row) -> Result<String> {
+    let mut object = String::from("{");
+    for (i, (name, value)) in names.iter().zip(row.values()).enumerate() {
+        if i > 0 {
+            object.push(',');
         }
+        object.push_str(&serde_json::to_string(name)?);
+        object.push(':');
+        object.push_str(&serde_json::to_string(value)?);
     }
-    println!();
-    Ok(())
+    object.push('}');
+    Ok(object)
 }
 
-fn print_explain(results: &[Row]) -> Result<()> {
-    println!("-[ EXPLAIN ]-----------------------------------");
+fn format_explain(results: &[Row]) -> String {
+    let mut out = String::from("-[ EXPLAIN ]-----------------------------------\n");
     for result in results {
-        println!("{}", result.values()[0]);
+        let _ = writeln!(out, "{}", result.values()[0]);
     }
-    println!();
-    Ok(())
+    out
 }
 
 pub fn humanize_count(num: f64) -> String {
@@ -0,0 +1,274 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Generate random data for demo tables with `.gen <table> <rows> <spec>`.
+//!
+//! The spec is a comma separated list of `name:type[:distribution]`:
+//! - types: `int`, `float`, `string[(len)]`, `bool`, `timestamp`, `date`
+//! - distributions: `seq`, `uniform(lo,hi)`, `normal(mean,std)`, `choice(a|b|c)`,
+//!   ranges of `timestamp` and `date` are in days relative to now, values of
+//!   `choice` must be valid for the column type.
+//!
+//! e.g. `id:int:seq, name:string(6), score:float:normal(60,10), ts:timestamp:uniform(-7,0)`
+
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use databend_driver::{quote_ident, quote_qualified_ident};
+use rand::Rng;
+
+#[derive(Debug, PartialEq)]
+enum ColumnKind {
+    Int,
+    Float,
+    String(usize),
+    Bool,
+    Timestamp,
+    Date,
+}
+
+#[derive(Debug, PartialEq)]
+enum Distribution {
+    Default,
+    Seq,
+    Uniform(f64, f64),
+    Normal(f64, f64),
+    Choice(Vec<String>),
+}
+
+#[derive(Debug)]
+pub struct ColumnSpec {
+    name: String,
+    kind: ColumnKind,
+    dist: Distribution,
+}
+
+impl ColumnKind {
+    /// Whether a value of `choice(..)` could be loaded into the column.
+    fn accepts(&self, value: &str) -> bool {
+        match self {
+            ColumnKind::Int => value.parse::<i64>().is_ok(),
+            ColumnKind::Float => value.parse::<f64>().is_ok(),
+            ColumnKind::String(_) => !value.is_empty(),
+            ColumnKind::Bool => value.parse::<bool>().is_ok(),
+            ColumnKind::Timestamp => {
+                NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").is_ok()
+            }
+            ColumnKind::Date => NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok(),
+        }
+    }
+}
+
+impl ColumnSpec {
+    fn data_type(&self) -> &'static str {
+        match self.kind {
+            ColumnKind::Int => "BIGINT",
+            ColumnKind::Float => "DOUBLE",
+            ColumnKind::String(_) => "STRING",
+            ColumnKind::Bool => "BOOLEAN",
+            ColumnKind::Timestamp => "TIMESTAMP",
+            ColumnKind::Date => "DATE",
+        }
+    }
+
+    fn generate<R: Rng>(&self, rng: &mut R, i: usize, now: NaiveDateTime) -> String {
+        if let Distribution::Choice(choices) = &self.dist {
+            return choices[rng.gen_range(0..choices.len())].clone();
+        }
+        match self.kind {
+            ColumnKind::Int => match self.dist {
+                Distribution::Seq => (i + 1).to_string(),
+                _ => (self.sample(rng, 0.0, 1_000_000.0).round() as i64).to_string(),
+            },
+            ColumnKind::Float => format!("{:.6}", self.sample(rng, 0.0, 1.0)),
+            ColumnKind::String(len) => (0..len)
+                .map(|_| rng.sample(rand::distributions::Alphanumeric) as char)
+                .collect(),
+            ColumnKind::Bool => rng.gen_bool(0.5).to_string(),
+            ColumnKind::Timestamp | ColumnKind::Date => {
+                let ts = match self.dist {
+                    Distribution::Seq => now + chrono::Duration::seconds(i as i64),
+                    _ => {
+                        let days = self.sample(rng, -30.0, 0.0);
+                        now + chrono::Duration::seconds((days * 86400.0) as i64)
+                    }
+                };
+                if self.kind == ColumnKind::Date {
+                    ts.format("%Y-%m-%d").to_string()
+                } else {
+                    ts.format("%Y-%m-%d %H:%M:%S").to_string()
+                }
+            }
+        }
+    }
+
+    fn sample<R: Rng>(&self, rng: &mut R, lo: f64, hi: f64) -> f64 {
+        match self.dist {
+            Distribution::Uniform(lo, hi) => rng.gen_range(lo..=hi),
+            Distribution::Normal(mean, std) => {
+                // Box-Muller transform
+                let u1: f64 = 1.0 - rng.gen::<f64>();
+                let u2: f64 = rng.gen();
+                mean + std * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+            }
+            _ => rng.gen_range(lo..=hi),
+        }
+    }
+}
+
+pub fn parse_spec(spec: &str) -> Result<Vec<ColumnSpec>> {
+    let mut columns = Vec::new();
+    for column in split_top_level(spec) {
+        let column = column.trim();
+        let mut parts = column.splitn(3, ':').map(str::trim);
+        let (name, kind) = match (parts.next(), parts.next()) {
+            (Some(name), Some(kind)) if !name.is_empty() => (name, kind),
+            _ => return Err(anyhow!("invalid column spec `{}`", column)),
+        };
+        let kind = match parse_call(kind)? {
+            ("int", None) => ColumnKind::Int,
+            ("float", None) => ColumnKind::Float,
+            ("string", None) => ColumnKind::String(8),
+            ("string", Some(len)) => ColumnKind::String(len.parse()?),
+            ("bool", None) => ColumnKind::Bool,
+            ("timestamp", None) => ColumnKind::Timestamp,
+            ("date", None) => ColumnKind::Date,
+            _ => return Err(anyhow!("unknown column type `{}`", kind)),
+        };
+        let dist = match parts.next() {
+            None => Distribution::Default,
+            Some(dist) => match parse_call(dist)? {
+                ("seq", None) => Distribution::Seq,
+                ("uniform", Some(args)) => {
+                    let (lo, hi) = parse_pair(args)?;
+                    if lo > hi {
+                        return Err(anyhow!("invalid range in `{}`", dist));
+                    }
+                    Distribution::Uniform(lo, hi)
+                }
+                ("normal", Some(args)) => {
+                    let (mean, std) = parse_pair(args)?;
+                    Distribution::Normal(mean, std)
+                }
+                ("choice", Some(args)) => {
+                    let choices = args
+                        .split('|')
+                        .map(|s| s.trim().to_string())
+                        .collect::<Vec<_>>();
+                    for choice in &choices {
+                        if !kind.accepts(choice) {
+                            return Err(anyhow!(
+                                "invalid choice `{}` for column `{}`",
+                                choice,
+                                column
+                            ));
+                        }
+                    }
+                    Distribution::Choice(choices)
+                }
+                _ => return Err(anyhow!("unknown distribution `{}`", dist)),
+            },
+        };
+        let valid = match (&kind, &dist) {
+            (_, Distribution::Default | Distribution::Choice(_)) => true,
+            (ColumnKind::Int | ColumnKind::Timestamp | ColumnKind::Date, Distribution::Seq) => true,
+            (ColumnKind::String(_) | ColumnKind::Bool, _) => false,
+            (_, Distribution::Seq) => false,
+            _ => true,
+        };
+        if !valid {
+            return Err(anyhow!("unsupported distribution for column `{}`", column));
+        }
+        columns.push(ColumnSpec {
+            name: name.to_string(),
+            kind,
+            dist,
+        });
+    }
+    if columns.is_empty() {
+        return Err(anyhow!("no column specified"));
+    }
+    Ok(columns)
+}
+
+pub fn create_table_sql(table: &str, columns: &[ColumnSpec]) -> String {
+    let columns = columns
+        .iter()
+        .map(|c| format!("{} {}", quote_ident(&c.name), c.data_type()))
+        .collect::<Vec<_>>();
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} ({})",
+        quote_qualified_ident(table),
+        columns.join(", ")
+    )
+}
+
+pub fn insert_sql(table: &str) -> String {
+    format!("INSERT INTO {} VALUES", quote_qualified_ident(table))
+}
+
+/// Generate rows as CSV in memory.
+pub fn generate_csv(columns: &[ColumnSpec], rows: usize) -> Result<Vec<u8>> {
+    let mut rng = rand::thread_rng();
+    let now = Utc::now().naive_utc();
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let mut record = Vec::with_capacity(columns.len());
+    for i in 0..rows {
+        record.clear();
+        for column in columns {
+            record.push(column.generate(&mut rng, i, now));
+        }
+        writer.write_record(&record)?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+// split by commas not in parentheses
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !s[start..].trim().is_empty() {
+        parts.push(&s[start..]);
+    }
+    parts
+}
+
+// parse `name` or `name(args)`
+fn parse_call(s: &str) -> Result<(&str, Option<&str>)> {
+    match s.split_once('(') {
+        None => Ok((s.trim(), None)),
+        Some((name, rest)) => match rest.strip_suffix(')') {
+            Some(args) => Ok((name.trim(), Some(args))),
+            None => Err(anyhow!("missing `)` in `{}`", s)),
+        },
+    }
+}
+
+fn parse_pair(args: &str) -> Result<(f64, f64)> {
+    match args.split_once(',') {
+        Some((a, b)) => Ok((a.trim().parse()?, b.trim().parse()?)),
+        None => Err(anyhow!("expected two arguments, got `{}`", args)),
+    }
+}
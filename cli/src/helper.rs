@@ -13,7 +13,6 @@
 // limitations under the License.
 
 use std::borrow::Cow;
-use std::sync::Arc;
 
 use rustyline::completion::Completer;
 use rustyline::completion::FilenameCompleter;
@@ -31,24 +30,25 @@ use rustyline::Result;
 use crate::ast::all_reserved_keywords;
 use crate::ast::tokenize_sql;
 use crate::ast::TokenKind;
+use crate::completion::{word_start, Metadata};
 
 pub struct CliHelper {
     completer: FilenameCompleter,
-    keywords: Arc<Vec<String>>,
+    metadata: Option<Metadata>,
 }
 
 impl CliHelper {
     pub fn new() -> Self {
         Self {
             completer: FilenameCompleter::new(),
-            keywords: Arc::new(Vec::new()),
+            metadata: None,
         }
     }
 
-    pub fn with_keywords(keywords: Arc<Vec<String>>) -> Self {
+    pub fn with_metadata(metadata: Metadata) -> Self {
         Self {
             completer: FilenameCompleter::new(),
-            keywords,
+            metadata: Some(metadata),
         }
     }
 }
@@ -111,18 +111,15 @@ impl Hinter for CliHelper {
     type Hint = String;
 
     fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<Self::Hint> {
-        let last_word = line
-            .split(|p: char| p.is_whitespace() || p == '.')
-            .last()
-            .unwrap_or(line);
-
+        // only names already fetched are hinted while typing
+        let (start, res) = KeyWordCompleter::complete(line, pos, self.metadata.as_ref(), false);
+        let last_word = &line[start..pos];
         if last_word.is_empty() {
             return None;
         }
 
-        let (_, res) = KeyWordCompleter::complete(line, pos, &self.keywords);
         if !res.is_empty() {
-            Some(res[0].replacement[last_word.len()..].to_owned())
+            Some(res[0].replacement.get(last_word.len()..)?.to_owned())
         } else {
             None
         }
@@ -138,7 +135,8 @@ impl Completer for CliHelper {
         pos: usize,
         ctx: &Context<'_>,
     ) -> std::result::Result<(usize, Vec<Pair>), ReadlineError> {
-        let keyword_candidates = KeyWordCompleter::complete(line, pos, self.keywords.as_ref());
+        let keyword_candidates =
+            KeyWordCompleter::complete(line, pos, self.metadata.as_ref(), true);
         if !keyword_candidates.1.is_empty() {
             return Ok(keyword_candidates);
         }
@@ -162,40 +160,36 @@ impl Helper for CliHelper {}
 struct KeyWordCompleter {}
 
 impl KeyWordCompleter {
-    fn complete(s: &str, pos: usize, keywords: &[String]) -> (usize, Vec<Pair>) {
-        let hint = s
-            .split(|p: char| p.is_whitespace() || p == '.')
-            .last()
-            .unwrap_or(s);
-        let all_keywords = all_reserved_keywords();
-
-        let mut results: Vec<Pair> = all_keywords
-            .iter()
-            .filter(|keyword| keyword.starts_with(&hint.to_ascii_lowercase()))
-            .map(|keyword| Pair {
-                display: keyword.to_string(),
-                replacement: keyword.to_string(),
-            })
-            .collect();
-
-        results.extend(
-            keywords
+    fn complete(
+        s: &str,
+        pos: usize,
+        metadata: Option<&Metadata>,
+        fetch: bool,
+    ) -> (usize, Vec<Pair>) {
+        let (start, names) = match metadata {
+            Some(metadata) => metadata.complete(s, pos, fetch),
+            None => (word_start(&s[..pos]), vec![]),
+        };
+        let hint = &s[start..pos];
+
+        // keywords are not expected after `database.` or `table.`
+        let mut results: Vec<Pair> = if s[..start].ends_with('.') {
+            vec![]
+        } else {
+            all_reserved_keywords()
                 .iter()
-                .filter(|keyword| {
-                    keyword
-                        .to_lowercase()
-                        .starts_with(&hint.to_ascii_lowercase())
-                })
+                .filter(|keyword| keyword.starts_with(&hint.to_ascii_lowercase()))
                 .map(|keyword| Pair {
                     display: keyword.to_string(),
                     replacement: keyword.to_string(),
-                }),
-        );
-
-        if pos >= hint.len() {
-            (pos - hint.len(), results)
-        } else {
-            (0, results)
-        }
+                })
+                .collect()
+        };
+
+        results.extend(names.into_iter().map(|name| Pair {
+            display: name.clone(),
+            replacement: name,
+        }));
+        (start, results)
     }
 }
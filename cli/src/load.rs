@@ -0,0 +1,165 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `bendsql load` to load local files into a table in one shot, files are
+//! uploaded to a temp stage in parallel and copied into the table with
+//! `COPY INTO`, the table could be created with the schema of the files.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use databend_driver::client::stage::StageLocation;
+use databend_driver::{
+    builder, describe_local_file, Connection, ConnectionExt, ErrorKind, RowWithProgress,
+};
+use indicatif::HumanBytes;
+use tokio::time::Instant;
+use tokio_stream::StreamExt;
+
+pub struct LoadArgs {
+    pub table: String,
+    /// Local paths, each could be a glob pattern.
+    pub files: Vec<String>,
+    pub create_if_missing: bool,
+}
+
+pub async fn run(
+    conn: Box<dyn Connection>,
+    args: LoadArgs,
+    format_options: BTreeMap<&str, &str>,
+) -> Result<()> {
+    let start = Instant::now();
+    let files = expand(&args.files)?;
+    prepare_table(conn.as_ref(), &args.table, &files, args.create_if_missing).await?;
+
+    let now = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default();
+    let stage = format!("@~/bendsql_load_{}_{}/", std::process::id(), now);
+    let result = async {
+        let (count, bytes) = upload(conn.as_ref(), &files, &stage).await?;
+        eprintln!("uploaded {} files, {}", count, HumanBytes(bytes));
+        copy(conn.as_ref(), &args.table, &stage, &format_options).await
+    }
+    .await;
+    // the temp files are removed even if the load failed
+    if let Err(e) = conn.remove_stage_files(&stage).await {
+        eprintln!("warning: failed to remove {}: {}", stage, e);
+    }
+    let rows = result?;
+    eprintln!(
+        "loaded {} rows from {} files into {} in {:.3}s",
+        rows,
+        files.len(),
+        args.table,
+        start.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
+
+fn expand(patterns: &[String]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for pattern in patterns {
+        let before = files.len();
+        for entry in glob::glob(pattern)? {
+            files.push(entry?);
+        }
+        if files.len() == before {
+            return Err(anyhow!("no file matches {}", pattern));
+        }
+    }
+    Ok(files)
+}
+
+/// Create the table with the schema of the first file if it does not exist,
+/// only for Parquet, CSV with header and NDJSON files.
+async fn prepare_table(
+    conn: &dyn Connection,
+    table: &str,
+    files: &[PathBuf],
+    create_if_missing: bool,
+) -> Result<()> {
+    let sql = builder::select(&[]).from(table).limit(0).build()?;
+    match conn.query_iter_ext(&sql).await {
+        Ok(_) => Ok(()),
+        Err(e) if create_if_missing && e.kind() == ErrorKind::UnknownTable => {
+            let info = describe_local_file(&files[0]).await?;
+            conn.create_table(table, &info.schema).await?;
+            eprintln!(
+                "created table {} with {} columns",
+                table,
+                info.schema.fields().len()
+            );
+            Ok(())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Upload the expanded files by their names, which fails if two files have
+/// the same name instead of overwriting one with the other.
+async fn upload<C>(conn: &C, files: &[PathBuf], stage: &str) -> Result<(u64, u64)>
+where
+    C: Connection + ?Sized + 'static,
+{
+    let (mut count, mut bytes) = (0, 0);
+    let (_, mut rows) = conn.put_local_files(files, stage).await?;
+    while let Some(row) = rows.next().await {
+        if let RowWithProgress::Row(row) = row? {
+            let (file, status, size): (String, String, u64) =
+                row.try_into().map_err(|e: String| anyhow!(e))?;
+            if status != "SUCCESS" {
+                return Err(anyhow!("failed to upload {}: {}", file, status));
+            }
+            count += 1;
+            bytes += size;
+        }
+    }
+    Ok((count, bytes))
+}
+
+/// Copy all files in the stage, and print rows loaded of each file.
+async fn copy(
+    conn: &dyn Connection,
+    table: &str,
+    stage: &str,
+    format_options: &BTreeMap<&str, &str>,
+) -> Result<u64> {
+    let location = StageLocation::try_from(stage)?;
+    let sql = builder::copy_into_table(table, &location, format_options, &BTreeMap::new())?;
+    let (_, mut rows) = conn.query_iter_ext(&sql).await?;
+    let mut total = 0;
+    while let Some(row) = rows.next().await {
+        if let RowWithProgress::Row(row) = row? {
+            let (file, loaded, errors, first_error, _): (
+                String,
+                u64,
+                u64,
+                Option<String>,
+                Option<u64>,
+            ) = row.try_into().map_err(|e: String| anyhow!(e))?;
+            let file = file.strip_prefix(&location.path).unwrap_or(&file);
+            total += loaded;
+            println!("{:>10} rows  {}", loaded, file);
+            if errors > 0 {
+                eprintln!(
+                    "{} errors in {}: {}",
+                    errors,
+                    file,
+                    first_error.unwrap_or_default()
+                );
+            }
+        }
+    }
+    Ok(total)
+}
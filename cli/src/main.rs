@@ -15,10 +15,19 @@
 #![allow(clippy::upper_case_acronyms)]
 
 mod ast;
+mod backslash;
+mod bench;
+mod chart;
+mod completion;
 mod config;
+mod diff;
 mod display;
+mod gen;
 mod helper;
+mod load;
+mod record;
 mod session;
+mod stage;
 
 use std::{
     collections::BTreeMap,
@@ -26,8 +35,8 @@ use std::{
 };
 
 use anyhow::{anyhow, Result};
-use clap::{CommandFactory, Parser, ValueEnum};
-use config::{Config, OutputFormat, Settings};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use config::{Config, ConfigAction, OutputFormat, Settings};
 use once_cell::sync::Lazy;
 
 static VERSION: Lazy<String> = Lazy::new(|| {
@@ -94,6 +103,39 @@ impl InputFormat {
         }
         options
     }
+
+    /// Add the options of `--load` to the format options, checking those
+    /// only for delimited text.
+    fn add_load_options(&self, load: LoadOptions, opts: &mut Vec<(String, String)>) -> Result<()> {
+        let delimited = matches!(self, InputFormat::CSV | InputFormat::TSV);
+        if let Some(delimiter) = load.delimiter {
+            if !delimited {
+                return Err(anyhow!("delimiter is only for CSV and TSV"));
+            }
+            opts.push(("field_delimiter".to_string(), delimiter));
+        }
+        if let Some(lines) = load.skip_header {
+            if !delimited {
+                return Err(anyhow!("skip_header is only for CSV and TSV"));
+            }
+            opts.push(("skip_header".to_string(), lines.to_string()));
+        }
+        if let Some(compression) = load.compression {
+            if *self == InputFormat::Parquet {
+                return Err(anyhow!("compression is not for Parquet"));
+            }
+            opts.push(("compression".to_string(), compression.to_uppercase()));
+        }
+        Ok(())
+    }
+}
+
+/// Options of loading a local file with `--load` or `\copy`.
+#[derive(Debug, Default)]
+pub struct LoadOptions {
+    pub delimiter: Option<String>,
+    pub compression: Option<String>,
+    pub skip_header: Option<u64>,
 }
 
 #[derive(Debug, Parser, PartialEq)]
@@ -128,13 +170,16 @@ struct Args {
     #[clap(long, value_parser = parse_key_val::<String, String>, help = "Settings")]
     set: Vec<(String, String)>,
 
+    #[clap(long, help = "Use the connection of the profile in config")]
+    profile: Option<String>,
+
     #[clap(long, env = "BENDSQL_DSN", help = "Data source name")]
     dsn: Option<String>,
 
     #[clap(short = 'n', long, help = "Force non-interactive mode")]
     non_interactive: bool,
 
-    #[clap(long, require_equals = true, help = "Query to execute")]
+    #[clap(short = 'e', long, help = "Query to execute")]
     query: Option<String>,
 
     #[clap(short = 'd', long, help = "Data to load, @file or @- for stdin")]
@@ -146,6 +191,33 @@ struct Args {
     #[clap(long, value_parser = parse_key_val::<String, String>, help = "Data format options")]
     format_opt: Vec<(String, String)>,
 
+    #[clap(long, requires = "file", help = "Table to load the file into")]
+    load: Option<String>,
+
+    #[clap(long, requires = "load", help = "Local file to load, - for stdin")]
+    file: Option<String>,
+
+    #[clap(
+        long,
+        requires = "load",
+        help = "Field delimiter of CSV and TSV to load"
+    )]
+    delimiter: Option<String>,
+
+    #[clap(
+        long,
+        requires = "load",
+        help = "Compression of the file to load, like gzip or zstd"
+    )]
+    compression: Option<String>,
+
+    #[clap(
+        long,
+        requires = "load",
+        help = "Lines to skip at the beginning of CSV and TSV to load"
+    )]
+    skip_header: Option<u64>,
+
     #[clap(short = 'o', long, help = "Output format")]
     output: Option<OutputFormat>,
 
@@ -166,6 +238,76 @@ struct Args {
         help = "Only show execution time without results, will implicitly set output format to `null`."
     )]
     time: bool,
+
+    #[clap(
+        long,
+        help = "Marker file to record executed statements when sourcing a script, reconnect on connection lost and resume from the failed statement on rerun."
+    )]
+    resume_from: Option<std::path::PathBuf>,
+
+    #[clap(
+        long,
+        requires = "resume_from",
+        help = "Also retry writes and DDL after reconnecting with --resume-from, only for idempotent scripts, reads are always retried."
+    )]
+    retry_writes: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand, PartialEq)]
+enum Command {
+    /// Run a query repeatedly and report latency percentiles and throughput
+    Bench {
+        #[clap(long, default_value = "100", help = "Number of times to run the query")]
+        count: usize,
+
+        #[clap(long, default_value = "1", help = "Number of concurrent connections")]
+        concurrency: usize,
+
+        #[clap(help = "Query to run")]
+        query: String,
+    },
+    /// Inspect and modify the config file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Load local files into a table, uploaded to a temp stage in parallel
+    Load {
+        #[clap(long, help = "Table to load the files into")]
+        table: String,
+
+        #[clap(long, required = true, num_args = 1.., help = "Local files, could be glob patterns")]
+        file: Vec<String>,
+
+        #[clap(long, value_enum, default_value = "csv", help = "Format of the files")]
+        format: InputFormat,
+
+        #[clap(
+            long,
+            help = "Create the table with the schema of the first file if not exists"
+        )]
+        create_if_missing: bool,
+
+        #[clap(long, help = "Number of files uploaded at the same time")]
+        concurrency: Option<usize>,
+
+        #[clap(long, help = "Field delimiter of CSV and TSV")]
+        delimiter: Option<String>,
+
+        #[clap(long, help = "Compression of the files, like gzip or zstd")]
+        compression: Option<String>,
+
+        #[clap(long, help = "Lines to skip at the beginning of CSV and TSV")]
+        skip_header: Option<u64>,
+    },
+    /// List, upload, download and remove files in stages
+    Stage {
+        #[command(subcommand)]
+        action: stage::StageAction,
+    },
 }
 
 /// Parse a single key-value pair
@@ -238,6 +380,12 @@ pub async fn main() -> Result<()> {
         cmd.print_help()?;
         return Ok(());
     }
+    if let Some(Command::Config { action }) = args.command {
+        return config::run_config_action(action);
+    }
+    if let Some(ref profile) = args.profile {
+        config.use_profile(profile)?;
+    }
     let dsn = match args.dsn {
         Some(dsn) => {
             if args.host.is_some() {
@@ -295,9 +443,68 @@ pub async fn main() -> Result<()> {
             conn_args.get_dsn()?
         }
     };
+    if let Some(command) = args.command {
+        return match command {
+            Command::Bench {
+                count,
+                concurrency,
+                query,
+            } => {
+                let client = databend_driver::Client::new(dsn);
+                let opts = bench::BenchOptions { count, concurrency };
+                let report = bench::run(&client, &query, &opts).await?;
+                println!("{}", report);
+                Ok(())
+            }
+            Command::Load {
+                table,
+                file,
+                format,
+                create_if_missing,
+                concurrency,
+                delimiter,
+                compression,
+                skip_header,
+            } => {
+                let load = LoadOptions {
+                    delimiter,
+                    compression,
+                    skip_header,
+                };
+                let mut format_opt = Vec::new();
+                format.add_load_options(load, &mut format_opt)?;
+                let mut dsn = url::Url::parse(&dsn)?;
+                if let Some(concurrency) = concurrency {
+                    dsn.query_pairs_mut()
+                        .append_pair("upload_concurrency", &concurrency.to_string());
+                }
+                let client = databend_driver::Client::new(dsn.to_string());
+                let conn = client.get_conn().await?;
+                let args = load::LoadArgs {
+                    table,
+                    files: file,
+                    create_if_missing,
+                };
+                load::run(conn, args, format.get_options(&format_opt)).await
+            }
+            Command::Stage { action } => {
+                let mut dsn = url::Url::parse(&dsn)?;
+                if let Some(concurrency) = action.concurrency() {
+                    dsn.query_pairs_mut()
+                        .append_pair("upload_concurrency", &concurrency.to_string());
+                }
+                let client = databend_driver::Client::new(dsn.to_string());
+                let conn = client.get_conn().await?;
+                stage::run(conn, action).await
+            }
+            Command::Config { .. } => unreachable!(),
+        };
+    }
+
     let mut settings = Settings::default();
     let is_terminal = stdin().is_terminal();
-    let is_repl = is_terminal && !args.non_interactive && args.query.is_none();
+    let is_repl =
+        is_terminal && !args.non_interactive && args.query.is_none() && args.load.is_none();
     if is_repl {
         settings.display_pretty_sql = true;
         settings.show_progress = true;
@@ -323,13 +530,37 @@ pub async fn main() -> Result<()> {
         settings.output_format = OutputFormat::Null;
     }
 
+    // checked before connecting
+    let mut format_opt = args.format_opt;
+    if args.load.is_some() {
+        let load = LoadOptions {
+            delimiter: args.delimiter,
+            compression: args.compression,
+            skip_header: args.skip_header,
+        };
+        args.format.add_load_options(load, &mut format_opt)?;
+    }
+
     let mut session = session::Session::try_new(dsn, settings, is_repl).await?;
+    if let Some(marker) = args.resume_from {
+        if is_repl {
+            eprintln!("warning: --resume-from is ignored in interactive mode");
+        } else {
+            session.resume_from(marker, args.retry_writes)?;
+        }
+    }
 
     if is_repl {
         session.handle_repl().await;
         return Ok(());
     }
 
+    if let (Some(table), Some(file)) = (args.load, args.file) {
+        let options = args.format.get_options(&format_opt);
+        session.load_file(&table, &file, options).await?;
+        return Ok(());
+    }
+
     match args.query {
         None => {
             if args.non_interactive {
@@ -342,7 +573,7 @@ pub async fn main() -> Result<()> {
                 session.handle_reader(std::io::Cursor::new(query)).await?;
             }
             Some(data) => {
-                let options = args.format.get_options(&args.format_opt);
+                let options = args.format.get_options(&format_opt);
                 if data.starts_with('@') {
                     match data.strip_prefix('@') {
                         Some("-") => session.stream_load_stdin(&query, options).await?,
@@ -0,0 +1,81 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Record statements of a REPL session with `.record <file> [--output]` until
+//! `.stop`, outputs are kept as comments so that the file could be replayed
+//! with `.source <file>` or `bendsql < file`.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+
+pub struct Recorder {
+    path: PathBuf,
+    file: File,
+    with_output: bool,
+    statements: usize,
+}
+
+impl Recorder {
+    pub fn create(args: &str) -> Result<Self> {
+        let usage = || anyhow!("Usage: .record <file> [--output]");
+        let mut path = None;
+        let mut with_output = false;
+        for arg in args.split_whitespace() {
+            match arg {
+                "--output" => with_output = true,
+                _ if path.is_none() => path = Some(PathBuf::from(arg)),
+                _ => return Err(usage()),
+            }
+        }
+        let path = path.ok_or_else(usage)?;
+        let file = File::create(&path)?;
+        Ok(Self {
+            path,
+            file,
+            with_output,
+            statements: 0,
+        })
+    }
+
+    pub fn with_output(&self) -> bool {
+        self.with_output
+    }
+
+    /// Written at once, so that the statements are kept if bendsql exits
+    /// without `.stop`.
+    pub fn record(&mut self, query: &str, output: Option<&str>) -> Result<()> {
+        let mut text = format!("{};\n", query.trim_end_matches(';'));
+        if let Some(output) = output {
+            for line in output.trim_end().lines() {
+                if line.is_empty() {
+                    text.push_str("--\n");
+                } else {
+                    text.push_str(&format!("-- {}\n", line));
+                }
+            }
+        }
+        text.push('\n');
+        self.file.write_all(text.as_bytes())?;
+        self.file.flush()?;
+        self.statements += 1;
+        Ok(())
+    }
+
+    pub fn finish(self) -> (PathBuf, usize) {
+        (self.path, self.statements)
+    }
+}
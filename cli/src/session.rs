@@ -13,13 +13,16 @@
 // limitations under the License.
 
 use std::collections::BTreeMap;
-use std::io::BufRead;
-use std::path::Path;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::anyhow;
 use anyhow::Result;
-use databend_driver::{Client, Connection};
+use databend_driver::{
+    builder, quote_ident, split_statements, Client, Connection, ConnectionExt, Error,
+};
 use rustyline::config::Builder;
 use rustyline::error::ReadlineError;
 use rustyline::history::DefaultHistory;
@@ -27,15 +30,20 @@ use rustyline::{CompletionType, Editor};
 use tokio::fs::{remove_file, File};
 use tokio::io::AsyncWriteExt;
 use tokio::time::Instant;
-use tokio_stream::StreamExt;
 
 use crate::ast::{TokenKind, Tokenizer};
-use crate::config::Settings;
+use crate::backslash;
+use crate::bench;
+use crate::completion::Metadata;
+use crate::config::{ExpandMode, OutputFormat, Settings};
+use crate::diff;
 use crate::display::{format_write_progress, ChunkDisplay, FormatDisplay};
+use crate::gen;
 use crate::helper::CliHelper;
+use crate::record::Recorder;
 use crate::VERSION;
 
-static PROMPT_SQL: &str = "select name from system.tables union all select name from system.columns union all select name from system.databases union all select name from system.functions";
+const RECONNECT_ATTEMPTS: u32 = 5;
 
 pub struct Session {
     client: Client,
@@ -44,9 +52,36 @@ pub struct Session {
 
     settings: Settings,
     query: String,
-    in_comment_block: bool,
 
-    keywords: Arc<Vec<String>>,
+    metadata: Metadata,
+
+    resume: Option<ResumeState>,
+    // `USE` and `SET` statements executed, replayed on reconnect
+    session_statements: Vec<String>,
+
+    recorder: Option<Recorder>,
+
+    // id of the query being displayed, to kill it on Ctrl-C
+    running_query: Option<String>,
+}
+
+/// Progress of sourcing a script, recorded in the marker file
+/// as the number of statements executed.
+struct ResumeState {
+    marker: PathBuf,
+    skip: usize,
+    executed: usize,
+    // retry writes and DDL after reconnecting, not only reads
+    retry_writes: bool,
+}
+
+/// How a query from REPL ended.
+enum Interruptible {
+    Done,
+    /// The query asked to exit the REPL.
+    Exit,
+    /// Cancelled with Ctrl-C, the rest of the line should be skipped.
+    Interrupted,
 }
 
 impl Session {
@@ -54,29 +89,34 @@ impl Session {
         let client = Client::new(dsn);
         let conn = client.get_conn().await?;
         let info = conn.info().await;
-        let mut keywords = Vec::with_capacity(1024);
+        let metadata = Metadata::new(conn.clone(), info.database.as_deref().unwrap_or("default"));
         if is_repl {
             println!("Welcome to BendSQL {}.", VERSION.as_str());
             println!(
                 "Connecting to {}:{} as user {}.",
                 info.host, info.port, info.user
             );
-            let version = conn.version().await?;
-            println!("Connected to {}", version);
-            println!();
-
-            let rows = conn.query_iter(PROMPT_SQL).await;
-            match rows {
-                Ok(mut rows) => {
-                    while let Some(row) = rows.next().await {
-                        let name: (String,) = row.unwrap().try_into().unwrap();
-                        keywords.push(name.0);
+            // the banner is informational, failures should not stop the session
+            match conn.server_info().await {
+                Ok(server) => {
+                    println!("Connected to {}", server.version);
+                    let mut location = format!("Database {}", server.database);
+                    if let Some(ref warehouse) = server.warehouse {
+                        location.push_str(&format!(" on warehouse {}", warehouse));
+                        if let Some(ref state) = server.warehouse_state {
+                            location.push_str(&format!(" ({})", state));
+                        }
+                    }
+                    match conn.ping().await {
+                        Ok(latency) => {
+                            println!("{}, latency {}ms.", location, latency.as_millis())
+                        }
+                        Err(_) => println!("{}.", location),
                     }
                 }
-                Err(e) => {
-                    eprintln!("loading auto complete keywords failed: {}", e);
-                }
+                Err(e) => eprintln!("Failed to get server info: {}", e),
             }
+            println!();
         }
 
         Ok(Self {
@@ -85,11 +125,40 @@ impl Session {
             is_repl,
             settings,
             query: String::new(),
-            in_comment_block: false,
-            keywords: Arc::new(keywords),
+            metadata,
+            resume: None,
+            session_statements: Vec::new(),
+            recorder: None,
+            running_query: None,
         })
     }
 
+    pub fn resume_from(&mut self, marker: PathBuf, retry_writes: bool) -> Result<()> {
+        let skip = if marker.exists() {
+            let content = std::fs::read_to_string(&marker)?;
+            content
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("invalid marker file {}", marker.display()))?
+        } else {
+            0
+        };
+        if skip > 0 {
+            eprintln!(
+                "resuming from statement {} recorded in {}",
+                skip + 1,
+                marker.display()
+            );
+        }
+        self.resume = Some(ResumeState {
+            marker,
+            skip,
+            executed: 0,
+            retry_writes,
+        });
+        Ok(())
+    }
+
     async fn prompt(&self) -> String {
         if !self.query.is_empty() {
             "> ".to_owned()
@@ -117,28 +186,47 @@ impl Session {
         let config = Builder::new()
             .completion_prompt_limit(5)
             .completion_type(CompletionType::Circular)
-            .build();
+            .max_history_size(HISTORY_SIZE)
+            .and_then(|b| b.history_ignore_dups(true))
+            .map(|b| b.history_ignore_space(true).build())
+            .unwrap_or_default();
         let mut rl = Editor::<CliHelper, DefaultHistory>::with_config(config).unwrap();
 
-        rl.set_helper(Some(CliHelper::with_keywords(self.keywords.clone())));
+        rl.set_helper(Some(CliHelper::with_metadata(self.metadata.clone())));
         rl.load_history(&get_history_path()).ok();
 
         'F: loop {
-            match rl.readline(&self.prompt().await) {
+            let prompt = self.prompt().await;
+            // lines of an unfinished statement are prompted with `>` aligned to the prompt
+            let prompt = if self.query.trim().is_empty() {
+                prompt
+            } else {
+                format!(
+                    "{:>width$} ",
+                    ">",
+                    width = prompt.trim_end().chars().count()
+                )
+            };
+            match rl.readline(&prompt) {
                 Ok(line) => {
                     let queries = self.append_query(&line);
                     for query in queries {
                         let _ = rl.add_history_entry(&query);
-                        match self.handle_query(true, &query).await {
-                            Ok(true) => {
+                        match self.handle_interruptible(&query).await {
+                            Ok(Interruptible::Exit) => {
                                 break 'F;
                             }
-                            Ok(false) => {}
+                            Ok(Interruptible::Done) => self.track_session_statement(&query),
+                            Ok(Interruptible::Interrupted) => {
+                                // skip the rest of the queries in the line
+                                self.query.clear();
+                                break;
+                            }
                             Err(e) => {
                                 if e.to_string().contains("Unauthenticated") {
                                     if let Err(e) = self.reconnect().await {
                                         eprintln!("reconnect error: {}", e);
-                                    } else if let Err(e) = self.handle_query(true, &query).await {
+                                    } else if let Err(e) = self.handle_interruptible(&query).await {
                                         eprintln!("error: {}", e);
                                     }
                                 } else {
@@ -167,6 +255,58 @@ impl Session {
         }
         println!("Bye~");
         let _ = rl.save_history(&get_history_path());
+        let _ = self.conn.close().await;
+    }
+
+    /// Run a query from REPL, Ctrl-C kills the query on the server
+    /// instead of exiting.
+    async fn handle_interruptible(&mut self, query: &str) -> Result<Interruptible> {
+        self.running_query = None;
+        tokio::select! {
+            res = self.handle_query(true, query) => {
+                self.running_query = None;
+                if res.is_ok() {
+                    self.refresh_metadata(query).await;
+                }
+                return res.map(|exit| if exit { Interruptible::Exit } else { Interruptible::Done });
+            }
+            _ = tokio::signal::ctrl_c() => {}
+        }
+        match self.running_query.take() {
+            Some(query_id) => match self.conn.kill_query(&query_id).await {
+                Ok(_) => eprintln!("^C query {} killed", query_id),
+                Err(e) => eprintln!("^C failed to kill query {}: {}", query_id, e),
+            },
+            None => eprintln!("^C cancelled"),
+        }
+        eprintln!();
+        Ok(Interruptible::Interrupted)
+    }
+
+    /// Keep names for completion in sync with the current database
+    /// and the schema.
+    async fn refresh_metadata(&mut self, query: &str) {
+        let mut tz = Tokenizer::new(query);
+        match tz.next() {
+            Some(Ok(t)) if t.kind == TokenKind::USE => {
+                let info = self.conn.info().await;
+                self.metadata
+                    .set_database(info.database.as_deref().unwrap_or("default"));
+            }
+            Some(Ok(t))
+                if matches!(
+                    t.kind,
+                    TokenKind::CREATE
+                        | TokenKind::DROP
+                        | TokenKind::ALTER
+                        | TokenKind::RENAME
+                        | TokenKind::UNDROP
+                ) =>
+            {
+                self.metadata.invalidate();
+            }
+            _ => {}
+        }
     }
 
     pub async fn handle_reader<R: BufRead>(&mut self, r: R) -> Result<()> {
@@ -175,7 +315,7 @@ impl Session {
         while let Some(Ok(line)) = lines.next() {
             let queries = self.append_query(&line);
             for query in queries {
-                self.handle_query(false, &query).await?;
+                self.handle_script_query(&query).await?;
             }
         }
 
@@ -183,104 +323,260 @@ impl Session {
         let query = self.query.trim().to_owned();
         if !query.is_empty() {
             self.query.clear();
-            self.handle_query(false, &query).await?;
+            self.handle_script_query(&query).await?;
+        }
+        if let Some(resume) = self.resume.take() {
+            if resume.marker.exists() {
+                std::fs::remove_file(&resume.marker)?;
+            }
         }
+        self.conn.close().await?;
         if self.settings.time {
             println!("{:.3}", start.elapsed().as_secs_f64());
         }
         Ok(())
     }
 
-    pub fn append_query(&mut self, line: &str) -> Vec<String> {
-        let line = line.trim();
-        if line.is_empty() {
-            return vec![];
+    async fn handle_script_query(&mut self, query: &str) -> Result<()> {
+        let (seq, retry_writes) = match self.resume.as_mut() {
+            None => {
+                self.handle_query(false, query).await?;
+                self.track_session_statement(query);
+                return Ok(());
+            }
+            Some(resume) => {
+                resume.executed += 1;
+                if resume.executed <= resume.skip {
+                    // statements already executed are skipped, except those
+                    // restoring the session state for the following ones
+                    if is_session_statement(query) {
+                        self.handle_query(false, query).await?;
+                        self.track_session_statement(query);
+                    }
+                    return Ok(());
+                }
+                (resume.executed, resume.retry_writes)
+            }
+        };
+
+        // writes may have been applied before the connection is lost,
+        // so only reads are retried unless the script is idempotent
+        let retryable = retry_writes || is_read(query) || is_session_statement(query);
+        let mut attempt = 0;
+        loop {
+            match self.handle_query(false, query).await {
+                Ok(_) => break,
+                Err(e) if retryable && attempt < RECONNECT_ATTEMPTS && is_connection_error(&e) => {
+                    attempt += 1;
+                    let delay = Duration::from_secs(1 << attempt);
+                    eprintln!(
+                        "warning: connection lost at statement {}: {}, reconnecting in {}s ({}/{})",
+                        seq,
+                        e,
+                        delay.as_secs(),
+                        attempt,
+                        RECONNECT_ATTEMPTS
+                    );
+                    tokio::time::sleep(delay).await;
+                    if let Err(e) = self.reconnect().await {
+                        eprintln!("reconnect error: {}", e);
+                    }
+                }
+                Err(e) => {
+                    return Err(e.context(format!(
+                        "statement {} failed, rerun with the same --resume-from to continue",
+                        seq
+                    )));
+                }
+            }
         }
+        self.track_session_statement(query);
+        if let Some(resume) = &self.resume {
+            std::fs::write(&resume.marker, seq.to_string())?;
+        }
+        Ok(())
+    }
 
-        if self.query.is_empty()
-            && (line.starts_with('.')
-                || line == "exit"
-                || line == "quit"
-                || line.to_uppercase().starts_with("PUT"))
-        {
-            return vec![line.to_owned()];
+    fn track_session_statement(&mut self, query: &str) {
+        if is_session_statement(query) {
+            self.session_statements.push(query.to_string());
+        }
+    }
+
+    pub fn append_query(&mut self, line: &str) -> Vec<String> {
+        let trimmed = line.trim();
+        if self.query.is_empty() {
+            if trimmed.is_empty() {
+                return vec![];
+            }
+            if trimmed.starts_with('.')
+                || trimmed.starts_with('\\')
+                || trimmed == "exit"
+                || trimmed == "quit"
+                || trimmed.to_uppercase().starts_with("PUT")
+            {
+                return vec![trimmed.to_owned()];
+            }
         }
 
         if !self.settings.multi_line {
-            if line.starts_with("--") {
+            if trimmed.starts_with("--") {
                 return vec![];
             } else {
-                return vec![line.to_owned()];
+                return vec![trimmed.to_owned()];
             }
         }
 
-        self.query.push(' ');
-
-        let mut queries = Vec::new();
-        let mut tokenizer = Tokenizer::new(line);
-        let mut in_comment = false;
-        let mut start = 0;
-        let mut comment_block_start = 0;
-
-        while let Some(Ok(token)) = tokenizer.next() {
-            match token.kind {
-                TokenKind::SemiColon => {
-                    if in_comment || self.in_comment_block {
-                        continue;
-                    } else {
-                        let mut sql = self.query.trim().to_owned();
-                        if sql.is_empty() {
-                            continue;
-                        }
-                        sql.push(';');
-
-                        queries.push(sql);
-                        self.query.clear();
-                    }
-                }
-                TokenKind::Comment => {
-                    in_comment = true;
-                }
-                TokenKind::EOI => {
-                    in_comment = false;
-                }
-                TokenKind::Newline => {
-                    in_comment = false;
-                    self.query.push(' ');
-                }
-                TokenKind::CommentBlockStart => {
-                    if !self.in_comment_block {
-                        comment_block_start = token.span.start;
-                    }
-                    self.in_comment_block = true;
-                }
-                TokenKind::CommentBlockEnd => {
-                    self.in_comment_block = false;
-                    self.query
-                        .push_str(&line[comment_block_start..token.span.end]);
-                }
-                _ => {
-                    if !in_comment && !self.in_comment_block {
-                        self.query.push_str(&line[start..token.span.end]);
-                    }
+        // lines are kept as is, such as those of `$$` bodies, and `\G` ends
+        // the statement like `;` and displays it vertically
+        let vertical = match line.trim_end().strip_suffix("\\G") {
+            Some(line) => {
+                let query = format!("{}{};", self.query, line);
+                let terminated = matches!(
+                    split_statements(&query),
+                    Ok(statements) if statements
+                        .last()
+                        .is_some_and(|s| query[s.span.end..].trim() == ";")
+                );
+                if terminated {
+                    self.query = query;
                 }
+                terminated
             }
-            start = token.span.end;
+            None => false,
+        };
+        if !vertical {
+            self.query.push_str(line);
         }
+        self.query.push('\n');
 
-        if self.in_comment_block {
-            self.query.push_str(&line[comment_block_start..]);
+        let statements = match split_statements(&self.query) {
+            Ok(statements) => statements,
+            // unterminated quote, comment or `$$` body, wait for more lines
+            Err(_) => return vec![],
+        };
+        let mut queries = Vec::new();
+        let mut consumed = 0;
+        for statement in statements {
+            let rest = &self.query[statement.span.end..];
+            if !rest.trim_start().starts_with(';') {
+                break;
+            }
+            queries.push(format!("{};", strip_leading_comments(statement.text)));
+            consumed = statement.span.end + rest.find(';').unwrap_or_default() + 1;
+        }
+        self.query.drain(..consumed);
+        if matches!(split_statements(&self.query), Ok(s) if s.is_empty()) {
+            self.query.clear();
+        }
+        if vertical {
+            if let Some(last) = queries.last_mut() {
+                last.pop();
+                last.push_str("\\G");
+            }
         }
         queries
     }
 
     pub async fn handle_query(&mut self, is_repl: bool, query: &str) -> Result<bool> {
+        let (query, vertical) = match query.strip_suffix("\\G") {
+            Some(query) => (query, true),
+            None => (query, false),
+        };
         let query = query.trim_end_matches(';').trim();
         if is_repl && (query == "exit" || query == "quit") {
             return Ok(true);
         }
 
+        let translated;
+        let query = if is_repl && query.starts_with('\\') {
+            match self.handle_backslash(query).await? {
+                Some(sql) => {
+                    translated = sql;
+                    translated.as_str()
+                }
+                None => return Ok(false),
+            }
+        } else {
+            query
+        };
+
         if is_repl && query.starts_with('.') {
+            if let Some(args) = query.strip_prefix(".bench ") {
+                let (opts, sql) = bench::BenchOptions::parse(args)?;
+                let report = bench::run(&self.client, &sql, &opts).await?;
+                println!("{}", report);
+                println!();
+                return Ok(false);
+            }
+            if query == ".diagnostics" {
+                println!("bendsql version: {}", VERSION.as_str());
+                println!("{}", self.client.diagnostics()?);
+                println!();
+                return Ok(false);
+            }
+            if query == ".processlist" {
+                let sql = builder::select(&[]).from("system.processes").build()?;
+                let start = Instant::now();
+                let (schema, data) = self.conn.query_iter_ext(&sql).await?;
+                let mut displayer =
+                    FormatDisplay::new(&self.settings, &sql, false, start, Arc::new(schema), data);
+                displayer.display().await?;
+                return Ok(false);
+            }
+            if let Some(query_id) = query.strip_prefix(".kill ") {
+                let query_id = query_id.trim();
+                if confirm(&format!("Kill query {}?", query_id))? {
+                    self.conn.kill_query(query_id).await?;
+                    eprintln!("query {} killed", query_id);
+                }
+                eprintln!();
+                return Ok(false);
+            }
+            if let Some(args) = query.strip_prefix(".diff ") {
+                let args = diff::DiffArgs::parse(args)?;
+                let report = diff::run(self.conn.as_ref(), &args).await?;
+                if report.is_empty() {
+                    eprintln!("no difference");
+                } else {
+                    report.print();
+                }
+                eprintln!();
+                return Ok(false);
+            }
+            if let Some(args) = query.strip_prefix(".gen ") {
+                self.handle_gen(args).await?;
+                return Ok(false);
+            }
+            if let Some(args) = query.strip_prefix(".record ") {
+                let recorder = Recorder::create(args)?;
+                if let Some(previous) = self.recorder.replace(recorder) {
+                    let (path, n) = previous.finish();
+                    eprintln!("recorded {} statements to {}", n, path.display());
+                }
+                eprintln!();
+                return Ok(false);
+            }
+            if query == ".stop" {
+                match self.recorder.take() {
+                    Some(recorder) => {
+                        let (path, n) = recorder.finish();
+                        eprintln!("recorded {} statements to {}", n, path.display());
+                    }
+                    None => eprintln!("not recording"),
+                }
+                eprintln!();
+                return Ok(false);
+            }
+            if query == ".dump-schema" || query.starts_with(".dump-schema ") {
+                self.dump_schema(query[".dump-schema".len()..].trim())
+                    .await?;
+                return Ok(false);
+            }
+            if let Some(path) = query.strip_prefix(".source ") {
+                self.source(path.trim()).await?;
+                return Ok(false);
+            }
             let query = query
                 .trim_start_matches('.')
                 .split_whitespace()
@@ -294,6 +590,16 @@ impl Session {
             return Ok(false);
         }
 
+        if is_repl
+            && self.settings.safe_mode
+            && is_destructive(query)
+            && !confirm("Destructive statement, execute anyway?")?
+        {
+            eprintln!("cancelled");
+            eprintln!();
+            return Ok(false);
+        }
+
         let start = Instant::now();
         let kind = QueryKind::from(query);
         match (kind, is_repl) {
@@ -339,21 +645,101 @@ impl Session {
                     }
                     _ => self.conn.query_iter_ext(query).await?,
                 };
+                self.running_query = data.handle().map(|h| h.query_id().to_string());
 
+                let vertical_settings;
+                let settings = if vertical {
+                    vertical_settings = Settings {
+                        output_format: OutputFormat::Vertical,
+                        ..self.settings.clone()
+                    };
+                    &vertical_settings
+                } else {
+                    &self.settings
+                };
                 let mut displayer = FormatDisplay::new(
-                    &self.settings,
+                    settings,
                     query,
                     replace_newline,
                     start,
                     Arc::new(schema),
                     data,
                 );
+                if self.recorder.as_ref().is_some_and(|r| r.with_output()) {
+                    displayer = displayer.with_capture();
+                }
                 displayer.display().await?;
+                let output = displayer.take_captured();
+                self.record(query, output);
                 Ok(false)
             }
         }
     }
 
+    /// Run a command changing settings, or return the query of a command
+    /// listing objects to be displayed as results of other queries.
+    async fn handle_backslash(&mut self, line: &str) -> Result<Option<String>> {
+        let cmd = backslash::Command::parse(line)?;
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        match cmd {
+            backslash::Command::Connect(ref database) => {
+                let sql = format!("USE {}", quote_ident(database));
+                self.conn.exec(&sql).await?;
+                self.metadata.set_database(database);
+                eprintln!("database changed to {}", database);
+            }
+            backslash::Command::Copy {
+                ref table,
+                ref file,
+                ref format,
+                load,
+                mut options,
+            } => {
+                format.add_load_options(load, &mut options)?;
+                let options = format.get_options(&options);
+                self.load_file(table, file, options).await?;
+            }
+            backslash::Command::ToggleExpanded => {
+                let on = !matches!(self.settings.expand, ExpandMode::On);
+                self.settings.expand = if on { ExpandMode::On } else { ExpandMode::Off };
+                eprintln!("expanded display is {}", on_off(on));
+            }
+            backslash::Command::ToggleTiming => {
+                self.settings.show_stats = !self.settings.show_stats;
+                eprintln!("timing is {}", on_off(self.settings.show_stats));
+            }
+            backslash::Command::Format(ref format) => {
+                self.settings.inject_ctrl_cmd("output_format", format)?;
+            }
+            backslash::Command::Help => eprintln!("{}", backslash::HELP),
+            _ => {
+                let database = self.conn.info().await.database;
+                return cmd.to_sql(database.as_deref().unwrap_or("default"));
+            }
+        }
+        eprintln!();
+        Ok(None)
+    }
+
+    /// Upload the local file, or stdin with `-`, to a stage and insert it
+    /// into the table.
+    pub async fn load_file(
+        &mut self,
+        table: &str,
+        file: &str,
+        options: BTreeMap<&str, &str>,
+    ) -> Result<()> {
+        let query = gen::insert_sql(table);
+        if file == "-" {
+            return self.stream_load_stdin(&query, options).await;
+        }
+        let path = Path::new(file);
+        if !path.exists() {
+            return Err(anyhow!("file not found: {}", file));
+        }
+        self.stream_load_file(&query, path, options).await
+    }
+
     pub async fn stream_load_stdin(
         &mut self,
         query: &str,
@@ -405,8 +791,107 @@ impl Session {
         Ok(())
     }
 
+    // .gen <table> <rows> <spec>
+    async fn handle_gen(&mut self, args: &str) -> Result<()> {
+        let usage = || anyhow!("Usage: .gen <table> <rows> <name:type[:distribution]>, ...");
+        let mut parts = args.trim().splitn(3, char::is_whitespace);
+        let (table, rows, spec) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(table), Some(rows), Some(spec)) => (table, rows, spec),
+            _ => return Err(usage()),
+        };
+        let rows: usize = rows.parse().map_err(|_| usage())?;
+        let columns = gen::parse_spec(spec)?;
+
+        let start = Instant::now();
+        self.conn
+            .exec(&gen::create_table_sql(table, &columns))
+            .await?;
+        let data = gen::generate_csv(&columns, rows)?;
+        let size = data.len() as u64;
+        let mut options = BTreeMap::new();
+        options.insert("type", "CSV");
+        options.insert("compression", "NONE");
+        let progress = self
+            .conn
+            .stream_load(
+                &gen::insert_sql(table),
+                Box::new(std::io::Cursor::new(data)),
+                size,
+                Some(options),
+                None,
+            )
+            .await?;
+        eprintln!(
+            "{} rows generated into {}\n    {}",
+            rows,
+            table,
+            format_write_progress(&progress, start.elapsed().as_secs_f64())
+        );
+        eprintln!();
+        Ok(())
+    }
+
+    /// Statements are recorded once succeeded, commands like `.expand` are not.
+    fn record(&mut self, query: &str, output: Option<String>) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(e) = recorder.record(query, output.as_deref()) {
+                eprintln!("record error: {}", e);
+            }
+        }
+    }
+
+    /// Run statements and commands in the file like they are typed in REPL.
+    async fn source(&mut self, path: &str) -> Result<()> {
+        let content = std::fs::read_to_string(path)?;
+        // keep the statement being typed
+        let pending = std::mem::take(&mut self.query);
+        let mut queries = Vec::new();
+        for line in content.lines() {
+            queries.extend(self.append_query(line));
+        }
+        let rest = std::mem::replace(&mut self.query, pending);
+        if !rest.trim().is_empty() {
+            queries.push(rest.trim().to_string());
+        }
+        for query in queries {
+            if Box::pin(self.handle_query(true, &query)).await? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// `.dump-schema [database] [file]`, the current database by default
+    /// and printed if no file is given.
+    async fn dump_schema(&mut self, args: &str) -> Result<()> {
+        let mut args = args.split_whitespace();
+        let database = match args.next() {
+            Some(database) => database.to_string(),
+            None => self
+                .conn
+                .info()
+                .await
+                .database
+                .unwrap_or_else(|| "default".to_string()),
+        };
+        let script = self.conn.dump_schema(&database).await?;
+        match args.next() {
+            Some(path) => {
+                std::fs::write(path, &script)?;
+                eprintln!("schema of {} dumped to {}", database, path);
+            }
+            None => print!("{}", script),
+        }
+        eprintln!();
+        Ok(())
+    }
+
     async fn reconnect(&mut self) -> Result<()> {
         self.conn = self.client.get_conn().await?;
+        self.metadata.set_conn(self.conn.clone());
+        for query in &self.session_statements {
+            self.conn.exec(query).await?;
+        }
         if self.is_repl {
             let info = self.conn.info().await;
             eprintln!(
@@ -421,6 +906,29 @@ impl Session {
     }
 }
 
+fn is_connection_error(e: &anyhow::Error) -> bool {
+    match e.downcast_ref::<Error>() {
+        Some(Error::Transport(_)) | Some(Error::IO(_)) => true,
+        Some(Error::Api(e)) => matches!(
+            e,
+            databend_driver::ApiError::Request(_) | databend_driver::ApiError::IO(_)
+        ),
+        _ => false,
+    }
+}
+
+/// Ask for confirmation on stderr, only `y` or `yes` confirms.
+fn confirm(prompt: &str) -> Result<bool> {
+    eprint!("{} [y/N] ", prompt);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim().to_ascii_lowercase();
+    Ok(answer == "y" || answer == "yes")
+}
+
+const HISTORY_SIZE: usize = 10000;
+
 fn get_history_path() -> String {
     format!(
         "{}/.bendsql_history",
@@ -460,6 +968,58 @@ impl From<&str> for QueryKind {
     }
 }
 
+/// Comments before the statement, which would hide its kind.
+fn strip_leading_comments(mut query: &str) -> &str {
+    loop {
+        query = query.trim_start();
+        let end = if query.starts_with("--") {
+            query.find('\n').map_or(query.len(), |pos| pos + 1)
+        } else if query.starts_with("/*") && !query.starts_with("/*+") {
+            query.find("*/").map_or(query.len(), |pos| pos + 2)
+        } else {
+            return query;
+        };
+        query = &query[end..];
+    }
+}
+
+/// `USE`, `SET` and `UNSET` changing the state of the session.
+fn is_session_statement(query: &str) -> bool {
+    matches!(
+        Tokenizer::new(query).next(),
+        Some(Ok(t)) if matches!(t.kind, TokenKind::USE | TokenKind::SET | TokenKind::UNSET)
+    )
+}
+
+/// Statements without side effects, safe to retry.
+fn is_read(query: &str) -> bool {
+    matches!(
+        Tokenizer::new(query).next(),
+        Some(Ok(t)) if matches!(
+            t.kind,
+            TokenKind::SELECT
+                | TokenKind::WITH
+                | TokenKind::SHOW
+                | TokenKind::DESC
+                | TokenKind::DESCRIBE
+                | TokenKind::EXPLAIN
+        )
+    )
+}
+
+/// `DROP`, `TRUNCATE`, and `DELETE` without `WHERE`.
+fn is_destructive(query: &str) -> bool {
+    let mut tz = Tokenizer::new(query);
+    match tz.next() {
+        Some(Ok(t)) => match t.kind {
+            TokenKind::DROP | TokenKind::TRUNCATE => true,
+            TokenKind::DELETE => !tz.any(|t| matches!(t, Ok(t) if t.kind == TokenKind::WHERE)),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
 fn get_put_get_args(query: &str) -> Vec<String> {
     query
         .split_ascii_whitespace()
@@ -471,7 +1031,8 @@ fn replace_newline_in_box_display(query: &str) -> bool {
     let mut tz = Tokenizer::new(query);
     match tz.next() {
         Some(Ok(t)) => match t.kind {
-            TokenKind::EXPLAIN => false,
+            // results of scripts are already formatted in lines
+            TokenKind::EXPLAIN | TokenKind::EXECUTE => false,
             TokenKind::SHOW => !matches!(tz.next(), Some(Ok(t)) if t.kind == TokenKind::CREATE),
             _ => true,
         },
@@ -0,0 +1,148 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `bendsql stage` to manage files in stages without writing SQL, the last
+//! segment of stage locations could be a glob pattern like `@s1/data/*.csv`.
+
+use anyhow::{anyhow, Result};
+use clap::Subcommand;
+use databend_driver::{Connection, ConnectionExt, RowWithProgress};
+use indicatif::HumanBytes;
+use tokio_stream::StreamExt;
+
+#[derive(Debug, Subcommand, PartialEq)]
+pub enum StageAction {
+    /// List files under the stage location
+    Ls { location: String },
+    /// Upload local files matching the glob to the stage location
+    Put {
+        #[clap(long, help = "Number of files uploaded at the same time")]
+        concurrency: Option<usize>,
+        local: String,
+        location: String,
+    },
+    /// Download files under the stage location to the local directory
+    Get {
+        #[clap(long, help = "Number of files downloaded at the same time")]
+        concurrency: Option<usize>,
+        location: String,
+        #[clap(default_value = ".")]
+        local: String,
+    },
+    /// Remove files under the stage location
+    Rm { location: String },
+}
+
+impl StageAction {
+    /// Set in the dsn as `upload_concurrency`, shared by uploads and downloads.
+    pub fn concurrency(&self) -> Option<usize> {
+        match self {
+            StageAction::Put { concurrency, .. } | StageAction::Get { concurrency, .. } => {
+                *concurrency
+            }
+            _ => None,
+        }
+    }
+}
+
+pub async fn run(conn: Box<dyn Connection>, action: StageAction) -> Result<()> {
+    match action {
+        StageAction::Ls { location } => {
+            let files = conn.list_stage_files(&stage_location(&location)).await?;
+            let total: u64 = files.iter().map(|f| f.size).sum();
+            for file in &files {
+                println!(
+                    "{:>10}  {}  {}",
+                    HumanBytes(file.size).to_string(),
+                    file.last_modified,
+                    file.name
+                );
+            }
+            eprintln!("{} files, {}", files.len(), HumanBytes(total));
+        }
+        StageAction::Put {
+            local, location, ..
+        } => {
+            let local = local_url(&local, false)?;
+            let (_, rows) = conn.put_files(&local, &stage_location(&location)).await?;
+            report(rows, "uploaded").await?;
+        }
+        StageAction::Get {
+            location, local, ..
+        } => {
+            let local = local_url(&local, true)?;
+            let (_, rows) = conn.get_files(&stage_location(&location), &local).await?;
+            report(rows, "downloaded").await?;
+        }
+        StageAction::Rm { location } => {
+            let files = conn.remove_stage_files(&stage_location(&location)).await?;
+            for file in &files {
+                println!("{}", file.name);
+            }
+            eprintln!("{} files removed", files.len());
+        }
+    }
+    Ok(())
+}
+
+/// `@s1` is taken as the root of the stage.
+fn stage_location(location: &str) -> String {
+    let location = if location.starts_with('@') {
+        location.to_string()
+    } else {
+        format!("@{}", location)
+    };
+    if location.contains('/') {
+        location
+    } else {
+        format!("{}/", location)
+    }
+}
+
+/// Local paths are made absolute as `fs://` urls, directories end with `/`.
+pub fn local_url(path: &str, dir: bool) -> Result<String> {
+    let path = std::env::current_dir()?.join(path);
+    let mut path = path
+        .to_str()
+        .ok_or_else(|| anyhow!("Invalid local path: {:?}", path))?
+        .to_string();
+    if dir && !path.ends_with('/') {
+        path.push('/');
+    }
+    Ok(format!("fs://{}", path))
+}
+
+/// Print the rows of `put_files` or `get_files`, fails if any file failed.
+async fn report(mut rows: databend_driver::RowProgressIterator, action: &str) -> Result<()> {
+    let (mut count, mut bytes, mut failed) = (0, 0, 0);
+    while let Some(row) = rows.next().await {
+        if let RowWithProgress::Row(row) = row? {
+            let (file, status, size): (String, String, u64) =
+                row.try_into().map_err(|e: String| anyhow!(e))?;
+            if status == "SUCCESS" {
+                count += 1;
+                bytes += size;
+                println!("{:>10}  {}", HumanBytes(size).to_string(), file);
+            } else {
+                failed += 1;
+                eprintln!("failed {}: {}", file, status);
+            }
+        }
+    }
+    eprintln!("{} files {}, {}", count, action, HumanBytes(bytes));
+    if failed > 0 {
+        return Err(anyhow!("{} files failed", failed));
+    }
+    Ok(())
+}
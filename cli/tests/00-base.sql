@@ -28,6 +28,16 @@ insert into test_decimal select number, number from numbers(3);
 
 select * from test_decimal;
 
+EXECUTE IMMEDIATE $$
+BEGIN
+    LET x := 0;
+    FOR i IN 1 TO 3 DO
+        x := x + i;
+    END FOR;
+    RETURN x;
+END;
+$$;
+
 select 'bye';
 drop table test;
 drop table test_decimal;
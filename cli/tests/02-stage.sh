@@ -0,0 +1,13 @@
+#!/bin/bash
+
+echo "DROP STAGE IF EXISTS ss_stage" | ${BENDSQL}
+echo "CREATE STAGE ss_stage" | ${BENDSQL}
+
+echo "ABCD" >/tmp/b1.txt
+echo "ABCD" >/tmp/b2.txt
+echo "ABCDEFG" >/tmp/b3.csv
+
+${BENDSQL} stage put '/tmp/b*' @ss_stage/data 2>/dev/null
+${BENDSQL} stage get '@ss_stage/data/*.txt' /tmp/stage_get 2>/dev/null
+${BENDSQL} stage rm '@ss_stage/data/*.txt' 2>/dev/null
+${BENDSQL} stage ls ss_stage 2>/dev/null | awk '{print $NF}'
@@ -0,0 +1,11 @@
+#!/bin/bash
+
+echo "DROP TABLE IF EXISTS test_load_books" | ${BENDSQL}
+echo "CREATE TABLE test_load_books (title VARCHAR NULL, author VARCHAR NULL, date VARCHAR NULL, publish_time TIMESTAMP NULL)" | ${BENDSQL}
+
+${BENDSQL} load --table test_load_books --file 'cli/tests/data/books*.csv' --format csv 2>/dev/null
+
+echo "SELECT COUNT(*) FROM test_load_books" | ${BENDSQL} --output=tsv
+echo "DROP TABLE test_load_books" | ${BENDSQL}
+
+${BENDSQL} load --table test_load_missing --file cli/tests/data/books.csv 2>/dev/null || echo "failed"
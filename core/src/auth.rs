@@ -0,0 +1,182 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use reqwest::RequestBuilder;
+
+use crate::credentials::{Credentials, CredentialsProvider};
+use crate::error::Result;
+
+pub type AuthFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// How requests of `APIClient` are authenticated.
+pub trait Auth: Send + Sync {
+    /// Add the authentication to a request.
+    fn wrap(&self, builder: RequestBuilder) -> RequestBuilder;
+
+    /// The user shown in connection info.
+    fn username(&self) -> String;
+
+    /// Called when the server responds with 401, the request is sent once
+    /// more if it returns true.
+    fn refresh(&self) -> AuthFuture<'_, bool> {
+        Box::pin(async { Ok(false) })
+    }
+}
+
+/// `Authorization: Basic` with user and password.
+pub struct BasicAuth {
+    credentials: Mutex<Credentials>,
+    provider: Option<CredentialsProvider>,
+}
+
+impl BasicAuth {
+    pub fn new(credentials: Credentials) -> Self {
+        Self {
+            credentials: Mutex::new(credentials),
+            provider: None,
+        }
+    }
+
+    /// Fetch new credentials with the provider when rejected.
+    pub fn with_provider(mut self, provider: CredentialsProvider) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    pub fn credentials(&self) -> Credentials {
+        self.credentials.lock().unwrap().clone()
+    }
+}
+
+impl Auth for BasicAuth {
+    fn wrap(&self, builder: RequestBuilder) -> RequestBuilder {
+        let credentials = self.credentials();
+        builder.basic_auth(credentials.user, credentials.password)
+    }
+
+    fn username(&self) -> String {
+        self.credentials.lock().unwrap().user.clone()
+    }
+
+    fn refresh(&self) -> AuthFuture<'_, bool> {
+        Box::pin(async move {
+            match self.provider {
+                Some(ref provider) => {
+                    let credentials = provider().await?;
+                    *self.credentials.lock().unwrap() = credentials;
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        })
+    }
+}
+
+pub type TokenFuture = Pin<Box<dyn Future<Output = Result<String>> + Send>>;
+
+/// Callback to fetch a new token when the current one is rejected or expired.
+pub type TokenProvider = Arc<dyn Fn() -> TokenFuture + Send + Sync>;
+
+/// `Authorization: Bearer` with an access token or a JWT, set with
+/// `?token=` in dsn.
+pub struct TokenAuth {
+    token: Mutex<String>,
+    user: String,
+    provider: Option<TokenProvider>,
+}
+
+impl TokenAuth {
+    /// The user is only for display, taken from the `sub` claim if the
+    /// token is a JWT.
+    pub fn new(token: &str, user: &str) -> Self {
+        let user = jwt_subject(token).unwrap_or_else(|| user.to_string());
+        Self {
+            token: Mutex::new(token.to_string()),
+            user,
+            provider: None,
+        }
+    }
+
+    /// Fetch a new token with the provider when rejected.
+    pub fn with_provider(mut self, provider: TokenProvider) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+}
+
+impl Auth for TokenAuth {
+    fn wrap(&self, builder: RequestBuilder) -> RequestBuilder {
+        builder.bearer_auth(self.token.lock().unwrap().as_str())
+    }
+
+    fn username(&self) -> String {
+        self.user.clone()
+    }
+
+    fn refresh(&self) -> AuthFuture<'_, bool> {
+        Box::pin(async move {
+            match self.provider {
+                Some(ref provider) => {
+                    let token = provider().await?;
+                    *self.token.lock().unwrap() = token;
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        })
+    }
+}
+
+/// The `sub` claim of a JWT, the signature is verified by the server.
+fn jwt_subject(token: &str) -> Option<String> {
+    let payload = token.split('.').nth(1)?;
+    let payload = URL_SAFE_NO_PAD.decode(payload).ok()?;
+    let claims: serde_json::Value = serde_json::from_slice(&payload).ok()?;
+    claims.get("sub")?.as_str().map(|s| s.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn auth_header(auth: &dyn Auth) -> String {
+        let req = auth
+            .wrap(reqwest::Client::new().get("http://localhost"))
+            .build()
+            .unwrap();
+        req.headers()["authorization"].to_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn token_auth() -> Result<()> {
+        // {"sub":"alice"}
+        let jwt = "eyJhbGciOiJIUzI1NiJ9.eyJzdWIiOiJhbGljZSJ9.sig";
+        let auth = TokenAuth::new(jwt, "root");
+        assert_eq!(auth.username(), "alice");
+        assert_eq!(auth_header(&auth), format!("Bearer {}", jwt));
+
+        let provider: TokenProvider = Arc::new(|| Box::pin(async { Ok("t2".to_string()) }));
+        let auth = TokenAuth::new("t1", "root").with_provider(provider);
+        assert_eq!(auth.username(), "root");
+        assert!(auth.refresh().await?);
+        assert_eq!(auth_header(&auth), "Bearer t2");
+        Ok(())
+    }
+}
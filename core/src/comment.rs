@@ -0,0 +1,125 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use crate::error::{Error, Result};
+
+/// Tags prepended to every statement as a comment like
+/// `/* app:etl job:daily trace:4bf92f35 */`, so queries in the server logs
+/// can be attributed to the workloads of clients.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueryComment {
+    tags: Vec<(String, String)>,
+}
+
+impl QueryComment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse tags like `app:etl,job:daily`.
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut comment = Self::new();
+        for tag in s.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            let (key, value) = tag
+                .split_once(':')
+                .ok_or_else(|| Error::BadArgument(format!("Invalid query comment tag: {}", tag)))?;
+            comment = comment.tag(key.trim(), value.trim());
+        }
+        Ok(comment)
+    }
+
+    /// Add a tag or replace the value of the key, characters which could end
+    /// the comment or break the tags are replaced with `_`.
+    pub fn tag(mut self, key: &str, value: &str) -> Self {
+        let key = sanitize(key);
+        let value = sanitize(value);
+        match self.tags.iter_mut().find(|(k, _)| *k == key) {
+            Some(tag) => tag.1 = value,
+            None => self.tags.push((key, value)),
+        }
+        self
+    }
+
+    pub fn app(self, app: &str) -> Self {
+        self.tag("app", app)
+    }
+
+    pub fn job(self, job: &str) -> Self {
+        self.tag("job", job)
+    }
+
+    pub fn trace(self, trace_id: &str) -> Self {
+        self.tag("trace", trace_id)
+    }
+
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// The sql with the comment prepended, untouched if there are no tags.
+    pub fn apply(&self, sql: &str) -> String {
+        if self.is_empty() {
+            return sql.to_string();
+        }
+        format!("{} {}", self, sql)
+    }
+}
+
+impl fmt::Display for QueryComment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("/*")?;
+        for (key, value) in &self.tags {
+            write!(f, " {}:{}", key, value)?;
+        }
+        f.write_str(" */")
+    }
+}
+
+fn sanitize(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | '@' | '=' | '+') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn apply_query_comment() {
+        let comment = QueryComment::parse("app:etl, job:daily").unwrap();
+        assert_eq!(
+            comment.apply("SELECT 1"),
+            "/* app:etl job:daily */ SELECT 1"
+        );
+
+        let comment = comment.job("hourly").trace("a b*/c");
+        assert_eq!(comment.to_string(), "/* app:etl job:hourly trace:a_b_/c */");
+
+        assert_eq!(QueryComment::new().apply("SELECT 1"), "SELECT 1");
+        assert!(QueryComment::parse("app").is_err());
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Failures since the client was created, shared by its clones, to tell
+/// flaky networks from server errors in bug reports.
+#[derive(Debug, Default)]
+pub struct ErrorCounters {
+    connect_errors: AtomicU64,
+    retries: AtomicU64,
+    transport_errors: AtomicU64,
+    server_errors: AtomicU64,
+    auth_failures: AtomicU64,
+}
+
+/// Values of `ErrorCounters` at a time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ErrorCounts {
+    /// Connections failed to create.
+    pub connect_errors: u64,
+    /// Requests sent again by the retry policy.
+    pub retries: u64,
+    /// Requests failed without a response, after retries.
+    pub transport_errors: u64,
+    /// Responses with 5xx status, after retries.
+    pub server_errors: u64,
+    /// Responses with 401 or 403 status.
+    pub auth_failures: u64,
+}
+
+impl ErrorCounters {
+    pub fn snapshot(&self) -> ErrorCounts {
+        ErrorCounts {
+            connect_errors: self.connect_errors.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            transport_errors: self.transport_errors.load(Ordering::Relaxed),
+            server_errors: self.server_errors.load(Ordering::Relaxed),
+            auth_failures: self.auth_failures.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn record_connect_error(&self) {
+        self.connect_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_transport_error(&self) {
+        self.transport_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// For transports without http status, like FlightSQL.
+    pub fn record_server_error(&self) {
+        self.server_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// For transports without http status, like FlightSQL.
+    pub fn record_auth_failure(&self) {
+        self.auth_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_status(&self, status: u16) {
+        match status {
+            401 | 403 => self.auth_failures.fetch_add(1, Ordering::Relaxed),
+            500..=599 => self.server_errors.fetch_add(1, Ordering::Relaxed),
+            _ => return,
+        };
+    }
+}
+
+/// Health of the endpoints requests are sent to, keyed by `host:port`,
+/// to tell which nodes are degraded behind a load balancer or DNS.
+#[derive(Debug, Default)]
+pub struct EndpointCounters {
+    endpoints: Mutex<BTreeMap<String, EndpointStats>>,
+}
+
+/// Requests sent to an endpoint, each attempt of retries counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EndpointStats {
+    /// Responses without 5xx status.
+    pub successes: u64,
+    /// Requests failed without a response, or with 5xx status.
+    pub errors: u64,
+    /// Time to the response or the failure of the last request.
+    pub last_latency: Option<Duration>,
+}
+
+impl EndpointCounters {
+    pub fn snapshot(&self) -> BTreeMap<String, EndpointStats> {
+        self.endpoints.lock().unwrap().clone()
+    }
+
+    pub(crate) fn record(&self, endpoint: &str, ok: bool, latency: Duration) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let stats = match endpoints.get_mut(endpoint) {
+            Some(stats) => stats,
+            None => endpoints.entry(endpoint.to_string()).or_default(),
+        };
+        if ok {
+            stats.successes += 1;
+        } else {
+            stats.errors += 1;
+        }
+        stats.last_latency = Some(latency);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn count_errors() {
+        let counters = ErrorCounters::default();
+        counters.record_retry();
+        counters.record_status(503);
+        counters.record_status(403);
+        counters.record_status(200);
+        assert_eq!(
+            counters.snapshot(),
+            ErrorCounts {
+                retries: 1,
+                server_errors: 1,
+                auth_failures: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn count_endpoints() {
+        let counters = EndpointCounters::default();
+        counters.record("n1:8000", true, Duration::from_millis(5));
+        counters.record("n1:8000", false, Duration::from_millis(30));
+        counters.record("n2:8000", true, Duration::from_millis(7));
+        let stats = counters.snapshot();
+        assert_eq!(
+            stats["n1:8000"],
+            EndpointStats {
+                successes: 1,
+                errors: 1,
+                last_latency: Some(Duration::from_millis(30)),
+            }
+        );
+        assert_eq!(stats["n2:8000"].successes, 1);
+    }
+}
@@ -0,0 +1,51 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::error::Result;
+
+#[derive(Clone, Default)]
+pub struct Credentials {
+    pub user: String,
+    pub password: Option<String>,
+}
+
+impl Credentials {
+    pub fn new(user: &str, password: Option<&str>) -> Self {
+        Self {
+            user: user.to_string(),
+            password: password.map(|s| s.to_string()),
+        }
+    }
+}
+
+// never print the password
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("user", &self.user)
+            .field("password", &self.password.as_ref().map(|_| "******"))
+            .finish()
+    }
+}
+
+pub type CredentialsFuture = Pin<Box<dyn Future<Output = Result<Credentials>> + Send>>;
+
+/// Callback invoked when the server rejects the current credentials,
+/// so that rotated passwords or tokens could be fetched without
+/// rebuilding the client.
+pub type CredentialsProvider = Arc<dyn Fn() -> CredentialsFuture + Send + Sync>;
@@ -0,0 +1,235 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Logging of request and response bodies through `tracing` at debug level,
+//! enabled with `debug_http=1` in dsn to diagnose protocol mismatches.
+//! Credentials and signatures of presigned urls are masked and long bodies
+//! are truncated.
+
+use serde_json::Value;
+
+/// Bodies longer than this are truncated in logs.
+pub const MAX_BODY_LEN: usize = 4096;
+
+const MASK: &str = "***";
+
+/// Keys or SQL options whose values are never logged.
+const SECRET_KEYS: &[&str] = &[
+    "password",
+    "secret",
+    "token",
+    "access_key",
+    "account_key",
+    "credential",
+];
+
+/// Query params of presigned urls which grant access to the object,
+/// of S3, GCS and Azure.
+const SIGNED_URL_PARAMS: &[&str] = &[
+    "x-amz-signature",
+    "x-amz-credential",
+    "x-amz-security-token",
+    "x-goog-signature",
+    "x-goog-credential",
+    "sig",
+    "signature",
+];
+
+pub fn log_request(method: &str, target: &str, body: &[u8]) {
+    tracing::debug!("{} {}\n{}", method, target, sanitize_body(body));
+}
+
+pub fn log_response(status: &str, body: &[u8]) {
+    tracing::debug!("response {}\n{}", status, sanitize_body(body));
+}
+
+/// Pretty print JSON bodies with secrets masked, in both JSON fields and
+/// SQL text, then truncate the result to `MAX_BODY_LEN` bytes.
+pub fn sanitize_body(body: &[u8]) -> String {
+    let text = match serde_json::from_slice::<Value>(body) {
+        Ok(mut value) => {
+            mask_json(&mut value);
+            serde_json::to_string_pretty(&value).unwrap_or_default()
+        }
+        Err(_) => mask_url_params(&mask_sql(&String::from_utf8_lossy(body))),
+    };
+    truncate(text)
+}
+
+fn is_secret(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    SECRET_KEYS.iter().any(|k| key.contains(k))
+}
+
+fn mask_json(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                if is_secret(k) && !v.is_null() {
+                    *v = Value::String(MASK.to_string());
+                } else {
+                    mask_json(v);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(mask_json),
+        Value::String(s) => *s = mask_url_params(&mask_sql(s)),
+        _ => {}
+    }
+}
+
+/// Mask string literals following secret options like
+/// `secret_access_key = '...'` or `IDENTIFIED BY '...'`.
+pub fn mask_sql(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        out.push(c);
+        if c != '\'' {
+            continue;
+        }
+        let secret = follows_secret(&sql[..i]);
+        // the rest of the literal, with quotes escaped by doubling or backslash
+        while let Some((_, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    let escaped = chars.next().map(|(_, c)| c);
+                    if !secret {
+                        out.push(c);
+                        out.extend(escaped);
+                    }
+                }
+                '\'' if matches!(chars.peek(), Some((_, '\''))) => {
+                    chars.next();
+                    if !secret {
+                        out.push_str("''");
+                    }
+                }
+                '\'' => break,
+                c if !secret => out.push(c),
+                _ => {}
+            }
+        }
+        if secret {
+            out.push_str(MASK);
+        }
+        out.push('\'');
+    }
+    out
+}
+
+/// Mask signatures in query params of presigned urls, like
+/// `?X-Amz-Signature=...` or `&sig=...`.
+pub fn mask_url_params(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find(['?', '&']) {
+        out.push_str(&rest[..=i]);
+        rest = &rest[i + 1..];
+        let end = rest
+            .find(|c: char| matches!(c, '&' | '"' | '\'') || c.is_whitespace())
+            .unwrap_or(rest.len());
+        if let Some((name, value)) = rest[..end].split_once('=') {
+            let name_lower = name.to_ascii_lowercase();
+            if !value.is_empty() && SIGNED_URL_PARAMS.contains(&name_lower.as_str()) {
+                out.push_str(name);
+                out.push('=');
+                out.push_str(MASK);
+                rest = &rest[end..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn follows_secret(prefix: &str) -> bool {
+    let prefix = prefix.trim_end().trim_end_matches('=').trim_end();
+    let word = prefix
+        .rsplit(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .next()
+        .unwrap_or_default();
+    if word.eq_ignore_ascii_case("by") {
+        let rest = prefix[..prefix.len() - word.len()].trim_end();
+        return rest.to_ascii_lowercase().ends_with("identified");
+    }
+    is_secret(word)
+}
+
+fn truncate(mut text: String) -> String {
+    if text.len() <= MAX_BODY_LEN {
+        return text;
+    }
+    let total = text.len();
+    let mut end = MAX_BODY_LEN;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    text.push_str(&format!("... ({} bytes truncated)", total - end));
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mask_secrets_in_sql() {
+        assert_eq!(
+            mask_sql("CREATE USER u IDENTIFIED BY 'p''w\\'d' WITH x = 'y'"),
+            "CREATE USER u IDENTIFIED BY '***' WITH x = 'y'"
+        );
+        assert_eq!(
+            mask_sql("COPY INTO t FROM 's3://b/' CONNECTION = (access_key_id='ak' SECRET_ACCESS_KEY = 'sk')"),
+            "COPY INTO t FROM 's3://b/' CONNECTION = (access_key_id='***' SECRET_ACCESS_KEY = '***')"
+        );
+        assert_eq!(mask_sql("SELECT 'it''s'"), "SELECT 'it''s'");
+        assert_eq!(
+            mask_sql("CONNECTION = (account_name='a' account_key='k')"),
+            "CONNECTION = (account_name='a' account_key='***')"
+        );
+    }
+
+    #[test]
+    fn mask_presigned_urls() {
+        assert_eq!(
+            mask_url_params("https://b.s3.amazonaws.com/f?X-Amz-Date=1&X-Amz-Signature=abc&x=1"),
+            "https://b.s3.amazonaws.com/f?X-Amz-Date=1&X-Amz-Signature=***&x=1"
+        );
+        assert_eq!(
+            mask_url_params("PUT https://a.blob.core.windows.net/c/f?sv=1&sig=abc%3D"),
+            "PUT https://a.blob.core.windows.net/c/f?sv=1&sig=***"
+        );
+        let body = br#"{"data":[["PUT","{}","https://s3/f?X-Amz-Signature=abc"]]}"#;
+        let text = sanitize_body(body);
+        assert!(!text.contains("abc"), "{}", text);
+    }
+
+    #[test]
+    fn sanitize_json_body() {
+        let body = br#"{"sql":"ALTER USER u IDENTIFIED BY 'pw'","session":{"settings":{"password":"pw","x":"1"}}}"#;
+        let text = sanitize_body(body);
+        assert!(
+            !text.contains("pw'") && !text.contains("\"pw\""),
+            "{}",
+            text
+        );
+        assert!(text.contains("\"x\": \"1\""), "{}", text);
+
+        let long = "é".repeat(MAX_BODY_LEN);
+        let text = sanitize_body(long.as_bytes());
+        assert!(text.ends_with(&format!("... ({} bytes truncated)", MAX_BODY_LEN)));
+    }
+}
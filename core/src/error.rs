@@ -20,8 +20,23 @@ pub enum Error {
     BadArgument(String),
     Request(String),
     IO(String),
+    /// Downloaded data not matching the expected size or checksum.
+    Integrity(String),
+    /// Error of the query reported by the server, with a Databend code.
     InvalidResponse(response::QueryError),
     InvalidPage(response::QueryError),
+    /// Unexpected HTTP status, with the status as the code, e.g. from a
+    /// proxy before the request reached the server.
+    HttpStatus(response::QueryError),
+    /// Credentials rejected with 401, even after refreshed by the provider.
+    AuthFailure(response::QueryError),
+    /// Authenticated but not allowed with 403, e.g. blocked by network policy.
+    PermissionDenied(response::QueryError),
+    /// A request did not finish before the deadline.
+    Timeout(String),
+    /// The query did not finish before `query_timeout` and was killed,
+    /// which would happen again if retried.
+    QueryTimeout(String),
 }
 
 impl std::fmt::Display for Error {
@@ -31,16 +46,107 @@ impl std::fmt::Display for Error {
             Error::BadArgument(msg) => write!(f, "BadArgument: {msg}"),
             Error::Request(msg) => write!(f, "RequestError: {msg}"),
             Error::IO(msg) => write!(f, "IOError: {msg}"),
+            Error::Integrity(msg) => write!(f, "IntegrityError: {msg}"),
             Error::InvalidResponse(e) => {
                 write!(f, "ResponseError with {}: {}", e.code, e.message)
             }
             Error::InvalidPage(e) => write!(f, "PageError with {}: {}", e.code, e.message),
+            Error::HttpStatus(e) => write!(f, "HttpError with {}: {}", e.code, e.message),
+            Error::AuthFailure(e) => write!(f, "AuthFailure with {}: {}", e.code, e.message),
+            Error::PermissionDenied(e) => {
+                write!(f, "PermissionDenied with {}: {}", e.code, e.message)
+            }
+            Error::Timeout(msg) => write!(f, "TimeoutError: {msg}"),
+            Error::QueryTimeout(msg) => write!(f, "QueryTimeoutError: {msg}"),
         }
     }
 }
 
 impl std::error::Error for Error {}
 
+/// Kind of a failure, classified by the Databend error code or the HTTP
+/// status, for callers to branch on without matching messages. More kinds
+/// may be told apart later, so matches need a fallback arm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    Syntax,
+    UnknownDatabase,
+    UnknownTable,
+    UnknownColumn,
+    /// Database or table to create already exists.
+    AlreadyExists,
+    AuthFailure,
+    PermissionDenied,
+    /// Query or session aborted, e.g. killed by another session.
+    Aborted,
+    Timeout,
+    RateLimited,
+    /// Server or proxy not available for now, e.g. 502 or 503.
+    Unavailable,
+    /// Connection broken or not established.
+    Transport,
+    Other,
+}
+
+impl ErrorKind {
+    /// Classify by a Databend error code.
+    pub fn from_code(code: u16) -> Self {
+        match code {
+            1005 => ErrorKind::Syntax,
+            1003 => ErrorKind::UnknownDatabase,
+            1025 => ErrorKind::UnknownTable,
+            1058 => ErrorKind::UnknownColumn,
+            2301 | 2302 => ErrorKind::AlreadyExists,
+            1063 => ErrorKind::PermissionDenied,
+            2001 => ErrorKind::AuthFailure,
+            1042 | 1043 => ErrorKind::Aborted,
+            _ => ErrorKind::Other,
+        }
+    }
+
+    /// Classify by an HTTP status, for errors returned before the query
+    /// started.
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            401 => ErrorKind::AuthFailure,
+            403 => ErrorKind::PermissionDenied,
+            408 | 504 => ErrorKind::Timeout,
+            429 => ErrorKind::RateLimited,
+            502 | 503 => ErrorKind::Unavailable,
+            _ => ErrorKind::Other,
+        }
+    }
+
+    /// Whether the same request could succeed if retried later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ErrorKind::Timeout
+                | ErrorKind::RateLimited
+                | ErrorKind::Unavailable
+                | ErrorKind::Transport
+        )
+    }
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Request(_) => ErrorKind::Transport,
+            // local files, not the connection
+            Error::IO(_) => ErrorKind::Other,
+            Error::AuthFailure(_) => ErrorKind::AuthFailure,
+            Error::PermissionDenied(_) => ErrorKind::PermissionDenied,
+            Error::Timeout(_) => ErrorKind::Timeout,
+            Error::QueryTimeout(_) => ErrorKind::Aborted,
+            Error::InvalidResponse(e) | Error::InvalidPage(e) => ErrorKind::from_code(e.code),
+            Error::HttpStatus(e) => ErrorKind::from_status(e.code),
+            Error::Parsing(_) | Error::BadArgument(_) | Error::Integrity(_) => ErrorKind::Other,
+        }
+    }
+}
+
 pub type Result<T, E = Error> = core::result::Result<T, E>;
 
 impl From<url::ParseError> for Error {
@@ -69,6 +175,9 @@ impl From<serde_json::Error> for Error {
 
 impl From<reqwest::Error> for Error {
     fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            return Error::Timeout(e.to_string());
+        }
         Error::Request(e.to_string())
     }
 }
@@ -78,3 +187,35 @@ impl From<std::io::Error> for Error {
         Error::IO(e.to_string())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn error_kinds() {
+        let err = |code| {
+            Error::InvalidResponse(response::QueryError {
+                code,
+                message: String::new(),
+            })
+        };
+        assert_eq!(err(1025).kind(), ErrorKind::UnknownTable);
+        assert_eq!(err(1005).kind(), ErrorKind::Syntax);
+        assert!(!err(1063).kind().is_retryable());
+        assert_eq!(err(9999).kind(), ErrorKind::Other);
+        // http statuses are not taken as Databend codes
+        assert_eq!(err(503).kind(), ErrorKind::Other);
+        let status = |code| {
+            Error::HttpStatus(response::QueryError {
+                code,
+                message: String::new(),
+            })
+        };
+        assert_eq!(status(429).kind(), ErrorKind::RateLimited);
+        assert!(status(503).kind().is_retryable());
+        assert_eq!(Error::Request("reset".into()).kind(), ErrorKind::Transport);
+        assert_eq!(Error::IO("disk full".into()).kind(), ErrorKind::Other);
+        assert!(!Error::QueryTimeout("q1".into()).kind().is_retryable());
+    }
+}
@@ -14,10 +14,26 @@
 
 mod client;
 
+pub mod auth;
+pub mod comment;
+pub mod counters;
+pub mod credentials;
+pub mod debug;
 pub mod error;
+pub mod limit;
+mod page;
 pub mod presign;
+pub mod quote;
 pub mod request;
 pub mod response;
+pub mod retry;
 pub mod stage;
+pub mod template;
+pub mod upload;
+pub mod wait;
 
-pub use client::APIClient;
+pub use client::{parse_bool, APIClient, APIClientBuilder};
+pub use page::PageStream;
+
+/// The HTTP client used by `APIClient`, which could be shared by many of them.
+pub use reqwest::Client as HttpClient;
@@ -0,0 +1,88 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Limit of requests to a server in flight at the same time, shared by clones
+/// of a client, and by connections of a driver client. Waiting requests are
+/// sent in the order they arrive, so a busy task could not starve others.
+#[derive(Debug)]
+pub struct RequestLimiter {
+    semaphore: Semaphore,
+    max: usize,
+}
+
+impl RequestLimiter {
+    /// At least one request is allowed.
+    pub fn new(max_concurrent_requests: usize) -> Self {
+        let max = max_concurrent_requests.max(1);
+        Self {
+            semaphore: Semaphore::new(max),
+            max,
+        }
+    }
+
+    pub fn max_concurrent_requests(&self) -> usize {
+        self.max
+    }
+
+    /// Requests waiting for responses now.
+    pub fn in_flight(&self) -> usize {
+        self.max - self.semaphore.available_permits()
+    }
+
+    pub(crate) async fn acquire(&self) -> SemaphorePermit<'_> {
+        // the semaphore is never closed
+        self.semaphore
+            .acquire()
+            .await
+            .expect("request limiter closed")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn limit_requests_in_order() {
+        let limiter = Arc::new(RequestLimiter::new(1));
+        assert_eq!(limiter.max_concurrent_requests(), 1);
+        let permit = limiter.acquire().await;
+        assert_eq!(limiter.in_flight(), 1);
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        for i in 0..3 {
+            let limiter = limiter.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let _permit = limiter.acquire().await;
+                tx.send(i).unwrap();
+            });
+            // queued one by one
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        drop(tx);
+        drop(permit);
+        let mut order = Vec::new();
+        while let Some(i) = rx.recv().await {
+            order.push(i);
+        }
+        assert_eq!(order, vec![0, 1, 2]);
+        assert_eq!(limiter.in_flight(), 0);
+    }
+}
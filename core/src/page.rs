@@ -0,0 +1,91 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio_stream::Stream;
+
+use crate::client::APIClient;
+use crate::error::Result;
+use crate::response::QueryResponse;
+
+type PageFut = Pin<Box<dyn Future<Output = Result<QueryResponse>> + Send>>;
+
+/// Pages of a query as returned by the server, created by `APIClient::pages`.
+///
+/// The stream ends after the page without `next_uri`, or the first error.
+/// Pages are not finalized, call `APIClient::finalize_query` with the
+/// `final_uri` to release the results early.
+///
+/// `next()` is cancellation safe, the request of the page in flight is kept
+/// in the stream and its response returned by the next call.
+pub struct PageStream {
+    client: APIClient,
+    page: Option<PageFut>,
+    // routing hints of the query are forgotten once the stream is dropped
+    query_id: Option<String>,
+}
+
+impl PageStream {
+    pub(crate) fn new(client: APIClient, sql: &str) -> Self {
+        let sql = sql.to_string();
+        let cli = client.clone();
+        Self {
+            client,
+            page: Some(Box::pin(async move { cli.query(&sql).await })),
+            query_id: None,
+        }
+    }
+}
+
+impl Stream for PageStream {
+    type Item = Result<QueryResponse>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let page = match self.page {
+            Some(ref mut page) => page,
+            None => return Poll::Ready(None),
+        };
+        match Pin::new(page).poll(cx) {
+            Poll::Ready(Ok(resp)) => {
+                self.query_id = Some(resp.id.clone());
+                self.page = resp.next_uri.clone().map(|next_uri| {
+                    let client = self.client.clone();
+                    Box::pin(async move { client.query_page(&next_uri).await }) as PageFut
+                });
+                Poll::Ready(Some(Ok(resp)))
+            }
+            Poll::Ready(Err(e)) => {
+                self.page = None;
+                Poll::Ready(Some(Err(e)))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for PageStream {
+    fn drop(&mut self) {
+        let query_id = match self.query_id.take() {
+            Some(query_id) => query_id,
+            None => return,
+        };
+        if let Ok(rt) = tokio::runtime::Handle::try_current() {
+            let client = self.client.clone();
+            rt.spawn(async move { client.forget_query(&query_id).await });
+        }
+    }
+}
@@ -12,29 +12,49 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{collections::BTreeMap, path::Path};
+use std::collections::BTreeMap;
 
-use reqwest::{Body, Client as HttpClient, StatusCode};
 use tokio::io::AsyncRead;
-use tokio::io::AsyncWriteExt;
-use tokio_stream::StreamExt;
-use tokio_util::io::ReaderStream;
 
-use crate::error::{Error, Result};
+#[cfg(feature = "presign")]
+use {
+    crate::error::{Error, Result},
+    crate::retry::RetryPolicy,
+    md5::{Digest, Md5},
+    reqwest::header::ETAG,
+    reqwest::{Body, Client as HttpClient, Response, StatusCode},
+    std::io::SeekFrom,
+    std::path::Path,
+    tokio::io::{AsyncSeekExt, AsyncWriteExt},
+    tokio_stream::StreamExt,
+    tokio_util::io::ReaderStream,
+};
 
 pub type Reader = Box<dyn AsyncRead + Send + Sync + Unpin + 'static>;
 
+#[derive(Clone)]
 pub struct PresignedResponse {
     pub method: String,
     pub headers: BTreeMap<String, String>,
     pub url: String,
 }
 
+#[cfg(feature = "presign")]
 pub async fn presign_upload_to_stage(
     presigned: PresignedResponse,
     data: Reader,
     size: u64,
 ) -> Result<()> {
+    let resp = send_presigned_upload(presigned, data, size).await?;
+    check_presigned_upload(resp).await
+}
+
+#[cfg(feature = "presign")]
+async fn send_presigned_upload(
+    presigned: PresignedResponse,
+    data: Reader,
+    size: u64,
+) -> reqwest::Result<Response> {
     let client = HttpClient::new();
     let mut builder = client.put(presigned.url);
     if !presigned.headers.contains_key("content-length") {
@@ -44,7 +64,11 @@ pub async fn presign_upload_to_stage(
         builder = builder.header(k, v);
     }
     let stream = Body::wrap_stream(ReaderStream::new(data));
-    let resp = builder.body(stream).send().await?;
+    builder.body(stream).send().await
+}
+
+#[cfg(feature = "presign")]
+async fn check_presigned_upload(resp: Response) -> Result<()> {
     let status = resp.status();
     let body = resp.bytes().await?;
     match status {
@@ -56,9 +80,65 @@ pub async fn presign_upload_to_stage(
     }
 }
 
+/// Upload a local file with presigned url, the file is rewound and sent again
+/// if the connection is broken during the upload, which is common for large
+/// files to object storage.
+#[cfg(feature = "presign")]
+pub async fn presign_upload_file_to_stage(
+    presigned: PresignedResponse,
+    file: tokio::fs::File,
+    size: u64,
+) -> Result<()> {
+    presign_upload_file_to_stage_with_retry(presigned, file, size, &RetryPolicy::default()).await
+}
+
+/// Same as `presign_upload_file_to_stage`, retried as the policy allows,
+/// broken connections are always retryable since the upload is a `PUT`.
+#[cfg(feature = "presign")]
+pub async fn presign_upload_file_to_stage_with_retry(
+    presigned: PresignedResponse,
+    file: tokio::fs::File,
+    size: u64,
+    policy: &RetryPolicy,
+) -> Result<()> {
+    let mut attempt = 1;
+    loop {
+        // the cloned handle shares the cursor with the original one
+        let mut data = file.try_clone().await?;
+        data.seek(SeekFrom::Start(0)).await?;
+        let delay = match send_presigned_upload(presigned.clone(), Box::new(data), size).await {
+            Ok(resp) if policy.can_retry(attempt) && policy.retryable_status(resp.status()) => {
+                policy.delay(attempt, Some(resp.headers()))
+            }
+            Ok(resp) => return check_presigned_upload(resp).await,
+            Err(e) if policy.can_retry(attempt) && policy.retryable_error(&e, true) => {
+                policy.delay(attempt, None)
+            }
+            Err(e) => return Err(e.into()),
+        };
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(feature = "presign")]
 pub async fn presign_download_from_stage(
     presigned: PresignedResponse,
     local_path: &Path,
+) -> Result<u64> {
+    presign_download_from_stage_verified(presigned, local_path, None, None).await
+}
+
+/// Same as `presign_download_from_stage`, and the file is checked against
+/// the size and MD5 from the stage listing if given, or the `ETag` of the
+/// response if it is an MD5. The file is removed if not matched.
+/// Truncated downloads are always rejected by `Content-Length`.
+#[cfg(feature = "presign")]
+pub async fn presign_download_from_stage_verified(
+    presigned: PresignedResponse,
+    local_path: &Path,
+    size: Option<u64>,
+    md5: Option<&str>,
 ) -> Result<u64> {
     if let Some(p) = local_path.parent() {
         tokio::fs::create_dir_all(p).await?;
@@ -71,20 +151,80 @@ pub async fn presign_download_from_stage(
 
     let resp = builder.send().await?;
     let status = resp.status();
-    match status {
-        StatusCode::OK => {
-            let mut file = tokio::fs::File::create(local_path).await?;
-            let mut body = resp.bytes_stream();
-            while let Some(chunk) = body.next().await {
-                file.write_all(&chunk?).await?;
-            }
-            file.flush().await?;
-            let metadata = file.metadata().await?;
-            Ok(metadata.len())
-        }
-        _ => Err(Error::IO(format!(
+    if status != StatusCode::OK {
+        return Err(Error::IO(format!(
             "Download with presigned url failed: {}",
             status
-        ))),
+        )));
+    }
+    let content_length = resp.content_length();
+    let etag = resp
+        .headers()
+        .get(ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    let mut file = tokio::fs::File::create(local_path).await?;
+    let mut hasher = Md5::new();
+    let mut body = resp.bytes_stream();
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk?;
+        hasher.update(&chunk);
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    let written = file.metadata().await?.len();
+
+    let checksum = format!("{:x}", hasher.finalize());
+    let expected_md5 = md5.or(etag.as_deref()).and_then(as_md5);
+    let mismatch = if let Some(len) = content_length.filter(|len| *len != written) {
+        Some(format!("expected {} bytes, got {}", len, written))
+    } else if let Some(size) = size.filter(|size| *size != written) {
+        Some(format!(
+            "expected {} bytes as listed, got {}",
+            size, written
+        ))
+    } else {
+        expected_md5
+            .filter(|md5| *md5 != checksum)
+            .map(|md5| format!("expected md5 {}, got {}", md5, checksum))
+    };
+    match mismatch {
+        Some(reason) => {
+            drop(file);
+            tokio::fs::remove_file(local_path).await.ok();
+            Err(Error::Integrity(format!(
+                "Downloaded {}: {}",
+                local_path.display(),
+                reason
+            )))
+        }
+        None => Ok(written),
+    }
+}
+
+/// MD5 in hex, possibly quoted like an `ETag`, which is not an MD5 for
+/// multipart uploads like `"<hex>-3"`.
+#[cfg(feature = "presign")]
+fn as_md5(s: &str) -> Option<String> {
+    let s = s.trim().trim_matches('"');
+    if s.len() == 32 && s.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(s.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+#[cfg(all(test, feature = "presign"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_md5() {
+        assert_eq!(
+            as_md5("\"D41D8CD98F00B204E9800998ECF8427E\""),
+            Some("d41d8cd98f00b204e9800998ecf8427e".to_string())
+        );
+        assert_eq!(as_md5("\"d41d8cd98f00b204e9800998ecf8427e-3\""), None);
+        assert_eq!(as_md5(""), None);
     }
 }
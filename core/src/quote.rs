@@ -0,0 +1,116 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Quote an identifier with backticks, embedded backticks are doubled.
+///
+/// Quoted identifiers are case sensitive in Databend, so the name should be
+/// exactly what is stored, e.g. from `system.tables`.
+pub fn quote_ident(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+/// Quote a dot separated name like `db.table` part by part, parts already
+/// quoted with backticks or double quotes may contain dots, e.g. `` db.`a.b` ``.
+pub fn quote_qualified_ident(name: &str) -> String {
+    split_qualified_ident(name)
+        .iter()
+        .map(|part| quote_ident(part))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn split_qualified_ident(name: &str) -> Vec<String> {
+    let mut parts = vec![];
+    let mut part = String::new();
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '`' | '"' if part.is_empty() => {
+                // doubled quotes inside are escaped ones
+                while let Some(q) = chars.next() {
+                    if q != c {
+                        part.push(q);
+                    } else if chars.peek() == Some(&c) {
+                        chars.next();
+                        part.push(c);
+                    } else {
+                        break;
+                    }
+                }
+            }
+            '.' => parts.push(std::mem::take(&mut part)),
+            c => part.push(c),
+        }
+    }
+    parts.push(part);
+    parts
+}
+
+/// Escape a string into a single quoted literal.
+///
+/// Quotes and backslashes are escaped with backslash, control characters
+/// are written as escape sequences so the literal stays in one line.
+pub fn escape_literal(value: &str) -> String {
+    let mut s = String::with_capacity(value.len() + 2);
+    s.push('\'');
+    for c in value.chars() {
+        match c {
+            '\'' => s.push_str("\\'"),
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            '\r' => s.push_str("\\r"),
+            '\t' => s.push_str("\\t"),
+            '\0' => s.push_str("\\0"),
+            _ => s.push(c),
+        }
+    }
+    s.push('\'');
+    s
+}
+
+/// Escape regex metacharacters, to match the text literally in `PATTERN`.
+pub fn escape_regex(text: &str) -> String {
+    let mut s = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$#&-~".contains(c) {
+            s.push('\\');
+        }
+        s.push(c);
+    }
+    s
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn quote() {
+        assert_eq!(quote_ident("books"), "`books`");
+        assert_eq!(quote_ident("Books"), "`Books`");
+        assert_eq!(quote_ident("a`b"), "`a``b`");
+        assert_eq!(quote_qualified_ident("db.t1"), "`db`.`t1`");
+        assert_eq!(quote_qualified_ident("db.`a.b`"), "`db`.`a.b`");
+        assert_eq!(quote_qualified_ident(r#""d.b".t"#), "`d.b`.`t`");
+        assert_eq!(quote_qualified_ident("`a``b.c`"), "`a``b.c`");
+
+        assert_eq!(escape_literal("hello"), "'hello'");
+        assert_eq!(escape_literal("it's"), r"'it\'s'");
+        assert_eq!(escape_literal(r"C:\data"), r"'C:\\data'");
+        assert_eq!(escape_literal("a\nb\tc"), r"'a\nb\tc'");
+
+        assert_eq!(escape_regex("a.csv"), r"a\.csv");
+        assert_eq!(escape_regex("f(1)[2]"), r"f\(1\)\[2\]");
+    }
+}
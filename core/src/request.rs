@@ -22,6 +22,10 @@ pub struct SessionConfig {
     pub database: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub settings: Option<BTreeMap<String, String>>,
+    /// State of the explicit transaction from the server, sent back with
+    /// the next query to continue it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub txn_state: Option<String>,
 }
 
 #[derive(Serialize, Debug)]
@@ -55,7 +59,7 @@ pub struct StageAttachmentConfig<'a> {
 }
 
 impl<'r, 't: 'r> QueryRequest<'r> {
-    pub fn new(sql: &'r str) -> QueryRequest {
+    pub fn new(sql: &'r str) -> QueryRequest<'r> {
         QueryRequest {
             session: None,
             sql,
@@ -94,6 +98,7 @@ mod test {
             .with_session(Some(SessionConfig {
                 database: Some("default".to_string()),
                 settings: Some(BTreeMap::new()),
+                txn_state: None,
             }))
             .with_pagination(Some(PaginationConfig {
                 wait_time_secs: Some(1),
@@ -44,7 +44,7 @@ pub struct ProgressValues {
     pub bytes: usize,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct SchemaField {
     pub name: String,
     #[serde(rename = "type")]
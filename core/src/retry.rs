@@ -0,0 +1,164 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use tokio_retry::strategy::jitter;
+
+use crate::error::{Error, Result};
+
+/// When and how often failed requests of `APIClient` are sent again.
+///
+/// Uploads to stage through the server (`presigned_url_disabled`) are never
+/// retried since their stream body could not be replayed, only uploads of
+/// files with presigned urls are.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts including the first one, `1` to disable retries.
+    pub max_attempts: usize,
+    /// Delay before the first retry, doubled for each of the next ones.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Responses with these status codes are retried.
+    pub retryable_statuses: Vec<u16>,
+    /// Retry when failed to connect, the request is never received by the
+    /// server then. Other transport errors are only retried for requests
+    /// safe to repeat, like fetching a page.
+    pub retry_on_connect_error: bool,
+    /// Wait as long as `Retry-After` of the response if longer than the
+    /// backoff, but never longer than `max_backoff`.
+    pub honor_retry_after: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(5),
+            retryable_statuses: vec![StatusCode::SERVICE_UNAVAILABLE.as_u16()],
+            retry_on_connect_error: true,
+            honor_retry_after: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// No retries at all.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Parse the comma separated status codes, like `502,503,504`.
+    pub fn parse_statuses(s: &str) -> Result<Vec<u16>> {
+        s.split(',')
+            .map(|code| {
+                let code = code.trim();
+                match code.parse::<u16>() {
+                    Ok(code) if StatusCode::from_u16(code).is_ok() => Ok(code),
+                    _ => Err(Error::BadArgument(format!(
+                        "Invalid status code to retry: {}",
+                        code
+                    ))),
+                }
+            })
+            .collect()
+    }
+
+    /// Delay after the attempt failed, starting from 1, with jitter.
+    pub fn backoff(&self, attempt: usize) -> Duration {
+        let exp = attempt.saturating_sub(1).min(31) as u32;
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.pow(exp))
+            .min(self.max_backoff);
+        jitter(backoff)
+    }
+
+    pub(crate) fn can_retry(&self, attempt: usize) -> bool {
+        attempt < self.max_attempts
+    }
+
+    pub(crate) fn retryable_status(&self, status: StatusCode) -> bool {
+        self.retryable_statuses.contains(&status.as_u16())
+    }
+
+    pub(crate) fn retryable_error(&self, e: &reqwest::Error, idempotent: bool) -> bool {
+        if e.is_connect() {
+            return self.retry_on_connect_error;
+        }
+        idempotent && (e.is_timeout() || e.is_request() || e.is_body())
+    }
+
+    /// The backoff, or `Retry-After` of the response if longer, capped by
+    /// `max_backoff` so that a gateway could not stall the client.
+    pub(crate) fn delay(&self, attempt: usize, headers: Option<&HeaderMap>) -> Duration {
+        let backoff = self.backoff(attempt);
+        match headers.and_then(retry_after) {
+            Some(after) if self.honor_retry_after => after.max(backoff).min(self.max_backoff),
+            _ => backoff,
+        }
+    }
+}
+
+/// Only delay in seconds is supported, which is what servers and gateways
+/// in front of Databend send.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?;
+    value.trim().parse::<u64>().ok().map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn retry_policy() -> Result<()> {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(300),
+            ..Default::default()
+        };
+        assert!(policy.can_retry(3));
+        assert!(!policy.can_retry(4));
+        assert!(!RetryPolicy::none().can_retry(1));
+        assert!(policy.backoff(1) <= Duration::from_millis(100));
+        assert!(policy.backoff(10) <= Duration::from_millis(300));
+        assert!(policy.retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!policy.retryable_status(StatusCode::BAD_REQUEST));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, "2".parse().unwrap());
+        assert_eq!(policy.delay(1, Some(&headers)), Duration::from_millis(300));
+        let long = RetryPolicy {
+            max_backoff: Duration::from_secs(5),
+            ..policy.clone()
+        };
+        assert_eq!(long.delay(1, Some(&headers)), Duration::from_secs(2));
+        let policy = RetryPolicy {
+            honor_retry_after: false,
+            ..policy
+        };
+        assert!(policy.delay(1, Some(&headers)) <= Duration::from_millis(100));
+
+        assert_eq!(RetryPolicy::parse_statuses("502, 503")?, vec![502, 503]);
+        assert!(RetryPolicy::parse_statuses("5xx").is_err());
+        Ok(())
+    }
+}
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use crate::error::{Error, Result};
+use crate::quote::escape_literal;
 
 pub struct StageLocation {
     pub name: String,
@@ -39,6 +40,8 @@ impl TryFrom<&str> for StageLocation {
         let path = parts
             .next()
             .ok_or_else(|| Error::Parsing(format!("Invalid stage location: {}", s)))?;
+        validate_name(name)?;
+        validate_path(path)?;
         Ok(Self {
             name: name.to_string(),
             path: path.to_string(),
@@ -46,7 +49,58 @@ impl TryFrom<&str> for StageLocation {
     }
 }
 
+// the location is used unquoted in SQL and as the `stage_name` header,
+// only `~` for user stage or plain identifiers are allowed as name
+fn validate_name(name: &str) -> Result<()> {
+    if name == "~" {
+        return Ok(());
+    }
+    if name.is_empty() {
+        return Err(Error::BadArgument("Stage name is empty".to_string()));
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && *c != '_')
+    {
+        return Err(Error::BadArgument(format!(
+            "Invalid character {:?} in stage name: {}",
+            c, name
+        )));
+    }
+    Ok(())
+}
+
+// paths with whitespace or quotes are quoted in SQL with `sql_literal()`,
+// only characters that could not be quoted are rejected
+fn validate_path(path: &str) -> Result<()> {
+    if let Some(c) = path.chars().find(|c| c.is_control()) {
+        return Err(Error::BadArgument(format!(
+            "Invalid character {:?} in stage path: {}",
+            c, path
+        )));
+    }
+    if path.split('/').any(|segment| segment == "..") {
+        return Err(Error::BadArgument(format!(
+            "Path traversal is not allowed in stage path: {}",
+            path
+        )));
+    }
+    Ok(())
+}
+
 impl StageLocation {
+    /// The location to be used in SQL, quoted as a string literal if the
+    /// path contains characters ending an unquoted location.
+    pub fn sql_literal(&self) -> String {
+        let location = self.to_string();
+        if !location.chars().any(|c| {
+            c.is_whitespace() || matches!(c, '\'' | '"' | '`' | '\\' | ';' | '(' | ')' | ',')
+        }) {
+            return location;
+        }
+        escape_literal(&location)
+    }
+
     pub fn file_path(&self, file_name: &str) -> String {
         if self.path.ends_with('/') {
             format!("{}{}", self, file_name)
@@ -74,6 +128,33 @@ mod test {
         let location = "stage_name/path/to/file";
         let stage = StageLocation::try_from(location);
         assert!(stage.is_err());
+
+        for location in [
+            "@/path",
+            "@s-1/path",
+            "@s1;DROP/path",
+            "@s1/path/../../other",
+            "@s1/a\nb",
+            "@s1/a\tb",
+        ] {
+            assert!(StageLocation::try_from(location).is_err(), "{}", location);
+        }
+        assert!(StageLocation::try_from("@~/a/b..c/./d.csv").is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn sql_literal() -> Result<()> {
+        for (location, expected) in [
+            ("@s1/path/to/file", "@s1/path/to/file"),
+            ("@~/a/b*.csv", "@~/a/b*.csv"),
+            ("@s1/path to/file", "'@s1/path to/file'"),
+            ("@s1/it's", r"'@s1/it\'s'"),
+            ("@s1/a\\b;DROP", "'@s1/a\\\\b;DROP'"),
+        ] {
+            let stage = StageLocation::try_from(location)?;
+            assert_eq!(stage.sql_literal(), expected, "{}", location);
+        }
         Ok(())
     }
 }
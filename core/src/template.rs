@@ -0,0 +1,105 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Templates for statements generated by the driver, every `?` in the template
+//! is replaced by a parameter rendered according to its kind, so that user input
+//! never reaches the statement without quoting or validation.
+
+use crate::error::{Error, Result};
+use crate::quote::{escape_literal, quote_ident, quote_qualified_ident};
+use crate::stage::StageLocation;
+
+pub enum Param<'a> {
+    /// Quoted with backticks.
+    Ident(&'a str),
+    /// Dot separated name like `db.table`, quoted part by part.
+    QualifiedIdent(&'a str),
+    /// Single quoted string literal.
+    Literal(&'a str),
+    /// Stage location, which is validated when parsed.
+    Location(&'a StageLocation),
+    /// Unquoted keyword, only ascii letters and `_` are allowed.
+    Keyword(&'a str),
+}
+
+impl Param<'_> {
+    fn render(&self) -> Result<String> {
+        match self {
+            Param::Ident(s) => Ok(quote_ident(s)),
+            Param::QualifiedIdent(s) => Ok(quote_qualified_ident(s)),
+            Param::Literal(s) => Ok(escape_literal(s)),
+            Param::Location(location) => Ok(location.sql_literal()),
+            Param::Keyword(s) => {
+                if s.is_empty() || !s.chars().all(|c| c.is_ascii_alphabetic() || c == '_') {
+                    return Err(Error::BadArgument(format!("Invalid keyword: {}", s)));
+                }
+                Ok(s.to_uppercase())
+            }
+        }
+    }
+}
+
+/// Render the template, the number of parameters must match the placeholders.
+pub fn render(template: &str, params: &[Param]) -> Result<String> {
+    let mut sql = String::with_capacity(template.len());
+    let mut params = params.iter();
+    for (i, part) in template.split('?').enumerate() {
+        if i > 0 {
+            let param = params.next().ok_or_else(|| {
+                Error::BadArgument(format!("Missing parameters for template: {}", template))
+            })?;
+            sql.push_str(&param.render()?);
+        }
+        sql.push_str(part);
+    }
+    if params.next().is_some() {
+        return Err(Error::BadArgument(format!(
+            "Too many parameters for template: {}",
+            template
+        )));
+    }
+    Ok(sql)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render_template() -> Result<()> {
+        let location = StageLocation::try_from("@s1/data/")?;
+        assert_eq!(
+            render(
+                "COPY INTO ? FROM ? PATTERN = ?",
+                &[
+                    Param::QualifiedIdent("db.t"),
+                    Param::Location(&location),
+                    Param::Literal("it's.*"),
+                ]
+            )?,
+            r"COPY INTO `db`.`t` FROM @s1/data/ PATTERN = 'it\'s.*'"
+        );
+        assert_eq!(
+            render(
+                "PRESIGN ? ?",
+                &[Param::Keyword("upload"), Param::Location(&location)]
+            )?,
+            "PRESIGN UPLOAD @s1/data/"
+        );
+        assert!(render("PRESIGN ? ?", &[Param::Keyword("UPLOAD; DROP")]).is_err());
+        assert!(render("LIST ?", &[Param::Ident("a"), Param::Ident("b")]).is_err());
+        assert!(render("LIST ?", &[]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// average sizes of files bound by round trips or by bandwidth
+const SMALL_FILE: u64 = 16 << 20;
+const LARGE_FILE: u64 = 256 << 20;
+
+/// Tuning of uploads to stage.
+///
+/// Presigned urls from `PRESIGN UPLOAD` take a whole file in a single `PUT`,
+/// so a file could not be split into parts uploaded in parallel, there is no
+/// multipart threshold or part size to tune. Files are uploaded concurrently
+/// instead when putting many of them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UploadOptions {
+    /// Files uploaded or downloaded at the same time, chosen by the file
+    /// sizes if `None`.
+    pub concurrency: Option<usize>,
+    /// Attempts of each file uploaded with presigned url, broken uploads
+    /// start over from the beginning. The retry policy is followed if `None`.
+    /// Uploads through the server with `presigned_url_disabled` are never
+    /// retried.
+    pub presigned_max_attempts: Option<usize>,
+}
+
+impl UploadOptions {
+    /// Concurrency to upload files of the sizes, more for small files
+    /// and less for large ones unless set explicitly.
+    pub fn concurrency_for(&self, sizes: &[u64]) -> usize {
+        let concurrency = match self.concurrency {
+            Some(concurrency) => concurrency,
+            None if sizes.is_empty() => 1,
+            None => match sizes.iter().sum::<u64>() / sizes.len() as u64 {
+                avg if avg < SMALL_FILE => 8,
+                avg if avg < LARGE_FILE => 4,
+                _ => 2,
+            },
+        };
+        concurrency.clamp(1, sizes.len().max(1))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn upload_concurrency() {
+        let options = UploadOptions::default();
+        assert_eq!(options.concurrency_for(&[]), 1);
+        assert_eq!(options.concurrency_for(&[1 << 10; 20]), 8);
+        assert_eq!(options.concurrency_for(&[1 << 10; 3]), 3);
+        assert_eq!(options.concurrency_for(&[64 << 20; 20]), 4);
+        assert_eq!(options.concurrency_for(&[1 << 30; 20]), 2);
+        let options = UploadOptions {
+            concurrency: Some(16),
+            ..Default::default()
+        };
+        assert_eq!(options.concurrency_for(&[1 << 30; 20]), 16);
+        assert_eq!(options.concurrency_for(&[1 << 30; 2]), 2);
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+/// How long to wait for results of a running query.
+///
+/// The server only takes `wait_time_secs` with the query request, and holds
+/// each page request up to that long before responding without data. Longer
+/// waits of long running queries are done by the client, pausing before the
+/// next poll instead of asking again right after a page without data.
+pub trait WaitStrategy: Send + Sync {
+    /// Sent with the query request, the server default is used if `None`.
+    fn wait_time_secs(&self) -> Option<i64>;
+
+    /// Pause before polling the next page, after `empty_pages` pages in a row
+    /// without data, and `elapsed` since the first response of the query.
+    fn poll_delay(&self, elapsed: Duration, empty_pages: u32) -> Duration;
+}
+
+/// The same wait for all queries, never pausing between polls.
+#[derive(Clone, Debug, Default)]
+pub struct FixedWait {
+    pub wait_time_secs: Option<i64>,
+}
+
+impl WaitStrategy for FixedWait {
+    fn wait_time_secs(&self) -> Option<i64> {
+        self.wait_time_secs
+    }
+
+    fn poll_delay(&self, _elapsed: Duration, _empty_pages: u32) -> Duration {
+        Duration::ZERO
+    }
+}
+
+/// Short waits for interactive queries, backing off once a query runs
+/// longer than `interactive`, to reduce requests of long running ones.
+#[derive(Clone, Debug)]
+pub struct AdaptiveWait {
+    pub wait_time_secs: i64,
+    pub interactive: Duration,
+    /// Pause after the first page without data, doubled for each of the next ones.
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for AdaptiveWait {
+    fn default() -> Self {
+        Self {
+            wait_time_secs: 1,
+            interactive: Duration::from_secs(3),
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl WaitStrategy for AdaptiveWait {
+    fn wait_time_secs(&self) -> Option<i64> {
+        Some(self.wait_time_secs)
+    }
+
+    fn poll_delay(&self, elapsed: Duration, empty_pages: u32) -> Duration {
+        if elapsed < self.interactive || empty_pages == 0 {
+            return Duration::ZERO;
+        }
+        let exp = (empty_pages - 1).min(31);
+        self.initial_delay
+            .saturating_mul(2u32.pow(exp))
+            .min(self.max_delay)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn adaptive_wait() {
+        let wait = AdaptiveWait::default();
+        assert_eq!(wait.wait_time_secs(), Some(1));
+        assert_eq!(wait.poll_delay(Duration::from_secs(1), 5), Duration::ZERO);
+        assert_eq!(wait.poll_delay(Duration::from_secs(5), 0), Duration::ZERO);
+        assert_eq!(
+            wait.poll_delay(Duration::from_secs(5), 1),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            wait.poll_delay(Duration::from_secs(5), 3),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            wait.poll_delay(Duration::from_secs(60), 100),
+            Duration::from_secs(10)
+        );
+        let fixed = FixedWait {
+            wait_time_secs: Some(5),
+        };
+        assert_eq!(
+            fixed.poll_delay(Duration::from_secs(60), 100),
+            Duration::ZERO
+        );
+    }
+}
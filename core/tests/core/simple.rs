@@ -23,3 +23,35 @@ async fn select_simple() {
     let resp = client.query("select 15532").await.unwrap();
     assert_eq!(resp.data, [["15532"]]);
 }
+
+#[tokio::test]
+async fn select_pages() {
+    use tokio_stream::StreamExt;
+
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let client = APIClient::from_dsn(dsn).await.unwrap();
+    let mut pages = client.pages("select * from numbers(10)");
+    let mut rows = 0;
+    let mut last = None;
+    while let Some(page) = pages.next().await {
+        let page = page.unwrap();
+        rows += page.data.len();
+        last = Some(page);
+    }
+    assert_eq!(rows, 10);
+    assert!(last.unwrap().next_uri.is_none());
+}
+
+#[tokio::test]
+async fn select_query_wait() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let client = APIClient::from_dsn(&format!("{}&max_rows_per_page=3", dsn))
+        .await
+        .unwrap();
+    let resp = client
+        .query_wait("select * from numbers(10) order by number")
+        .await
+        .unwrap();
+    let numbers: Vec<&str> = resp.data.iter().map(|row| row[0].as_str()).collect();
+    assert_eq!(numbers, ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"]);
+}
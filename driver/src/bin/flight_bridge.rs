@@ -0,0 +1,61 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::net::SocketAddr;
+
+use databend_driver::FlightBridge;
+
+const USAGE: &str = "Usage: databend-flight-bridge [--listen <addr>] --dsn <dsn>";
+
+#[tokio::main]
+async fn main() {
+    let mut listen = "127.0.0.1:8900".to_string();
+    let mut dsn = std::env::var("BENDSQL_DSN").ok();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--listen", Some(v)) => listen = v,
+            ("--dsn", Some(v)) => dsn = Some(v),
+            _ => exit(USAGE),
+        }
+    }
+    let Some(dsn) = dsn else { exit(USAGE) };
+    let addr: SocketAddr = match listen.parse() {
+        Ok(addr) => addr,
+        Err(e) => exit(&format!("invalid listen address {}: {}", listen, e)),
+    };
+    let bridge = match FlightBridge::new(&dsn) {
+        Ok(bridge) => bridge,
+        Err(e) => exit(&e.to_string()),
+    };
+    eprintln!("forwarding FlightSQL on {} to {}", addr, redact(&dsn));
+    if let Err(e) = bridge.serve(addr).await {
+        exit(&e.to_string());
+    }
+}
+
+fn redact(dsn: &str) -> String {
+    match url::Url::parse(dsn) {
+        Ok(mut u) => {
+            let _ = u.set_password(None);
+            u.to_string()
+        }
+        Err(_) => dsn.to_string(),
+    }
+}
+
+fn exit(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    std::process::exit(1)
+}
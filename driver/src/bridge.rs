@@ -0,0 +1,490 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A FlightSQL server forwarding statements to a Databend RestAPI endpoint,
+//! for Arrow native tools on deployments without the Flight port exposed.
+
+// errors of tonic services are always `Status`
+#![allow(clippy::result_large_err)]
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::error::FlightError;
+use arrow_flight::flight_service_server::{FlightService, FlightServiceServer};
+use arrow_flight::sql::server::FlightSqlService;
+use arrow_flight::sql::{
+    ActionClosePreparedStatementRequest, ActionCreatePreparedStatementRequest,
+    ActionCreatePreparedStatementResult, CommandPreparedStatementQuery,
+    CommandPreparedStatementUpdate, CommandStatementQuery, CommandStatementUpdate, ProstMessageExt,
+    SqlInfo, TicketStatementQuery,
+};
+use arrow_flight::{
+    Action, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo, HandshakeRequest,
+    HandshakeResponse, Ticket,
+};
+use arrow_schema::{Schema as ArrowSchema, SchemaRef as ArrowSchemaRef};
+use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
+use base64::Engine;
+use prost::Message;
+use tokio_stream::{Stream, StreamExt};
+use tonic::metadata::MetadataMap;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status, Streaming};
+use url::Url;
+
+use databend_sql::error::{Error, Result};
+use databend_sql::ipc::rows_to_batch;
+use databend_sql::rows::RowIterator;
+
+use crate::conn::{Client, Connection};
+use crate::ext::ConnectionExt;
+
+const BATCH_SIZE: usize = 8192;
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const DEFAULT_MAX_SESSIONS: usize = 1024;
+// per session, clients rarely keep more open at once
+const MAX_STATEMENTS: usize = 1024;
+const MAX_RESULTS: usize = 64;
+
+/// Sessions are created on handshake with the user and password of the
+/// FlightSQL client, which replace the ones in the dsn of the endpoint.
+///
+/// Sessions, prepared statements and results not fetched are evicted when
+/// idle longer than the idle timeout, or the least recently used ones when
+/// there are too many, queries of evicted results are killed.
+#[derive(Clone)]
+pub struct FlightBridge {
+    dsn: String,
+    idle_timeout: Duration,
+    sessions: Arc<Mutex<LruMap<String, Arc<Session>>>>,
+}
+
+struct Session {
+    conn: Box<dyn Connection>,
+    statements: Mutex<LruMap<Vec<u8>, String>>,
+    // results of queries not fetched yet, by ticket
+    results: Mutex<LruMap<Vec<u8>, RowIterator>>,
+}
+
+impl Session {
+    fn new(conn: Box<dyn Connection>, idle_timeout: Duration) -> Self {
+        Self {
+            conn,
+            statements: Mutex::new(LruMap::new(idle_timeout, MAX_STATEMENTS)),
+            results: Mutex::new(LruMap::new(idle_timeout, MAX_RESULTS)),
+        }
+    }
+
+    fn add_result(&self, handle: Vec<u8>, rows: RowIterator) {
+        let evicted = self.results.lock().unwrap().insert(handle, rows);
+        evicted.into_iter().for_each(kill_rows);
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let results = self.results.get_mut().unwrap().drain();
+        results.into_iter().for_each(kill_rows);
+    }
+}
+
+/// Map with entries expired after being idle for the ttl, and the least
+/// recently used ones evicted when there are more than the capacity.
+struct LruMap<K, V> {
+    entries: HashMap<K, (V, Instant)>,
+    ttl: Duration,
+    capacity: usize,
+}
+
+impl<K: Eq + std::hash::Hash + Clone, V> LruMap<K, V> {
+    fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            ttl,
+            capacity,
+        }
+    }
+
+    /// Returns the evicted values.
+    fn insert(&mut self, key: K, value: V) -> Vec<V> {
+        self.entries.insert(key, (value, Instant::now()));
+        self.evict()
+    }
+
+    fn get(&mut self, key: &K) -> Option<&V> {
+        let (value, used) = self.entries.get_mut(key)?;
+        *used = Instant::now();
+        Some(value)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.entries.remove(key).map(|(value, _)| value)
+    }
+
+    fn evict(&mut self) -> Vec<V> {
+        let now = Instant::now();
+        let expired = self
+            .entries
+            .iter()
+            .filter(|(_, (_, used))| now.duration_since(*used) > self.ttl)
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        let mut evicted = expired
+            .iter()
+            .filter_map(|key| self.remove(key))
+            .collect::<Vec<_>>();
+        while self.entries.len() > self.capacity {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone());
+            match oldest.and_then(|key| self.remove(&key)) {
+                Some(value) => evicted.push(value),
+                None => break,
+            }
+        }
+        evicted
+    }
+
+    fn drain(&mut self) -> Vec<V> {
+        self.entries.drain().map(|(_, (value, _))| value).collect()
+    }
+}
+
+/// Kill the query of results which will never be fetched.
+fn kill_rows(rows: RowIterator) {
+    let Some(handle) = rows.handle() else {
+        return;
+    };
+    if let Ok(rt) = tokio::runtime::Handle::try_current() {
+        rt.spawn(async move {
+            let _ = handle.cancel().await;
+        });
+    }
+}
+
+impl FlightBridge {
+    pub fn new(dsn: &str) -> Result<Self> {
+        let u = Url::parse(dsn)?;
+        if !matches!(u.scheme(), "databend" | "databend+http" | "databend+https") {
+            return Err(Error::BadArgument(format!(
+                "Bridge only forwards to RestAPI dsn, got scheme: {}",
+                u.scheme()
+            )));
+        }
+        Ok(Self {
+            dsn: dsn.to_string(),
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            sessions: Arc::new(Mutex::new(LruMap::new(
+                DEFAULT_IDLE_TIMEOUT,
+                DEFAULT_MAX_SESSIONS,
+            ))),
+        })
+    }
+
+    /// Evict sessions, prepared statements and results idle for longer,
+    /// 30 minutes by default.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self.sessions.lock().unwrap().ttl = timeout;
+        self
+    }
+
+    /// Evict the least recently used sessions when there are more,
+    /// 1024 by default.
+    pub fn with_max_sessions(self, max_sessions: usize) -> Self {
+        self.sessions.lock().unwrap().capacity = max_sessions;
+        self
+    }
+
+    /// Listen on the address until the server fails.
+    pub async fn serve(self, addr: SocketAddr) -> Result<()> {
+        // idle entries are also evicted on access, this catches the ones
+        // of clients gone away
+        let sweeper = tokio::spawn({
+            let bridge = self.clone();
+            async move {
+                let mut interval =
+                    tokio::time::interval((bridge.idle_timeout / 4).max(Duration::from_secs(1)));
+                loop {
+                    interval.tick().await;
+                    bridge.evict();
+                }
+            }
+        });
+        let served = Server::builder()
+            .add_service(FlightServiceServer::new(self))
+            .serve(addr)
+            .await
+            .map_err(|e| Error::Transport(e.to_string()));
+        sweeper.abort();
+        served
+    }
+
+    fn evict(&self) {
+        // dropped outside of the lock, which kills their queries
+        let evicted = self.sessions.lock().unwrap().evict();
+        drop(evicted);
+        let sessions = self
+            .sessions
+            .lock()
+            .unwrap()
+            .entries
+            .values()
+            .map(|(session, _)| session.clone())
+            .collect::<Vec<_>>();
+        for session in sessions {
+            session.statements.lock().unwrap().evict();
+            let results = session.results.lock().unwrap().evict();
+            results.into_iter().for_each(kill_rows);
+        }
+    }
+
+    fn session(&self, metadata: &MetadataMap) -> std::result::Result<Arc<Session>, Status> {
+        let token = metadata
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or_else(|| Status::unauthenticated("missing bearer token"))?;
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(&token.to_string())
+            .cloned()
+            .ok_or_else(|| Status::unauthenticated("invalid bearer token"))
+    }
+
+    fn dsn_with_user(&self, user: &str, password: &str) -> Result<String> {
+        let mut u = Url::parse(&self.dsn)?;
+        u.set_username(user)
+            .and_then(|_| u.set_password(Some(password)))
+            .map_err(|_| Error::BadArgument("Invalid user for dsn".to_string()))?;
+        Ok(u.to_string())
+    }
+
+    /// Run the query and keep the rows until fetched with the ticket.
+    async fn flight_info(
+        &self,
+        session: &Session,
+        sql: &str,
+        descriptor: FlightDescriptor,
+    ) -> std::result::Result<FlightInfo, Status> {
+        let rows = session.conn.query_iter(sql).await.map_err(to_status)?;
+        let schema = ArrowSchema::try_from(rows.schema().as_ref()).map_err(to_status)?;
+        let handle = new_token().into_bytes();
+        session.add_result(handle.clone(), rows);
+        let ticket = TicketStatementQuery {
+            statement_handle: handle.into(),
+        };
+        let endpoint =
+            FlightEndpoint::new().with_ticket(Ticket::new(ticket.as_any().encode_to_vec()));
+        FlightInfo::new()
+            .try_with_schema(&schema)
+            .map_err(|e| Status::internal(e.to_string()))
+            .map(|info| info.with_endpoint(endpoint).with_descriptor(descriptor))
+    }
+}
+
+type DoGetStream = <FlightBridge as FlightService>::DoGetStream;
+type HandshakeStream =
+    Pin<Box<dyn Stream<Item = std::result::Result<HandshakeResponse, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl FlightSqlService for FlightBridge {
+    type FlightService = FlightBridge;
+
+    async fn do_handshake(
+        &self,
+        request: Request<Streaming<HandshakeRequest>>,
+    ) -> std::result::Result<Response<HandshakeStream>, Status> {
+        let basic = request
+            .metadata()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Basic "))
+            .ok_or_else(|| Status::unauthenticated("missing basic authorization"))?;
+        let decoded = BASE64_STANDARD
+            .decode(basic)
+            .ok()
+            .and_then(|v| String::from_utf8(v).ok())
+            .ok_or_else(|| Status::unauthenticated("invalid basic authorization"))?;
+        let (user, password) = decoded.split_once(':').unwrap_or((&decoded, ""));
+        let dsn = self.dsn_with_user(user, password).map_err(to_status)?;
+        let conn = Client::new(dsn).get_conn().await.map_err(to_status)?;
+        // authenticate before handing out the token
+        conn.version()
+            .await
+            .map_err(|e| Status::unauthenticated(e.to_string()))?;
+
+        let token = new_token();
+        let session = Arc::new(Session::new(conn, self.idle_timeout));
+        let evicted = self.sessions.lock().unwrap().insert(token.clone(), session);
+        drop(evicted);
+        let result = HandshakeResponse {
+            protocol_version: 0,
+            payload: token.clone().into(),
+        };
+        let stream: HandshakeStream = Box::pin(tokio_stream::iter(vec![Ok(result)]));
+        let mut resp = Response::new(stream);
+        let value = format!("Bearer {}", token)
+            .parse()
+            .map_err(|_| Status::internal("invalid token"))?;
+        resp.metadata_mut().insert("authorization", value);
+        Ok(resp)
+    }
+
+    async fn get_flight_info_statement(
+        &self,
+        query: CommandStatementQuery,
+        request: Request<FlightDescriptor>,
+    ) -> std::result::Result<Response<FlightInfo>, Status> {
+        let session = self.session(request.metadata())?;
+        let info = self
+            .flight_info(&session, &query.query, request.into_inner())
+            .await?;
+        Ok(Response::new(info))
+    }
+
+    async fn get_flight_info_prepared_statement(
+        &self,
+        query: CommandPreparedStatementQuery,
+        request: Request<FlightDescriptor>,
+    ) -> std::result::Result<Response<FlightInfo>, Status> {
+        let session = self.session(request.metadata())?;
+        let sql = statement(&session, &query.prepared_statement_handle)?;
+        let info = self
+            .flight_info(&session, &sql, request.into_inner())
+            .await?;
+        Ok(Response::new(info))
+    }
+
+    async fn do_get_statement(
+        &self,
+        ticket: TicketStatementQuery,
+        request: Request<Ticket>,
+    ) -> std::result::Result<Response<DoGetStream>, Status> {
+        let session = self.session(request.metadata())?;
+        let rows = session
+            .results
+            .lock()
+            .unwrap()
+            .remove(&ticket.statement_handle.to_vec())
+            .ok_or_else(|| Status::not_found("unknown ticket"))?;
+        let schema = ArrowSchema::try_from(rows.schema().as_ref()).map_err(to_status)?;
+        let schema = Arc::new(schema);
+        let batches = record_batches(rows, schema.clone());
+        let stream = FlightDataEncoderBuilder::new()
+            .with_schema(schema)
+            .build(batches)
+            .map(|data| data.map_err(|e| Status::internal(e.to_string())));
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn do_put_statement_update(
+        &self,
+        ticket: CommandStatementUpdate,
+        request: Request<Streaming<FlightData>>,
+    ) -> std::result::Result<i64, Status> {
+        let session = self.session(request.metadata())?;
+        session.conn.exec(&ticket.query).await.map_err(to_status)
+    }
+
+    async fn do_put_prepared_statement_update(
+        &self,
+        query: CommandPreparedStatementUpdate,
+        request: Request<Streaming<FlightData>>,
+    ) -> std::result::Result<i64, Status> {
+        let session = self.session(request.metadata())?;
+        let sql = statement(&session, &query.prepared_statement_handle)?;
+        session.conn.exec(&sql).await.map_err(to_status)
+    }
+
+    async fn do_action_create_prepared_statement(
+        &self,
+        query: ActionCreatePreparedStatementRequest,
+        request: Request<Action>,
+    ) -> std::result::Result<ActionCreatePreparedStatementResult, Status> {
+        let session = self.session(request.metadata())?;
+        let handle = new_token().into_bytes();
+        // evicted statements are only kept by clients, nothing to clean up
+        let _ = session
+            .statements
+            .lock()
+            .unwrap()
+            .insert(handle.clone(), query.query);
+        // the schema is only known after the query is run
+        Ok(ActionCreatePreparedStatementResult {
+            prepared_statement_handle: handle.into(),
+            ..Default::default()
+        })
+    }
+
+    async fn do_action_close_prepared_statement(
+        &self,
+        query: ActionClosePreparedStatementRequest,
+        request: Request<Action>,
+    ) -> std::result::Result<(), Status> {
+        let session = self.session(request.metadata())?;
+        session
+            .statements
+            .lock()
+            .unwrap()
+            .remove(&query.prepared_statement_handle.to_vec());
+        Ok(())
+    }
+
+    async fn register_sql_info(&self, _id: i32, _result: &SqlInfo) {}
+}
+
+fn statement(session: &Session, handle: &[u8]) -> std::result::Result<String, Status> {
+    session
+        .statements
+        .lock()
+        .unwrap()
+        .get(&handle.to_vec())
+        .cloned()
+        .ok_or_else(|| Status::not_found("unknown prepared statement"))
+}
+
+/// Rows in batches of `BATCH_SIZE`, converted as they arrive.
+fn record_batches(
+    rows: RowIterator,
+    schema: ArrowSchemaRef,
+) -> impl Stream<Item = std::result::Result<arrow::record_batch::RecordBatch, FlightError>> {
+    rows.chunks_timeout(BATCH_SIZE, std::time::Duration::from_secs(1))
+        .map(move |chunk| {
+            let rows = chunk
+                .into_iter()
+                .collect::<Result<Vec<_>>>()
+                .map_err(|e| FlightError::ExternalError(Box::new(e)))?;
+            rows_to_batch(&schema, &rows).map_err(|e| FlightError::ExternalError(Box::new(e)))
+        })
+}
+
+fn new_token() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+fn to_status(e: Error) -> Status {
+    match e {
+        Error::BadArgument(msg) | Error::Parsing(msg) => Status::invalid_argument(msg),
+        e => Status::internal(e.to_string()),
+    }
+}
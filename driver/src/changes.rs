@@ -0,0 +1,163 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use databend_sql::builder;
+use databend_sql::error::{Error, Result};
+use databend_sql::rows::{Row, RowIterator};
+use databend_sql::schema::SchemaRef;
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::conn::Connection;
+
+/// Changes consumed in a transaction, which is committed once the reader
+/// is done with all of them.
+struct ChangeBatch {
+    rows: Vec<Result<Row>>,
+    done: Option<oneshot::Sender<()>>,
+}
+
+/// Consume changes of the stream in a session of its own, see
+/// [`crate::ConnectionExt::stream_changes`].
+pub(crate) async fn stream_changes(
+    conn: Box<dyn Connection>,
+    stream: &str,
+    poll_interval: Duration,
+) -> Result<RowIterator> {
+    let sql = builder::consume_stream(stream);
+    // the first batch is consumed in place to get the schema
+    let (schema, first) = consume(conn.as_ref(), &sql).await?;
+    // one batch at a time, the next one is consumed after the last committed
+    let (tx, rx) = mpsc::channel(1);
+    tokio::spawn(async move {
+        let mut first = Some(first);
+        let mut failures = 0u32;
+        loop {
+            let consumed = match first.take() {
+                Some(rows) => Ok(rows),
+                None => consume(conn.as_ref(), &sql).await.map(|(_, rows)| rows),
+            };
+            let result = match consumed {
+                Ok(rows) if rows.is_empty() => Ok(false),
+                Ok(rows) => {
+                    let (done, read) = oneshot::channel();
+                    let rows = rows.into_iter().map(Ok).collect();
+                    let batch = ChangeBatch {
+                        rows,
+                        done: Some(done),
+                    };
+                    // rolled back if the reader is gone before reading all
+                    if tx.send(batch).await.is_err() || read.await.is_err() {
+                        let _ = conn.exec("ROLLBACK").await;
+                        return;
+                    }
+                    conn.exec("COMMIT").await.map(|_| true)
+                }
+                Err(e) => Err(e),
+            };
+            let wait = match result {
+                Ok(true) => {
+                    failures = 0;
+                    continue;
+                }
+                Ok(false) => {
+                    failures = 0;
+                    poll_interval
+                }
+                Err(e) => {
+                    failures += 1;
+                    let batch = ChangeBatch {
+                        rows: vec![Err(e)],
+                        done: None,
+                    };
+                    if tx.send(batch).await.is_err() {
+                        return;
+                    }
+                    poll_interval.saturating_mul(1 << failures.min(6))
+                }
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = tx.closed() => return,
+            }
+        }
+    });
+    let rows = ChangeRows {
+        rx,
+        rows: Vec::new().into_iter(),
+        done: None,
+    };
+    Ok(RowIterator::new(schema, Box::pin(rows)))
+}
+
+/// Consume pending changes in a transaction, which is left open for the
+/// caller if any change is consumed, and rolled back otherwise.
+async fn consume(conn: &dyn Connection, sql: &str) -> Result<(SchemaRef, Vec<Row>)> {
+    conn.exec("BEGIN").await?;
+    let consumed = async {
+        let mut rows = conn.query_iter(sql).await?;
+        let schema = rows.schema();
+        let mut batch = Vec::new();
+        while let Some(row) = rows.next().await {
+            batch.push(row?);
+        }
+        Ok::<_, Error>((schema, batch))
+    }
+    .await;
+    match consumed {
+        Ok((schema, rows)) if rows.is_empty() => {
+            conn.exec("ROLLBACK").await?;
+            Ok((schema, rows))
+        }
+        Ok(consumed) => Ok(consumed),
+        Err(e) => {
+            let _ = conn.exec("ROLLBACK").await;
+            Err(e)
+        }
+    }
+}
+
+/// Rows of the received batches, a batch is marked as read when rows after
+/// its last one are asked for.
+struct ChangeRows {
+    rx: mpsc::Receiver<ChangeBatch>,
+    rows: std::vec::IntoIter<Result<Row>>,
+    done: Option<oneshot::Sender<()>>,
+}
+
+impl Stream for ChangeRows {
+    type Item = Result<Row>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(row) = self.rows.next() {
+                return Poll::Ready(Some(row));
+            }
+            if let Some(done) = self.done.take() {
+                let _ = done.send(());
+            }
+            match ready!(self.rx.poll_recv(cx)) {
+                Some(batch) => {
+                    self.rows = batch.rows.into_iter();
+                    self.done = batch.done;
+                }
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
@@ -13,7 +13,8 @@
 // limitations under the License.
 
 use std::collections::BTreeMap;
-use std::path::Path;
+use std::ops::Range;
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use dyn_clone::DynClone;
@@ -24,34 +25,149 @@ use url::Url;
 #[cfg(feature = "flight-sql")]
 use crate::flight_sql::FlightSQLConnection;
 
-use databend_client::presign::{presign_download_from_stage, PresignedResponse};
-use databend_client::stage::StageLocation;
+use databend_client::comment::QueryComment;
+use databend_client::counters::{EndpointCounters, EndpointStats, ErrorCounters};
+use databend_client::credentials::CredentialsProvider;
+use databend_client::limit::RequestLimiter;
+use databend_client::presign::PresignedResponse;
+use databend_client::upload::UploadOptions;
+use databend_client::HttpClient;
 use databend_sql::error::{Error, Result};
-use databend_sql::rows::{QueryProgress, Row, RowIterator, RowProgressIterator, RowWithProgress};
-use databend_sql::schema::{DataType, Field, NumberDataType, Schema};
-use databend_sql::value::{NumberValue, Value};
+#[cfg(feature = "arrow-ipc")]
+use databend_sql::ipc::{RecordBatchIterator, BATCH_SIZE};
+use databend_sql::rows::{
+    QueryProgress, QueryStats, ResumeToken, Row, RowIterator, RowProgressIterator, RowWithProgress,
+};
+use databend_sql::schema::Schema;
 
+use crate::diagnostics::Diagnostics;
+use crate::ext::ConnectionExt;
 use crate::rest_api::RestAPIConnection;
+use crate::stmt::PreparedStatement;
+use crate::temp::TempObject;
+use crate::utils::ColumnIndex;
+use crate::version::ServerFeature;
 
+#[derive(Clone)]
 pub struct Client {
     dsn: String,
+    credentials_provider: Option<CredentialsProvider>,
+    // shared by connections of the client, for diagnostics
+    error_counters: Arc<ErrorCounters>,
+    // health of the nodes behind dsn, shared by REST connections
+    endpoint_counters: Arc<EndpointCounters>,
+    http_client: Option<HttpClient>,
+    // limit of concurrent requests from dsn, shared by REST connections
+    request_limiter: Arc<std::sync::Mutex<Option<Arc<RequestLimiter>>>>,
 }
 
-impl<'c> Client {
+impl Client {
     pub fn new(dsn: String) -> Self {
-        Self { dsn }
+        Self {
+            dsn,
+            credentials_provider: None,
+            error_counters: Arc::new(ErrorCounters::default()),
+            endpoint_counters: Arc::new(EndpointCounters::default()),
+            http_client: None,
+            request_limiter: Arc::new(std::sync::Mutex::new(None)),
+        }
+    }
+
+    /// Redacted configuration with errors of connections from this client,
+    /// safe to attach to bug reports.
+    pub fn diagnostics(&self) -> Result<Diagnostics> {
+        Diagnostics::new(&self.dsn, self.error_counters.snapshot())
+    }
+
+    /// Successes, errors and the last latency of requests by endpoint,
+    /// to see which nodes are degraded. Only REST connections record them.
+    pub fn endpoint_stats(&self) -> BTreeMap<String, EndpointStats> {
+        self.endpoint_counters.snapshot()
+    }
+
+    /// Set a callback to refresh credentials when authentication fails,
+    /// applies to all connections created by this client.
+    pub fn with_credentials_provider(mut self, provider: CredentialsProvider) -> Self {
+        self.credentials_provider = Some(provider);
+        self
+    }
+
+    /// Send requests of REST connections with the HTTP client, which could
+    /// be shared by clients of different users or databases to reuse one
+    /// connection pool. TLS options in dsn are ignored then.
+    pub fn with_http_client(mut self, cli: HttpClient) -> Self {
+        self.http_client = Some(cli);
+        self
     }
 
     pub async fn get_conn(&self) -> Result<Box<dyn Connection>> {
-        let u = Url::parse(&self.dsn)?;
+        self.connect(&self.dsn).await
+    }
+
+    /// Get a connection with queries routed to the warehouse instead of the
+    /// one in dsn, to run workloads on different warehouses with one client.
+    pub async fn get_conn_with_warehouse(&self, warehouse: &str) -> Result<Box<dyn Connection>> {
+        let mut u = Url::parse(&self.dsn)?;
+        let pairs: Vec<(String, String)> = u
+            .query_pairs()
+            .filter(|(k, _)| k != "warehouse")
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        u.query_pairs_mut()
+            .clear()
+            .extend_pairs(pairs)
+            .append_pair("warehouse", warehouse);
+        self.connect(u.as_str()).await
+    }
+
+    async fn connect(&self, dsn: &str) -> Result<Box<dyn Connection>> {
+        let conn = self.try_connect(dsn).await;
+        if conn.is_err() {
+            self.error_counters.record_connect_error();
+        }
+        conn
+    }
+
+    async fn try_connect(&self, dsn: &str) -> Result<Box<dyn Connection>> {
+        let (dsn, check_version) = take_check_server_version(dsn)?;
+        let conn = self.connect_scheme(&dsn).await?;
+        if check_version {
+            // failed to get the version is not a reason to refuse the
+            // connection, operations needing a newer server would explain
+            // themselves when failed
+            if let Ok(Some(version)) = conn.server_version().await {
+                version.check_supported()?;
+            }
+        }
+        Ok(conn)
+    }
+
+    async fn connect_scheme(&self, dsn: &str) -> Result<Box<dyn Connection>> {
+        let u = Url::parse(dsn)?;
         match u.scheme() {
             "databend" | "databend+http" | "databend+https" => {
-                let conn = RestAPIConnection::try_create(&self.dsn).await?;
+                let mut conn =
+                    RestAPIConnection::try_create(dsn, self.credentials_provider.clone())
+                        .await?
+                        .with_error_counters(self.error_counters.clone())
+                        .with_endpoint_counters(self.endpoint_counters.clone());
+                if let Some(ref cli) = self.http_client {
+                    conn = conn.with_http_client(cli.clone());
+                }
+                // the first connection with `max_concurrent_requests` decides
+                // the limit for all connections of the client
+                let mut limiter = self.request_limiter.lock().unwrap();
+                match *limiter {
+                    Some(ref shared) => conn = conn.with_request_limiter(shared.clone()),
+                    None => *limiter = conn.request_limiter(),
+                }
                 Ok(Box::new(conn))
             }
             #[cfg(feature = "flight-sql")]
             "databend+flight" | "databend+grpc" => {
-                let conn = FlightSQLConnection::try_create(&self.dsn).await?;
+                let conn = FlightSQLConnection::try_create(dsn, self.credentials_provider.clone())
+                    .await?
+                    .with_error_counters(self.error_counters.clone());
                 Ok(Box::new(conn))
             }
             _ => Err(Error::Parsing(format!(
@@ -62,6 +178,53 @@ impl<'c> Client {
     }
 }
 
+/// Remove the driver option `check_server_version` from dsn, which is off by
+/// default and not known to the server.
+fn take_check_server_version(dsn: &str) -> Result<(String, bool)> {
+    let mut u = Url::parse(dsn)?;
+    if !u.query_pairs().any(|(k, _)| k == "check_server_version") {
+        return Ok((dsn.to_string(), false));
+    }
+    let mut check = false;
+    let mut pairs = Vec::new();
+    for (k, v) in u.query_pairs() {
+        if k == "check_server_version" {
+            check = match v.as_ref() {
+                "true" | "1" => true,
+                "false" | "0" => false,
+                _ => {
+                    return Err(Error::BadArgument(format!(
+                        "Invalid value for check_server_version: {}",
+                        v
+                    )))
+                }
+            };
+        } else {
+            pairs.push((k.to_string(), v.to_string()));
+        }
+    }
+    if pairs.is_empty() {
+        u.set_query(None);
+    } else {
+        u.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    Ok((u.to_string(), check))
+}
+
+/// Replace the error of a failed operation with one naming the version
+/// required if the server is too old for the feature, so users are not left
+/// with a parse error from the server.
+pub(crate) async fn explain_failure<C: Connection + ?Sized>(
+    conn: &C,
+    feature: ServerFeature,
+    err: Error,
+) -> Error {
+    match conn.server_version().await {
+        Ok(Some(version)) => feature.check(&version).unwrap_or(err),
+        _ => err,
+    }
+}
+
 pub struct ConnectionInfo {
     pub handler: String,
     pub host: String,
@@ -71,35 +234,218 @@ pub struct ConnectionInfo {
     pub warehouse: Option<String>,
 }
 
+pub struct ServerInfo {
+    pub version: String,
+    pub database: String,
+    pub warehouse: Option<String>,
+    /// State of the warehouse like `Running` or `Suspended`, only known on
+    /// Databend Cloud.
+    pub warehouse_state: Option<String>,
+}
+
+/// Result of `load_data`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoadStats {
+    pub rows_loaded: usize,
+    /// Size of the data uploaded to the stage.
+    pub bytes_uploaded: u64,
+    /// Rows read from the uploaded data but not written to the table, as
+    /// reported by the progress of the insert. Bad rows fail the load by
+    /// default, so it is zero unless the server skips rows it could not
+    /// insert, e.g. with `on_error` of `stream_load_with_options`.
+    pub error_rows: usize,
+}
+
+/// A statement of a script run by `exec_batch`.
+#[derive(Debug)]
+pub struct StatementResult {
+    /// The statement without the trailing `;`.
+    pub sql: String,
+    /// Byte range of the statement in the script.
+    pub span: Range<usize>,
+    /// Affected rows as returned by `exec`.
+    pub result: Result<i64>,
+}
+
+/// One row of `SHOW SETTINGS`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Setting {
+    pub name: String,
+    pub value: String,
+    pub default: String,
+    /// `DEFAULT`, `SESSION` or `GLOBAL`, where the value is from.
+    pub level: String,
+    pub description: Option<String>,
+}
+
+impl Setting {
+    pub(crate) fn from_row(index: &ColumnIndex, row: &Row) -> Result<Self> {
+        Ok(Self {
+            name: index.get(row, "name")?,
+            value: index.get(row, "value")?,
+            default: index.get(row, "default")?,
+            level: index.get(row, "level")?,
+            description: index.get_opt(row, "description")?,
+        })
+    }
+}
+
+/// Id of a query submitted without waiting for results, which could be
+/// persisted as a string and fetched later by another connection.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct QueryId(String);
+
+impl QueryId {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for QueryId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::str::FromStr for QueryId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.is_empty() || s.contains('/') {
+            return Err(Error::BadArgument(format!("Invalid query id: {}", s)));
+        }
+        Ok(Self(s.to_string()))
+    }
+}
+
 pub type Reader = Box<dyn AsyncRead + Send + Sync + Unpin + 'static>;
 
 #[async_trait]
 pub trait Connection: DynClone + Send + Sync {
     async fn info(&self) -> ConnectionInfo;
 
-    async fn version(&self) -> Result<String> {
-        let row = self.query_row("SELECT version()").await?;
-        let version = match row {
-            Some(row) => {
-                let (version,): (String,) = row.try_into().map_err(Error::Parsing)?;
-                version
+    async fn exec(&self, sql: &str) -> Result<i64>;
+
+    /// Execute a statement, returns the resource usage like bytes scanned
+    /// and running time instead of the affected rows.
+    async fn exec_with_stats(&self, sql: &str) -> Result<QueryStats> {
+        let (_, mut rows) = self.query_iter_ext(sql).await?;
+        let mut stats = QueryStats::default();
+        while let Some(item) = rows.next().await {
+            if let RowWithProgress::Stats(s) = item? {
+                stats = s;
             }
-            None => "".to_string(),
-        };
-        Ok(version)
+        }
+        Ok(stats)
     }
 
-    async fn exec(&self, sql: &str) -> Result<i64>;
     async fn query_row(&self, sql: &str) -> Result<Option<Row>>;
+
     async fn query_iter(&self, sql: &str) -> Result<RowIterator>;
+
+    /// Rows with progress, and the schema of the result with names, types
+    /// and nullability of the columns for both handlers.
     async fn query_iter_ext(&self, sql: &str) -> Result<(Schema, RowProgressIterator)>;
 
+    /// Results as arrow record batches, for zero-copy use in dataframe
+    /// libraries. Rows of RestAPI are converted to batches as pages arrive,
+    /// while batches of FlightSQL are passed through as received.
+    #[cfg(feature = "arrow-ipc")]
+    async fn query_arrow(&self, sql: &str) -> Result<RecordBatchIterator> {
+        let rows = self.query_iter(sql).await?;
+        RecordBatchIterator::from_rows(rows, BATCH_SIZE)
+    }
+
+    /// Start the query and return its id at once, results are kept by the
+    /// server until fetched or expired.
+    async fn submit(&self, _sql: &str) -> Result<QueryId> {
+        Err(Error::Protocol(
+            "submit is not supported by this connection".to_string(),
+        ))
+    }
+
+    /// Fetch results of a submitted query from the first page, which could
+    /// be done in another process.
+    async fn fetch(&self, _id: &QueryId) -> Result<RowIterator> {
+        Err(Error::Protocol(
+            "fetch is not supported by this connection".to_string(),
+        ))
+    }
+
+    /// Continue fetching rows of a result from the token of another
+    /// iterator, which could be in another process.
+    async fn resume(&self, _token: &ResumeToken) -> Result<RowIterator> {
+        Err(Error::Protocol(
+            "resume is not supported by this connection".to_string(),
+        ))
+    }
+
+    /// Replace the tags prepended as a comment to all statements sent from
+    /// this connection, e.g. `/* app:etl job:daily */`, to attribute queries
+    /// in the server logs.
+    async fn set_query_comment(&self, _comment: QueryComment) -> Result<()> {
+        Err(Error::Protocol(
+            "query comment is not supported by this connection".to_string(),
+        ))
+    }
+
+    /// Prepare a statement with `?` placeholders to run with parameters.
+    async fn prepare(&self, _sql: &str) -> Result<PreparedStatement> {
+        Err(Error::Protocol(
+            "prepare is not supported by this connection".to_string(),
+        ))
+    }
+
+    /// Restore the session to the state from the dsn, so that `USE` and
+    /// `SET` statements and query comments of one user do not leak to the
+    /// next one when the connection is shared.
+    async fn reset_session(&self) -> Result<()> {
+        Err(Error::Protocol(
+            "reset_session is not supported by this connection".to_string(),
+        ))
+    }
+
+    /// Open a session of its own to the same server, starting with the
+    /// current database and settings, for transactions that must not be
+    /// seen by queries of this connection.
+    async fn new_session(&self) -> Result<Box<dyn Connection>> {
+        Err(Error::Protocol(
+            "new_session is only available in HTTP API".to_string(),
+        ))
+    }
+
     /// Get presigned url for a given operation and stage location.
     /// The operation can be "UPLOAD" or "DOWNLOAD".
     async fn get_presigned_url(&self, operation: &str, stage: &str) -> Result<PresignedResponse>;
 
+    /// Track an object to be dropped when the connection is closed.
+    fn track_temp_object(&self, object: TempObject);
+
+    fn untrack_temp_object(&self, object: &TempObject);
+
+    fn temp_objects(&self) -> Vec<TempObject>;
+
+    /// Drop the tracked temp objects, it is also done in background
+    /// when the last clone of the connection is dropped.
+    async fn close(&self) -> Result<()>;
+
     async fn upload_to_stage(&self, stage: &str, data: Reader, size: u64) -> Result<()>;
 
+    /// Upload a local file, which could be retried by rewinding the file.
+    async fn upload_file_to_stage(
+        &self,
+        stage: &str,
+        file: tokio::fs::File,
+        size: u64,
+    ) -> Result<()> {
+        self.upload_to_stage(stage, Box::new(file), size).await
+    }
+
     async fn stream_load(
         &self,
         _sql: &str,
@@ -113,145 +459,9 @@ pub trait Connection: DynClone + Send + Sync {
         ))
     }
 
-    // PUT file://<path_to_file>/<filename> internalStage|externalStage
-    async fn put_files(
-        &self,
-        local_file: &str,
-        stage: &str,
-    ) -> Result<(Schema, RowProgressIterator)> {
-        let mut total_count: usize = 0;
-        let mut total_size: usize = 0;
-        let local_dsn = url::Url::parse(local_file)?;
-        validate_local_scheme(local_dsn.scheme())?;
-        let mut results = Vec::new();
-        let stage_location = StageLocation::try_from(stage)?;
-        for entry in glob::glob(local_dsn.path())? {
-            let entry = entry?;
-            let filename = entry
-                .file_name()
-                .ok_or(Error::BadArgument(format!(
-                    "Invalid local file path: {:?}",
-                    entry
-                )))?
-                .to_str()
-                .ok_or(Error::BadArgument(format!(
-                    "Invalid local file path: {:?}",
-                    entry
-                )))?;
-            let stage_file = stage_location.file_path(filename);
-            let data = tokio::fs::File::open(&entry).await?;
-            let size = data.metadata().await?.len();
-            let (fname, status) = match self
-                .upload_to_stage(&stage_file, Box::new(data), size)
-                .await
-            {
-                Ok(_) => {
-                    total_count += 1;
-                    total_size += size as usize;
-                    (entry.to_string_lossy().to_string(), "SUCCESS".to_owned())
-                }
-                Err(e) => (entry.to_string_lossy().to_string(), e.to_string()),
-            };
-            let progress = QueryProgress {
-                total_rows: 0,
-                total_bytes: 0,
-                read_rows: 0,
-                read_bytes: 0,
-                write_rows: total_count,
-                write_bytes: total_size,
-            };
-            results.push(Ok(RowWithProgress::Progress(progress)));
-            results.push(Ok(RowWithProgress::Row(Row::from_vec(vec![
-                Value::String(fname),
-                Value::String(status),
-                Value::Number(NumberValue::UInt64(size)),
-            ]))));
-        }
-        Ok((
-            put_get_schema(),
-            RowProgressIterator::new(Box::pin(tokio_stream::iter(results))),
-        ))
-    }
-
-    async fn get_files(
-        &self,
-        stage: &str,
-        local_file: &str,
-    ) -> Result<(Schema, RowProgressIterator)> {
-        let mut total_count: usize = 0;
-        let mut total_size: usize = 0;
-        let local_dsn = url::Url::parse(local_file)?;
-        validate_local_scheme(local_dsn.scheme())?;
-        let mut location = StageLocation::try_from(stage)?;
-        if !location.path.ends_with('/') {
-            location.path.push('/');
-        }
-        let list_sql = format!("LIST {}", location);
-        let mut response = self.query_iter(&list_sql).await?;
-        let mut results = Vec::new();
-        while let Some(row) = response.next().await {
-            let (mut name, _, _, _, _): (String, u64, Option<String>, String, Option<String>) =
-                row?.try_into().map_err(Error::Parsing)?;
-            if !location.path.is_empty() && name.starts_with(&location.path) {
-                name = name[location.path.len()..].to_string();
-            }
-            let stage_file = format!("{}/{}", location, name);
-            let presign = self.get_presigned_url("DOWNLOAD", &stage_file).await?;
-            let local_file = Path::new(local_dsn.path()).join(&name);
-            let status = presign_download_from_stage(presign, &local_file).await;
-            let (status, size) = match status {
-                Ok(size) => {
-                    total_count += 1;
-                    total_size += size as usize;
-                    ("SUCCESS".to_owned(), size)
-                }
-                Err(e) => (e.to_string(), 0),
-            };
-            let progress = QueryProgress {
-                total_rows: 0,
-                total_bytes: 0,
-                read_rows: total_count,
-                read_bytes: total_size,
-                write_rows: 0,
-                write_bytes: 0,
-            };
-            results.push(Ok(RowWithProgress::Progress(progress)));
-            results.push(Ok(RowWithProgress::Row(Row::from_vec(vec![
-                Value::String(local_file.to_string_lossy().to_string()),
-                Value::String(status),
-                Value::Number(NumberValue::UInt64(size)),
-            ]))));
-        }
-        Ok((
-            put_get_schema(),
-            RowProgressIterator::new(Box::pin(tokio_stream::iter(results))),
-        ))
+    /// Concurrency and attempts of uploads to stage.
+    fn upload_options(&self) -> UploadOptions {
+        UploadOptions::default()
     }
 }
 dyn_clone::clone_trait_object!(Connection);
-
-fn put_get_schema() -> Schema {
-    Schema::from_vec(vec![
-        Field {
-            name: "file".to_string(),
-            data_type: DataType::String,
-        },
-        Field {
-            name: "status".to_string(),
-            data_type: DataType::String,
-        },
-        Field {
-            name: "size".to_string(),
-            data_type: DataType::Number(NumberDataType::UInt64),
-        },
-    ])
-}
-
-fn validate_local_scheme(scheme: &str) -> Result<()> {
-    match scheme {
-        "file" | "fs" => Ok(()),
-        _ => Err(Error::BadArgument(
-            "Supported schemes: file:// or fs://".to_string(),
-        )),
-    }
-}
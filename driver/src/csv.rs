@@ -0,0 +1,393 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::{Path, PathBuf};
+
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
+
+use databend_sql::builder;
+use databend_sql::error::{Error, Result};
+use databend_sql::schema::Schema;
+
+use crate::conn::{Connection, LoadStats, Reader};
+use crate::ext::ConnectionExt;
+
+// rejected rows listed in the error when there are too many
+const REJECTED_IN_ERROR: usize = 10;
+
+/// Options of `insert_csv`.
+#[derive(Clone, Debug)]
+pub struct CsvOptions {
+    /// The first row names the columns to insert, matched against the table
+    /// by name, otherwise rows must have all columns of the table in order.
+    pub header: bool,
+    pub delimiter: u8,
+    pub quote: u8,
+    /// Rows which could not be loaded are skipped up to this number,
+    /// nothing is loaded if there are more.
+    pub max_rejected: usize,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            header: true,
+            delimiter: b',',
+            quote: b'"',
+            max_rejected: 0,
+        }
+    }
+}
+
+/// A row skipped by `insert_csv`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RejectedRow {
+    /// Line number in the input where the row starts, from 1.
+    pub line: usize,
+    pub reason: String,
+}
+
+/// Result of `insert_csv`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CsvLoad {
+    pub stats: LoadStats,
+    pub rejected: Vec<RejectedRow>,
+}
+
+/// Rows checked and normalized into a file for loading.
+pub(crate) struct NormalizedCsv {
+    pub columns: Vec<String>,
+    pub rows: usize,
+    pub size: u64,
+    pub rejected: Vec<RejectedRow>,
+}
+
+/// Temp file removed when dropped, also when the load is cancelled.
+pub(crate) struct TempFile {
+    path: PathBuf,
+}
+
+impl TempFile {
+    pub(crate) fn new(prefix: &str) -> Self {
+        let now = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default();
+        let name = format!("{}_{}_{}", prefix, std::process::id(), now);
+        Self {
+            path: std::env::temp_dir().join(name),
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Check rows against the table and write them to the file record by record
+/// with `\n` between records and without BOM, rows with a wrong number of
+/// fields or invalid UTF-8 are rejected with their line numbers.
+pub(crate) async fn normalize(
+    data: Reader,
+    table: &Schema,
+    options: &CsvOptions,
+    path: &Path,
+) -> Result<NormalizedCsv> {
+    let mut reader = BufReader::new(data);
+    let mut writer = BufWriter::new(File::create(path).await?);
+    let mut splitter = RecordSplitter::new(options.quote);
+    let mut columns: Option<Vec<String>> = None;
+    let mut normalized = NormalizedCsv {
+        columns: vec![],
+        rows: 0,
+        size: 0,
+        rejected: vec![],
+    };
+
+    loop {
+        let buf = reader.fill_buf().await?;
+        let (consumed, record) = if buf.is_empty() {
+            (0, splitter.finish())
+        } else {
+            splitter.feed(buf)
+        };
+        let eof = buf.is_empty();
+        reader.consume(consumed);
+        let (line, record) = match record {
+            Some(record) => record,
+            None if eof => break,
+            None => continue,
+        };
+        let text = match std::str::from_utf8(&record) {
+            Ok(text) => text,
+            Err(e) => {
+                normalized.rejected.push(RejectedRow {
+                    line,
+                    reason: format!("invalid UTF-8: {}", e),
+                });
+                continue;
+            }
+        };
+        let expected = match columns {
+            Some(ref columns) => columns.len(),
+            None => {
+                let names = if options.header {
+                    split_fields(text, options.delimiter, options.quote)
+                } else {
+                    table.fields().iter().map(|f| f.name.clone()).collect()
+                };
+                columns = Some(match_columns(names, table)?);
+                if options.header {
+                    continue;
+                }
+                table.fields().len()
+            }
+        };
+        let fields = count_fields(text, options.delimiter, options.quote);
+        if fields != expected {
+            normalized.rejected.push(RejectedRow {
+                line,
+                reason: format!("expected {} fields, found {}", expected, fields),
+            });
+            continue;
+        }
+        writer.write_all(&record).await?;
+        writer.write_all(b"\n").await?;
+        normalized.rows += 1;
+        normalized.size += record.len() as u64 + 1;
+    }
+    writer.flush().await?;
+
+    if normalized.rejected.len() > options.max_rejected {
+        let listed = normalized
+            .rejected
+            .iter()
+            .take(REJECTED_IN_ERROR)
+            .map(|r| format!("line {}: {}", r.line, r.reason))
+            .collect::<Vec<_>>();
+        return Err(Error::BadArgument(format!(
+            "{} rows rejected, more than {} allowed: {}",
+            normalized.rejected.len(),
+            options.max_rejected,
+            listed.join("; ")
+        )));
+    }
+    normalized.columns = columns.unwrap_or_default();
+    Ok(normalized)
+}
+
+/// Names in the header with the case of the table columns.
+fn match_columns(names: Vec<String>, table: &Schema) -> Result<Vec<String>> {
+    let mut columns: Vec<String> = Vec::with_capacity(names.len());
+    for name in names {
+        let column = table
+            .fields()
+            .iter()
+            .find(|f| f.name.eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| Error::BadArgument(format!("Column {} not found in table", name)))?;
+        if columns.contains(&column.name) {
+            return Err(Error::BadArgument(format!(
+                "Column {} appears more than once in header",
+                name
+            )));
+        }
+        columns.push(column.name.clone());
+    }
+    Ok(columns)
+}
+
+fn count_fields(record: &str, delimiter: u8, quote: u8) -> usize {
+    let mut in_quote = false;
+    let mut fields = 1;
+    for b in record.bytes() {
+        if b == quote {
+            in_quote = !in_quote;
+        } else if b == delimiter && !in_quote {
+            fields += 1;
+        }
+    }
+    fields
+}
+
+fn split_fields(record: &str, delimiter: u8, quote: u8) -> Vec<String> {
+    let quote = quote as char;
+    let mut in_quote = false;
+    let mut fields = vec![String::new()];
+    for c in record.chars() {
+        if c == quote {
+            in_quote = !in_quote;
+        } else if c == delimiter as char && !in_quote {
+            fields.push(String::new());
+        } else if let Some(field) = fields.last_mut() {
+            field.push(c);
+        }
+    }
+    fields
+}
+
+/// Split bytes into records by `\n`, `\r\n` or `\r` outside quotes, line
+/// breaks in quotes are kept as they are. Empty lines are skipped.
+struct RecordSplitter {
+    quote: u8,
+    record: Vec<u8>,
+    in_quote: bool,
+    after_cr: bool,
+    // line of the next byte and where the current record starts
+    line: usize,
+    record_line: usize,
+    first: bool,
+}
+
+impl RecordSplitter {
+    fn new(quote: u8) -> Self {
+        Self {
+            quote,
+            record: vec![],
+            in_quote: false,
+            after_cr: false,
+            line: 1,
+            record_line: 1,
+            first: true,
+        }
+    }
+
+    /// Bytes consumed from the buffer, with the record completed by them.
+    fn feed(&mut self, buf: &[u8]) -> (usize, Option<(usize, Vec<u8>)>) {
+        for (i, &b) in buf.iter().enumerate() {
+            let after_cr = std::mem::take(&mut self.after_cr);
+            if self.in_quote {
+                if b == b'\r' || (b == b'\n' && !after_cr) {
+                    self.line += 1;
+                }
+                self.after_cr = b == b'\r';
+                if b == self.quote {
+                    self.in_quote = false;
+                }
+                self.record.push(b);
+                continue;
+            }
+            match b {
+                b'\n' if after_cr => {}
+                b'\r' | b'\n' => {
+                    self.after_cr = b == b'\r';
+                    self.line += 1;
+                    let record = self.take();
+                    self.record_line = self.line;
+                    if record.is_some() {
+                        return (i + 1, record);
+                    }
+                }
+                _ => {
+                    if b == self.quote {
+                        self.in_quote = true;
+                    }
+                    self.record.push(b);
+                }
+            }
+        }
+        (buf.len(), None)
+    }
+
+    fn finish(&mut self) -> Option<(usize, Vec<u8>)> {
+        self.take()
+    }
+    fn take(&mut self) -> Option<(usize, Vec<u8>)> {
+        let mut record = std::mem::take(&mut self.record);
+        if std::mem::take(&mut self.first) && record.starts_with(b"\xef\xbb\xbf") {
+            record.drain(..3);
+        }
+        if record.is_empty() {
+            return None;
+        }
+        Some((self.record_line, record))
+    }
+}
+
+pub(crate) async fn insert_csv<C>(
+    conn: &C,
+    table: &str,
+    data: Reader,
+    options: &CsvOptions,
+) -> Result<CsvLoad>
+where
+    C: Connection + ?Sized,
+{
+    let sql = builder::select(&[]).from(table).limit(0).build()?;
+    let (schema, _) = conn.query_iter_ext(&sql).await?;
+    // removed also when the future is dropped
+    let temp = TempFile::new("databend_csv");
+    let normalized = normalize(data, &schema, options, temp.path()).await?;
+    if normalized.rows == 0 {
+        return Ok(CsvLoad {
+            stats: LoadStats::default(),
+            rejected: normalized.rejected,
+        });
+    }
+    let columns = normalized
+        .columns
+        .iter()
+        .map(|c| c.as_str())
+        .collect::<Vec<_>>();
+    let sql = builder::insert_columns(table, &columns);
+    let delimiter = (options.delimiter as char).to_string();
+    let quote = (options.quote as char).to_string();
+    let format_options = vec![
+        ("type", "CSV"),
+        ("field_delimiter", delimiter.as_str()),
+        ("quote", quote.as_str()),
+        ("record_delimiter", "\n"),
+        ("skip_header", "0"),
+    ]
+    .into_iter()
+    .collect();
+    let file = tokio::fs::File::open(temp.path()).await?;
+    let stats = conn
+        .load_data(&sql, Box::new(file), normalized.size, Some(format_options))
+        .await?;
+    Ok(CsvLoad {
+        stats,
+        rejected: normalized.rejected,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn split_records() {
+        let data = b"\xef\xbb\xbfa,b\r\n\"x\r\ny\",1\r\n\r\nz,2";
+        let mut splitter = RecordSplitter::new(b'"');
+        let mut records = vec![];
+        // feed byte by byte, records could end at any chunk boundary
+        for b in data.chunks(1) {
+            let (consumed, record) = splitter.feed(b);
+            assert_eq!(consumed, 1);
+            records.extend(record);
+        }
+        records.extend(splitter.finish());
+        assert_eq!(
+            records,
+            vec![
+                (1, b"a,b".to_vec()),
+                (2, b"\"x\r\ny\",1".to_vec()),
+                (5, b"z,2".to_vec()),
+            ]
+        );
+    }
+}
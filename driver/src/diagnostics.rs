@@ -0,0 +1,155 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Redacted snapshot of the client configuration with error counters,
+//! to attach to bug reports.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use url::Url;
+
+use databend_client::counters::ErrorCounts;
+use databend_client::APIClient;
+use databend_sql::error::Result;
+
+// args whose values should never leave the machine
+const SECRET_ARGS: [&str; 4] = ["password", "token", "secret", "key"];
+const REDACTED: &str = "***";
+
+#[derive(Clone, Debug)]
+pub struct Diagnostics {
+    /// Version of the driver.
+    pub version: String,
+    /// The dsn with password and secrets redacted.
+    pub dsn: String,
+    /// `RestAPI` or `FlightSQL`.
+    pub handler: String,
+    pub host: String,
+    pub port: Option<u16>,
+    pub tls: bool,
+    /// Args of the dsn, with secrets redacted.
+    pub args: BTreeMap<String, String>,
+    /// Effective timeouts, retries and other options of the handler,
+    /// with defaults for those not set in the dsn.
+    pub options: BTreeMap<String, String>,
+    /// Cargo features the driver is built with.
+    pub features: Vec<&'static str>,
+    pub errors: ErrorCounts,
+}
+
+impl Diagnostics {
+    pub(crate) fn new(dsn: &str, errors: ErrorCounts) -> Result<Self> {
+        let mut u = Url::parse(dsn)?;
+        let (handler, options) = match u.scheme() {
+            "databend+flight" | "databend+grpc" => ("FlightSQL", flight_sql_options(dsn)?),
+            _ => (
+                "RestAPI",
+                APIClient::builder_from_dsn(dsn)?.effective_options(),
+            ),
+        };
+        if u.password().is_some() {
+            // only fails for urls without host, which is not a valid dsn
+            u.set_password(Some(REDACTED)).ok();
+        }
+        let args: BTreeMap<String, String> = u
+            .query_pairs()
+            .map(|(k, v)| {
+                let v = if is_secret(&k) {
+                    REDACTED.to_string()
+                } else {
+                    v.to_string()
+                };
+                (k.to_string(), v)
+            })
+            .collect();
+        u.query_pairs_mut().clear().extend_pairs(&args);
+        if args.is_empty() {
+            u.set_query(None);
+        }
+        Ok(Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            handler: handler.to_string(),
+            host: u.host_str().unwrap_or_default().to_string(),
+            port: u.port(),
+            tls: args.get("sslmode").map(|m| m != "disable").unwrap_or(true),
+            args,
+            options,
+            features: enabled_features(),
+            errors,
+            dsn: u.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "driver version: {}", self.version)?;
+        writeln!(f, "dsn: {}", self.dsn)?;
+        writeln!(f, "handler: {}", self.handler)?;
+        match self.port {
+            Some(port) => writeln!(f, "endpoint: {}:{}", self.host, port)?,
+            None => writeln!(f, "endpoint: {}", self.host)?,
+        }
+        writeln!(f, "tls: {}", self.tls)?;
+        for (k, v) in &self.args {
+            writeln!(f, "arg {}: {}", k, v)?;
+        }
+        for (k, v) in &self.options {
+            writeln!(f, "option {}: {}", k, v)?;
+        }
+        writeln!(f, "features: {}", self.features.join(", "))?;
+        let e = &self.errors;
+        write!(
+            f,
+            "errors: {} connect, {} retries, {} transport, {} server, {} auth",
+            e.connect_errors, e.retries, e.transport_errors, e.server_errors, e.auth_failures
+        )
+    }
+}
+
+#[cfg(feature = "flight-sql")]
+fn flight_sql_options(dsn: &str) -> Result<BTreeMap<String, String>> {
+    crate::flight_sql::effective_options(dsn)
+}
+
+#[cfg(not(feature = "flight-sql"))]
+fn flight_sql_options(_dsn: &str) -> Result<BTreeMap<String, String>> {
+    Ok(BTreeMap::new())
+}
+
+fn is_secret(arg: &str) -> bool {
+    let arg = arg.to_lowercase();
+    SECRET_ARGS.iter().any(|s| arg.contains(s))
+}
+
+fn enabled_features() -> Vec<&'static str> {
+    let features = [
+        ("rustls", cfg!(feature = "rustls")),
+        ("native-tls", cfg!(feature = "native-tls")),
+        ("presign", cfg!(feature = "presign")),
+        ("compression", cfg!(feature = "compression")),
+        ("flight-sql", cfg!(feature = "flight-sql")),
+        ("arrow-ipc", cfg!(feature = "arrow-ipc")),
+        ("spill", cfg!(feature = "spill")),
+        ("local-file", cfg!(feature = "local-file")),
+        ("scheduler", cfg!(feature = "scheduler")),
+        ("bridge", cfg!(feature = "bridge")),
+    ];
+    features
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name)
+        .collect()
+}
@@ -0,0 +1,153 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Schema DDL of a database as a script, for backups and promoting
+//! schemas between environments.
+
+use databend_sql::builder;
+use databend_sql::error::{Error, Result};
+use databend_sql::quote::quote_ident;
+
+use crate::conn::Connection;
+use crate::udf::UserFunction;
+use crate::utils::query_named;
+
+/// Statements are ordered so that the script could be run as is: stages and
+/// UDFs first since tables and views may use them, then tables, and views
+/// after the tables and views they select from.
+pub(crate) async fn dump_schema<C>(conn: &C, database: &str) -> Result<String>
+where
+    C: Connection + ?Sized,
+{
+    let mut statements = Vec::new();
+    let sql = builder::show_create_database(database);
+    statements.push(create_statement(conn, &sql).await?);
+    statements.push(format!("USE {}", quote_ident(database)));
+
+    // stages and UDFs are not per database, all of them are dumped
+    let stages = query_named(conn, "SHOW STAGES", |index, row| {
+        Ok((
+            index.get::<String>(row, "name")?,
+            index.get::<String>(row, "stage_type")?,
+        ))
+    })
+    .await?;
+    for (name, stage_type) in stages {
+        if stage_type.eq_ignore_ascii_case("internal") {
+            statements.push(format!("CREATE STAGE IF NOT EXISTS {}", quote_ident(&name)));
+        } else {
+            statements.push(format!(
+                "-- {} stage {} is skipped, its credentials are not readable",
+                stage_type.to_lowercase(),
+                quote_ident(&name)
+            ));
+        }
+    }
+    // a function which could not be parsed should not fail the whole dump
+    let functions = query_named(conn, "SHOW USER FUNCTIONS", |index, row| {
+        let name = index.get::<String>(row, "name")?;
+        Ok((name, UserFunction::from_row(index, row)))
+    })
+    .await?;
+    for (name, function) in functions {
+        match function.and_then(|f| f.create_sql()) {
+            Ok(sql) => statements.push(sql),
+            Err(e) => statements.push(format!(
+                "-- function {} is skipped, its definition could not be parsed: {}",
+                quote_ident(&name),
+                e.to_string().replace(['\r', '\n'], " ")
+            )),
+        }
+    }
+
+    let sql = builder::select(&["name", "engine"])
+        .from("system.tables")
+        .where_("database", "=", database)
+        .order_by("name", true)
+        .build()?;
+    let tables = query_named(conn, &sql, |index, row| {
+        Ok((
+            index.get::<String>(row, "name")?,
+            index.get::<String>(row, "engine")?,
+        ))
+    })
+    .await?;
+    let mut views = Vec::new();
+    for (name, engine) in tables {
+        let table = format!("{}.{}", database, name);
+        let ddl = create_statement(conn, &builder::show_create_table(&table)).await?;
+        if engine.eq_ignore_ascii_case("view") {
+            views.push((name, ddl));
+        } else {
+            statements.push(ddl);
+        }
+    }
+    statements.extend(order_views(views));
+
+    let mut script = String::new();
+    for statement in statements {
+        script.push_str(&statement);
+        if !statement.starts_with("--") {
+            script.push(';');
+        }
+        script.push('\n');
+    }
+    Ok(script)
+}
+
+/// The DDL is the second column of `SHOW CREATE`.
+async fn create_statement<C>(conn: &C, sql: &str) -> Result<String>
+where
+    C: Connection + ?Sized,
+{
+    let row = conn
+        .query_row(sql)
+        .await?
+        .ok_or_else(|| Error::InvalidResponse(format!("Empty response for {}", sql)))?;
+    match row.values().get(1) {
+        Some(ddl) => Ok(ddl.to_string().trim_end_matches(';').to_string()),
+        None => Err(Error::InvalidResponse(format!(
+            "Missing DDL column for {}",
+            sql
+        ))),
+    }
+}
+
+/// Views selecting from other views are placed after them, the name of a view
+/// appearing in the DDL of another is taken as a dependency.
+fn order_views(mut views: Vec<(String, String)>) -> Vec<String> {
+    let mut ordered = Vec::with_capacity(views.len());
+    while !views.is_empty() {
+        let ready = views
+            .iter()
+            .position(|(name, ddl)| {
+                !views
+                    .iter()
+                    .any(|(other, _)| other != name && references(ddl, other))
+            })
+            // a cycle or a false dependency, keep the order by name
+            .unwrap_or(0);
+        ordered.push(views.remove(ready).1);
+    }
+    ordered
+}
+
+fn references(ddl: &str, name: &str) -> bool {
+    ddl.match_indices(name).any(|(i, _)| {
+        let before = ddl[..i].chars().next_back();
+        let after = ddl[i + name.len()..].chars().next();
+        let is_ident = |c: Option<char>| matches!(c, Some(c) if c.is_alphanumeric() || c == '_');
+        !is_ident(before) && !is_ident(after)
+    })
+}
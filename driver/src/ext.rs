@@ -0,0 +1,466 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers built on the SQL of [`Connection`], which work with any of its
+//! implementations.
+
+use std::collections::BTreeMap;
+#[cfg(feature = "local-file")]
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+
+use databend_sql::builder;
+use databend_sql::copy::{as_str_map, CopyOptions, FileFormatOptions, UnloadOptions};
+use databend_sql::error::{Error, Result};
+use databend_sql::rows::{QueryProgress, Row, RowIterator, RowProgressIterator};
+use databend_sql::schema::Schema;
+use databend_sql::split_statements;
+
+use crate::changes;
+use crate::conn::{Connection, LoadStats, Reader, ServerInfo, Setting, StatementResult};
+use crate::csv::{self, CsvLoad, CsvOptions};
+use crate::fuse::{self, ClusteringInformation, FuseSegment, FuseSnapshot};
+use crate::grants::{self, Grant, Grantee, RoleInfo};
+use crate::monitor::{self, QueryLogEntry, SystemMetric, WarehouseUtilization};
+use crate::options::{self, QueryOptions};
+#[cfg(feature = "local-file")]
+use crate::parquet_export::{self, ParquetOptions};
+#[cfg(feature = "spill")]
+use crate::spill::{self, ResultSet, SpillOptions};
+use crate::stage::{self, ExportedFile, StageFile};
+use crate::temp::{self, TempTable};
+use crate::udf::{self, UserFunction};
+use crate::utils::query_named;
+use crate::version::{ServerFeature, ServerVersion};
+
+/// Helpers of [`Connection`] built on its queries, for all connections
+/// including `dyn Connection`. Import it along with `Connection`, or with
+/// `databend_driver::prelude::*`.
+#[async_trait]
+pub trait ConnectionExt: Connection {
+    async fn version(&self) -> Result<String> {
+        let row = self.query_row("SELECT version()").await?;
+        let version = match row {
+            Some(row) => {
+                let (version,): (String,) = row.try_into().map_err(Error::Parsing)?;
+                version
+            }
+            None => "".to_string(),
+        };
+        Ok(version)
+    }
+
+    /// Release of the server, `None` if the version has no `vX.Y.Z` like
+    /// for local dev builds.
+    async fn server_version(&self) -> Result<Option<ServerVersion>> {
+        Ok(ServerVersion::parse(&self.version().await?))
+    }
+
+    /// Check the server is new enough for the feature, to fail early with
+    /// the version required instead of an obscure error mid-operation.
+    /// Servers of unknown versions pass.
+    async fn require_server_version(&self, feature: ServerFeature) -> Result<()> {
+        match self.server_version().await? {
+            Some(version) => feature.check(&version).map_or(Ok(()), Err),
+            None => Ok(()),
+        }
+    }
+
+    /// Version and current database from the server, with the warehouse
+    /// queries are routed to and its state if available.
+    async fn server_info(&self) -> Result<ServerInfo> {
+        let row = self
+            .query_row("SELECT version(), current_database()")
+            .await?
+            .ok_or_else(|| Error::InvalidResponse("Empty response for server info".to_string()))?;
+        let (version, database): (String, String) = row.try_into().map_err(Error::Parsing)?;
+        let warehouse = self.info().await.warehouse;
+        let warehouse_state = match warehouse {
+            Some(ref warehouse) => self.warehouse_state(warehouse).await.unwrap_or(None),
+            None => None,
+        };
+        Ok(ServerInfo {
+            version,
+            database,
+            warehouse,
+            warehouse_state,
+        })
+    }
+
+    /// State of the warehouse from `SHOW WAREHOUSES`, which is only supported
+    /// by Databend Cloud.
+    async fn warehouse_state(&self, warehouse: &str) -> Result<Option<String>> {
+        let warehouses = query_named(self, "SHOW WAREHOUSES", |index, row| {
+            let name: String = index.get(row, "warehouse")?;
+            let state: Option<String> = index.get_opt(row, "status")?;
+            Ok((name, state))
+        })
+        .await?;
+        Ok(warehouses
+            .into_iter()
+            .find(|(name, _)| name == warehouse)
+            .and_then(|(_, state)| state))
+    }
+
+    /// Round trip time of a trivial query.
+    async fn ping(&self) -> Result<Duration> {
+        let start = std::time::Instant::now();
+        self.query_row("SELECT 1").await?;
+        Ok(start.elapsed())
+    }
+
+    /// List snapshots of a fuse table, the latest first.
+    async fn fuse_snapshot(&self, database: &str, table: &str) -> Result<Vec<FuseSnapshot>> {
+        fuse::fuse_snapshot(self, database, table).await
+    }
+
+    /// List snapshots of a table, which could be qualified as `db.table`,
+    /// otherwise it is looked up in the current database.
+    async fn list_snapshots(&self, table: &str) -> Result<Vec<FuseSnapshot>> {
+        fuse::list_snapshots(self, table).await
+    }
+
+    /// List segments of a fuse table, for the given snapshot or the latest one.
+    async fn fuse_segment(
+        &self,
+        database: &str,
+        table: &str,
+        snapshot_id: Option<&str>,
+    ) -> Result<Vec<FuseSegment>> {
+        fuse::fuse_segment(self, database, table, snapshot_id).await
+    }
+
+    /// Get clustering information of a table with cluster key defined.
+    async fn clustering_information(
+        &self,
+        database: &str,
+        table: &str,
+    ) -> Result<ClusteringInformation> {
+        fuse::clustering_information(self, database, table).await
+    }
+
+    /// Metrics of all nodes of the warehouse in `system.metrics`.
+    async fn system_metrics(&self) -> Result<Vec<SystemMetric>> {
+        monitor::system_metrics(self).await
+    }
+
+    /// Finished or failed queries logged after the time, the oldest first,
+    /// poll with `query_log_after` the last entry to get the next slice.
+    async fn query_log_since(
+        &self,
+        since: NaiveDateTime,
+        limit: u64,
+    ) -> Result<Vec<QueryLogEntry>> {
+        monitor::query_log_since(self, since, limit).await
+    }
+
+    /// Finished or failed queries logged after the entry, the oldest first.
+    /// Entries are ordered by `event_time` and `query_id`, so those logged
+    /// at the same time as the last one are neither lost nor repeated.
+    async fn query_log_after(
+        &self,
+        last: &QueryLogEntry,
+        limit: u64,
+    ) -> Result<Vec<QueryLogEntry>> {
+        monitor::query_log_after(self, last, limit).await
+    }
+
+    /// Running queries, and load of queries finished after the time.
+    async fn warehouse_utilization(&self, since: NaiveDateTime) -> Result<WarehouseUtilization> {
+        monitor::warehouse_utilization(self, since).await
+    }
+
+    /// User defined functions, lambda and external ones.
+    async fn list_functions(&self) -> Result<Vec<UserFunction>> {
+        udf::list_functions(self).await
+    }
+
+    /// Create the function if not exists.
+    async fn create_function(&self, function: &UserFunction) -> Result<()> {
+        udf::create_function(self, function).await
+    }
+
+    async fn drop_function(&self, name: &str) -> Result<()> {
+        udf::drop_function(self, name).await
+    }
+
+    /// Privileges granted to the user or role, including the granted roles.
+    async fn show_grants(&self, grantee: &Grantee) -> Result<Vec<Grant>> {
+        grants::show_grants(self, grantee).await
+    }
+
+    /// Roles granted to the current user.
+    async fn roles(&self) -> Result<Vec<RoleInfo>> {
+        grants::roles(self).await
+    }
+
+    /// DDL of the database, its tables and views, and the stages and
+    /// UDFs they may use, as a script to recreate them elsewhere. Objects
+    /// which could not be recreated are left as comments in the script.
+    async fn dump_schema(&self, database: &str) -> Result<String> {
+        crate::dump::dump_schema(self, database).await
+    }
+
+    /// Run statements of a script one by one in the session, like migration
+    /// scripts. It stops at the first failed statement, which is the last of
+    /// the results, statements before it are not rolled back.
+    async fn exec_batch(&self, sql: &str) -> Result<Vec<StatementResult>> {
+        let mut results = Vec::new();
+        for statement in split_statements(sql)? {
+            let result = self.exec(statement.text).await;
+            let failed = result.is_err();
+            results.push(StatementResult {
+                sql: statement.text.to_string(),
+                span: statement.span,
+                result,
+            });
+            if failed {
+                break;
+            }
+        }
+        Ok(results)
+    }
+
+    /// Create a `TEMP` table with a random name, which is dropped with the
+    /// returned handle or when the connection is closed.
+    async fn create_temp_table(&self, schema: &Schema) -> Result<TempTable>
+    where
+        Self: 'static,
+    {
+        temp::create_temp_table(self, schema).await
+    }
+
+    /// Collect all rows of the query, spilled to a memory mapped temp file
+    /// instead of memory once the result is big, to keep memory flat for
+    /// multi-GB results.
+    #[cfg(feature = "spill")]
+    async fn query_all(&self, sql: &str, options: &SpillOptions) -> Result<ResultSet> {
+        spill::query_all(self, sql, options).await
+    }
+
+    /// Create a table with columns mapped from the schema.
+    async fn create_table(&self, table: &str, schema: &Schema) -> Result<()> {
+        self.exec(&builder::create_table_sql(table, schema)?)
+            .await?;
+        Ok(())
+    }
+
+    async fn query_row_with_options(
+        &self,
+        sql: &str,
+        options: &QueryOptions,
+    ) -> Result<Option<Row>> {
+        options::query_row_with_options(self, sql, options).await
+    }
+
+    async fn query_iter_with_options(
+        &self,
+        sql: &str,
+        options: &QueryOptions,
+    ) -> Result<RowIterator> {
+        options::query_iter_with_options(self, sql, options).await
+    }
+
+    /// Change a setting of the session like `timezone` or `max_threads`,
+    /// applied to all queries of the connection later.
+    async fn set_setting(&self, name: &str, value: &str) -> Result<()> {
+        self.exec(&builder::set_setting(name, value)?).await?;
+        Ok(())
+    }
+
+    /// All settings with values of the session.
+    async fn settings(&self) -> Result<Vec<Setting>> {
+        query_named(self, "SHOW SETTINGS", Setting::from_row).await
+    }
+
+    /// Kill a running query by id, which could be found in `system.processes`.
+    async fn kill_query(&self, query_id: &str) -> Result<()> {
+        self.exec(&builder::kill_query(query_id)).await?;
+        Ok(())
+    }
+
+    /// Same as [`Connection::stream_load`] with typed options, which are
+    /// validated before the data is uploaded. Empty `copy_options` keep the
+    /// default of purging the uploaded file.
+    async fn stream_load_with_options(
+        &self,
+        sql: &str,
+        data: Reader,
+        size: u64,
+        file_format_options: &FileFormatOptions,
+        copy_options: &CopyOptions,
+    ) -> Result<QueryProgress> {
+        let file_format_options = file_format_options.to_map()?;
+        let copy_options = copy_options.to_map()?;
+        self.stream_load(
+            sql,
+            data,
+            size,
+            Some(as_str_map(&file_format_options)),
+            (!copy_options.is_empty()).then(|| as_str_map(&copy_options)),
+        )
+        .await
+    }
+
+    /// Upload the data to a temp stage and insert it with the statement like
+    /// `INSERT INTO t VALUES`, the uploaded file is purged once loaded.
+    /// CSV is assumed without `format_options`.
+    async fn load_data(
+        &self,
+        sql: &str,
+        data: Reader,
+        size: u64,
+        format_options: Option<BTreeMap<&str, &str>>,
+    ) -> Result<LoadStats> {
+        let progress = self
+            .stream_load(sql, data, size, format_options, None)
+            .await?;
+        Ok(LoadStats {
+            rows_loaded: progress.write_rows,
+            bytes_uploaded: size,
+            error_rows: progress.read_rows.saturating_sub(progress.write_rows),
+        })
+    }
+
+    /// Insert CSV rows into the table through a temp stage. Rows are checked
+    /// against the table schema first, those with a wrong number of fields or
+    /// invalid UTF-8 are rejected with their line numbers instead of failing
+    /// the load on the server. Line endings between records are normalized to
+    /// `\n` and BOM is removed before uploading, line breaks in quoted fields
+    /// are kept.
+    async fn insert_csv(&self, table: &str, data: Reader, options: &CsvOptions) -> Result<CsvLoad> {
+        csv::insert_csv(self, table, data, options).await
+    }
+
+    /// Consume changes of a stream endlessly, polling every `poll_interval`
+    /// when there is no new change.
+    ///
+    /// Changes are consumed in transactions of a session of its own, one
+    /// batch of all pending changes at a time. The transaction is committed
+    /// when rows after the last one of the batch are asked for, so the offset
+    /// of the stream only advances after the whole batch is read, and the
+    /// batch is consumed again if the iterator is dropped or the process
+    /// dies before that. Errors are yielded and polling continues after a
+    /// backoff doubling `poll_interval` on each consecutive failure.
+    async fn stream_changes(&self, stream: &str, poll_interval: Duration) -> Result<RowIterator> {
+        let conn = self.new_session().await?;
+        changes::stream_changes(conn, stream, poll_interval).await
+    }
+
+    // PUT file://<path_to_file>/<filename> internalStage|externalStage
+    async fn put_files(
+        &self,
+        local_file: &str,
+        stage: &str,
+    ) -> Result<(Schema, RowProgressIterator)>
+    where
+        Self: 'static,
+    {
+        stage::put_files(self, local_file, stage).await
+    }
+
+    /// Upload the local files into the stage directory by their file names,
+    /// fails before uploading anything if two files have the same name.
+    async fn put_local_files(
+        &self,
+        entries: &[PathBuf],
+        stage: &str,
+    ) -> Result<(Schema, RowProgressIterator)>
+    where
+        Self: 'static,
+    {
+        stage::put_local_files(self, entries, stage).await
+    }
+
+    /// Download files under the stage location into the local directory,
+    /// the last segment of the location could be a glob pattern.
+    async fn get_files(
+        &self,
+        stage: &str,
+        local_file: &str,
+    ) -> Result<(Schema, RowProgressIterator)>
+    where
+        Self: 'static,
+    {
+        stage::get_files(self, stage, local_file).await
+    }
+
+    /// List files under the stage location, the last segment of the path
+    /// could be a glob pattern like `@s1/data/*.csv` to match file names.
+    async fn list_stage_files(&self, location: &str) -> Result<Vec<StageFile>> {
+        stage::list_stage_files(self, location).await
+    }
+
+    /// Remove files under the stage location, or only the ones matching the
+    /// glob pattern in the last segment, returns the files removed. A path
+    /// not ending with `/` removes the file or the directory of the name,
+    /// not other files having it as a prefix.
+    async fn remove_stage_files(&self, location: &str) -> Result<Vec<StageFile>> {
+        stage::remove_stage_files(self, location).await
+    }
+
+    /// Unload results of the query with `COPY INTO` to files under the stage
+    /// prefix, and presign them for download, which is much faster than
+    /// fetching very large results through the connection.
+    ///
+    /// Files already under the prefix are listed as well, a fresh prefix
+    /// should be used for each export.
+    async fn export_via_stage(
+        &self,
+        sql: &str,
+        format: &str,
+        stage_prefix: &str,
+    ) -> Result<Vec<ExportedFile>> {
+        stage::export_via_stage(self, sql, format, stage_prefix).await
+    }
+
+    /// Same as `export_via_stage` with typed options, to choose the codec of
+    /// Parquet files or to limit the size of the files.
+    async fn export_via_stage_with_options(
+        &self,
+        sql: &str,
+        file_format_options: &FileFormatOptions,
+        unload_options: &UnloadOptions,
+        stage_prefix: &str,
+    ) -> Result<Vec<ExportedFile>> {
+        stage::export_via_stage_with_options(
+            self,
+            sql,
+            file_format_options,
+            unload_options,
+            stage_prefix,
+        )
+        .await
+    }
+
+    /// Write results of the query to a local Parquet file with the writer
+    /// knobs, returns the number of rows written. Unlike `export_via_stage`,
+    /// results are fetched through the connection.
+    #[cfg(feature = "local-file")]
+    async fn export_parquet(
+        &self,
+        sql: &str,
+        path: &Path,
+        options: &ParquetOptions,
+    ) -> Result<usize> {
+        let batches = self.query_arrow(sql).await?;
+        parquet_export::export_parquet(batches, path, options).await
+    }
+}
+
+impl<T: Connection + ?Sized> ConnectionExt for T {}
@@ -16,34 +16,58 @@ use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use arrow::ipc::{convert::fb_to_schema, root_as_message};
 use arrow_flight::utils::flight_data_to_arrow_batch;
-use arrow_flight::{sql::client::FlightSqlServiceClient, FlightData};
-use arrow_schema::SchemaRef as ArrowSchemaRef;
+use arrow_flight::{
+    sql::client::FlightSqlServiceClient, FlightData, FlightInfo, HandshakeRequest, Ticket,
+};
+use arrow_schema::{ArrowError, SchemaRef as ArrowSchemaRef};
 use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
+use base64::Engine;
 use percent_encoding::percent_decode_str;
 use tokio::sync::Mutex;
 use tokio_stream::{Stream, StreamExt};
-use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+use tonic::transport::ClientTlsConfig;
+use tonic::transport::{Channel, Endpoint};
 use tonic::Streaming;
 use url::Url;
 
-use databend_client::presign::{presign_upload_to_stage, PresignedResponse};
+use databend_client::comment::QueryComment;
+use databend_client::counters::ErrorCounters;
+use databend_client::credentials::{Credentials, CredentialsProvider};
+use databend_client::debug;
+use databend_client::presign::{
+    presign_upload_file_to_stage, presign_upload_to_stage, PresignedResponse,
+};
+use databend_sql::builder;
 use databend_sql::error::{Error, Result};
+use databend_sql::ipc::RecordBatchIterator;
 use databend_sql::rows::{
-    QueryProgress, Row, RowIterator, RowProgressIterator, RowWithProgress, Rows,
+    QueryProgress, QueryStats, ResumeState, ResumeToken, Row, RowIterator, RowProgressIterator,
+    RowWithProgress, Rows,
 };
-use databend_sql::schema::Schema;
+use databend_sql::schema::{Schema, SchemaRef};
 
 use crate::conn::{Connection, ConnectionInfo, Reader};
+use crate::schema_cache::SchemaCache;
+use crate::stmt::PreparedStatement;
+use crate::temp::{TempObject, TempObjects};
 
 #[derive(Clone)]
 pub struct FlightSQLConnection {
     client: Arc<Mutex<FlightSqlServiceClient<Channel>>>,
     handshaked: Arc<Mutex<bool>>,
+    credentials: Arc<Mutex<Credentials>>,
+    credentials_provider: Option<CredentialsProvider>,
+    query_comment: Arc<Mutex<QueryComment>>,
     args: Args,
+    temp_objects: TempObjects,
+    schema_cache: Arc<SchemaCache<ArrowSchemaRef>>,
+    error_counters: Arc<ErrorCounters>,
 }
 
 #[async_trait]
@@ -53,7 +77,7 @@ impl Connection for FlightSQLConnection {
             handler: "FlightSQL".to_string(),
             host: self.args.host.clone(),
             port: self.args.port,
-            user: self.args.user.clone(),
+            user: self.credentials.lock().await.user.clone(),
             database: self.args.database.clone(),
             warehouse: self.args.warehouse.clone(),
         }
@@ -62,7 +86,17 @@ impl Connection for FlightSQLConnection {
     async fn exec(&self, sql: &str) -> Result<i64> {
         self.handshake().await?;
         let mut client = self.client.lock().await;
-        let affected_rows = client.execute_update(sql.to_string(), None).await?;
+        let sql = self.query_comment.lock().await.apply(sql);
+        if self.args.debug_http {
+            debug::log_request("ExecuteUpdate", &self.args.uri, sql.as_bytes());
+        }
+        let affected_rows = client
+            .execute_update(sql, None)
+            .await
+            .map_err(|e| self.count_error(e))?;
+        if self.args.debug_http {
+            debug::log_response("OK", format!("{} affected rows", affected_rows).as_bytes());
+        }
         Ok(affected_rows)
     }
 
@@ -73,31 +107,70 @@ impl Connection for FlightSQLConnection {
     }
 
     async fn query_iter(&self, sql: &str) -> Result<RowIterator> {
-        let (_, rows_with_progress) = self.query_iter_ext(sql).await?;
-        let rows = rows_with_progress.filter_map(|r| match r {
-            Ok(RowWithProgress::Row(r)) => Some(Ok(r)),
-            Ok(_) => None,
-            Err(err) => Some(Err(err)),
-        });
-        Ok(RowIterator::new(Box::pin(rows)))
+        let (schema, rows_with_progress) = self.query_iter_ext(sql).await?;
+        Ok(rows_with_progress.filter_rows(Arc::new(schema)).await)
     }
 
     async fn query_iter_ext(&self, sql: &str) -> Result<(Schema, RowProgressIterator)> {
+        let (flight_data, ticket) = self.do_get(sql).await?;
+        let cache = Some((self.schema_cache.as_ref(), sql));
+        let (schema, rows) =
+            FlightSQLRows::try_from_flight_data(flight_data, ticket, 0, cache).await?;
+        let resume = rows.resume.clone();
+        Ok((
+            schema,
+            RowProgressIterator::new(Box::pin(rows)).with_resume(Some(resume)),
+        ))
+    }
+
+    async fn query_arrow(&self, sql: &str) -> Result<RecordBatchIterator> {
+        let (flight_data, _) = self.do_get(sql).await?;
+        read_batches(flight_data).await
+    }
+
+    async fn reset_session(&self) -> Result<()> {
+        *self.query_comment.lock().await = self.args.query_comment.clone();
+        // the server keeps a session per handshake, a new one starts without
+        // `USE` and `SET` of the old one
+        *self.handshaked.lock().await = false;
+        self.handshake().await
+    }
+
+    async fn resume(&self, token: &ResumeToken) -> Result<RowIterator> {
+        let (ticket, offset) = match token {
+            ResumeToken::FlightSql { ticket, offset } => (ticket.clone(), *offset),
+            _ => {
+                return Err(Error::BadArgument(
+                    "Resume token is not from FlightSQL".to_string(),
+                ))
+            }
+        };
         self.handshake().await?;
         let mut client = self.client.lock().await;
-        let mut stmt = client.prepare(sql.to_string(), None).await?;
-        let flight_info = stmt.execute().await?;
-        let ticket = flight_info.endpoint[0]
-            .ticket
-            .as_ref()
-            .ok_or(Error::Protocol("Ticket is empty".to_string()))?;
-        let flight_data = client.do_get(ticket.clone()).await?;
-        let (schema, rows) = FlightSQLRows::try_from_flight_data(flight_data).await?;
-        Ok((schema, RowProgressIterator::new(Box::pin(rows))))
+        let flight_data = client
+            .do_get(Ticket::new(ticket.clone()))
+            .await
+            .map_err(|e| self.count_error(e))?;
+        let (schema, rows) =
+            FlightSQLRows::try_from_flight_data(flight_data, ticket, offset, None).await?;
+        let resume = rows.resume.clone();
+        Ok(RowProgressIterator::new(Box::pin(rows))
+            .with_resume(Some(resume))
+            .filter_rows(Arc::new(schema))
+            .await)
+    }
+
+    async fn prepare(&self, sql: &str) -> Result<PreparedStatement> {
+        PreparedStatement::new(Box::new(self.clone()), sql)
+    }
+
+    async fn set_query_comment(&self, comment: QueryComment) -> Result<()> {
+        *self.query_comment.lock().await = comment;
+        Ok(())
     }
 
     async fn get_presigned_url(&self, operation: &str, stage: &str) -> Result<PresignedResponse> {
-        let sql = format!("PRESIGN {} {}", operation, stage);
+        let sql = builder::presign(operation, stage)?;
         let row = self.query_row(&sql).await?.ok_or(Error::InvalidResponse(
             "Empty response from server for presigned request".to_string(),
         ))?;
@@ -111,50 +184,236 @@ impl Connection for FlightSQLConnection {
         })
     }
 
+    fn track_temp_object(&self, object: TempObject) {
+        self.temp_objects.track(object);
+    }
+
+    fn untrack_temp_object(&self, object: &TempObject) {
+        self.temp_objects.untrack(object);
+    }
+
+    fn temp_objects(&self) -> Vec<TempObject> {
+        self.temp_objects.list()
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.temp_objects.cleanup(self).await
+    }
+
     /// Always use presigned url to upload stage for FlightSQL
     async fn upload_to_stage(&self, stage: &str, data: Reader, size: u64) -> Result<()> {
         let presign = self.get_presigned_url("UPLOAD", stage).await?;
         presign_upload_to_stage(presign, data, size).await?;
         Ok(())
     }
+
+    async fn upload_file_to_stage(
+        &self,
+        stage: &str,
+        file: tokio::fs::File,
+        size: u64,
+    ) -> Result<()> {
+        let presign = self.get_presigned_url("UPLOAD", stage).await?;
+        presign_upload_file_to_stage(presign, file, size).await?;
+        Ok(())
+    }
+}
+
+impl Drop for FlightSQLConnection {
+    fn drop(&mut self) {
+        self.temp_objects.cleanup_on_drop(self);
+    }
+}
+
+impl FlightSQLConnection {
+    /// Run the query without fetching results. Tickets in the endpoints of
+    /// the flight info could be sent to other processes, and fetched there
+    /// with [`FlightSQLConnection::do_get_raw`] to read them in parallel.
+    pub async fn get_flight_info(&self, sql: &str) -> Result<FlightInfo> {
+        self.handshake().await?;
+        let mut client = self.client.lock().await;
+        let sql = self.query_comment.lock().await.apply(sql);
+        if self.args.debug_http {
+            debug::log_request("GetFlightInfo", &self.args.uri, sql.as_bytes());
+        }
+        let mut stmt = client
+            .prepare(sql, None)
+            .await
+            .map_err(|e| self.count_error(e))?;
+        let flight_info = stmt.execute().await.map_err(|e| self.count_error(e))?;
+        if self.args.debug_http {
+            let summary = format!(
+                "{} endpoints, {} records, {} bytes",
+                flight_info.endpoint.len(),
+                flight_info.total_records,
+                flight_info.total_bytes
+            );
+            debug::log_response("OK", summary.as_bytes());
+        }
+        Ok(flight_info)
+    }
+
+    /// Fetch results of a ticket from [`FlightSQLConnection::get_flight_info`],
+    /// which could be called by another connection to the same server with
+    /// the same user. Tickets are valid until the results expire on server.
+    pub async fn do_get_raw(&self, ticket: Ticket) -> Result<RecordBatchIterator> {
+        if ticket.ticket.is_empty() {
+            return Err(Error::BadArgument("Ticket is empty".to_string()));
+        }
+        self.handshake().await?;
+        let mut client = self.client.lock().await;
+        let flight_data = client
+            .do_get(ticket)
+            .await
+            .map_err(|e| self.count_error(e))?;
+        read_batches(flight_data).await
+    }
 }
 
 impl FlightSQLConnection {
-    pub async fn try_create(dsn: &str) -> Result<Self> {
+    pub async fn try_create(
+        dsn: &str,
+        credentials_provider: Option<CredentialsProvider>,
+    ) -> Result<Self> {
         let (args, endpoint) = Self::parse_dsn(dsn).await?;
         let channel = endpoint.connect_lazy();
         let mut client = FlightSqlServiceClient::new(channel);
-        // enable progress
-        client.set_header("bendsql", "1");
-        if let Some(tenant) = args.tenant.as_ref() {
-            client.set_header("x-databend-tenant", tenant);
-        }
-        if let Some(warehouse) = args.warehouse.as_ref() {
-            client.set_header("x-databend-warehouse", warehouse);
+        for (key, value) in args.headers() {
+            client.set_header(key, value);
         }
+        let credentials = Credentials::new(&args.user, Some(&args.password));
         Ok(Self {
             client: Arc::new(Mutex::new(client)),
+            query_comment: Arc::new(Mutex::new(args.query_comment.clone())),
             args,
             handshaked: Arc::new(Mutex::new(false)),
+            credentials: Arc::new(Mutex::new(credentials)),
+            credentials_provider,
+            temp_objects: TempObjects::default(),
+            schema_cache: Arc::new(SchemaCache::new()),
+            error_counters: Arc::new(ErrorCounters::default()),
         })
     }
 
+    pub(crate) fn with_error_counters(mut self, counters: Arc<ErrorCounters>) -> Self {
+        self.error_counters = counters;
+        self
+    }
+
+    /// Count the error by the status code of the response, which is only
+    /// available in the message of the arrow error.
+    fn count_error(&self, e: ArrowError) -> ArrowError {
+        if let ArrowError::IoError(ref msg) = e {
+            let code = msg
+                .split("code: ")
+                .nth(1)
+                .and_then(|rest| rest.split([',', ' ']).next())
+                .unwrap_or_default();
+            match code {
+                "Unauthenticated" | "PermissionDenied" => self.error_counters.record_auth_failure(),
+                "Unavailable" | "DeadlineExceeded" | "Cancelled" => {
+                    self.error_counters.record_transport_error()
+                }
+                "" => {}
+                _ => self.error_counters.record_server_error(),
+            }
+        }
+        e
+    }
+
     async fn handshake(&self) -> Result<()> {
         let mut handshaked = self.handshaked.lock().await;
         if *handshaked {
             return Ok(());
         }
         let mut client = self.client.lock().await;
-        let _token = client
-            .handshake(&self.args.user, &self.args.password)
-            .await?;
+        let mut credentials = self.credentials.lock().await;
+        let result = self.basic_handshake(&mut client, &credentials).await;
+        match (result, &self.credentials_provider) {
+            (Ok(()), _) => {}
+            // only retry when the credentials are rejected, with refreshed ones
+            // from the provider, other errors are returned as is
+            (Err(status), Some(provider)) if status.code() == tonic::Code::Unauthenticated => {
+                *credentials = provider().await?;
+                self.basic_handshake(&mut client, &credentials)
+                    .await
+                    .map_err(|status| self.handshake_error(status))?;
+            }
+            (Err(status), _) => return Err(self.handshake_error(status).into()),
+        }
         *handshaked = true;
         Ok(())
     }
 
+    /// Same as `FlightSqlServiceClient::handshake`, but keeps the status of
+    /// the failure, which is only in the message of its error.
+    async fn basic_handshake(
+        &self,
+        client: &mut FlightSqlServiceClient<Channel>,
+        credentials: &Credentials,
+    ) -> std::result::Result<(), tonic::Status> {
+        let cmd = HandshakeRequest {
+            protocol_version: 0,
+            payload: Default::default(),
+        };
+        let mut req = tonic::Request::new(tokio_stream::iter(vec![cmd]));
+        let basic = BASE64_STANDARD.encode(format!(
+            "{}:{}",
+            credentials.user,
+            credentials.password.as_deref().unwrap_or_default()
+        ));
+        let invalid = |_| tonic::Status::invalid_argument("invalid header value");
+        let metadata = req.metadata_mut();
+        metadata.insert(
+            "authorization",
+            format!("Basic {}", basic).parse().map_err(invalid)?,
+        );
+        for (key, value) in self.args.headers() {
+            metadata.insert(key, value.parse().map_err(invalid)?);
+        }
+        let resp = client.inner_mut().handshake(req).await?;
+        let token = resp
+            .metadata()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(|v| v.to_string());
+        let mut responses = resp.into_inner();
+        while responses.next().await.transpose()?.is_some() {}
+        if let Some(token) = token {
+            client.set_token(token);
+        }
+        Ok(())
+    }
+
+    fn handshake_error(&self, status: tonic::Status) -> ArrowError {
+        self.count_error(ArrowError::IoError(format!("Can't handshake {:?}", status)))
+    }
+
+    /// Run the query and fetch its results with the ticket of the only
+    /// endpoint, the ticket is returned for resuming.
+    async fn do_get(&self, sql: &str) -> Result<(Streaming<FlightData>, Vec<u8>)> {
+        let flight_info = self.get_flight_info(sql).await?;
+        let ticket = flight_info
+            .endpoint
+            .first()
+            .and_then(|endpoint| endpoint.ticket.as_ref())
+            .ok_or(Error::Protocol("Ticket is empty".to_string()))?;
+        let mut client = self.client.lock().await;
+        let flight_data = client
+            .do_get(ticket.clone())
+            .await
+            .map_err(|e| self.count_error(e))?;
+        Ok((flight_data, ticket.ticket.to_vec()))
+    }
+
     async fn parse_dsn(dsn: &str) -> Result<(Args, Endpoint)> {
         let u = Url::parse(dsn)?;
         let args = Args::from_url(&u)?;
+        #[cfg_attr(
+            not(any(feature = "rustls", feature = "native-tls")),
+            allow(unused_mut)
+        )]
         let mut endpoint = Endpoint::new(args.uri.clone())?
             .connect_timeout(args.connect_timeout)
             .timeout(args.query_timeout)
@@ -199,6 +458,23 @@ struct Args {
     http2_keep_alive_interval: Duration,
     keep_alive_timeout: Duration,
     keep_alive_while_idle: bool,
+    query_comment: QueryComment,
+    debug_http: bool,
+}
+
+impl Args {
+    /// Headers sent with every request, including the handshake.
+    fn headers(&self) -> Vec<(&'static str, String)> {
+        // enable progress
+        let mut headers = vec![("bendsql", "1".to_string())];
+        if let Some(tenant) = self.tenant.as_ref() {
+            headers.push(("x-databend-tenant", tenant.clone()));
+        }
+        if let Some(warehouse) = self.warehouse.as_ref() {
+            headers.push(("x-databend-warehouse", warehouse.clone()));
+        }
+        headers
+    }
 }
 
 impl Default for Args {
@@ -221,10 +497,48 @@ impl Default for Args {
             http2_keep_alive_interval: Duration::from_secs(300),
             keep_alive_timeout: Duration::from_secs(20),
             keep_alive_while_idle: true,
+            query_comment: QueryComment::default(),
+            debug_http: false,
         }
     }
 }
 
+/// Options of the connection to the dsn, keyed by the dsn args, with
+/// defaults filled in for those not set.
+pub(crate) fn effective_options(dsn: &str) -> Result<BTreeMap<String, String>> {
+    let args = Args::from_url(&Url::parse(dsn)?)?;
+    let tcp_keepalive = match args.tcp_keepalive {
+        Some(d) => d.as_secs().to_string(),
+        None => "close".to_string(),
+    };
+    let options = [
+        (
+            "connect_timeout",
+            args.connect_timeout.as_secs().to_string(),
+        ),
+        ("query_timeout", args.query_timeout.as_secs().to_string()),
+        ("tcp_nodelay", args.tcp_nodelay.to_string()),
+        ("tcp_keepalive", tcp_keepalive),
+        (
+            "http2_keep_alive_interval",
+            args.http2_keep_alive_interval.as_secs().to_string(),
+        ),
+        (
+            "keep_alive_timeout",
+            args.keep_alive_timeout.as_secs().to_string(),
+        ),
+        (
+            "keep_alive_while_idle",
+            args.keep_alive_while_idle.to_string(),
+        ),
+        ("debug_http", args.debug_http.to_string()),
+    ];
+    Ok(options
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect())
+}
+
 impl Args {
     fn from_url(u: &Url) -> Result<Self> {
         let mut args = Self::default();
@@ -233,11 +547,9 @@ impl Args {
             match k.as_ref() {
                 "tenant" => args.tenant = Some(v.to_string()),
                 "warehouse" => args.warehouse = Some(v.to_string()),
-                "sslmode" => {
-                    if v == "disable" {
-                        scheme = "http";
-                        args.tls = false;
-                    }
+                "sslmode" if v == "disable" => {
+                    scheme = "http";
+                    args.tls = false;
                 }
                 "tls_ca_file" => args.tls_ca_file = Some(v.to_string()),
                 "connect_timeout" => args.connect_timeout = Duration::from_secs(v.parse()?),
@@ -256,6 +568,8 @@ impl Args {
                 }
                 "keep_alive_timeout" => args.keep_alive_timeout = Duration::from_secs(v.parse()?),
                 "keep_alive_while_idle" => args.keep_alive_while_idle = v.parse()?,
+                "query_comment" => args.query_comment = QueryComment::parse(&v)?,
+                "debug_http" => args.debug_http = databend_client::parse_bool(&k, &v)?,
                 _ => {}
             }
         }
@@ -284,47 +598,116 @@ impl Args {
     }
 }
 
+/// The schema is sent as the first message before any data.
+async fn read_schema(data: &mut Streaming<FlightData>) -> Result<ArrowSchemaRef> {
+    let datum = data
+        .try_next()
+        .await?
+        .ok_or(Error::Protocol("No flight data in stream".to_string()))?;
+    let message = root_as_message(&datum.data_header[..])
+        .map_err(|err| Error::Protocol(format!("InvalidFlatbuffer: {}", err)))?;
+    let ipc_schema = message.header_as_schema().ok_or(Error::Protocol(
+        "Invalid Message: Cannot get header as Schema".to_string(),
+    ))?;
+    Ok(Arc::new(fb_to_schema(ipc_schema)))
+}
+
+/// Record batches of the flight data, progress messages are skipped.
+async fn read_batches(mut flight_data: Streaming<FlightData>) -> Result<RecordBatchIterator> {
+    let schema = read_schema(&mut flight_data).await?;
+    let batch_schema = schema.clone();
+    let batches = flight_data.filter_map(move |datum| match datum {
+        // magic number 1 is used to indicate progress
+        Ok(datum) if datum.app_metadata[..] == [0x01] => None,
+        Ok(datum) => Some(
+            flight_data_to_arrow_batch(&datum, batch_schema.clone(), &HashMap::new())
+                .map_err(Error::from),
+        ),
+        Err(err) => Some(Err(err.into())),
+    });
+    Ok(RecordBatchIterator::new(schema, Box::pin(batches)))
+}
+
 pub struct FlightSQLRows {
     schema: ArrowSchemaRef,
     data: Streaming<FlightData>,
     rows: VecDeque<Row>,
+    // rows returned before from the ticket, to be skipped when resumed
+    ticket: Vec<u8>,
+    offset: usize,
+    skip: usize,
+    resume: ResumeState,
+    // for stats returned after all rows
+    started: Instant,
+    progress: QueryProgress,
+    finished: bool,
 }
 
 impl FlightSQLRows {
-    async fn try_from_flight_data(flight_data: Streaming<FlightData>) -> Result<(Schema, Self)> {
+    /// The schema is cached by the statement if it is given.
+    async fn try_from_flight_data(
+        flight_data: Streaming<FlightData>,
+        ticket: Vec<u8>,
+        skip: usize,
+        cache: Option<(&SchemaCache<ArrowSchemaRef>, &str)>,
+    ) -> Result<(Schema, Self)> {
         let mut data = flight_data;
-        let datum = data
-            .try_next()
-            .await?
-            .ok_or(Error::Protocol("No flight data in stream".to_string()))?;
-        let message = root_as_message(&datum.data_header[..])
-            .map_err(|err| Error::Protocol(format!("InvalidFlatbuffer: {}", err)))?;
-        let ipc_schema = message.header_as_schema().ok_or(Error::Protocol(
-            "Invalid Message: Cannot get header as Schema".to_string(),
-        ))?;
-        let arrow_schema = Arc::new(fb_to_schema(ipc_schema));
-        let schema = arrow_schema.clone().try_into()?;
+        let arrow_schema = read_schema(&mut data).await?;
+        let schema: SchemaRef = match cache {
+            Some((cache, sql)) => {
+                cache.get_or_convert(sql, arrow_schema.clone(), Schema::try_from)?
+            }
+            None => Arc::new(arrow_schema.clone().try_into()?),
+        };
+        let schema = schema.as_ref().clone();
         let rows = Self {
             schema: arrow_schema,
             data,
             rows: VecDeque::new(),
+            ticket,
+            offset: skip,
+            skip,
+            resume: ResumeState::default(),
+            started: Instant::now(),
+            progress: QueryProgress::default(),
+            finished: false,
         };
+        rows.update_resume();
         Ok((schema, rows))
     }
+
+    fn update_resume(&self) {
+        self.resume.set(Some(ResumeToken::FlightSql {
+            ticket: self.ticket.clone(),
+            offset: self.offset,
+        }));
+    }
 }
 
 impl Stream for FlightSQLRows {
     type Item = Result<RowWithProgress>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        if let Some(row) = self.rows.pop_front() {
-            return Poll::Ready(Some(Ok(RowWithProgress::Row(row))));
+        while self.skip > 0 && !self.rows.is_empty() {
+            self.rows.pop_front();
+            self.skip -= 1;
+        }
+        if self.skip == 0 {
+            if let Some(row) = self.rows.pop_front() {
+                self.offset += 1;
+                self.update_resume();
+                return Poll::Ready(Some(Ok(RowWithProgress::Row(row))));
+            }
+        }
+        if self.finished {
+            return Poll::Ready(None);
         }
         match Pin::new(&mut self.data).poll_next(cx) {
             Poll::Ready(Some(Ok(datum))) => {
                 // magic number 1 is used to indicate progress
                 if datum.app_metadata[..] == [0x01] {
                     let progress: QueryProgress = serde_json::from_slice(&datum.data_body)?;
+                    self.progress = progress.clone();
                     Poll::Ready(Some(Ok(RowWithProgress::Progress(progress))))
                 } else {
                     let dicitionaries_by_id = HashMap::new();
@@ -339,11 +722,23 @@ impl Stream for FlightSQLRows {
                 }
             }
             Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(err.into()))),
-            Poll::Ready(None) => Poll::Ready(None),
-            Poll::Pending => {
-                cx.waker().wake_by_ref();
-                Poll::Pending
+            Poll::Ready(None) => {
+                self.resume.set(None);
+                self.finished = true;
+                let stats = QueryStats {
+                    scan_rows: self.progress.read_rows,
+                    scan_bytes: self.progress.read_bytes,
+                    write_rows: self.progress.write_rows,
+                    write_bytes: self.progress.write_bytes,
+                    result_rows: self.offset,
+                    result_bytes: 0,
+                    running_time: self.started.elapsed(),
+                };
+                Poll::Ready(Some(Ok(RowWithProgress::Stats(stats))))
             }
+            // woken by the stream once more data arrives, rows buffered
+            // and the position are kept in self if the caller gives up
+            Poll::Pending => Poll::Pending,
         }
     }
 }
@@ -0,0 +1,187 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed results of the fuse engine table functions,
+//! for building table maintenance tools like vacuum or recluster schedulers.
+
+use chrono::NaiveDateTime;
+
+use databend_sql::error::{Error, Result};
+use databend_sql::quote::escape_literal;
+use databend_sql::rows::Row;
+use databend_sql::time_travel::TimeTravelPoint;
+
+use crate::conn::Connection;
+use crate::utils::{query_named, ColumnIndex};
+
+/// One row of `FUSE_SNAPSHOT(<database>, <table>)`.
+#[derive(Clone, Debug)]
+pub struct FuseSnapshot {
+    pub snapshot_id: String,
+    pub snapshot_location: String,
+    pub format_version: u64,
+    pub previous_snapshot_id: Option<String>,
+    pub segment_count: u64,
+    pub block_count: u64,
+    pub row_count: u64,
+    pub bytes_uncompressed: u64,
+    pub bytes_compressed: u64,
+    pub index_size: Option<u64>,
+    pub timestamp: Option<NaiveDateTime>,
+}
+
+impl FuseSnapshot {
+    pub(crate) fn from_row(index: &ColumnIndex, row: &Row) -> Result<Self> {
+        Ok(Self {
+            snapshot_id: index.get(row, "snapshot_id")?,
+            snapshot_location: index.get(row, "snapshot_location")?,
+            format_version: index.get(row, "format_version")?,
+            previous_snapshot_id: index.get_opt(row, "previous_snapshot_id")?,
+            segment_count: index.get(row, "segment_count")?,
+            block_count: index.get(row, "block_count")?,
+            row_count: index.get(row, "row_count")?,
+            bytes_uncompressed: index.get(row, "bytes_uncompressed")?,
+            bytes_compressed: index.get(row, "bytes_compressed")?,
+            index_size: index.get_opt(row, "index_size")?,
+            timestamp: index.get_opt(row, "timestamp")?,
+        })
+    }
+}
+
+impl From<&FuseSnapshot> for TimeTravelPoint {
+    fn from(snapshot: &FuseSnapshot) -> Self {
+        TimeTravelPoint::Snapshot(snapshot.snapshot_id.clone())
+    }
+}
+
+/// One row of `FUSE_SEGMENT(<database>, <table>, <snapshot_id>)`.
+#[derive(Clone, Debug)]
+pub struct FuseSegment {
+    pub file_location: String,
+    pub format_version: u64,
+    pub block_count: u64,
+    pub row_count: u64,
+    pub bytes_uncompressed: u64,
+    pub bytes_compressed: u64,
+}
+
+impl FuseSegment {
+    pub(crate) fn from_row(index: &ColumnIndex, row: &Row) -> Result<Self> {
+        Ok(Self {
+            file_location: index.get(row, "file_location")?,
+            format_version: index.get(row, "format_version")?,
+            block_count: index.get(row, "block_count")?,
+            row_count: index.get(row, "row_count")?,
+            bytes_uncompressed: index.get(row, "bytes_uncompressed")?,
+            bytes_compressed: index.get(row, "bytes_compressed")?,
+        })
+    }
+}
+
+/// Result of `CLUSTERING_INFORMATION(<database>, <table>)`.
+#[derive(Clone, Debug)]
+pub struct ClusteringInformation {
+    pub cluster_key: String,
+    pub total_block_count: u64,
+    pub constant_block_count: u64,
+    pub unclustered_block_count: u64,
+    pub average_overlaps: f64,
+    pub average_depth: f64,
+    /// JSON text of the block depth histogram
+    pub block_depth_histogram: String,
+}
+
+impl ClusteringInformation {
+    pub(crate) fn from_row(index: &ColumnIndex, row: &Row) -> Result<Self> {
+        Ok(Self {
+            cluster_key: index.get(row, "cluster_key")?,
+            total_block_count: index.get(row, "total_block_count")?,
+            constant_block_count: index.get(row, "constant_block_count")?,
+            unclustered_block_count: index.get(row, "unclustered_block_count")?,
+            average_overlaps: index.get(row, "average_overlaps")?,
+            average_depth: index.get(row, "average_depth")?,
+            block_depth_histogram: index.get(row, "block_depth_histogram")?,
+        })
+    }
+}
+
+pub(crate) async fn fuse_snapshot<C>(
+    conn: &C,
+    database: &str,
+    table: &str,
+) -> Result<Vec<FuseSnapshot>>
+where
+    C: Connection + ?Sized,
+{
+    let sql = format!(
+        "SELECT * FROM FUSE_SNAPSHOT({}, {})",
+        escape_literal(database),
+        escape_literal(table)
+    );
+    query_named(conn, &sql, FuseSnapshot::from_row).await
+}
+
+pub(crate) async fn list_snapshots<C>(conn: &C, table: &str) -> Result<Vec<FuseSnapshot>>
+where
+    C: Connection + ?Sized,
+{
+    match table.split_once('.') {
+        Some((database, table)) => fuse_snapshot(conn, database, table).await,
+        None => {
+            let database = conn.info().await.database;
+            let database = database.as_deref().unwrap_or("default");
+            fuse_snapshot(conn, database, table).await
+        }
+    }
+}
+
+pub(crate) async fn fuse_segment<C>(
+    conn: &C,
+    database: &str,
+    table: &str,
+    snapshot_id: Option<&str>,
+) -> Result<Vec<FuseSegment>>
+where
+    C: Connection + ?Sized,
+{
+    let mut args = vec![escape_literal(database), escape_literal(table)];
+    if let Some(snapshot_id) = snapshot_id {
+        args.push(escape_literal(snapshot_id));
+    }
+    let sql = format!("SELECT * FROM FUSE_SEGMENT({})", args.join(", "));
+    query_named(conn, &sql, FuseSegment::from_row).await
+}
+
+pub(crate) async fn clustering_information<C>(
+    conn: &C,
+    database: &str,
+    table: &str,
+) -> Result<ClusteringInformation>
+where
+    C: Connection + ?Sized,
+{
+    let sql = format!(
+        "SELECT * FROM CLUSTERING_INFORMATION({}, {})",
+        escape_literal(database),
+        escape_literal(table)
+    );
+    let mut results = query_named(conn, &sql, ClusteringInformation::from_row).await?;
+    match results.pop() {
+        Some(info) => Ok(info),
+        None => Err(Error::InvalidResponse(format!(
+            "no clustering information for table {}.{}",
+            database, table
+        ))),
+    }
+}
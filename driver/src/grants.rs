@@ -0,0 +1,224 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed results of `SHOW GRANTS` and `SHOW ROLES`, for access audits
+//! and readable permission summaries.
+
+use std::fmt;
+
+use databend_sql::builder;
+use databend_sql::error::{Error, Result};
+use databend_sql::rows::Row;
+
+use crate::conn::Connection;
+use crate::utils::{query_named, ColumnIndex};
+
+/// Whom the privileges are granted to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Grantee {
+    User { name: String, host: String },
+    Role(String),
+}
+
+impl Grantee {
+    /// User allowed from any host, `'name'@'%'`.
+    pub fn user(name: &str) -> Self {
+        Self::User {
+            name: name.to_string(),
+            host: "%".to_string(),
+        }
+    }
+
+    pub fn role(name: &str) -> Self {
+        Self::Role(name.to_string())
+    }
+
+    pub(crate) fn show_grants_sql(&self) -> String {
+        match self {
+            Self::User { name, host } => builder::show_grants_for_user(name, host),
+            Self::Role(name) => builder::show_grants_for_role(name),
+        }
+    }
+}
+
+/// What the privileges of a grant are on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GrantObject {
+    /// `*.*`
+    Global,
+    Database(String),
+    Table {
+        database: String,
+        table: String,
+    },
+    Stage(String),
+    Udf(String),
+    /// All privileges of the role are inherited.
+    Role(String),
+}
+
+impl fmt::Display for GrantObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Global => write!(f, "*.*"),
+            Self::Database(database) => write!(f, "{}.*", database),
+            Self::Table { database, table } => write!(f, "{}.{}", database, table),
+            Self::Stage(stage) => write!(f, "STAGE {}", stage),
+            Self::Udf(udf) => write!(f, "UDF {}", udf),
+            Self::Role(role) => write!(f, "ROLE {}", role),
+        }
+    }
+}
+
+/// One row of `SHOW GRANTS`, parsed from the `GRANT` statement of it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grant {
+    /// Upper case like `SELECT` or `ALL`, empty for granted roles.
+    pub privileges: Vec<String>,
+    pub object: GrantObject,
+    /// The statement as shown by the server.
+    pub statement: String,
+}
+
+impl Grant {
+    /// Parse statements like `GRANT SELECT,INSERT ON 'default'.'db'.* TO 'u'@'%'`
+    /// or `GRANT ROLE r TO ROLE r2`.
+    pub fn from_statement(statement: &str) -> Result<Self> {
+        let invalid = || Error::InvalidResponse(format!("invalid grant: {}", statement));
+        let rest = strip_keyword(statement.trim(), "GRANT").ok_or_else(invalid)?;
+        if let Some(rest) = strip_keyword(rest, "ROLE") {
+            let (role, _) = split_keyword(rest, "TO").ok_or_else(invalid)?;
+            return Ok(Self {
+                privileges: vec![],
+                object: GrantObject::Role(unquote(role)),
+                statement: statement.to_string(),
+            });
+        }
+        let (privileges, rest) = split_keyword(rest, "ON").ok_or_else(invalid)?;
+        let (object, _) = split_keyword(rest, "TO").ok_or_else(invalid)?;
+        Ok(Self {
+            privileges: privileges
+                .split(',')
+                .map(|p| p.trim().to_uppercase())
+                .filter(|p| !p.is_empty())
+                .collect(),
+            object: parse_object(object).ok_or_else(invalid)?,
+            statement: statement.to_string(),
+        })
+    }
+
+    pub(crate) fn from_row(index: &ColumnIndex, row: &Row) -> Result<Self> {
+        let statement: String = index.get(row, "grants")?;
+        Self::from_statement(&statement)
+    }
+}
+
+/// One row of `SHOW ROLES`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoleInfo {
+    pub name: String,
+    pub inherited_roles: u64,
+    /// `None` if not reported by older servers.
+    pub is_current: Option<bool>,
+    pub is_default: Option<bool>,
+}
+
+impl RoleInfo {
+    pub(crate) fn from_row(index: &ColumnIndex, row: &Row) -> Result<Self> {
+        Ok(Self {
+            name: index.get(row, "name")?,
+            inherited_roles: index.get(row, "inherited_roles")?,
+            is_current: index.get_opt(row, "is_current")?,
+            is_default: index.get_opt(row, "is_default")?,
+        })
+    }
+}
+
+fn parse_object(object: &str) -> Option<GrantObject> {
+    let object = object.trim();
+    if let Some(stage) = strip_keyword(object, "STAGE") {
+        return Some(GrantObject::Stage(unquote(stage)));
+    }
+    if let Some(udf) = strip_keyword(object, "UDF") {
+        return Some(GrantObject::Udf(unquote(udf)));
+    }
+    // `*.*`, `db.*` or `db.table`, optionally prefixed by the catalog
+    let parts: Vec<String> = object.split('.').map(unquote).collect();
+    match parts.as_slice() {
+        [.., db, table] if db == "*" && table == "*" => Some(GrantObject::Global),
+        [.., db, table] if table == "*" => Some(GrantObject::Database(db.clone())),
+        [.., db, table] => Some(GrantObject::Table {
+            database: db.clone(),
+            table: table.clone(),
+        }),
+        _ => None,
+    }
+}
+
+/// The rest after the leading keyword, matched case insensitively.
+fn strip_keyword<'a>(s: &'a str, keyword: &str) -> Option<&'a str> {
+    let head = s.get(..keyword.len())?;
+    let rest = &s[keyword.len()..];
+    if head.eq_ignore_ascii_case(keyword) && rest.starts_with(char::is_whitespace) {
+        Some(rest.trim_start())
+    } else {
+        None
+    }
+}
+
+/// Split at the first standalone keyword outside of quotes.
+fn split_keyword<'a>(s: &'a str, keyword: &str) -> Option<(&'a str, &'a str)> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"' | '`') => quote = Some(c),
+            (None, c) if c.is_whitespace() => {
+                if let Some(rest) = strip_keyword(s[i..].trim_start(), keyword) {
+                    return Some((s[..i].trim(), rest));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn unquote(s: &str) -> String {
+    let s = s.trim();
+    let quoted = s.len() >= 2
+        && ['\'', '"', '`']
+            .iter()
+            .any(|q| s.starts_with(*q) && s.ends_with(*q));
+    if quoted {
+        s[1..s.len() - 1].to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+pub(crate) async fn show_grants<C>(conn: &C, grantee: &Grantee) -> Result<Vec<Grant>>
+where
+    C: Connection + ?Sized,
+{
+    query_named(conn, &grantee.show_grants_sql(), Grant::from_row).await
+}
+
+pub(crate) async fn roles<C>(conn: &C) -> Result<Vec<RoleInfo>>
+where
+    C: Connection + ?Sized,
+{
+    query_named(conn, "SHOW ROLES", RoleInfo::from_row).await
+}
@@ -12,23 +12,115 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#[cfg(feature = "bridge")]
+mod bridge;
+mod changes;
 mod conn;
+mod csv;
+mod diagnostics;
+mod dump;
+mod ext;
 #[cfg(feature = "flight-sql")]
 mod flight_sql;
+mod fuse;
+mod grants;
+#[cfg(feature = "local-file")]
+mod local_file;
+mod monitor;
+mod options;
+#[cfg(feature = "local-file")]
+mod parquet_export;
+mod pool;
 mod rest_api;
+#[cfg(feature = "scheduler")]
+mod scheduler;
+mod schema_cache;
+#[cfg(feature = "spill")]
+mod spill;
+mod stage;
+mod stmt;
+mod temp;
+mod udf;
+mod utils;
+mod version;
 
-pub use conn::{Client, Connection, ConnectionInfo};
+#[cfg(feature = "bridge")]
+pub use bridge::FlightBridge;
+pub use conn::{
+    Client, Connection, ConnectionInfo, LoadStats, QueryId, ServerInfo, Setting, StatementResult,
+};
+pub use csv::{CsvLoad, CsvOptions, RejectedRow};
+pub use diagnostics::Diagnostics;
+pub use ext::ConnectionExt;
+#[cfg(feature = "flight-sql")]
+pub use flight_sql::FlightSQLConnection;
+pub use fuse::{ClusteringInformation, FuseSegment, FuseSnapshot};
+pub use grants::{Grant, GrantObject, Grantee, RoleInfo};
+#[cfg(feature = "local-file")]
+pub use local_file::{describe_local_file, LocalFileFormat, LocalFileInfo};
+pub use monitor::{QueryLogEntry, SystemMetric, WarehouseUtilization};
+pub use options::{QueryOptions, RowMapper};
+#[cfg(feature = "local-file")]
+pub use parquet_export::{write_parquet, ParquetCompression, ParquetOptions};
+pub use pool::{Pool, PoolOptions, PoolStatus, PooledConnection};
+#[cfg(feature = "scheduler")]
+pub use scheduler::{JobEvent, JobObserver, Scheduler, SchedulerHandle};
+#[cfg(feature = "spill")]
+pub use spill::{ResultSet, SpillOptions, SpilledRows};
+pub use stage::{ExportedFile, StageFile};
+pub use stmt::PreparedStatement;
+pub use temp::{TempObject, TempTable};
+pub use udf::{UserFunction, UserFunctionKind};
+pub use version::{ServerFeature, ServerVersion};
 
 // pub use for convenience
-pub use databend_sql::error::{Error, Result};
+#[cfg(feature = "flight-sql")]
+pub use arrow_flight::{FlightEndpoint, FlightInfo, Ticket};
+pub use databend_client::comment::QueryComment;
+pub use databend_client::counters::{EndpointStats, ErrorCounts};
+pub use databend_client::credentials::{Credentials, CredentialsFuture, CredentialsProvider};
+pub use databend_client::error::Error as ApiError;
+pub use databend_client::limit::RequestLimiter;
+pub use databend_client::upload::UploadOptions;
+pub use databend_client::HttpClient;
+pub use databend_sql::builder;
+pub use databend_sql::copy::{
+    Compression, CopyOptions, FileFormatOptions, FileType, OnError, UnloadOptions,
+};
+pub use databend_sql::error::{Error, ErrorKind, Result};
+#[cfg(feature = "arrow-ipc")]
+pub use databend_sql::ipc::{read_ipc, write_ipc, RecordBatchIterator};
+pub use databend_sql::quote::{escape_literal, quote_ident, quote_qualified_ident};
 pub use databend_sql::rows::{
-    QueryProgress, Row, RowIterator, RowProgressIterator, RowWithProgress,
+    NamedColumns, PartialRows, QueryHandle, QueryProgress, QueryStats, ResumeState, ResumeToken,
+    Row, RowError, RowIterator, RowProgressIterator, RowWithProgress, Rows,
 };
-pub use databend_sql::schema::{DataType, DecimalSize, Field, Schema, SchemaRef};
-pub use databend_sql::value::{NumberValue, Value};
+pub use databend_sql::schema::{
+    DataType, DecimalDataType, DecimalSize, Field, NumberDataType, Schema, SchemaRef,
+};
+pub use databend_sql::ser::{NonFinitePolicy, SerializeOptions};
+pub use databend_sql::time_travel::TimeTravelPoint;
+pub use databend_sql::value::{NumberValue, NumericMode, Value};
+pub use databend_sql::{find_placeholders, split_statements, Statement};
 
 pub use databend_driver_macros::TryFromRow;
 
+/// The underlying crates, re-exported so that only `databend-driver` is needed
+/// as a dependency, and the versions always match.
+pub use databend_client as client;
+pub use databend_sql as sql;
+
+/// Commonly used types and traits, `use databend_driver::prelude::*;`
+/// is enough for most applications.
+pub mod prelude {
+    pub use crate::{
+        Client, Connection, ConnectionExt, ConnectionInfo, Error, QueryOptions, Result, Row,
+        RowIterator, RowProgressIterator, RowWithProgress, Rows, Schema, TryFromRow, Value,
+    };
+    // to iterate over rows
+    pub use tokio_stream::StreamExt;
+}
+
 #[doc(hidden)]
 pub mod _macro_internal {
     pub use databend_sql::_macro_internal::*;
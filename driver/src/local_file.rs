@@ -0,0 +1,142 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use arrow::csv::reader::Format;
+use arrow::json::reader::infer_json_schema;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+use databend_sql::error::{Error, Result};
+use databend_sql::schema::{DataType, Schema};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LocalFileFormat {
+    Parquet,
+    /// CSV with header.
+    Csv,
+    NdJson,
+}
+
+impl LocalFileFormat {
+    /// Detect the format by file extension.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("parquet") => Ok(Self::Parquet),
+            Some("csv") => Ok(Self::Csv),
+            Some("ndjson") | Some("jsonl") => Ok(Self::NdJson),
+            _ => Err(Error::BadArgument(format!(
+                "Unknown file format for {:?}, expect .parquet, .csv or .ndjson",
+                path
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LocalFileInfo {
+    pub format: LocalFileFormat,
+    pub schema: Schema,
+    pub rows: u64,
+}
+
+impl LocalFileInfo {
+    /// Check the file could be loaded into a table with the schema,
+    /// columns are matched by name and types are only compared loosely,
+    /// since values are converted by the server when loading.
+    pub fn check_compatible(&self, table: &Schema) -> Result<()> {
+        for field in self.schema.fields() {
+            let column = table
+                .fields()
+                .iter()
+                .find(|f| f.name.eq_ignore_ascii_case(&field.name))
+                .ok_or_else(|| {
+                    Error::BadArgument(format!("Column {} not found in table", field.name))
+                })?;
+            let (src, dst) = (
+                unwrap_nullable(&field.data_type),
+                unwrap_nullable(&column.data_type),
+            );
+            let compatible = matches!(dst, DataType::String | DataType::Variant)
+                || (src.is_numeric() || matches!(src, DataType::Decimal(_)))
+                    == (dst.is_numeric() || matches!(dst, DataType::Decimal(_)));
+            if !compatible {
+                return Err(Error::BadArgument(format!(
+                    "Column {} of type {} is not compatible with {}",
+                    field.name, field.data_type, column.data_type
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn unwrap_nullable(dt: &DataType) -> &DataType {
+    match dt {
+        DataType::Nullable(inner) => inner,
+        _ => dt,
+    }
+}
+
+/// Read the schema and row count of a local file, CSV and NDJSON files are
+/// scanned fully to infer types, which is done in a blocking thread.
+pub async fn describe_local_file(path: impl AsRef<Path>) -> Result<LocalFileInfo> {
+    let path = path.as_ref().to_path_buf();
+    let format = LocalFileFormat::from_path(&path)?;
+    tokio::task::spawn_blocking(move || describe(&path, format))
+        .await
+        .map_err(|e| Error::IO(e.to_string()))?
+}
+
+fn describe(path: &PathBuf, format: LocalFileFormat) -> Result<LocalFileInfo> {
+    let file = File::open(path)?;
+    let (schema, rows) = match format {
+        LocalFileFormat::Parquet => {
+            let builder = ParquetRecordBatchReaderBuilder::try_new(file)
+                .map_err(|e| Error::IO(e.to_string()))?;
+            let rows = builder.metadata().file_metadata().num_rows() as u64;
+            (builder.schema().clone(), rows)
+        }
+        LocalFileFormat::Csv => {
+            let (schema, rows) = Format::default()
+                .with_header(true)
+                .infer_schema(file, None)?;
+            (Arc::new(schema), rows as u64)
+        }
+        LocalFileFormat::NdJson => {
+            let mut reader = BufReader::new(file);
+            let schema = infer_json_schema(&mut reader, None)?;
+            reader.rewind()?;
+            let mut rows = 0;
+            for line in reader.lines() {
+                if !line?.trim().is_empty() {
+                    rows += 1;
+                }
+            }
+            (Arc::new(schema), rows)
+        }
+    };
+    Ok(LocalFileInfo {
+        format,
+        schema: schema.try_into()?,
+        rows,
+    })
+}
@@ -0,0 +1,256 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed results of the system tables, for building monitoring exporters
+//! which poll the server periodically.
+
+use chrono::NaiveDateTime;
+
+use databend_sql::builder;
+use databend_sql::error::{Error, Result};
+use databend_sql::rows::Row;
+use databend_sql::value::Value;
+
+use crate::conn::Connection;
+use crate::utils::{query_named, ColumnIndex};
+
+/// One row of `system.metrics`.
+#[derive(Clone, Debug)]
+pub struct SystemMetric {
+    pub node: Option<String>,
+    pub metric: String,
+    /// `counter`, `gauge`, `histogram` or `summary`.
+    pub kind: String,
+    /// JSON text of the labels.
+    pub labels: String,
+    /// Number as text for counters and gauges, JSON text of the buckets
+    /// or quantiles for histograms and summaries.
+    pub value: String,
+}
+
+impl SystemMetric {
+    pub(crate) fn from_row(index: &ColumnIndex, row: &Row) -> Result<Self> {
+        Ok(Self {
+            node: index.get_opt(row, "node")?,
+            metric: index.get(row, "metric")?,
+            kind: index.get(row, "kind")?,
+            labels: index.get(row, "labels")?,
+            value: index.get(row, "value")?,
+        })
+    }
+
+    /// Value of counters and gauges.
+    pub fn as_f64(&self) -> Option<f64> {
+        self.value.trim().parse().ok()
+    }
+}
+
+/// One row of `system.query_log`, only the columns useful for monitoring.
+#[derive(Clone, Debug)]
+pub struct QueryLogEntry {
+    pub query_id: String,
+    /// `Start`, `Finish`, `Aborted` or `Exception`.
+    pub log_type: String,
+    pub query_kind: String,
+    pub query_text: String,
+    pub sql_user: String,
+    pub current_database: String,
+    pub event_time: NaiveDateTime,
+    pub query_duration_ms: i64,
+    pub scan_rows: u64,
+    pub scan_bytes: u64,
+    pub written_rows: u64,
+    pub written_bytes: u64,
+    pub result_rows: u64,
+    pub result_bytes: u64,
+    pub exception_code: i32,
+    pub exception_text: String,
+}
+
+impl QueryLogEntry {
+    pub(crate) const COLUMNS: &'static [&'static str] = &[
+        "query_id",
+        "log_type_name",
+        "query_kind",
+        "query_text",
+        "sql_user",
+        "current_database",
+        "event_time",
+        "query_duration_ms",
+        "scan_rows",
+        "scan_bytes",
+        "written_rows",
+        "written_bytes",
+        "result_rows",
+        "result_bytes",
+        "exception_code",
+        "exception_text",
+    ];
+
+    pub(crate) fn from_row(index: &ColumnIndex, row: &Row) -> Result<Self> {
+        Ok(Self {
+            query_id: index.get(row, "query_id")?,
+            log_type: index.get(row, "log_type_name")?,
+            query_kind: index.get(row, "query_kind")?,
+            query_text: index.get(row, "query_text")?,
+            sql_user: index.get(row, "sql_user")?,
+            current_database: index.get(row, "current_database")?,
+            event_time: index.get(row, "event_time")?,
+            query_duration_ms: index.get(row, "query_duration_ms")?,
+            scan_rows: index.get(row, "scan_rows")?,
+            scan_bytes: index.get(row, "scan_bytes")?,
+            written_rows: index.get(row, "written_rows")?,
+            written_bytes: index.get(row, "written_bytes")?,
+            result_rows: index.get(row, "result_rows")?,
+            result_bytes: index.get(row, "result_bytes")?,
+            exception_code: index.get(row, "exception_code")?,
+            exception_text: index.get(row, "exception_text")?,
+        })
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.exception_code != 0
+    }
+}
+
+/// Load of the warehouse since a time, aggregated from `system.query_log`
+/// and `system.processes`.
+#[derive(Clone, Debug, Default)]
+pub struct WarehouseUtilization {
+    /// Queries running now.
+    pub running_queries: u64,
+    /// Queries finished or failed since the time.
+    pub finished_queries: u64,
+    pub failed_queries: u64,
+    /// Sum of the duration of finished queries.
+    pub busy_ms: u64,
+    pub scan_bytes: u64,
+    pub written_bytes: u64,
+}
+
+impl WarehouseUtilization {
+    pub(crate) fn from_row(index: &ColumnIndex, row: &Row) -> Result<Self> {
+        Ok(Self {
+            running_queries: index.get(row, "running_queries")?,
+            finished_queries: index.get(row, "finished_queries")?,
+            failed_queries: index.get(row, "failed_queries")?,
+            busy_ms: index.get_opt(row, "busy_ms")?.unwrap_or_default(),
+            scan_bytes: index.get_opt(row, "scan_bytes")?.unwrap_or_default(),
+            written_bytes: index.get_opt(row, "written_bytes")?.unwrap_or_default(),
+        })
+    }
+}
+
+pub(crate) fn utilization_sql(since: &NaiveDateTime) -> Result<String> {
+    builder::bind(
+        "SELECT (SELECT COUNT(*) FROM system.processes) AS running_queries, \
+         COUNT(*) AS finished_queries, \
+         COUNT_IF(exception_code <> 0) AS failed_queries, \
+         SUM(query_duration_ms)::UInt64 AS busy_ms, \
+         SUM(scan_bytes)::UInt64 AS scan_bytes, \
+         SUM(written_bytes)::UInt64 AS written_bytes \
+         FROM system.query_log \
+         WHERE log_type_name <> 'Start' AND event_time > ?",
+        &[Value::Timestamp(since.timestamp_micros())],
+    )
+}
+
+pub(crate) async fn system_metrics<C>(conn: &C) -> Result<Vec<SystemMetric>>
+where
+    C: Connection + ?Sized,
+{
+    query_named(conn, "SELECT * FROM system.metrics", SystemMetric::from_row).await
+}
+
+pub(crate) async fn query_log_since<C>(
+    conn: &C,
+    since: NaiveDateTime,
+    limit: u64,
+) -> Result<Vec<QueryLogEntry>>
+where
+    C: Connection + ?Sized,
+{
+    let sql = builder::select(QueryLogEntry::COLUMNS)
+        .from("system.query_log")
+        .where_("log_type_name", "<>", "Start")
+        .where_(
+            "event_time",
+            ">",
+            Value::Timestamp(since.timestamp_micros()),
+        )
+        .order_by("event_time", true)
+        .order_by("query_id", true)
+        .limit(limit)
+        .build()?;
+    query_named(conn, &sql, QueryLogEntry::from_row).await
+}
+
+pub(crate) async fn query_log_after<C>(
+    conn: &C,
+    last: &QueryLogEntry,
+    limit: u64,
+) -> Result<Vec<QueryLogEntry>>
+where
+    C: Connection + ?Sized,
+{
+    let event_time = Value::Timestamp(last.event_time.timestamp_micros());
+    let sql = builder::select(QueryLogEntry::COLUMNS)
+        .from("system.query_log")
+        .where_("log_type_name", "<>", "Start")
+        .where_("event_time", ">=", event_time.clone())
+        .where_any(&[
+            ("event_time", ">", event_time),
+            ("query_id", ">", Value::String(last.query_id.clone())),
+        ])
+        .order_by("event_time", true)
+        .order_by("query_id", true)
+        .limit(limit)
+        .build()?;
+    query_named(conn, &sql, QueryLogEntry::from_row).await
+}
+
+pub(crate) async fn warehouse_utilization<C>(
+    conn: &C,
+    since: NaiveDateTime,
+) -> Result<WarehouseUtilization>
+where
+    C: Connection + ?Sized,
+{
+    let sql = utilization_sql(&since)?;
+    let mut results = query_named(conn, &sql, WarehouseUtilization::from_row).await?;
+    results
+        .pop()
+        .ok_or_else(|| Error::InvalidResponse("no result of warehouse utilization".to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn utilization_since() -> Result<()> {
+        let since = chrono::DateTime::from_timestamp(0, 1000)
+            .unwrap()
+            .naive_utc();
+        let sql = utilization_sql(&since)?;
+        assert!(
+            sql.ends_with(
+                "WHERE log_type_name <> 'Start' AND event_time > '1970-01-01 00:00:00.000001'"
+            ),
+            "{}",
+            sql
+        );
+        Ok(())
+    }
+}
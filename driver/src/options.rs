@@ -0,0 +1,180 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use databend_sql::builder::set_var_hint;
+use databend_sql::error::{Error, Result};
+use databend_sql::rows::{Row, RowIterator};
+use databend_sql::schema::{Schema, SchemaRef};
+use databend_sql::time_travel::{rewrite_at, TimeTravelPoint};
+use tokio_stream::StreamExt;
+
+use crate::conn::{explain_failure, Connection};
+use crate::version::ServerFeature;
+
+/// Transformation applied to each row in the result stream, with the schema
+/// of the query result, e.g. masking, reordering or deriving columns.
+pub type RowMapper = Arc<dyn Fn(&Schema, Row) -> Result<Row> + Send + Sync>;
+
+/// Options applied to a single query.
+#[derive(Clone, Default)]
+pub struct QueryOptions {
+    at: Option<TimeTravelPoint>,
+    mapper: Option<RowMapper>,
+    mapped_schema: Option<SchemaRef>,
+    no_result_cache: bool,
+}
+
+impl std::fmt::Debug for QueryOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueryOptions")
+            .field("at", &self.at)
+            .field("mapper", &self.mapper.as_ref().map(|_| "RowMapper"))
+            .field("mapped_schema", &self.mapped_schema)
+            .field("no_result_cache", &self.no_result_cache)
+            .finish()
+    }
+}
+
+impl QueryOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Query the table at a point of its history, only works for `SELECT`
+    /// from a single fuse table.
+    pub fn at(mut self, point: TimeTravelPoint) -> Self {
+        self.at = Some(point);
+        self
+    }
+
+    /// Neither read from nor write to the query result cache, for benchmarks
+    /// and queries which must see the latest data. Only works for statements
+    /// accepting hints like `SELECT` and `INSERT`, but not `WITH`.
+    pub fn no_result_cache(mut self) -> Self {
+        self.no_result_cache = true;
+        self
+    }
+
+    /// Transform rows before they are returned, errors from the mapper
+    /// are returned as items of the stream. The mapped rows must keep the
+    /// columns of the query result, use `mapper_with_schema` otherwise.
+    pub fn mapper<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Schema, Row) -> Result<Row> + Send + Sync + 'static,
+    {
+        self.mapper = Some(Arc::new(f));
+        self.mapped_schema = None;
+        self
+    }
+
+    /// Transform rows into the given schema, e.g. renaming, dropping or
+    /// deriving columns, which is reported as the schema of the result.
+    /// Mapped rows with a different number of columns are returned as errors.
+    pub fn mapper_with_schema<F>(mut self, schema: Schema, f: F) -> Self
+    where
+        F: Fn(&Schema, Row) -> Result<Row> + Send + Sync + 'static,
+    {
+        self.mapper = Some(Arc::new(f));
+        self.mapped_schema = Some(Arc::new(schema));
+        self
+    }
+
+    pub(crate) fn has_mapper(&self) -> bool {
+        self.mapper.is_some()
+    }
+
+    /// Whether optimizer hints are added to the query.
+    pub(crate) fn has_hints(&self) -> bool {
+        self.no_result_cache
+    }
+
+    pub(crate) fn map_rows(&self, rows: RowIterator) -> RowIterator {
+        match self.mapper {
+            Some(ref mapper) => {
+                let mapper = mapper.clone();
+                let schema = rows.schema();
+                let mapped_schema = self.mapped_schema.clone();
+                let resume = rows.resume_state();
+                let handle = rows.handle();
+                let rows = rows.map({
+                    let schema = schema.clone();
+                    let mapped_schema = mapped_schema.clone();
+                    move |row| {
+                        let row = mapper(&schema, row?)?;
+                        match mapped_schema {
+                            Some(ref mapped) if mapped.fields().len() != row.len() => {
+                                Err(Error::BadArgument(format!(
+                                    "mapped row has {} columns, but the schema has {}",
+                                    row.len(),
+                                    mapped.fields().len()
+                                )))
+                            }
+                            _ => Ok(row),
+                        }
+                    }
+                });
+                RowIterator::new(mapped_schema.unwrap_or(schema), Box::pin(rows))
+                    .with_resume(resume)
+                    .with_handle(handle)
+            }
+            None => rows,
+        }
+    }
+
+    pub(crate) fn apply(&self, sql: &str) -> Result<String> {
+        let sql = match self.at {
+            Some(ref point) => rewrite_at(sql, point)?,
+            None => sql.to_string(),
+        };
+        if self.no_result_cache {
+            return set_var_hint(&sql, "enable_query_result_cache", "0");
+        }
+        Ok(sql)
+    }
+}
+
+pub(crate) async fn query_row_with_options<C>(
+    conn: &C,
+    sql: &str,
+    options: &QueryOptions,
+) -> Result<Option<Row>>
+where
+    C: Connection + ?Sized,
+{
+    if options.has_mapper() {
+        let mut rows = query_iter_with_options(conn, sql, options).await?;
+        return rows.next().await.transpose();
+    }
+    conn.query_row(&options.apply(sql)?).await
+}
+
+pub(crate) async fn query_iter_with_options<C>(
+    conn: &C,
+    sql: &str,
+    options: &QueryOptions,
+) -> Result<RowIterator>
+where
+    C: Connection + ?Sized,
+{
+    let rows = match conn.query_iter(&options.apply(sql)?).await {
+        Ok(rows) => rows,
+        Err(e) if options.has_hints() => {
+            return Err(explain_failure(conn, ServerFeature::SetVarHint, e).await)
+        }
+        Err(e) => return Err(e),
+    };
+    Ok(options.map_rows(rows))
+}
@@ -0,0 +1,219 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, GzipLevel, ZstdLevel};
+use parquet::file::properties::{EnabledStatistics, WriterProperties};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio_stream::StreamExt;
+
+use databend_sql::error::{Error, Result};
+use databend_sql::ipc::RecordBatchIterator;
+
+/// Compression codec of the pages in exported Parquet files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParquetCompression {
+    #[default]
+    Uncompressed,
+    /// Gzip with level from 0 to 10.
+    Gzip(u32),
+    Snappy,
+    /// Zstd with level from 1 to 22.
+    Zstd(i32),
+}
+
+/// Writer knobs of exported Parquet files, engines reading them are often
+/// sensitive to the row group size and the presence of statistics.
+#[derive(Clone, Debug)]
+pub struct ParquetOptions {
+    compression: ParquetCompression,
+    max_row_group_size: usize,
+    data_page_size: usize,
+    dictionary: bool,
+    statistics: bool,
+}
+
+impl Default for ParquetOptions {
+    fn default() -> Self {
+        Self {
+            compression: ParquetCompression::Uncompressed,
+            max_row_group_size: 1024 * 1024,
+            data_page_size: 1024 * 1024,
+            dictionary: true,
+            statistics: true,
+        }
+    }
+}
+
+impl ParquetOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn compression(mut self, compression: ParquetCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Max rows of a row group, defaults to 1M.
+    pub fn max_row_group_size(mut self, rows: usize) -> Self {
+        self.max_row_group_size = rows;
+        self
+    }
+
+    /// Best effort max bytes of a data page, defaults to 1MB.
+    pub fn data_page_size(mut self, bytes: usize) -> Self {
+        self.data_page_size = bytes;
+        self
+    }
+
+    /// Dictionary encode the columns, enabled by default.
+    pub fn dictionary(mut self, enabled: bool) -> Self {
+        self.dictionary = enabled;
+        self
+    }
+
+    /// Write min/max statistics of each page and column chunk, enabled
+    /// by default.
+    pub fn statistics(mut self, enabled: bool) -> Self {
+        self.statistics = enabled;
+        self
+    }
+
+    fn properties(&self) -> Result<WriterProperties> {
+        if self.max_row_group_size == 0 || self.data_page_size == 0 {
+            return Err(Error::BadArgument(
+                "Row group and page size of Parquet must be positive".to_string(),
+            ));
+        }
+        let compression = match self.compression {
+            ParquetCompression::Uncompressed => Compression::UNCOMPRESSED,
+            ParquetCompression::Gzip(level) => Compression::GZIP(
+                GzipLevel::try_new(level).map_err(|e| Error::BadArgument(e.to_string()))?,
+            ),
+            ParquetCompression::Snappy => Compression::SNAPPY,
+            ParquetCompression::Zstd(level) => Compression::ZSTD(
+                ZstdLevel::try_new(level).map_err(|e| Error::BadArgument(e.to_string()))?,
+            ),
+        };
+        let statistics = if self.statistics {
+            EnabledStatistics::Page
+        } else {
+            EnabledStatistics::None
+        };
+        Ok(WriterProperties::builder()
+            .set_compression(compression)
+            .set_max_row_group_size(self.max_row_group_size)
+            .set_data_page_size_limit(self.data_page_size)
+            .set_dictionary_enabled(self.dictionary)
+            .set_statistics_enabled(statistics)
+            .build())
+    }
+}
+
+/// In memory sink of the blocking parquet writer, drained to the async
+/// writer after each batch.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Write all batches to the writer as a Parquet file, returns the number
+/// of rows written. Row groups are buffered in memory until full.
+pub async fn write_parquet<W>(
+    mut batches: RecordBatchIterator,
+    writer: &mut W,
+    options: &ParquetOptions,
+) -> Result<usize>
+where
+    W: AsyncWrite + Unpin,
+{
+    let buffer = SharedBuffer::default();
+    let mut parquet = ArrowWriter::try_new(
+        buffer.clone(),
+        batches.schema(),
+        Some(options.properties()?),
+    )
+    .map_err(|e| Error::IO(e.to_string()))?;
+    let mut total = 0;
+    while let Some(batch) = batches.next().await {
+        let batch = batch?;
+        total += batch.num_rows();
+        parquet
+            .write(&batch)
+            .map_err(|e| Error::IO(e.to_string()))?;
+        writer.write_all(&buffer.take()).await?;
+    }
+    parquet.close().map_err(|e| Error::IO(e.to_string()))?;
+    writer.write_all(&buffer.take()).await?;
+    writer.flush().await?;
+    Ok(total)
+}
+
+pub(crate) async fn export_parquet(
+    batches: RecordBatchIterator,
+    path: &Path,
+    options: &ParquetOptions,
+) -> Result<usize> {
+    // fail before creating the file for invalid options
+    options.properties()?;
+    let mut file = tokio::fs::File::create(path).await?;
+    let guard = PartialFile { path, done: false };
+    let rows = write_parquet(batches, &mut file, options).await?;
+    guard.finish();
+    Ok(rows)
+}
+
+/// Removes the file unless the export is finished, also when it is
+/// cancelled, so no truncated file is left behind.
+struct PartialFile<'a> {
+    path: &'a Path,
+    done: bool,
+}
+
+impl PartialFile<'_> {
+    fn finish(mut self) {
+        self.done = true;
+    }
+}
+
+impl Drop for PartialFile<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = std::fs::remove_file(self.path);
+        }
+    }
+}
@@ -0,0 +1,253 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use databend_client::counters::EndpointStats;
+use databend_sql::error::{Error, Result};
+
+use crate::conn::{Client, Connection};
+use crate::ext::ConnectionExt;
+
+#[derive(Clone, Debug)]
+pub struct PoolOptions {
+    /// Connections checked out and idle at most, `get()` waits for one
+    /// to be returned when reached.
+    pub max_size: usize,
+    /// How long `get()` waits for a connection before failing.
+    pub acquire_timeout: Duration,
+    /// Idle connections are closed after this.
+    pub idle_timeout: Option<Duration>,
+    /// Connections are closed after this since created, even if in use
+    /// they are not returned to the pool.
+    pub max_lifetime: Option<Duration>,
+    /// Ping idle connections before handing them out.
+    pub test_on_acquire: bool,
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        Self {
+            max_size: 10,
+            acquire_timeout: Duration::from_secs(30),
+            idle_timeout: Some(Duration::from_secs(600)),
+            max_lifetime: Some(Duration::from_secs(1800)),
+            test_on_acquire: true,
+        }
+    }
+}
+
+/// Number of connections of a pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolStatus {
+    pub size: usize,
+    pub idle: usize,
+}
+
+/// Connections shared by tasks, cheap to clone.
+///
+/// Idle connections are kept per warehouse, and the session is reset to the
+/// one from dsn on checkout, so that `USE` and `SET` do not leak between
+/// users. Connections failed to reset are closed, and a new one is opened.
+#[derive(Clone)]
+pub struct Pool {
+    inner: Arc<PoolInner>,
+}
+
+struct PoolInner {
+    client: Client,
+    options: PoolOptions,
+    semaphore: Arc<Semaphore>,
+    idle: Mutex<HashMap<Option<String>, VecDeque<IdleConnection>>>,
+}
+
+struct IdleConnection {
+    conn: Box<dyn Connection>,
+    created: Instant,
+    idle_since: Instant,
+}
+
+impl Pool {
+    pub fn new(client: Client, options: PoolOptions) -> Self {
+        let semaphore = Arc::new(Semaphore::new(options.max_size));
+        Self {
+            inner: Arc::new(PoolInner {
+                client,
+                options,
+                semaphore,
+                idle: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Check out a connection, which goes back to the pool when dropped.
+    pub async fn get(&self) -> Result<PooledConnection> {
+        self.acquire(None).await
+    }
+
+    /// Check out a connection with queries routed to the warehouse.
+    pub async fn get_with_warehouse(&self, warehouse: &str) -> Result<PooledConnection> {
+        self.acquire(Some(warehouse.to_string())).await
+    }
+
+    pub fn status(&self) -> PoolStatus {
+        let idle = self
+            .inner
+            .idle
+            .lock()
+            .unwrap()
+            .values()
+            .map(|q| q.len())
+            .sum();
+        let size = self.inner.options.max_size - self.inner.semaphore.available_permits() + idle;
+        PoolStatus { size, idle }
+    }
+
+    /// Health and latency of requests by endpoint, of all connections of
+    /// the pool, see [`Client::endpoint_stats`].
+    pub fn endpoint_stats(&self) -> BTreeMap<String, EndpointStats> {
+        self.inner.client.endpoint_stats()
+    }
+
+    async fn acquire(&self, warehouse: Option<String>) -> Result<PooledConnection> {
+        let timeout = self.inner.options.acquire_timeout;
+        let permit = tokio::time::timeout(timeout, self.inner.semaphore.clone().acquire_owned())
+            .await
+            .map_err(|_| {
+                Error::IO(format!(
+                    "timed out acquiring a connection from pool after {:?}",
+                    timeout
+                ))
+            })?
+            .map_err(|e| Error::IO(format!("pool is closed: {}", e)))?;
+
+        while let Some(idle) = self.inner.pop_idle(&warehouse) {
+            if self.inner.options.test_on_acquire && idle.conn.ping().await.is_err() {
+                continue;
+            }
+            // closed if the session could not be reset, not to leak it
+            if idle.conn.reset_session().await.is_err() {
+                continue;
+            }
+            return Ok(PooledConnection {
+                conn: Some(idle.conn),
+                created: idle.created,
+                warehouse,
+                pool: self.inner.clone(),
+                _permit: permit,
+            });
+        }
+
+        self.inner.make_room();
+        let conn = match &warehouse {
+            Some(warehouse) => self.inner.client.get_conn_with_warehouse(warehouse).await?,
+            None => self.inner.client.get_conn().await?,
+        };
+        Ok(PooledConnection {
+            conn: Some(conn),
+            created: Instant::now(),
+            warehouse,
+            pool: self.inner.clone(),
+            _permit: permit,
+        })
+    }
+}
+
+impl PoolInner {
+    fn expired(&self, created: Instant) -> bool {
+        matches!(self.options.max_lifetime, Some(d) if created.elapsed() >= d)
+    }
+
+    /// The most recently returned one, expired ones are dropped on the way.
+    fn pop_idle(&self, warehouse: &Option<String>) -> Option<IdleConnection> {
+        let mut idle = self.idle.lock().unwrap();
+        let queue = idle.get_mut(warehouse)?;
+        queue.retain(|c| {
+            !self.expired(c.created)
+                && !matches!(self.options.idle_timeout, Some(d) if c.idle_since.elapsed() >= d)
+        });
+        queue.pop_back()
+    }
+
+    /// Close the oldest idle connection of other warehouses if the pool is
+    /// full, called with a permit held before creating a new connection.
+    fn make_room(&self) {
+        let mut idle = self.idle.lock().unwrap();
+        let total: usize = idle.values().map(|q| q.len()).sum();
+        if total <= self.semaphore.available_permits() {
+            return;
+        }
+        let oldest = idle
+            .iter()
+            .filter_map(|(k, q)| q.front().map(|c| (k.clone(), c.idle_since)))
+            .min_by_key(|(_, idle_since)| *idle_since);
+        if let Some((key, _)) = oldest {
+            idle.get_mut(&key).and_then(|q| q.pop_front());
+        }
+    }
+
+    fn release(&self, conn: Box<dyn Connection>, created: Instant, warehouse: Option<String>) {
+        if self.expired(created) {
+            return;
+        }
+        self.idle
+            .lock()
+            .unwrap()
+            .entry(warehouse)
+            .or_default()
+            .push_back(IdleConnection {
+                conn,
+                created,
+                idle_since: Instant::now(),
+            });
+    }
+}
+
+/// A connection checked out from a [`Pool`].
+pub struct PooledConnection {
+    conn: Option<Box<dyn Connection>>,
+    created: Instant,
+    warehouse: Option<String>,
+    pool: Arc<PoolInner>,
+    // released after the connection is back in the pool
+    _permit: OwnedSemaphorePermit,
+}
+
+impl PooledConnection {
+    /// Take the connection out of the pool, which makes room for a new one.
+    pub fn detach(mut self) -> Box<dyn Connection> {
+        self.conn.take().unwrap()
+    }
+}
+
+impl Deref for PooledConnection {
+    type Target = dyn Connection;
+
+    fn deref(&self) -> &Self::Target {
+        self.conn.as_deref().unwrap()
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.release(conn, self.created, self.warehouse.take());
+        }
+    }
+}
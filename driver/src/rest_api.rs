@@ -19,20 +19,38 @@ use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use async_trait::async_trait;
-use tokio_stream::{Stream, StreamExt};
+use tokio_stream::Stream;
 
+use databend_client::comment::QueryComment;
+use databend_client::counters::{EndpointCounters, ErrorCounters};
+use databend_client::credentials::CredentialsProvider;
+use databend_client::limit::RequestLimiter;
 use databend_client::presign::PresignedResponse;
-use databend_client::response::QueryResponse;
-use databend_client::APIClient;
+use databend_client::response::{QueryResponse, SchemaField};
+use databend_client::upload::UploadOptions;
+use databend_client::{APIClient, HttpClient};
+use databend_sql::builder;
 use databend_sql::error::{Error, Result};
-use databend_sql::rows::{QueryProgress, Row, RowIterator, RowProgressIterator, RowWithProgress};
+use databend_sql::rows::{
+    CancelFuture, QueryHandle, QueryProgress, QueryStats, ResumeState, ResumeToken, Row,
+    RowIterator, RowProgressIterator, RowWithProgress,
+};
 use databend_sql::schema::{Schema, SchemaRef};
+use databend_sql::ser::NonFinitePolicy;
+use databend_sql::value::NumericMode;
 
-use crate::conn::{Connection, ConnectionInfo, Reader};
+use crate::conn::{explain_failure, Connection, ConnectionInfo, QueryId, Reader};
+use crate::schema_cache::SchemaCache;
+use crate::stmt::PreparedStatement;
+use crate::temp::{TempObject, TempObjects};
+use crate::version::ServerFeature;
 
 #[derive(Clone)]
 pub struct RestAPIConnection {
     client: APIClient,
+    parse_options: ParseOptions,
+    temp_objects: TempObjects,
+    schema_cache: Arc<SchemaCache<Vec<SchemaField>>>,
 }
 
 #[async_trait]
@@ -42,7 +60,7 @@ impl Connection for RestAPIConnection {
             handler: "RestAPI".to_string(),
             host: self.client.host.clone(),
             port: self.client.port,
-            user: self.client.user.clone(),
+            user: self.client.current_user().await,
             database: self.client.current_database().await,
             warehouse: self.client.current_warehouse().await,
         }
@@ -53,23 +71,150 @@ impl Connection for RestAPIConnection {
         while let Some(next_uri) = resp.next_uri {
             resp = self.client.query_page(&next_uri).await?;
         }
+        if let Some(ref final_uri) = resp.final_uri {
+            // results are not needed, the query could be released at once
+            let _ = self.client.finalize_query(final_uri).await;
+        }
         Ok(resp.stats.progresses.write_progress.rows as i64)
     }
 
+    async fn exec_with_stats(&self, sql: &str) -> Result<QueryStats> {
+        use tokio_stream::StreamExt;
+
+        // through the rows, so the query is killed if the future is dropped
+        // before it is done
+        let (_, mut rows) = self.query_iter_ext(sql).await?;
+        let mut stats = None;
+        while let Some(item) = rows.next().await {
+            if let RowWithProgress::Stats(s) = item? {
+                stats = Some(s);
+            }
+        }
+        stats.ok_or_else(|| Error::InvalidResponse("no stats in response".to_string()))
+    }
+
     async fn query_iter(&self, sql: &str) -> Result<RowIterator> {
-        let (_, rows_with_progress) = self.query_iter_ext(sql).await?;
-        let rows = rows_with_progress.filter_map(|r| match r {
-            Ok(RowWithProgress::Row(r)) => Some(Ok(r)),
-            Ok(_) => None,
-            Err(err) => Some(Err(err)),
-        });
-        Ok(RowIterator::new(Box::pin(rows)))
+        let (schema, rows_with_progress) = self.query_iter_ext(sql).await?;
+        Ok(rows_with_progress.filter_rows(Arc::new(schema)).await)
     }
 
     async fn query_iter_ext(&self, sql: &str) -> Result<(Schema, RowProgressIterator)> {
+        let mut resp = self.client.query(sql).await?;
+        let page_uri = first_page_uri(&resp.id);
+        let schema = self.response_schema(Some(sql), std::mem::take(&mut resp.schema))?;
+        let route_hints = self.client.route_hints_of(&page_uri).await;
+        let (schema, rows) = RestAPIRows::from_response(
+            self.client.clone(),
+            resp,
+            schema,
+            page_uri,
+            route_hints,
+            self.parse_options,
+        );
+        let resume = rows.resume.clone();
+        let handle = rows.handle.clone();
+        Ok((
+            schema,
+            RowProgressIterator::new(Box::pin(rows))
+                .with_resume(Some(resume))
+                .with_handle(handle),
+        ))
+    }
+
+    async fn prepare(&self, sql: &str) -> Result<PreparedStatement> {
+        PreparedStatement::new(Box::new(self.clone()), sql)
+    }
+
+    async fn set_query_comment(&self, comment: QueryComment) -> Result<()> {
+        self.client.set_query_comment(comment).await;
+        Ok(())
+    }
+
+    fn upload_options(&self) -> UploadOptions {
+        self.client.upload_options().clone()
+    }
+
+    async fn reset_session(&self) -> Result<()> {
+        self.client.reset_session().await;
+        Ok(())
+    }
+
+    async fn new_session(&self) -> Result<Box<dyn Connection>> {
+        Ok(Box::new(Self {
+            client: self.client.new_session().await,
+            parse_options: self.parse_options,
+            temp_objects: TempObjects::default(),
+            schema_cache: self.schema_cache.clone(),
+        }))
+    }
+
+    async fn submit(&self, sql: &str) -> Result<QueryId> {
         let resp = self.client.query(sql).await?;
-        let (schema, rows) = RestAPIRows::from_response(self.client.clone(), resp)?;
-        Ok((schema, RowProgressIterator::new(Box::pin(rows))))
+        Ok(QueryId::new(resp.id))
+    }
+
+    async fn fetch(&self, id: &QueryId) -> Result<RowIterator> {
+        let token = ResumeToken::RestApi {
+            page_uri: first_page_uri(id.as_str()),
+            offset: 0,
+            route_hints: BTreeMap::new(),
+        };
+        self.resume(&token).await
+    }
+
+    async fn resume(&self, token: &ResumeToken) -> Result<RowIterator> {
+        let (page_uri, offset) = match token {
+            ResumeToken::RestApi {
+                page_uri,
+                offset,
+                route_hints,
+            } => {
+                // the gateway routes pages of the query to the worker running it
+                self.client
+                    .restore_route_hints(page_uri, route_hints)
+                    .await?;
+                (page_uri.clone(), *offset)
+            }
+            _ => {
+                return Err(Error::BadArgument(
+                    "Resume token is not from RestAPI".to_string(),
+                ))
+            }
+        };
+        // the server keeps the last page for retries, so the page is still
+        // there if nothing has been fetched after it
+        let mut page_uri = page_uri;
+        let mut offset = offset;
+        let mut resp = self.client.query_page(&page_uri).await?;
+        // schema is unknown before the query starts to run
+        while resp.schema.is_empty() {
+            match resp.next_uri.take() {
+                Some(next_uri) => {
+                    resp = self.client.query_page(&next_uri).await?;
+                    page_uri = next_uri;
+                    offset = 0;
+                }
+                None => break,
+            }
+        }
+        let schema = self.response_schema(None, std::mem::take(&mut resp.schema))?;
+        let route_hints = self.client.route_hints_of(&page_uri).await;
+        let (schema, mut rows) = RestAPIRows::from_response(
+            self.client.clone(),
+            resp,
+            schema,
+            page_uri,
+            route_hints,
+            self.parse_options,
+        );
+        rows.skip(offset);
+        let resume = rows.resume.clone();
+        let handle = rows.handle.clone();
+        Ok(RowProgressIterator::new(Box::pin(rows))
+            .with_resume(Some(resume))
+            .with_handle(handle)
+            .filter_rows(Arc::new(schema))
+            .await)
     }
 
     async fn query_row(&self, sql: &str) -> Result<Option<Row>> {
@@ -79,17 +224,17 @@ impl Connection for RestAPIConnection {
             Some(uri) => self.client.kill_query(&uri).await.map_err(|e| e.into()),
             None => Err(Error::InvalidResponse("kill_uri is empty".to_string())),
         }?;
-        let schema = resp.schema.try_into()?;
+        let schema = self.response_schema(Some(sql), resp.schema)?;
         if resp.data.is_empty() {
             Ok(None)
         } else {
-            let row = Row::try_from((Arc::new(schema), &resp.data[0]))?;
+            let row = self.parse_options.parse_row(schema, &resp.data[0])?;
             Ok(Some(row))
         }
     }
 
     async fn get_presigned_url(&self, operation: &str, stage: &str) -> Result<PresignedResponse> {
-        let sql = format!("PRESIGN {} {}", operation, stage);
+        let sql = builder::presign(operation, stage)?;
         let row = self.query_row(&sql).await?.ok_or(Error::InvalidResponse(
             "Empty response from server for presigned request".to_string(),
         ))?;
@@ -103,11 +248,37 @@ impl Connection for RestAPIConnection {
         })
     }
 
+    fn track_temp_object(&self, object: TempObject) {
+        self.temp_objects.track(object);
+    }
+
+    fn untrack_temp_object(&self, object: &TempObject) {
+        self.temp_objects.untrack(object);
+    }
+
+    fn temp_objects(&self) -> Vec<TempObject> {
+        self.temp_objects.list()
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.temp_objects.cleanup(self).await
+    }
+
     async fn upload_to_stage(&self, stage: &str, data: Reader, size: u64) -> Result<()> {
         self.client.upload_to_stage(stage, data, size).await?;
         Ok(())
     }
 
+    async fn upload_file_to_stage(
+        &self,
+        stage: &str,
+        file: tokio::fs::File,
+        size: u64,
+    ) -> Result<()> {
+        self.client.upload_file_to_stage(stage, file, size).await?;
+        Ok(())
+    }
+
     async fn stream_load(
         &self,
         sql: &str,
@@ -127,15 +298,90 @@ impl Connection for RestAPIConnection {
         let resp = self
             .client
             .insert_with_stage(sql, &stage, file_format_options, copy_options)
-            .await?;
-        Ok(QueryProgress::from(resp.stats.progresses))
+            .await;
+        match resp {
+            Ok(resp) => Ok(QueryProgress::from(resp.stats.progresses)),
+            Err(e) => {
+                // uploaded file is only purged by a successful load
+                self.track_temp_object(TempObject::StageFile(stage));
+                Err(explain_failure(self, ServerFeature::StageAttachment, e.into()).await)
+            }
+        }
     }
 }
 
 impl<'o> RestAPIConnection {
-    pub async fn try_create(dsn: &str) -> Result<Self> {
-        let client = APIClient::from_dsn(dsn).await?;
-        Ok(Self { client })
+    pub async fn try_create(
+        dsn: &str,
+        credentials_provider: Option<CredentialsProvider>,
+    ) -> Result<Self> {
+        let (dsn, parse_options) = Self::take_driver_options(dsn)?;
+        let mut client = APIClient::from_dsn(&dsn).await?;
+        if let Some(provider) = credentials_provider {
+            client = client.with_credentials_provider(provider);
+        }
+        Ok(Self {
+            client,
+            parse_options,
+            temp_objects: TempObjects::default(),
+            schema_cache: Arc::new(SchemaCache::new()),
+        })
+    }
+
+    pub(crate) fn with_error_counters(mut self, counters: Arc<ErrorCounters>) -> Self {
+        self.client = self.client.clone().with_error_counters(counters);
+        self
+    }
+
+    pub(crate) fn with_endpoint_counters(mut self, counters: Arc<EndpointCounters>) -> Self {
+        self.client = self.client.clone().with_endpoint_counters(counters);
+        self
+    }
+
+    pub(crate) fn with_http_client(mut self, cli: HttpClient) -> Self {
+        self.client = self.client.clone().with_http_client(cli);
+        self
+    }
+
+    pub(crate) fn with_request_limiter(mut self, limiter: Arc<RequestLimiter>) -> Self {
+        self.client = self.client.clone().with_request_limiter(limiter);
+        self
+    }
+
+    pub(crate) fn request_limiter(&self) -> Option<Arc<RequestLimiter>> {
+        self.client.request_limiter()
+    }
+
+    // options handled by the driver, which should not be sent to server as settings
+    fn take_driver_options(dsn: &str) -> Result<(String, ParseOptions)> {
+        let mut u = url::Url::parse(dsn)?;
+        let mut options = ParseOptions::default();
+        let mut pairs = Vec::new();
+        for (k, v) in u.query_pairs() {
+            match k.as_ref() {
+                "numeric_mode" => options.numeric_mode = v.parse()?,
+                "non_finite" => options.non_finite = v.parse()?,
+                _ => pairs.push((k.to_string(), v.to_string())),
+            }
+        }
+        if pairs.is_empty() {
+            u.set_query(None);
+        } else {
+            u.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+        Ok((u.to_string(), options))
+    }
+
+    /// Schema of the response, cached by the statement if it is known.
+    /// The schema is empty before the query starts to run, never cached.
+    fn response_schema(&self, sql: Option<&str>, fields: Vec<SchemaField>) -> Result<SchemaRef> {
+        match sql {
+            Some(sql) if !fields.is_empty() => {
+                self.schema_cache
+                    .get_or_convert(sql, fields, |fields| fields.try_into())
+            }
+            _ => Ok(Arc::new(fields.try_into()?)),
+        }
     }
 
     async fn wait_for_data(&self, pre: QueryResponse) -> Result<QueryResponse> {
@@ -171,27 +417,186 @@ impl<'o> RestAPIConnection {
     }
 }
 
-type PageFut = Pin<Box<dyn Future<Output = Result<QueryResponse>> + Send>>;
+impl Drop for RestAPIConnection {
+    fn drop(&mut self) {
+        self.temp_objects.cleanup_on_drop(self);
+    }
+}
+
+/// How values in text returned by the server are parsed.
+#[derive(Clone, Copy, Debug)]
+struct ParseOptions {
+    numeric_mode: NumericMode,
+    non_finite: NonFinitePolicy,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            numeric_mode: NumericMode::default(),
+            // NaN and infinite floats are kept as returned by the server
+            non_finite: NonFinitePolicy::String,
+        }
+    }
+}
+
+impl ParseOptions {
+    fn parse_row(&self, schema: SchemaRef, data: &Vec<String>) -> Result<Row> {
+        let row = Row::try_from((schema, data, self.numeric_mode))?;
+        self.non_finite.apply_row(row)
+    }
+}
+
+/// The first page is returned by the query request, but could also be
+/// fetched again with the uri.
+fn first_page_uri(query_id: &str) -> String {
+    format!("/v1/query/{}/page/0", query_id)
+}
+
+type PageFut =
+    Pin<Box<dyn Future<Output = Result<(QueryResponse, BTreeMap<String, String>)>> + Send>>;
+type FinalFut = Pin<Box<dyn Future<Output = ()> + Send>>;
 
+/// Rows streamed page by page, the next page is only requested when the
+/// rows of the current one are consumed, so at most one page is in memory.
 pub struct RestAPIRows {
     client: APIClient,
     schema: SchemaRef,
     data: VecDeque<Vec<String>>,
     next_uri: Option<String>,
     next_page: Option<PageFut>,
+    // acknowledged once the last page is consumed
+    final_uri: Option<String>,
+    finalizing: Option<FinalFut>,
+    parse_options: ParseOptions,
+    // uri of the page in data, and rows returned from it
+    page_uri: String,
+    offset: usize,
+    // carried by the resume token to reach the same worker
+    route_hints: BTreeMap<String, String>,
+    resume: ResumeState,
+    // to kill the query if dropped before all pages are fetched
+    handle: Option<QueryHandle>,
+    // stats of the first response, returned before its rows
+    progress: Option<QueryProgress>,
+    // stats of the last response, returned after all rows
+    stats: Option<QueryStats>,
 }
 
 impl RestAPIRows {
-    fn from_response(client: APIClient, resp: QueryResponse) -> Result<(Schema, Self)> {
-        let schema: Schema = resp.schema.try_into()?;
+    fn from_response(
+        client: APIClient,
+        resp: QueryResponse,
+        schema: SchemaRef,
+        page_uri: String,
+        route_hints: BTreeMap<String, String>,
+        parse_options: ParseOptions,
+    ) -> (Schema, Self) {
+        let stats = QueryStats::from(&resp.stats);
+        let progress = QueryProgress::from(resp.stats.progresses);
+        let handle = resp.kill_uri.map(|kill_uri| {
+            let client = client.clone();
+            let cancel: Arc<dyn Fn() -> CancelFuture + Send + Sync> = Arc::new(move || {
+                let client = client.clone();
+                let kill_uri = kill_uri.clone();
+                Box::pin(async move { client.kill_query(&kill_uri).await.map_err(|e| e.into()) })
+            });
+            QueryHandle::new(resp.id, cancel)
+        });
         let rows = Self {
             client,
+            parse_options,
             next_uri: resp.next_uri,
-            schema: Arc::new(schema.clone()),
+            schema: schema.clone(),
             data: resp.data.into(),
             next_page: None,
+            final_uri: resp.final_uri,
+            finalizing: None,
+            page_uri,
+            offset: 0,
+            route_hints,
+            resume: ResumeState::default(),
+            handle,
+            progress: Some(progress),
+            stats: Some(stats),
+        };
+        rows.update_resume();
+        (schema.as_ref().clone(), rows)
+    }
+
+    /// Skip rows of the current page returned before.
+    fn skip(&mut self, n: usize) {
+        let n = n.min(self.data.len());
+        self.data.drain(..n);
+        self.offset += n;
+        self.update_resume();
+    }
+
+    fn update_resume(&self) {
+        if self.data.is_empty() && self.next_uri.is_none() {
+            self.resume.set(None);
+        } else {
+            self.resume.set(Some(ResumeToken::RestApi {
+                page_uri: self.page_uri.clone(),
+                offset: self.offset,
+                route_hints: self.route_hints.clone(),
+            }));
+        }
+    }
+}
+
+impl RestAPIRows {
+    // all rows are consumed, failures of the ack are ignored since
+    // the server would release the query after timeout anyway
+    fn poll_finalize(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<RowWithProgress>>> {
+        if self.finalizing.is_none() {
+            match self.final_uri.take() {
+                Some(final_uri) => {
+                    let client = self.client.clone();
+                    self.finalizing = Some(Box::pin(async move {
+                        let _ = client.finalize_query(&final_uri).await;
+                    }));
+                }
+                None => return Poll::Ready(None),
+            }
+        }
+        match self.finalizing.as_mut().map(|f| f.as_mut().poll(cx)) {
+            Some(Poll::Pending) => Poll::Pending,
+            _ => {
+                self.finalizing = None;
+                Poll::Ready(None)
+            }
+        }
+    }
+}
+
+impl Drop for RestAPIRows {
+    fn drop(&mut self) {
+        // the query is done on the server once the last page is fetched,
+        // but still acknowledge it so that the buffers are released early
+        if self.next_uri.is_none() && self.next_page.is_none() {
+            let finalizing = self.finalizing.take().or_else(|| {
+                let client = self.client.clone();
+                let final_uri = self.final_uri.take()?;
+                Some(Box::pin(async move {
+                    let _ = client.finalize_query(&final_uri).await;
+                }))
+            });
+            if let (Some(finalizing), Ok(rt)) = (finalizing, tokio::runtime::Handle::try_current())
+            {
+                rt.spawn(finalizing);
+            }
+            return;
+        }
+        let handle = match self.handle.take() {
+            Some(handle) if !handle.is_kept_alive() => handle,
+            _ => return,
         };
-        Ok((schema, rows))
+        if let Ok(rt) = tokio::runtime::Handle::try_current() {
+            rt.spawn(async move {
+                let _ = handle.cancel().await;
+            });
+        }
     }
 }
 
@@ -199,16 +604,31 @@ impl Stream for RestAPIRows {
     type Item = Result<RowWithProgress>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(progress) = self.progress.take() {
+            return Poll::Ready(Some(Ok(RowWithProgress::Progress(progress))));
+        }
         if let Some(row) = self.data.pop_front() {
-            let row = Row::try_from((self.schema.clone(), &row))?;
+            self.offset += 1;
+            self.update_resume();
+            let row = self.parse_options.parse_row(self.schema.clone(), &row)?;
             return Poll::Ready(Some(Ok(RowWithProgress::Row(row))));
         }
         match self.next_page {
             Some(ref mut next_page) => match Pin::new(next_page).poll(cx) {
-                Poll::Ready(Ok(resp)) => {
+                Poll::Ready(Ok((resp, route_hints))) => {
                     self.data = resp.data.into();
+                    self.route_hints = route_hints;
+                    if let Some(page_uri) = self.next_uri.take() {
+                        self.page_uri = page_uri;
+                    }
+                    self.offset = 0;
                     self.next_uri = resp.next_uri;
+                    self.update_resume();
+                    if resp.final_uri.is_some() {
+                        self.final_uri = resp.final_uri;
+                    }
                     self.next_page = None;
+                    self.stats = Some(QueryStats::from(&resp.stats));
                     let progress = QueryProgress::from(resp.stats.progresses);
                     Poll::Ready(Some(Ok(RowWithProgress::Progress(progress))))
                 }
@@ -216,21 +636,24 @@ impl Stream for RestAPIRows {
                     self.next_page = None;
                     Poll::Ready(Some(Err(e)))
                 }
-                Poll::Pending => {
-                    cx.waker().wake_by_ref();
-                    Poll::Pending
-                }
+                // woken by the request once the page arrives
+                Poll::Pending => Poll::Pending,
             },
             None => match self.next_uri {
                 Some(ref next_uri) => {
                     let client = self.client.clone();
                     let next_uri = next_uri.clone();
                     self.next_page = Some(Box::pin(async move {
-                        client.query_page(&next_uri).await.map_err(|e| e.into())
+                        let resp = client.query_page(&next_uri).await?;
+                        let route_hints = client.route_hints_of(&next_uri).await;
+                        Ok((resp, route_hints))
                     }));
                     self.poll_next(cx)
                 }
-                None => Poll::Ready(None),
+                None => match self.stats.take() {
+                    Some(stats) => Poll::Ready(Some(Ok(RowWithProgress::Stats(stats)))),
+                    None => self.poll_finalize(cx),
+                },
             },
         }
     }
@@ -0,0 +1,204 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Run SQL jobs on cron schedules from the client side,
+//! for deployments without server side tasks.
+
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use cron::Schedule;
+use tokio::task::JoinHandle;
+
+use databend_sql::error::{Error, Result};
+
+use crate::conn::Client;
+
+#[derive(Clone, Debug)]
+pub enum JobEvent {
+    Started {
+        job: String,
+    },
+    Succeeded {
+        job: String,
+        rows: i64,
+        elapsed: Duration,
+    },
+    /// A failed attempt, `retrying` is false after the last attempt.
+    Failed {
+        job: String,
+        attempt: u32,
+        error: String,
+        retrying: bool,
+    },
+}
+
+pub type JobObserver = Arc<dyn Fn(&JobEvent) + Send + Sync>;
+
+#[derive(Clone)]
+struct Job {
+    name: String,
+    schedule: Schedule,
+    sql: String,
+}
+
+pub struct Scheduler {
+    client: Client,
+    jobs: Vec<Job>,
+    max_retries: u32,
+    retry_delay: Duration,
+    observers: Vec<JobObserver>,
+}
+
+impl Scheduler {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            jobs: Vec::new(),
+            max_retries: 0,
+            retry_delay: Duration::from_secs(1),
+            observers: Vec::new(),
+        }
+    }
+
+    /// Register a job with a cron expression in UTC, with seconds field, e.g.
+    /// `0 */5 * * * *` runs every 5 minutes.
+    pub fn add_job(mut self, name: &str, cron: &str, sql: &str) -> Result<Self> {
+        let schedule = Schedule::from_str(cron)
+            .map_err(|e| Error::BadArgument(format!("Invalid cron expression {}: {}", cron, e)))?;
+        self.jobs.push(Job {
+            name: name.to_string(),
+            schedule,
+            sql: sql.to_string(),
+        });
+        Ok(self)
+    }
+
+    /// Retry a failed run up to `max_retries` times, waiting `delay` in between.
+    pub fn with_retries(mut self, max_retries: u32, delay: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_delay = delay;
+        self
+    }
+
+    pub fn with_observer(mut self, observer: JobObserver) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /// Spawn all jobs onto the current tokio runtime, runs of the same job never
+    /// overlap, a run is skipped if the previous one is still running.
+    pub fn start(self) -> SchedulerHandle {
+        let client = Arc::new(self.client);
+        let observers: Arc<[JobObserver]> = self.observers.into();
+        let handles = self
+            .jobs
+            .into_iter()
+            .map(|job| {
+                let runner = JobRunner {
+                    client: client.clone(),
+                    job,
+                    max_retries: self.max_retries,
+                    retry_delay: self.retry_delay,
+                    observers: observers.clone(),
+                };
+                tokio::spawn(runner.run())
+            })
+            .collect();
+        SchedulerHandle { handles }
+    }
+}
+
+/// Jobs are stopped when the handle is dropped.
+pub struct SchedulerHandle {
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl SchedulerHandle {
+    /// Stop all jobs, same as dropping the handle.
+    pub fn shutdown(self) {}
+}
+
+impl Drop for SchedulerHandle {
+    fn drop(&mut self) {
+        for handle in &self.handles {
+            handle.abort();
+        }
+    }
+}
+
+struct JobRunner {
+    client: Arc<Client>,
+    job: Job,
+    max_retries: u32,
+    retry_delay: Duration,
+    observers: Arc<[JobObserver]>,
+}
+
+impl JobRunner {
+    async fn run(self) {
+        while let Some(next) = self.job.schedule.upcoming(Utc).next() {
+            let wait = (next - Utc::now()).to_std().unwrap_or_default();
+            tokio::time::sleep(wait).await;
+            self.run_once().await;
+        }
+    }
+
+    async fn run_once(&self) {
+        self.notify(JobEvent::Started {
+            job: self.job.name.clone(),
+        });
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let start = Instant::now();
+            match self.exec().await {
+                Ok(rows) => {
+                    self.notify(JobEvent::Succeeded {
+                        job: self.job.name.clone(),
+                        rows,
+                        elapsed: start.elapsed(),
+                    });
+                    return;
+                }
+                Err(e) => {
+                    let retrying = attempt <= self.max_retries;
+                    self.notify(JobEvent::Failed {
+                        job: self.job.name.clone(),
+                        attempt,
+                        error: e.to_string(),
+                        retrying,
+                    });
+                    if !retrying {
+                        return;
+                    }
+                    tokio::time::sleep(self.retry_delay).await;
+                }
+            }
+        }
+    }
+
+    async fn exec(&self) -> Result<i64> {
+        let conn = self.client.get_conn().await?;
+        conn.exec(&self.job.sql).await
+    }
+
+    fn notify(&self, event: JobEvent) {
+        for observer in self.observers.iter() {
+            observer(&event);
+        }
+    }
+}
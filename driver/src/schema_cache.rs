@@ -0,0 +1,87 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+use databend_sql::error::Result;
+use databend_sql::schema::{Schema, SchemaRef};
+
+/// Statements with schemas cached by each connection.
+const CAPACITY: usize = 64;
+
+/// Schemas of recent statements by the fingerprint of SQL, so running the
+/// same statement again skips parsing the types from the response.
+///
+/// The source a schema converted from, like fields of the first page or the
+/// arrow schema, is kept to be compared with that of later responses, since
+/// the schema changes once the tables are altered.
+pub(crate) struct SchemaCache<S> {
+    entries: Mutex<Entries<S>>,
+}
+
+struct Entries<S> {
+    schemas: HashMap<u64, (S, SchemaRef)>,
+    // fingerprints in order of insertion, the oldest evicted first
+    order: VecDeque<u64>,
+}
+
+impl<S: Clone + PartialEq> SchemaCache<S> {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: Mutex::new(Entries {
+                schemas: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+        }
+    }
+
+    pub(crate) fn get_or_convert<F>(&self, sql: &str, source: S, convert: F) -> Result<SchemaRef>
+    where
+        F: FnOnce(S) -> Result<Schema>,
+    {
+        let key = fingerprint(sql);
+        if let Some((cached, schema)) = self.entries.lock().unwrap().schemas.get(&key) {
+            if *cached == source {
+                return Ok(schema.clone());
+            }
+        }
+        let schema = SchemaRef::new(convert(source.clone())?);
+        let mut entries = self.entries.lock().unwrap();
+        if entries
+            .schemas
+            .insert(key, (source, schema.clone()))
+            .is_none()
+        {
+            entries.order.push_back(key);
+            if entries.order.len() > CAPACITY {
+                if let Some(oldest) = entries.order.pop_front() {
+                    entries.schemas.remove(&oldest);
+                }
+            }
+        }
+        Ok(schema)
+    }
+}
+
+/// Statements differ only in whitespaces share the fingerprint.
+fn fingerprint(sql: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for token in sql.split_whitespace() {
+        token.hash(&mut hasher);
+    }
+    hasher.finish()
+}
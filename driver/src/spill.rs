@@ -0,0 +1,453 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use memmap2::Mmap;
+use serde_json::{json, Value as JsonValue};
+use tokio_stream::StreamExt;
+
+use databend_sql::error::{ConvertError, Error, Result};
+use databend_sql::rows::{Row, RowWithProgress, Rows};
+use databend_sql::schema::{DataType, DecimalDataType, DecimalSize, SchemaRef};
+use databend_sql::value::{NumberValue, Value};
+
+use crate::conn::Connection;
+
+/// When to spill the result of `query_all` to disk.
+#[derive(Clone, Debug)]
+pub struct SpillOptions {
+    /// Spill once the rows buffered, or the total rows reported by the
+    /// server progress, exceed this.
+    pub threshold_rows: usize,
+    /// Directory of the spill files, the system temp dir by default.
+    pub dir: PathBuf,
+}
+
+impl Default for SpillOptions {
+    fn default() -> Self {
+        Self {
+            threshold_rows: 1_000_000,
+            dir: std::env::temp_dir(),
+        }
+    }
+}
+
+/// All rows of a query, kept in memory for small results
+/// or in a memory mapped file for big ones.
+pub enum ResultSet {
+    Memory(Rows),
+    Disk(SpilledRows),
+}
+
+impl ResultSet {
+    pub fn schema(&self) -> SchemaRef {
+        match self {
+            ResultSet::Memory(rows) => rows.schema(),
+            ResultSet::Disk(rows) => rows.schema(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            ResultSet::Memory(rows) => rows.len(),
+            ResultSet::Disk(rows) => rows.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_spilled(&self) -> bool {
+        matches!(self, ResultSet::Disk(_))
+    }
+
+    pub fn get(&self, idx: usize) -> Result<Row> {
+        match self {
+            ResultSet::Memory(rows) => rows
+                .rows()
+                .get(idx)
+                .cloned()
+                .ok_or_else(|| out_of_range(idx, rows.len())),
+            ResultSet::Disk(rows) => rows.get(idx),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Result<Row>> + '_ {
+        (0..self.len()).map(move |i| self.get(i))
+    }
+}
+
+/// Rows stored as JSON arrays line by line in a temp file, only the
+/// offsets of lines are kept in memory. The file is removed on drop.
+///
+/// Values are tagged with their own types instead of read by the schema,
+/// so that rows read back are the same as the ones written.
+pub struct SpilledRows {
+    schema: SchemaRef,
+    path: PathBuf,
+    mmap: Option<Mmap>,
+    offsets: Vec<usize>,
+}
+
+impl SpilledRows {
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len().saturating_sub(1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, idx: usize) -> Result<Row> {
+        if idx >= self.len() {
+            return Err(out_of_range(idx, self.len()));
+        }
+        let mmap = self
+            .mmap
+            .as_ref()
+            .ok_or_else(|| Error::IO("spill file is not mapped".to_string()))?;
+        // without the trailing newline
+        let line = &mmap[self.offsets[idx]..self.offsets[idx + 1] - 1];
+        let values: Vec<JsonValue> = serde_json::from_slice(line)?;
+        let values = values
+            .into_iter()
+            .map(decode_value)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Row::from_vec(values))
+    }
+}
+
+impl Drop for SpilledRows {
+    fn drop(&mut self) {
+        self.mmap.take();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+pub(crate) struct SpillWriter {
+    rows: SpilledRows,
+    writer: BufWriter<File>,
+}
+
+impl SpillWriter {
+    pub(crate) fn try_create(options: &SpillOptions, schema: SchemaRef) -> Result<Self> {
+        let now = chrono::Utc::now()
+            .timestamp_nanos_opt()
+            .ok_or_else(|| Error::IO("Failed to get current timestamp".to_string()))?;
+        let path = options
+            .dir
+            .join(format!("bendsql-spill-{}-{}", std::process::id(), now));
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok(Self {
+            rows: SpilledRows {
+                schema,
+                path,
+                mmap: None,
+                offsets: vec![0],
+            },
+            writer: BufWriter::new(file),
+        })
+    }
+
+    pub(crate) fn write(&mut self, row: &Row) -> Result<()> {
+        let values: Vec<JsonValue> = row.values().iter().map(encode_value).collect();
+        let line = serde_json::to_string(&values)?;
+        self.writer.write_all(line.as_bytes())?;
+        self.writer.write_all(b"\n")?;
+        let last = self.rows.offsets[self.rows.offsets.len() - 1];
+        self.rows.offsets.push(last + line.len() + 1);
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self) -> Result<SpilledRows> {
+        self.writer.flush()?;
+        let file = self
+            .writer
+            .into_inner()
+            .map_err(|e| Error::IO(e.to_string()))?;
+        if !self.rows.is_empty() {
+            // SAFETY: the file is private to this process and never written after mapped.
+            let mmap = unsafe { Mmap::map(&file)? };
+            self.rows.mmap = Some(mmap);
+        }
+        Ok(self.rows)
+    }
+}
+
+fn out_of_range(idx: usize, len: usize) -> Error {
+    Error::BadArgument(format!("Row index {} out of range {}", idx, len))
+}
+
+/// Null, booleans and strings as they are, others as `[tag, payload]`.
+/// Floats are kept in bits, since NaN and infinity are not valid JSON.
+fn encode_value(value: &Value) -> JsonValue {
+    let list = |values: &[Value]| JsonValue::Array(values.iter().map(encode_value).collect());
+    match value {
+        Value::Null => JsonValue::Null,
+        Value::Boolean(b) => json!(b),
+        Value::String(s) => json!(s),
+        Value::Number(n) => match n {
+            NumberValue::Int8(v) => json!(["i8", v]),
+            NumberValue::Int16(v) => json!(["i16", v]),
+            NumberValue::Int32(v) => json!(["i32", v]),
+            NumberValue::Int64(v) => json!(["i64", v]),
+            NumberValue::UInt8(v) => json!(["u8", v]),
+            NumberValue::UInt16(v) => json!(["u16", v]),
+            NumberValue::UInt32(v) => json!(["u32", v]),
+            NumberValue::UInt64(v) => json!(["u64", v]),
+            NumberValue::Float32(v) => json!(["f32", v.to_bits()]),
+            NumberValue::Float64(v) => json!(["f64", v.to_bits()]),
+            NumberValue::Decimal128(_, size) => {
+                json!(["d128", value.to_string(), size.precision, size.scale])
+            }
+            NumberValue::Decimal256(_, size) => {
+                json!(["d256", value.to_string(), size.precision, size.scale])
+            }
+        },
+        Value::Timestamp(v) => json!(["ts", v]),
+        Value::Date(v) => json!(["date", v]),
+        Value::Array(values) => json!(["array", list(values)]),
+        Value::Map(entries) => {
+            let entries: Vec<JsonValue> = entries
+                .iter()
+                .map(|(k, v)| json!([encode_value(k), encode_value(v)]))
+                .collect();
+            json!(["map", entries])
+        }
+        Value::Tuple(values) => json!(["tuple", list(values)]),
+        Value::Variant(s) => json!(["variant", s]),
+        Value::Bitmap(s) => json!(["bitmap", s]),
+        Value::Geometry(s) => json!(["geometry", s]),
+    }
+}
+
+fn decode_value(value: JsonValue) -> Result<Value> {
+    let invalid =
+        |value: &JsonValue| Error::from(ConvertError::new("spilled value", value.to_string()));
+    let tagged = match value {
+        JsonValue::Null => return Ok(Value::Null),
+        JsonValue::Bool(b) => return Ok(Value::Boolean(b)),
+        JsonValue::String(s) => return Ok(Value::String(s)),
+        JsonValue::Array(ref tagged) => tagged,
+        ref other => return Err(invalid(other)),
+    };
+    let (tag, payload) = match tagged.as_slice() {
+        [JsonValue::String(tag), payload, ..] => (tag.as_str(), payload),
+        _ => return Err(invalid(&value)),
+    };
+    let int = || payload.as_i64().ok_or_else(|| invalid(&value));
+    let uint = || payload.as_u64().ok_or_else(|| invalid(&value));
+    let text = || {
+        payload
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| invalid(&value))
+    };
+    let list = || match payload {
+        JsonValue::Array(values) => values.iter().cloned().map(decode_value).collect(),
+        _ => Err(invalid(&value)),
+    };
+    let decimal = |wide: bool| -> Result<Value> {
+        let size = match (
+            tagged.get(2).and_then(|p| p.as_u64()),
+            tagged.get(3).and_then(|s| s.as_u64()),
+        ) {
+            (Some(precision), Some(scale)) => DecimalSize {
+                precision: precision as u8,
+                scale: scale as u8,
+            },
+            _ => return Err(invalid(&value)),
+        };
+        let data_type = match wide {
+            false => DataType::Decimal(DecimalDataType::Decimal128(size)),
+            true => DataType::Decimal(DecimalDataType::Decimal256(size)),
+        };
+        Value::try_from((&data_type, text()?.as_str()))
+    };
+    let number = |n: NumberValue| Ok(Value::Number(n));
+    match tag {
+        "i8" => number(NumberValue::Int8(int()? as i8)),
+        "i16" => number(NumberValue::Int16(int()? as i16)),
+        "i32" => number(NumberValue::Int32(int()? as i32)),
+        "i64" => number(NumberValue::Int64(int()?)),
+        "u8" => number(NumberValue::UInt8(uint()? as u8)),
+        "u16" => number(NumberValue::UInt16(uint()? as u16)),
+        "u32" => number(NumberValue::UInt32(uint()? as u32)),
+        "u64" => number(NumberValue::UInt64(uint()?)),
+        "f32" => number(NumberValue::Float32(f32::from_bits(uint()? as u32))),
+        "f64" => number(NumberValue::Float64(f64::from_bits(uint()?))),
+        "d128" => decimal(false),
+        "d256" => decimal(true),
+        "ts" => Ok(Value::Timestamp(int()?)),
+        "date" => Ok(Value::Date(int()? as i32)),
+        "array" => Ok(Value::Array(list()?)),
+        "tuple" => Ok(Value::Tuple(list()?)),
+        "map" => match payload {
+            JsonValue::Array(entries) => entries
+                .iter()
+                .map(|entry| match entry.as_array().map(|kv| kv.as_slice()) {
+                    Some([k, v]) => Ok((decode_value(k.clone())?, decode_value(v.clone())?)),
+                    _ => Err(invalid(&value)),
+                })
+                .collect::<Result<_>>()
+                .map(Value::Map),
+            _ => Err(invalid(&value)),
+        },
+        "variant" => Ok(Value::Variant(text()?)),
+        "bitmap" => Ok(Value::Bitmap(text()?)),
+        "geometry" => Ok(Value::Geometry(text()?)),
+        _ => Err(invalid(&value)),
+    }
+}
+
+pub(crate) async fn query_all<C>(conn: &C, sql: &str, options: &SpillOptions) -> Result<ResultSet>
+where
+    C: Connection + ?Sized,
+{
+    let (schema, mut rows) = conn.query_iter_ext(sql).await?;
+    let schema = Arc::new(schema);
+    let mut buffer = Vec::new();
+    let mut writer: Option<SpillWriter> = None;
+    while let Some(item) = rows.next().await {
+        match item? {
+            RowWithProgress::Row(row) => match writer {
+                Some(ref mut w) => w.write(&row)?,
+                None => buffer.push(row),
+            },
+            // the server reports the total rows to scan before returning all of them
+            RowWithProgress::Progress(progress)
+                if writer.is_none() && progress.total_rows > options.threshold_rows =>
+            {
+                writer = Some(SpillWriter::try_create(options, schema.clone())?);
+            }
+            _ => {}
+        }
+        if writer.is_none() && buffer.len() > options.threshold_rows {
+            writer = Some(SpillWriter::try_create(options, schema.clone())?);
+        }
+        if let Some(ref mut w) = writer {
+            for row in buffer.drain(..) {
+                w.write(&row)?;
+            }
+        }
+    }
+    match writer {
+        Some(w) => Ok(ResultSet::Disk(w.finish()?)),
+        None => Ok(ResultSet::Memory(Rows::new(schema, buffer))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use databend_sql::schema::{Field, NumberDataType, Schema};
+
+    use super::*;
+
+    #[test]
+    fn spilled_rows_round_trip() -> Result<()> {
+        let field = |name: &str, data_type| Field {
+            name: name.to_string(),
+            data_type,
+        };
+        let schema = Arc::new(Schema::from_vec(vec![
+            field("v", DataType::Variant),
+            field(
+                "m",
+                DataType::Map(Box::new(DataType::Tuple(vec![
+                    DataType::String,
+                    DataType::Number(NumberDataType::Float64),
+                ]))),
+            ),
+            field("f", DataType::Number(NumberDataType::Float32)),
+            field(
+                "d",
+                DataType::Decimal(DecimalDataType::Decimal128(DecimalSize {
+                    precision: 10,
+                    scale: 2,
+                })),
+            ),
+            field("s", DataType::Nullable(Box::new(DataType::String))),
+        ]));
+        let rows = vec![
+            Row::from_vec(vec![
+                Value::Variant(r#"{"b":1,"a":"x"}"#.to_string()),
+                Value::Map(vec![
+                    (
+                        Value::String("nan".to_string()),
+                        Value::Number(NumberValue::Float64(f64::NAN)),
+                    ),
+                    (
+                        Value::String("inf".to_string()),
+                        Value::Number(NumberValue::Float64(f64::INFINITY)),
+                    ),
+                ]),
+                Value::Number(NumberValue::Float32(f32::NAN)),
+                Value::Number(NumberValue::Decimal128(
+                    -150,
+                    DecimalSize {
+                        precision: 10,
+                        scale: 2,
+                    },
+                )),
+                Value::Null,
+            ]),
+            Row::from_vec(vec![
+                Value::Variant("\"text\"".to_string()),
+                Value::Map(vec![]),
+                Value::Number(NumberValue::Float32(-0.5)),
+                Value::Number(NumberValue::Decimal128(
+                    1,
+                    DecimalSize {
+                        precision: 10,
+                        scale: 2,
+                    },
+                )),
+                Value::String("[\"not\", \"tagged\"]".to_string()),
+            ]),
+        ];
+        let options = SpillOptions {
+            threshold_rows: 0,
+            dir: std::env::temp_dir(),
+        };
+        let mut writer = SpillWriter::try_create(&options, schema)?;
+        for row in &rows {
+            writer.write(row)?;
+        }
+        let spilled = writer.finish()?;
+        assert_eq!(spilled.len(), rows.len());
+        for (idx, row) in rows.iter().enumerate() {
+            // NaN is not equal to itself, compare in the debug form
+            assert_eq!(
+                format!("{:?}", spilled.get(idx)?.values()),
+                format!("{:?}", row.values())
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,437 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Files in stages: upload, download, list, remove and export.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio_stream::StreamExt;
+
+#[cfg(feature = "presign")]
+use databend_client::presign::presign_download_from_stage_verified;
+use databend_client::presign::PresignedResponse;
+use databend_client::stage::StageLocation;
+use databend_sql::builder;
+use databend_sql::copy::{FileFormatOptions, UnloadOptions};
+use databend_sql::error::{Error, Result};
+use databend_sql::rows::{QueryProgress, Row, RowProgressIterator, RowWithProgress};
+use databend_sql::schema::{DataType, Field, NumberDataType, Schema};
+use databend_sql::value::{NumberValue, Value};
+
+use crate::conn::Connection;
+
+/// A file unloaded by `export_via_stage`, downloadable without credentials
+/// until the presigned url expires.
+pub struct ExportedFile {
+    /// Location of the file like `@stage/path/data_0.parquet`.
+    pub location: String,
+    pub size: u64,
+    pub presigned: PresignedResponse,
+}
+
+/// A file listed by `list_stage_files`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StageFile {
+    /// Path of the file in the stage, without the stage name.
+    pub name: String,
+    pub size: u64,
+    pub md5: Option<String>,
+    pub last_modified: String,
+}
+
+pub(crate) async fn put_files<C>(
+    conn: &C,
+    local_file: &str,
+    stage: &str,
+) -> Result<(Schema, RowProgressIterator)>
+where
+    C: Connection + ?Sized + 'static,
+{
+    let local_dsn = url::Url::parse(local_file)?;
+    validate_local_scheme(local_dsn.scheme())?;
+    let mut entries = Vec::new();
+    for entry in glob::glob(local_dsn.path())? {
+        entries.push(entry?);
+    }
+    put_local_files(conn, &entries, stage).await
+}
+
+pub(crate) async fn put_local_files<C>(
+    conn: &C,
+    entries: &[PathBuf],
+    stage: &str,
+) -> Result<(Schema, RowProgressIterator)>
+where
+    C: Connection + ?Sized + 'static,
+{
+    let stage_location = StageLocation::try_from(stage)?;
+    let mut files = Vec::new();
+    let mut names = HashMap::new();
+    for entry in entries {
+        let entry = entry.clone();
+        let filename = entry
+            .file_name()
+            .ok_or(Error::BadArgument(format!(
+                "Invalid local file path: {:?}",
+                entry
+            )))?
+            .to_str()
+            .ok_or(Error::BadArgument(format!(
+                "Invalid local file path: {:?}",
+                entry
+            )))?;
+        if let Some(other) = names.insert(filename.to_owned(), entry.clone()) {
+            return Err(Error::BadArgument(format!(
+                "{:?} and {:?} would both be uploaded as {}",
+                other, entry, filename
+            )));
+        }
+        let stage_file = stage_location.file_path(filename);
+        let size = tokio::fs::metadata(&entry).await?.len();
+        files.push((entry, stage_file, size));
+    }
+
+    let sizes = files.iter().map(|(_, _, size)| *size).collect::<Vec<_>>();
+    let semaphore = Arc::new(Semaphore::new(
+        conn.upload_options().concurrency_for(&sizes),
+    ));
+    let mut tasks = JoinSet::new();
+    for (i, (entry, stage_file, size)) in files.into_iter().enumerate() {
+        let conn = dyn_clone::clone_box(conn);
+        let semaphore = semaphore.clone();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let status = match tokio::fs::File::open(&entry).await {
+                Ok(data) => conn.upload_file_to_stage(&stage_file, data, size).await,
+                Err(e) => Err(e.into()),
+            };
+            (i, entry, size, status)
+        });
+    }
+    let mut uploaded = Vec::with_capacity(sizes.len());
+    while let Some(task) = tasks.join_next().await {
+        uploaded.push(task.map_err(|e| Error::IO(e.to_string()))?);
+    }
+    // reported in the order of the files
+    uploaded.sort_by_key(|(i, _, _, _)| *i);
+
+    let mut total_count: usize = 0;
+    let mut total_size: usize = 0;
+    let mut results = Vec::new();
+    for (_, entry, size, status) in uploaded {
+        let (fname, status) = match status {
+            Ok(_) => {
+                total_count += 1;
+                total_size += size as usize;
+                (entry.to_string_lossy().to_string(), "SUCCESS".to_owned())
+            }
+            Err(e) => (entry.to_string_lossy().to_string(), e.to_string()),
+        };
+        let progress = QueryProgress {
+            total_rows: 0,
+            total_bytes: 0,
+            read_rows: 0,
+            read_bytes: 0,
+            write_rows: total_count,
+            write_bytes: total_size,
+        };
+        results.push(Ok(RowWithProgress::Progress(progress)));
+        results.push(Ok(RowWithProgress::Row(Row::from_vec(vec![
+            Value::String(fname),
+            Value::String(status),
+            Value::Number(NumberValue::UInt64(size)),
+        ]))));
+    }
+    Ok((
+        put_get_schema(),
+        RowProgressIterator::new(Box::pin(tokio_stream::iter(results))),
+    ))
+}
+
+pub(crate) async fn get_files<C>(
+    conn: &C,
+    stage: &str,
+    local_file: &str,
+) -> Result<(Schema, RowProgressIterator)>
+where
+    C: Connection + ?Sized + 'static,
+{
+    let local_dsn = url::Url::parse(local_file)?;
+    validate_local_scheme(local_dsn.scheme())?;
+    let (mut location, pattern) = split_stage_pattern(stage)?;
+    if !location.path.ends_with('/') {
+        location.path.push('/');
+    }
+    let files = list_files(conn, &location, pattern.as_ref()).await?;
+
+    let sizes = files.iter().map(|f| f.size).collect::<Vec<_>>();
+    let semaphore = Arc::new(Semaphore::new(
+        conn.upload_options().concurrency_for(&sizes),
+    ));
+    let mut tasks = JoinSet::new();
+    for (i, file) in files.into_iter().enumerate() {
+        let conn = dyn_clone::clone_box(conn);
+        let semaphore = semaphore.clone();
+        let name = file
+            .name
+            .strip_prefix(&location.path)
+            .unwrap_or(&file.name)
+            .to_string();
+        let stage_file = format!("@{}/{}", location.name, file.name);
+        let local_file = Path::new(local_dsn.path()).join(&name);
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let status = match conn.get_presigned_url("DOWNLOAD", &stage_file).await {
+                Ok(presign) => presign_download_from_stage_verified(
+                    presign,
+                    &local_file,
+                    Some(file.size),
+                    file.md5.as_deref(),
+                )
+                .await
+                .map_err(Error::from),
+                Err(e) => Err(e),
+            };
+            (i, local_file, status)
+        });
+    }
+    let mut downloaded = Vec::with_capacity(sizes.len());
+    while let Some(task) = tasks.join_next().await {
+        downloaded.push(task.map_err(|e| Error::IO(e.to_string()))?);
+    }
+    // reported in the order of the listing
+    downloaded.sort_by_key(|(i, _, _)| *i);
+
+    let mut total_count: usize = 0;
+    let mut total_size: usize = 0;
+    let mut results = Vec::new();
+    for (_, local_file, status) in downloaded {
+        let (status, size) = match status {
+            Ok(size) => {
+                total_count += 1;
+                total_size += size as usize;
+                ("SUCCESS".to_owned(), size)
+            }
+            Err(e) => (e.to_string(), 0),
+        };
+        let progress = QueryProgress {
+            total_rows: 0,
+            total_bytes: 0,
+            read_rows: total_count,
+            read_bytes: total_size,
+            write_rows: 0,
+            write_bytes: 0,
+        };
+        results.push(Ok(RowWithProgress::Progress(progress)));
+        results.push(Ok(RowWithProgress::Row(Row::from_vec(vec![
+            Value::String(local_file.to_string_lossy().to_string()),
+            Value::String(status),
+            Value::Number(NumberValue::UInt64(size)),
+        ]))));
+    }
+    Ok((
+        put_get_schema(),
+        RowProgressIterator::new(Box::pin(tokio_stream::iter(results))),
+    ))
+}
+
+pub(crate) async fn list_stage_files<C>(conn: &C, location: &str) -> Result<Vec<StageFile>>
+where
+    C: Connection + ?Sized,
+{
+    let (location, pattern) = split_stage_pattern(location)?;
+    list_files(conn, &location, pattern.as_ref()).await
+}
+
+pub(crate) async fn remove_stage_files<C>(conn: &C, location: &str) -> Result<Vec<StageFile>>
+where
+    C: Connection + ?Sized,
+{
+    let (location, pattern) = split_stage_pattern(location)?;
+    let files = list_files(conn, &location, pattern.as_ref()).await?;
+    if pattern.is_none() && (location.path.is_empty() || location.path.ends_with('/')) {
+        conn.exec(&builder::remove_stage(&location)?).await?;
+        return Ok(files);
+    }
+    // the path of `REMOVE` is a prefix, matched files are removed one by one
+    for file in &files {
+        conn.exec(&builder::remove_stage_file(&location.name, &file.name)?)
+            .await?;
+    }
+    Ok(files)
+}
+
+pub(crate) async fn export_via_stage<C>(
+    conn: &C,
+    sql: &str,
+    format: &str,
+    stage_prefix: &str,
+) -> Result<Vec<ExportedFile>>
+where
+    C: Connection + ?Sized,
+{
+    let location = StageLocation::try_from(stage_prefix)?;
+    conn.exec(&builder::copy_into_location(&location, sql, format)?)
+        .await?;
+    presign_exported(conn, &location).await
+}
+
+pub(crate) async fn export_via_stage_with_options<C>(
+    conn: &C,
+    sql: &str,
+    file_format_options: &FileFormatOptions,
+    unload_options: &UnloadOptions,
+    stage_prefix: &str,
+) -> Result<Vec<ExportedFile>>
+where
+    C: Connection + ?Sized,
+{
+    let location = StageLocation::try_from(stage_prefix)?;
+    let copy = builder::copy_into_location_with_options(
+        &location,
+        sql,
+        file_format_options,
+        unload_options,
+    )?;
+    conn.exec(&copy).await?;
+    presign_exported(conn, &location).await
+}
+
+/// Presign files under the location for download.
+async fn presign_exported<C: Connection + ?Sized>(
+    conn: &C,
+    location: &StageLocation,
+) -> Result<Vec<ExportedFile>> {
+    let mut files = Vec::new();
+    let mut rows = conn.query_iter(&builder::list_stage(location)?).await?;
+    while let Some(row) = rows.next().await {
+        let (name, size, _, _, _): (String, u64, Option<String>, String, Option<String>) =
+            row?.try_into().map_err(Error::Parsing)?;
+        let location = format!("@{}/{}", location.name, name);
+        let presigned = conn.get_presigned_url("DOWNLOAD", &location).await?;
+        files.push(ExportedFile {
+            location,
+            size,
+            presigned,
+        });
+    }
+    Ok(files)
+}
+
+fn put_get_schema() -> Schema {
+    Schema::from_vec(vec![
+        Field {
+            name: "file".to_string(),
+            data_type: DataType::String,
+        },
+        Field {
+            name: "status".to_string(),
+            data_type: DataType::String,
+        },
+        Field {
+            name: "size".to_string(),
+            data_type: DataType::Number(NumberDataType::UInt64),
+        },
+    ])
+}
+
+#[cfg(not(feature = "presign"))]
+async fn presign_download_from_stage_verified(
+    _presigned: PresignedResponse,
+    _local_path: &Path,
+    _size: Option<u64>,
+    _md5: Option<&str>,
+) -> databend_client::error::Result<u64> {
+    Err(databend_client::error::Error::BadArgument(
+        "downloading from stage requires the presign feature".to_string(),
+    ))
+}
+
+/// Split the glob pattern in the last segment of the stage location,
+/// files are listed under the path before it.
+fn split_stage_pattern(location: &str) -> Result<(StageLocation, Option<glob::Pattern>)> {
+    let mut location = StageLocation::try_from(location)?;
+    let (dir, last) = match location.path.rsplit_once('/') {
+        Some((dir, last)) => (format!("{}/", dir), last.to_string()),
+        None => (String::new(), location.path.clone()),
+    };
+    if !last.contains(['*', '?', '[']) {
+        return Ok((location, None));
+    }
+    let pattern = glob::Pattern::new(&last)?;
+    location.path = dir;
+    Ok((location, Some(pattern)))
+}
+
+async fn list_files<C>(
+    conn: &C,
+    location: &StageLocation,
+    pattern: Option<&glob::Pattern>,
+) -> Result<Vec<StageFile>>
+where
+    C: Connection + ?Sized,
+{
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+    let mut rows = conn.query_iter(&builder::list_stage(location)?).await?;
+    let mut files = Vec::new();
+    while let Some(row) = rows.next().await {
+        let (name, size, md5, last_modified, _): (
+            String,
+            u64,
+            Option<String>,
+            String,
+            Option<String>,
+        ) = row?.try_into().map_err(Error::Parsing)?;
+        match pattern {
+            Some(pattern) => {
+                let relative = name.strip_prefix(&location.path).unwrap_or(&name);
+                if !pattern.matches_with(relative, options) {
+                    continue;
+                }
+            }
+            // a path not ending with `/` is the file itself or a directory,
+            // not a prefix of other names
+            None if !location.path.is_empty() && !location.path.ends_with('/') => {
+                let rest = name.strip_prefix(&location.path).unwrap_or_default();
+                if !(rest.is_empty() || rest.starts_with('/')) {
+                    continue;
+                }
+            }
+            None => {}
+        }
+        files.push(StageFile {
+            name,
+            size,
+            md5,
+            last_modified,
+        });
+    }
+    Ok(files)
+}
+
+fn validate_local_scheme(scheme: &str) -> Result<()> {
+    match scheme {
+        "file" | "fs" => Ok(()),
+        _ => Err(Error::BadArgument(
+            "Supported schemes: file:// or fs://".to_string(),
+        )),
+    }
+}
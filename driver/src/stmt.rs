@@ -0,0 +1,68 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use databend_sql::builder;
+use databend_sql::error::Result;
+use databend_sql::find_placeholders;
+use databend_sql::rows::{Row, RowIterator};
+use databend_sql::value::Value;
+
+use crate::conn::Connection;
+
+/// A statement with `?` placeholders, created by `Connection::prepare`.
+///
+/// Parameters are bound on the client side as escaped literals, so values
+/// never need to be formatted into SQL by hand.
+#[derive(Clone)]
+pub struct PreparedStatement {
+    conn: Box<dyn Connection>,
+    sql: String,
+    params: usize,
+}
+
+impl PreparedStatement {
+    pub(crate) fn new(conn: Box<dyn Connection>, sql: &str) -> Result<Self> {
+        let params = find_placeholders(sql)?.len();
+        Ok(Self {
+            conn,
+            sql: sql.to_string(),
+            params,
+        })
+    }
+
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// Number of `?` placeholders.
+    pub fn param_count(&self) -> usize {
+        self.params
+    }
+
+    pub async fn execute(&self, params: &[Value]) -> Result<i64> {
+        self.conn.exec(&builder::bind(&self.sql, params)?).await
+    }
+
+    pub async fn query_row(&self, params: &[Value]) -> Result<Option<Row>> {
+        self.conn
+            .query_row(&builder::bind(&self.sql, params)?)
+            .await
+    }
+
+    pub async fn query_iter(&self, params: &[Value]) -> Result<RowIterator> {
+        self.conn
+            .query_iter(&builder::bind(&self.sql, params)?)
+            .await
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use databend_client::stage::StageLocation;
+use databend_sql::builder;
+use databend_sql::error::{ErrorKind, Result};
+use databend_sql::schema::Schema;
+
+use crate::conn::Connection;
+
+/// Server side objects created by the driver, which only live
+/// as long as the connection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TempObject {
+    Table(String),
+    Stage(String),
+    /// Files left in stage, such as the ones uploaded for a failed load.
+    StageFile(String),
+}
+
+impl TempObject {
+    fn drop_sql(&self) -> Result<String> {
+        match self {
+            TempObject::Table(table) => Ok(builder::drop_table(table)),
+            TempObject::Stage(stage) => Ok(builder::drop_stage(stage)),
+            TempObject::StageFile(path) => {
+                let location = StageLocation::try_from(path.as_str())?;
+                builder::remove_stage(&location)
+            }
+        }
+    }
+}
+
+/// Shared by all clones of a connection, objects are dropped once
+/// on `close()` or when the last clone is dropped.
+#[derive(Clone, Default)]
+pub(crate) struct TempObjects(Arc<Mutex<Vec<TempObject>>>);
+
+impl TempObjects {
+    pub(crate) fn track(&self, object: TempObject) {
+        let mut objects = self.0.lock().unwrap();
+        if !objects.contains(&object) {
+            objects.push(object);
+        }
+    }
+
+    pub(crate) fn untrack(&self, object: &TempObject) {
+        self.0.lock().unwrap().retain(|o| o != object);
+    }
+
+    pub(crate) fn list(&self) -> Vec<TempObject> {
+        self.0.lock().unwrap().clone()
+    }
+
+    fn take(&self) -> Vec<TempObject> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+
+    fn is_last_ref(&self) -> bool {
+        Arc::strong_count(&self.0) == 1
+    }
+
+    /// Drop all tracked objects, the first error is returned after trying all of them.
+    pub(crate) async fn cleanup<C: Connection + ?Sized>(&self, conn: &C) -> Result<()> {
+        let mut result = Ok(());
+        for object in self.take() {
+            let ret = match object.drop_sql() {
+                Ok(sql) => conn.exec(&sql).await.map(|_| ()),
+                Err(e) => Err(e),
+            };
+            if result.is_ok() {
+                result = ret;
+            }
+        }
+        result
+    }
+
+    /// Called in `Drop` of connections, cleanup is spawned in background
+    /// since there is no async drop, and skipped without a tokio runtime.
+    pub(crate) fn cleanup_on_drop<C: Connection + Clone + 'static>(&self, conn: &C) {
+        if !self.is_last_ref() || self.0.lock().unwrap().is_empty() {
+            return;
+        }
+        let handle = match tokio::runtime::Handle::try_current() {
+            Ok(handle) => handle,
+            Err(_) => return,
+        };
+        let objects = Self(Arc::new(Mutex::new(self.take())));
+        let conn = conn.clone();
+        handle.spawn(async move {
+            let _ = objects.cleanup(&conn).await;
+        });
+    }
+}
+
+type DropFut = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
+/// A scratch table dropped when the handle is closed or dropped, or when
+/// the connection is closed, whichever comes first.
+pub struct TempTable {
+    name: String,
+    dropper: Option<Box<dyn FnOnce(String) -> DropFut + Send + Sync>>,
+}
+
+impl TempTable {
+    pub(crate) fn new<C: Connection + ?Sized + 'static>(name: String, conn: Box<C>) -> Self {
+        Self {
+            name,
+            dropper: Some(Box::new(move |name| {
+                Box::pin(async move {
+                    conn.exec(&builder::drop_table(&name)).await?;
+                    conn.untrack_temp_object(&TempObject::Table(name));
+                    Ok(())
+                })
+            })),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Drop the table now and wait for it.
+    pub async fn close(mut self) -> Result<()> {
+        match self.dropper.take() {
+            Some(dropper) => dropper(self.name.clone()).await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for TempTable {
+    fn drop(&mut self) {
+        let dropper = match self.dropper.take() {
+            Some(dropper) => dropper,
+            None => return,
+        };
+        let handle = match tokio::runtime::Handle::try_current() {
+            Ok(handle) => handle,
+            // left to the cleanup of the connection
+            Err(_) => return,
+        };
+        let fut = dropper(std::mem::take(&mut self.name));
+        handle.spawn(async move {
+            let _ = fut.await;
+        });
+    }
+}
+
+pub(crate) async fn create_temp_table<C>(conn: &C, schema: &Schema) -> Result<TempTable>
+where
+    C: Connection + ?Sized + 'static,
+{
+    let name = format!("_bendsql_tmp_{:016x}", rand::random::<u64>());
+    // TEMP tables are not supported by older servers, TRANSIENT ones
+    // are still dropped with the handle
+    match conn
+        .exec(&builder::create_temp_table_sql(&name, schema)?)
+        .await
+    {
+        Err(e) if e.kind() == ErrorKind::Syntax => {
+            conn.exec(&builder::create_transient_table_sql(&name, schema)?)
+                .await?;
+        }
+        result => {
+            result?;
+        }
+    }
+    conn.track_temp_object(TempObject::Table(name.clone()));
+    Ok(TempTable::new(name, dyn_clone::clone_box(conn)))
+}
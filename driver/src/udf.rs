@@ -0,0 +1,262 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed user defined functions, listed from `SHOW USER FUNCTIONS` with the
+//! signatures parsed from their definitions.
+
+use databend_sql::builder;
+use databend_sql::error::{Error, Result};
+use databend_sql::quote::{escape_literal, quote_ident};
+use databend_sql::rows::Row;
+
+use crate::conn::{explain_failure, Connection};
+use crate::utils::{query_named, ColumnIndex};
+use crate::version::ServerFeature;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UserFunctionKind {
+    /// `(x, y) -> x + y`, expanded in queries.
+    Lambda {
+        parameters: Vec<String>,
+        body: String,
+    },
+    /// Served by an external server speaking the UDF protocol.
+    External {
+        arg_types: Vec<String>,
+        return_type: String,
+        language: String,
+        handler: String,
+        address: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UserFunction {
+    pub name: String,
+    pub description: Option<String>,
+    pub kind: UserFunctionKind,
+}
+
+impl UserFunction {
+    pub fn lambda(name: &str, parameters: &[&str], body: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: None,
+            kind: UserFunctionKind::Lambda {
+                parameters: parameters.iter().map(|p| p.to_string()).collect(),
+                body: body.to_string(),
+            },
+        }
+    }
+
+    pub fn external(
+        name: &str,
+        arg_types: &[&str],
+        return_type: &str,
+        language: &str,
+        handler: &str,
+        address: &str,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            description: None,
+            kind: UserFunctionKind::External {
+                arg_types: arg_types.iter().map(|t| t.to_string()).collect(),
+                return_type: return_type.to_string(),
+                language: language.to_string(),
+                handler: handler.to_string(),
+                address: address.to_string(),
+            },
+        }
+    }
+
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    pub(crate) fn from_row(index: &ColumnIndex, row: &Row) -> Result<Self> {
+        let name: String = index.get(row, "name")?;
+        let definition: String = index.get(row, "definition")?;
+        let language: Option<String> = index.get_opt(row, "language")?;
+        let kind = parse_definition(&definition, language.as_deref()).ok_or_else(|| {
+            Error::InvalidResponse(format!(
+                "Unsupported definition of function {}: {}",
+                name, definition
+            ))
+        })?;
+        let description = index
+            .get_opt::<String>(row, "description")?
+            .filter(|d| !d.is_empty());
+        Ok(Self {
+            name,
+            description,
+            kind,
+        })
+    }
+
+    /// The `CREATE FUNCTION` statement, the body and types are used as is.
+    pub fn create_sql(&self) -> Result<String> {
+        let mut sql = format!("CREATE FUNCTION IF NOT EXISTS {}", quote_ident(&self.name));
+        match &self.kind {
+            UserFunctionKind::Lambda { parameters, body } => {
+                if let Some(p) = parameters.iter().find(|p| !is_ident(p)) {
+                    return Err(Error::BadArgument(format!(
+                        "Invalid parameter name of function {}: {}",
+                        self.name, p
+                    )));
+                }
+                sql.push_str(&format!(" AS ({}) -> {}", parameters.join(", "), body));
+            }
+            UserFunctionKind::External {
+                arg_types,
+                return_type,
+                language,
+                handler,
+                address,
+            } => {
+                if !is_ident(language) {
+                    return Err(Error::BadArgument(format!(
+                        "Invalid language of function {}: {}",
+                        self.name, language
+                    )));
+                }
+                sql.push_str(&format!(
+                    " ({}) RETURNS {} LANGUAGE {} HANDLER = {} ADDRESS = {}",
+                    arg_types.join(", "),
+                    return_type,
+                    language,
+                    escape_literal(handler),
+                    escape_literal(address)
+                ));
+            }
+        }
+        if let Some(ref description) = self.description {
+            sql.push_str(&format!(" DESC = {}", escape_literal(description)));
+        }
+        Ok(sql)
+    }
+}
+
+fn is_ident(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Lambda like `(x, y) -> (x + y)`, or external like
+/// `(Int32, Int32) RETURNS Int32 LANGUAGE python HANDLER = gcd ADDRESS = http://...`.
+fn parse_definition(definition: &str, language: Option<&str>) -> Option<UserFunctionKind> {
+    let (args, rest) = split_args(definition.trim())?;
+    let rest = rest.trim();
+    if let Some(body) = rest.strip_prefix("->") {
+        if !matches!(language, None | Some("SQL") | Some("sql")) {
+            return None;
+        }
+        return Some(UserFunctionKind::Lambda {
+            parameters: args,
+            body: body.trim().to_string(),
+        });
+    }
+    let return_type = keyword_value(rest, "RETURNS", "LANGUAGE")?;
+    let language = match language {
+        Some(language) => language.to_string(),
+        None => keyword_value(rest, "LANGUAGE", "HANDLER")?,
+    };
+    let handler = keyword_value(rest, "HANDLER", "ADDRESS")?;
+    let address = keyword_value(rest, "ADDRESS", "")?;
+    Some(UserFunctionKind::External {
+        arg_types: args,
+        return_type,
+        language,
+        handler: unquote(&handler),
+        address: unquote(&address),
+    })
+}
+
+/// Items of the leading parenthesized list, split by commas not nested
+/// in types like `Decimal(10, 2)`, and the text after it.
+fn split_args(s: &str) -> Option<(Vec<String>, &str)> {
+    let inner = s.strip_prefix('(')?;
+    let mut depth = 0;
+    let mut args = Vec::new();
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth > 0 => depth -= 1,
+            ')' => {
+                let last = inner[start..i].trim();
+                if !last.is_empty() {
+                    args.push(last.to_string());
+                }
+                return Some((args, &inner[i + 1..]));
+            }
+            ',' if depth == 0 => {
+                args.push(inner[start..i].trim().to_string());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Text between the keyword and the next one, `=` after the keyword is
+/// optional.
+fn keyword_value(s: &str, keyword: &str, next: &str) -> Option<String> {
+    let start = s.find(keyword)? + keyword.len();
+    let rest = &s[start..];
+    let end = if next.is_empty() {
+        rest.len()
+    } else {
+        rest.find(next).unwrap_or(rest.len())
+    };
+    let value = rest[..end].trim();
+    let value = value.strip_prefix('=').unwrap_or(value).trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn unquote(s: &str) -> String {
+    s.strip_prefix('\'')
+        .and_then(|s| s.strip_suffix('\''))
+        .unwrap_or(s)
+        .to_string()
+}
+
+pub(crate) async fn list_functions<C>(conn: &C) -> Result<Vec<UserFunction>>
+where
+    C: Connection + ?Sized,
+{
+    query_named(conn, "SHOW USER FUNCTIONS", UserFunction::from_row).await
+}
+
+pub(crate) async fn create_function<C>(conn: &C, function: &UserFunction) -> Result<()>
+where
+    C: Connection + ?Sized,
+{
+    if let Err(e) = conn.exec(&function.create_sql()?).await {
+        if let UserFunctionKind::External { .. } = function.kind {
+            return Err(explain_failure(conn, ServerFeature::ExternalFunction, e).await);
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+pub(crate) async fn drop_function<C>(conn: &C, name: &str) -> Result<()>
+where
+    C: Connection + ?Sized,
+{
+    conn.exec(&builder::drop_function(name)).await?;
+    Ok(())
+}
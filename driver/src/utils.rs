@@ -0,0 +1,78 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use tokio_stream::StreamExt;
+
+use databend_sql::error::{Error, Result};
+use databend_sql::rows::{Row, RowWithProgress};
+use databend_sql::schema::Schema;
+use databend_sql::value::Value;
+
+use crate::conn::Connection;
+
+/// Lookup values in a row by column name, since columns of system tables
+/// and table functions vary between server versions.
+pub(crate) struct ColumnIndex(HashMap<String, usize>);
+
+impl ColumnIndex {
+    pub(crate) fn new(schema: &Schema) -> Self {
+        let index = schema
+            .fields()
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (f.name.to_lowercase(), i))
+            .collect();
+        Self(index)
+    }
+
+    pub(crate) fn get<T>(&self, row: &Row, name: &str) -> Result<T>
+    where
+        T: TryFrom<Value, Error = Error>,
+    {
+        match self.0.get(name) {
+            Some(i) => T::try_from(row.values()[*i].clone()),
+            None => Err(Error::InvalidResponse(format!("missing column: {}", name))),
+        }
+    }
+
+    /// Same as `get`, but returns `None` if the column does not exist or is null.
+    pub(crate) fn get_opt<T>(&self, row: &Row, name: &str) -> Result<Option<T>>
+    where
+        T: TryFrom<Value, Error = Error>,
+    {
+        match self.0.get(name).map(|i| &row.values()[*i]) {
+            None | Some(Value::Null) => Ok(None),
+            Some(v) => T::try_from(v.clone()).map(Some),
+        }
+    }
+}
+
+/// Run the query and convert each row with the column index.
+pub(crate) async fn query_named<C, T, F>(conn: &C, sql: &str, f: F) -> Result<Vec<T>>
+where
+    C: Connection + ?Sized,
+    F: Fn(&ColumnIndex, &Row) -> Result<T>,
+{
+    let (schema, mut rows) = conn.query_iter_ext(sql).await?;
+    let index = ColumnIndex::new(&schema);
+    let mut results = Vec::new();
+    while let Some(row) = rows.next().await {
+        if let RowWithProgress::Row(row) = row? {
+            results.push(f(&index, &row)?);
+        }
+    }
+    Ok(results)
+}
@@ -0,0 +1,148 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use databend_sql::error::Error;
+
+/// Release number of a Databend server, parsed from the result of
+/// `SELECT version()` like `DatabendQuery v1.2.100-nightly-5c1d3a5(rust-1.75)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ServerVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl ServerVersion {
+    /// Oldest server release the driver is tested against, connections to
+    /// older servers are refused with `check_server_version=true`.
+    pub const MIN_SUPPORTED: ServerVersion = ServerVersion::new(1, 0, 0);
+
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Returns `None` if no `vX.Y.Z` is found, e.g. for local dev builds.
+    pub fn parse(version: &str) -> Option<Self> {
+        version.split_whitespace().find_map(|word| {
+            let word = word.strip_prefix('v').unwrap_or(word);
+            let mut parts = word.splitn(3, '.');
+            let major = parts.next()?.parse().ok()?;
+            let minor = parts.next()?.parse().ok()?;
+            let patch = parts.next()?;
+            let end = patch
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(patch.len());
+            let patch = patch[..end].parse().ok()?;
+            Some(Self::new(major, minor, patch))
+        })
+    }
+
+    /// Error for connecting to a server older than [`Self::MIN_SUPPORTED`].
+    pub(crate) fn check_supported(&self) -> Result<(), Error> {
+        if *self < Self::MIN_SUPPORTED {
+            return Err(Error::Protocol(format!(
+                "Databend server {} is not supported, requires >= {}; upgrade the server or unset check_server_version to connect anyway",
+                self,
+                Self::MIN_SUPPORTED
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ServerVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Driver features which need a newer server than [`ServerVersion::MIN_SUPPORTED`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ServerFeature {
+    /// Loading data uploaded to a stage with `stream_load` and `load_data`.
+    StageAttachment,
+    /// Optimizer hints like `/*+ SET_VAR(...) */`, used by `no_result_cache`.
+    SetVarHint,
+    /// User functions served by an external server with `ADDRESS`.
+    ExternalFunction,
+}
+
+impl ServerFeature {
+    /// First server release known to support the feature.
+    pub fn min_version(&self) -> ServerVersion {
+        match self {
+            ServerFeature::StageAttachment => ServerVersion::new(1, 0, 0),
+            ServerFeature::SetVarHint => ServerVersion::new(1, 1, 0),
+            ServerFeature::ExternalFunction => ServerVersion::new(1, 2, 116),
+        }
+    }
+
+    /// Error for using the feature on a server of the version, or `None`
+    /// if the server is new enough.
+    pub fn check(&self, version: &ServerVersion) -> Option<Error> {
+        if *version >= self.min_version() {
+            return None;
+        }
+        Some(Error::Protocol(format!(
+            "{} requires Databend server >= {}, connected to {}",
+            self,
+            self.min_version(),
+            version
+        )))
+    }
+}
+
+impl fmt::Display for ServerFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ServerFeature::StageAttachment => "stage attachment",
+            ServerFeature::SetVarHint => "SET_VAR hint",
+            ServerFeature::ExternalFunction => "external function",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn server_version_requirements() {
+        let version =
+            ServerVersion::parse("DatabendQuery v1.2.100-nightly-5c1d3a5(rust-1.75.0-nightly)")
+                .unwrap();
+        assert_eq!(version, ServerVersion::new(1, 2, 100));
+        assert_eq!(version.to_string(), "v1.2.100");
+        assert_eq!(
+            ServerVersion::parse("1.1.2"),
+            Some(ServerVersion::new(1, 1, 2))
+        );
+        assert_eq!(ServerVersion::parse("DatabendQuery dev"), None);
+
+        assert!(ServerFeature::StageAttachment.check(&version).is_none());
+        let err = ServerFeature::ExternalFunction.check(&version).unwrap();
+        assert!(err.to_string().contains(
+            "external function requires Databend server >= v1.2.116, connected to v1.2.100"
+        ));
+        assert!(ServerVersion::new(0, 9, 9).check_supported().is_err());
+    }
+}
@@ -0,0 +1,40 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use databend_driver::{Client, FlightBridge};
+
+use crate::common::DEFAULT_DSN;
+
+#[tokio::test]
+async fn bridge_select() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    if dsn.starts_with("databend+flight://") {
+        return;
+    }
+    let bridge = FlightBridge::new(dsn).unwrap();
+    let addr = "127.0.0.1:18900".parse().unwrap();
+    tokio::spawn(bridge.serve(addr));
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let client = Client::new(format!(
+        "databend+flight://root:@{}/default?sslmode=disable",
+        addr
+    ));
+    let conn = client.get_conn().await.unwrap();
+    let row = conn.query_row("select 1, 'a'").await.unwrap().unwrap();
+    let (n, s): (u8, String) = row.try_into().unwrap();
+    assert_eq!((n, s.as_str()), (1, "a"));
+}
@@ -0,0 +1,82 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use databend_driver::{Client, ConnectionExt};
+use tokio_stream::StreamExt;
+
+use crate::common::DEFAULT_DSN;
+
+#[tokio::test]
+async fn stream_changes() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let client = Client::new(dsn.to_string());
+    let conn = client.get_conn().await.unwrap();
+    let suffix = chrono::Utc::now().timestamp();
+    let table = format!("stream_changes_{}", suffix);
+    let stream = format!("stream_changes_s_{}", suffix);
+    conn.exec(&format!("CREATE TABLE `{}` (a INT)", table))
+        .await
+        .unwrap();
+    conn.exec(&format!("CREATE STREAM `{}` ON TABLE `{}`", stream, table))
+        .await
+        .unwrap();
+
+    let mut changes = conn
+        .stream_changes(&stream, Duration::from_millis(200))
+        .await
+        .unwrap();
+    for i in 0..3 {
+        conn.exec(&format!("INSERT INTO `{}` VALUES ({})", table, i))
+            .await
+            .unwrap();
+    }
+    let mut values = Vec::new();
+    while values.len() < 3 {
+        let row = changes.next().await.unwrap().unwrap();
+        values.push(i32::try_from(row.values()[0].clone()).unwrap());
+    }
+    values.sort();
+    assert_eq!(values, vec![0, 1, 2]);
+    drop(changes);
+
+    // changes of a batch not read completely are consumed again
+    conn.exec(&format!("INSERT INTO `{}` VALUES (3), (4)", table))
+        .await
+        .unwrap();
+    let mut changes = conn
+        .stream_changes(&stream, Duration::from_millis(200))
+        .await
+        .unwrap();
+    changes.next().await.unwrap().unwrap();
+    drop(changes);
+    let mut changes = conn
+        .stream_changes(&stream, Duration::from_millis(200))
+        .await
+        .unwrap();
+    let mut values = Vec::new();
+    while values.len() < 2 {
+        let row = changes.next().await.unwrap().unwrap();
+        values.push(i32::try_from(row.values()[0].clone()).unwrap());
+    }
+    values.sort();
+    assert_eq!(values, vec![3, 4]);
+    drop(changes);
+
+    conn.exec(&format!("DROP STREAM `{}`", stream))
+        .await
+        .unwrap();
+    conn.exec(&format!("DROP TABLE `{}`", table)).await.unwrap();
+}
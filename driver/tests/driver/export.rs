@@ -0,0 +1,35 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use databend_driver::{Client, ConnectionExt};
+
+use crate::common::DEFAULT_DSN;
+
+#[tokio::test]
+async fn export_via_stage() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let conn = Client::new(dsn.to_string()).get_conn().await.unwrap();
+    let prefix = format!("@~/export_{}/", chrono::Utc::now().timestamp_micros());
+    let files = conn
+        .export_via_stage("SELECT number FROM numbers(10000)", "csv", &prefix)
+        .await
+        .unwrap();
+    assert!(!files.is_empty());
+    for file in files {
+        assert!(file.location.starts_with(&prefix));
+        assert!(file.size > 0);
+        assert_eq!(file.presigned.method, "GET");
+        assert!(!file.presigned.url.is_empty());
+    }
+}
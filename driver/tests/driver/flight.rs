@@ -0,0 +1,44 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use databend_driver::FlightSQLConnection;
+use tokio_stream::StreamExt;
+
+use crate::common::DEFAULT_DSN;
+
+#[tokio::test]
+async fn do_get_raw() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    if !dsn.starts_with("databend+flight://") {
+        return;
+    }
+    let conn = FlightSQLConnection::try_create(dsn, None).await.unwrap();
+    let info = conn
+        .get_flight_info("select number from NUMBERS(100)")
+        .await
+        .unwrap();
+    assert!(!info.endpoint.is_empty());
+
+    // tickets could be fetched by other connections
+    let worker = FlightSQLConnection::try_create(dsn, None).await.unwrap();
+    let mut total = 0;
+    for endpoint in info.endpoint {
+        let ticket = endpoint.ticket.unwrap();
+        let mut batches = worker.do_get_raw(ticket).await.unwrap();
+        while let Some(batch) = batches.next().await {
+            total += batch.unwrap().num_rows();
+        }
+    }
+    assert_eq!(total, 100);
+}
@@ -0,0 +1,89 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use databend_driver::{Client, Connection, ConnectionExt, QueryOptions};
+
+use crate::common::DEFAULT_DSN;
+
+async fn prepare(name: &str) -> (Box<dyn Connection>, String) {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let client = Client::new(dsn.to_string());
+    let conn = client.get_conn().await.unwrap();
+    let table = format!("{}_{}", name, chrono::Utc::now().timestamp());
+    (conn, table)
+}
+
+#[tokio::test]
+async fn fuse_table_stats() {
+    let (conn, table) = prepare("fuse_table_stats").await;
+    let sql = format!("CREATE TABLE `{}` (a INT, b STRING) CLUSTER BY (a)", table);
+    conn.exec(&sql).await.unwrap();
+    for i in 0..2 {
+        let sql = format!(
+            "INSERT INTO `{}` VALUES ({}, 'x'), ({}, 'y')",
+            table,
+            i,
+            i + 1
+        );
+        conn.exec(&sql).await.unwrap();
+    }
+
+    let snapshots = conn.fuse_snapshot("default", &table).await.unwrap();
+    assert_eq!(snapshots.len(), 2);
+    assert_eq!(snapshots[0].row_count, 4);
+    assert_eq!(
+        snapshots[0].previous_snapshot_id.as_deref(),
+        Some(snapshots[1].snapshot_id.as_str())
+    );
+
+    let segments = conn.fuse_segment("default", &table, None).await.unwrap();
+    assert_eq!(segments.len(), 2);
+    let segments = conn
+        .fuse_segment("default", &table, Some(&snapshots[1].snapshot_id))
+        .await
+        .unwrap();
+    assert_eq!(segments.len(), 1);
+
+    let info = conn
+        .clustering_information("default", &table)
+        .await
+        .unwrap();
+    assert_eq!(info.total_block_count, 2);
+
+    conn.exec(&format!("DROP TABLE `{}`", table)).await.unwrap();
+}
+
+#[tokio::test]
+async fn time_travel() {
+    let (conn, table) = prepare("time_travel").await;
+    conn.exec(&format!("CREATE TABLE `{}` (a INT)", table))
+        .await
+        .unwrap();
+    conn.exec(&format!("INSERT INTO `{}` VALUES (1)", table))
+        .await
+        .unwrap();
+    conn.exec(&format!("INSERT INTO `{}` VALUES (2)", table))
+        .await
+        .unwrap();
+
+    let snapshots = conn.list_snapshots(&table).await.unwrap();
+    assert_eq!(snapshots.len(), 2);
+    let options = QueryOptions::new().at((&snapshots[1]).into());
+    let sql = format!("SELECT count(*) FROM `{}`", table);
+    let row = conn.query_row_with_options(&sql, &options).await.unwrap();
+    let (count,): (u64,) = row.unwrap().try_into().unwrap();
+    assert_eq!(count, 1);
+
+    conn.exec(&format!("DROP TABLE `{}`", table)).await.unwrap();
+}
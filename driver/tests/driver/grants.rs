@@ -0,0 +1,59 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use databend_driver::{Client, ConnectionExt, Grant, GrantObject, Grantee};
+
+use crate::common::DEFAULT_DSN;
+
+#[tokio::test]
+async fn show_grants() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let conn = Client::new(dsn.to_string()).get_conn().await.unwrap();
+    let role = format!("auditor_{}", chrono::Utc::now().timestamp());
+    conn.exec(&format!("CREATE ROLE '{}'", role)).await.unwrap();
+    conn.exec(&format!("GRANT SELECT ON default.* TO ROLE '{}'", role))
+        .await
+        .unwrap();
+
+    let grants = conn.show_grants(&Grantee::role(&role)).await.unwrap();
+    assert_eq!(grants.len(), 1);
+    assert_eq!(grants[0].privileges, vec!["SELECT"]);
+    assert_eq!(
+        grants[0].object,
+        GrantObject::Database("default".to_string())
+    );
+
+    let grants = conn.show_grants(&Grantee::user("root")).await.unwrap();
+    assert!(!grants.is_empty());
+    let roles = conn.roles().await.unwrap();
+    assert!(!roles.is_empty());
+    conn.exec(&format!("DROP ROLE '{}'", role)).await.unwrap();
+
+    let grant =
+        Grant::from_statement("GRANT SELECT,INSERT ON 'default'.'db1'.'t1' TO 'u1'@'%'").unwrap();
+    assert_eq!(grant.privileges, vec!["SELECT", "INSERT"]);
+    assert_eq!(
+        grant.object,
+        GrantObject::Table {
+            database: "db1".to_string(),
+            table: "t1".to_string()
+        }
+    );
+    let grant = Grant::from_statement("GRANT ALL ON *.* TO 'root'@'%'").unwrap();
+    assert_eq!(grant.object, GrantObject::Global);
+    let grant = Grant::from_statement("GRANT ROLE account_admin TO 'root'@'%'").unwrap();
+    assert_eq!(grant.object, GrantObject::Role("account_admin".to_string()));
+    assert_eq!(grant.object.to_string(), "ROLE account_admin");
+    assert!(Grant::from_statement("REVOKE ALL ON *.* FROM 'u1'").is_err());
+}
@@ -0,0 +1,63 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use databend_driver::{read_ipc, write_ipc, Client};
+use tokio_stream::StreamExt;
+
+use crate::common::DEFAULT_DSN;
+
+#[tokio::test]
+async fn ipc_roundtrip() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let client = Client::new(dsn.to_string());
+    let conn = client.get_conn().await.unwrap();
+    let rows = conn
+        .query_iter("select number, to_string(number) from NUMBERS(3)")
+        .await
+        .unwrap();
+    let mut buf = Vec::new();
+    let written = write_ipc(rows, &mut buf).await.unwrap();
+    assert_eq!(written, 3);
+
+    let rows = read_ipc(&mut buf.as_slice()).await.unwrap();
+    assert_eq!(rows.schema().fields().len(), 2);
+    let ret: Vec<(u64, String)> = rows.map(|r| r.unwrap().try_into().unwrap()).collect().await;
+    assert_eq!(
+        ret,
+        vec![
+            (0, "0".to_string()),
+            (1, "1".to_string()),
+            (2, "2".to_string())
+        ]
+    );
+}
+
+#[tokio::test]
+async fn query_arrow() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let client = Client::new(dsn.to_string());
+    let conn = client.get_conn().await.unwrap();
+    let mut batches = conn
+        .query_arrow("select number, to_string(number) from NUMBERS(20000)")
+        .await
+        .unwrap();
+    assert_eq!(batches.schema().fields().len(), 2);
+    let mut total = 0;
+    while let Some(batch) = batches.next().await {
+        let batch = batch.unwrap();
+        assert_eq!(batch.num_columns(), 2);
+        total += batch.num_rows();
+    }
+    assert_eq!(total, 20000);
+}
@@ -0,0 +1,100 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use databend_driver::{
+    describe_local_file, Client, ConnectionExt, DataType, Field, LocalFileFormat,
+    ParquetCompression, ParquetOptions, Schema,
+};
+
+use crate::common::DEFAULT_DSN;
+
+#[tokio::test]
+async fn describe_csv_and_ndjson() {
+    let dir = std::env::temp_dir();
+    let csv = dir.join(format!("describe_{}.csv", chrono::Utc::now().timestamp()));
+    std::fs::write(&csv, "id,name\n1,a\n2,b\n3,c\n").unwrap();
+    let info = describe_local_file(&csv).await.unwrap();
+    assert_eq!(info.format, LocalFileFormat::Csv);
+    assert_eq!(info.rows, 3);
+    let names: Vec<&str> = info
+        .schema
+        .fields()
+        .iter()
+        .map(|f| f.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["id", "name"]);
+
+    let table = Schema::from_vec(vec![
+        Field {
+            name: "id".to_string(),
+            data_type: DataType::Number(databend_driver::sql::schema::NumberDataType::Int64),
+        },
+        Field {
+            name: "name".to_string(),
+            data_type: DataType::String,
+        },
+    ]);
+    info.check_compatible(&table).unwrap();
+    let table = Schema::from_vec(vec![Field {
+        name: "id".to_string(),
+        data_type: DataType::Date,
+    }]);
+    assert!(info.check_compatible(&table).is_err());
+    std::fs::remove_file(&csv).unwrap();
+
+    let ndjson = dir.join(format!(
+        "describe_{}.ndjson",
+        chrono::Utc::now().timestamp()
+    ));
+    std::fs::write(&ndjson, "{\"a\": 1}\n{\"a\": 2}\n\n").unwrap();
+    let info = describe_local_file(&ndjson).await.unwrap();
+    assert_eq!(info.format, LocalFileFormat::NdJson);
+    assert_eq!(info.rows, 2);
+    std::fs::remove_file(&ndjson).unwrap();
+
+    assert!(describe_local_file("data.txt").await.is_err());
+}
+
+#[tokio::test]
+async fn export_parquet_with_options() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let client = Client::new(dsn.to_string());
+    let conn = client.get_conn().await.unwrap();
+    let path =
+        std::env::temp_dir().join(format!("export_{}.parquet", chrono::Utc::now().timestamp()));
+    let options = ParquetOptions::new()
+        .compression(ParquetCompression::Gzip(6))
+        .max_row_group_size(1000)
+        .dictionary(false)
+        .statistics(false);
+    let written = conn
+        .export_parquet(
+            "select number, to_string(number) from NUMBERS(2500)",
+            &path,
+            &options,
+        )
+        .await
+        .unwrap();
+    assert_eq!(written, 2500);
+    let info = describe_local_file(&path).await.unwrap();
+    assert_eq!(info.format, LocalFileFormat::Parquet);
+    assert_eq!(info.rows, 2500);
+    std::fs::remove_file(&path).unwrap();
+
+    let options = ParquetOptions::new().compression(ParquetCompression::Gzip(11));
+    assert!(conn
+        .export_parquet("select 1", &path, &options)
+        .await
+        .is_err());
+}
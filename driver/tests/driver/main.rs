@@ -14,8 +14,25 @@
 
 mod common;
 
+#[cfg(feature = "bridge")]
+mod bridge;
+mod changes;
 mod connection;
+mod export;
+#[cfg(feature = "flight-sql")]
+mod flight;
+mod fuse;
+mod grants;
+#[cfg(feature = "arrow-ipc")]
+mod ipc;
+#[cfg(feature = "local-file")]
+mod local_file;
+mod monitor;
+mod pool;
 mod select_iter;
 mod select_simple;
 mod session;
+#[cfg(feature = "spill")]
+mod spill;
 mod stream_load;
+mod udf;
@@ -0,0 +1,42 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use databend_driver::{Client, ConnectionExt};
+
+use crate::common::DEFAULT_DSN;
+
+#[tokio::test]
+async fn system_monitoring() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let client = Client::new(dsn.to_string());
+    let conn = client.get_conn().await.unwrap();
+    let since = chrono::Utc::now().naive_utc() - chrono::Duration::minutes(10);
+    conn.exec("SELECT 1").await.unwrap();
+
+    let metrics = conn.system_metrics().await.unwrap();
+    assert!(metrics.iter().all(|m| !m.metric.is_empty()));
+
+    let entries = conn.query_log_since(since, 100).await.unwrap();
+    assert!(entries.iter().all(|e| e.event_time > since));
+    assert!(entries.iter().all(|e| e.log_type != "Start"));
+    if let Some(last) = entries.last() {
+        let next = conn.query_log_after(last, 100).await.unwrap();
+        assert!(next.iter().all(|e| e.event_time >= last.event_time));
+        assert!(next.iter().all(|e| e.query_id != last.query_id));
+    }
+
+    let utilization = conn.warehouse_utilization(since).await.unwrap();
+    assert!(utilization.running_queries >= 1);
+    assert!(utilization.failed_queries <= utilization.finished_queries);
+}
@@ -0,0 +1,58 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use databend_driver::{Client, Pool, PoolOptions, PoolStatus};
+
+use crate::common::DEFAULT_DSN;
+
+fn new_pool(max_size: usize) -> Pool {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let options = PoolOptions {
+        max_size,
+        acquire_timeout: Duration::from_millis(200),
+        ..Default::default()
+    };
+    Pool::new(Client::new(dsn.to_string()), options)
+}
+
+#[tokio::test]
+async fn pool_reuse() {
+    let pool = new_pool(2);
+    {
+        let conn = pool.get().await.unwrap();
+        conn.exec("set timezone='Europe/London'").await.unwrap();
+    }
+    assert_eq!(pool.status(), PoolStatus { size: 1, idle: 1 });
+
+    let conn = pool.get().await.unwrap();
+    assert_eq!(pool.status(), PoolStatus { size: 1, idle: 0 });
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    if !dsn.starts_with("databend+flight://") {
+        // session reset on checkout
+        let row = conn.query_row("select timezone()").await.unwrap().unwrap();
+        let (val,): (String,) = row.try_into().unwrap();
+        assert_eq!(val, "UTC");
+    }
+}
+
+#[tokio::test]
+async fn pool_acquire_timeout() {
+    let pool = new_pool(1);
+    let conn = pool.get().await.unwrap();
+    assert!(pool.get().await.is_err());
+    drop(conn);
+    assert!(pool.get().await.is_ok());
+}
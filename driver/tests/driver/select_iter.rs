@@ -14,7 +14,10 @@
 
 use tokio_stream::StreamExt;
 
-use databend_driver::{Client, Connection};
+use databend_driver::{
+    Client, Connection, ConnectionExt, DataType, Field, NumberDataType, QueryOptions, Row,
+    RowWithProgress, Schema, Value,
+};
 
 use crate::common::DEFAULT_DSN;
 
@@ -195,6 +198,158 @@ async fn select_numbers() {
     assert_eq!(ret, vec![0, 1, 2, 3, 4]);
 }
 
+#[tokio::test]
+async fn select_numbers_stats() {
+    let (conn, _) = prepare("select_numbers_stats").await;
+    let (_, rows) = conn
+        .query_iter_ext("select * from NUMBERS(5)")
+        .await
+        .unwrap();
+    let items = rows.collect::<Vec<_>>().await;
+    match items.last() {
+        Some(Ok(RowWithProgress::Stats(stats))) => assert_eq!(stats.result_rows, 5),
+        _ => panic!("stats should be the last item"),
+    }
+    let stats = conn
+        .exec_with_stats("select * from NUMBERS(5)")
+        .await
+        .unwrap();
+    assert_eq!(stats.scan_rows, 5);
+}
+
+#[tokio::test]
+async fn select_numbers_paged() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let client = Client::new(format!("{}&max_rows_per_page=100", dsn));
+    let conn = client.get_conn().await.unwrap();
+    let mut rows = conn
+        .query_iter("select * from numbers(10000) order by number")
+        .await
+        .unwrap();
+    let mut expected = 0u64;
+    while let Some(row) = rows.next().await {
+        let (n,): (u64,) = row.unwrap().try_into().unwrap();
+        assert_eq!(n, expected);
+        expected += 1;
+    }
+    assert_eq!(expected, 10000);
+}
+
+#[tokio::test]
+async fn select_numbers_paged_cancelled() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let client = Client::new(format!("{}&max_rows_per_page=100", dsn));
+    let conn = client.get_conn().await.unwrap();
+    let mut rows = conn
+        .query_iter("select * from numbers(10000) order by number")
+        .await
+        .unwrap();
+    // pages in flight are kept when `next()` is dropped by select!
+    let mut expected = 0u64;
+    let mut cancelled = 0;
+    loop {
+        tokio::select! {
+            biased;
+            _ = tokio::time::sleep(std::time::Duration::from_micros(100)) => cancelled += 1,
+            row = rows.next() => match row {
+                Some(row) => {
+                    let (n,): (u64,) = row.unwrap().try_into().unwrap();
+                    assert_eq!(n, expected);
+                    expected += 1;
+                }
+                None => break,
+            },
+        }
+    }
+    assert_eq!(expected, 10000);
+    assert!(cancelled > 0);
+}
+
+#[tokio::test]
+async fn select_with_mapper() {
+    let (conn, _) = prepare("select_with_mapper").await;
+    let options = QueryOptions::new().mapper(|schema, row| {
+        let values = schema
+            .fields()
+            .iter()
+            .zip(row)
+            .map(|(field, value)| match field.name.as_str() {
+                "secret" => Value::String("***".to_string()),
+                _ => value,
+            })
+            .collect();
+        Ok(Row::from_vec(values))
+    });
+    let rows = conn
+        .query_iter_with_options("select number, 'x' as secret from NUMBERS(2)", &options)
+        .await
+        .unwrap();
+    let ret: Vec<(u64, String)> = rows.map(|r| r.unwrap().try_into().unwrap()).collect().await;
+    assert_eq!(ret, vec![(0, "***".to_string()), (1, "***".to_string())]);
+}
+
+#[tokio::test]
+async fn select_with_mapper_schema() {
+    let (conn, _) = prepare("select_with_mapper_schema").await;
+    let schema = Schema::from_vec(vec![Field::new(
+        "doubled",
+        DataType::Number(NumberDataType::UInt64),
+    )]);
+    let options = QueryOptions::new().mapper_with_schema(schema, |_, row| {
+        let (number,): (u64,) = row.try_into().map_err(databend_driver::Error::Parsing)?;
+        Ok(Row::from_vec(vec![Value::Number(
+            databend_driver::NumberValue::UInt64(number * 2),
+        )]))
+    });
+    let rows = conn
+        .query_iter_with_options("select number from NUMBERS(2)", &options)
+        .await
+        .unwrap();
+    let schema = rows.schema();
+    assert_eq!(schema.fields()[0].name, "doubled");
+    let ret: Vec<(u64,)> = rows.map(|r| r.unwrap().try_into().unwrap()).collect().await;
+    assert_eq!(ret, vec![(0,), (2,)]);
+}
+
+#[tokio::test]
+async fn select_without_result_cache() {
+    let (conn, _) = prepare("select_without_result_cache").await;
+    let options = QueryOptions::new().no_result_cache();
+    let row = conn
+        .query_row_with_options("select count(*) from NUMBERS(10)", &options)
+        .await
+        .unwrap()
+        .unwrap();
+    let (count,): (u64,) = row.try_into().unwrap();
+    assert_eq!(count, 10);
+    assert!(conn
+        .query_row_with_options("show tables", &options)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn select_project() {
+    let (conn, _) = prepare("select_project").await;
+    let rows = conn
+        .query_iter("select number, 'x' as secret, number + 1 as next from NUMBERS(2)")
+        .await
+        .unwrap();
+    let rows = rows.project(&["next", "number"]).unwrap();
+    let names: Vec<String> = rows
+        .schema()
+        .fields()
+        .iter()
+        .map(|f| f.name.clone())
+        .collect();
+    assert_eq!(names, vec!["next", "number"]);
+    let ret: Vec<(u64, u64)> = rows.map(|r| r.unwrap().try_into().unwrap()).collect().await;
+    assert_eq!(ret, vec![(1, 0), (2, 1)]);
+
+    let rows = conn.query_iter("select 1 as a").await.unwrap();
+    assert!(rows.project(&["b"]).is_err());
+}
+
 #[tokio::test]
 async fn select_sleep() {
     let (conn, _) = prepare("select_sleep").await;
@@ -222,3 +377,130 @@ async fn select_sleep() {
 //     }
 //     assert_eq!(result, vec!["11".to_string()]);
 // }
+
+#[tokio::test]
+async fn select_resume() {
+    let (conn, _) = prepare("select_resume").await;
+    let sql = "select number from NUMBERS(10) order by number";
+    let mut rows = conn.query_iter(sql).await.unwrap();
+    let mut ret = Vec::new();
+    for _ in 0..3 {
+        let (n,): (u64,) = rows.next().await.unwrap().unwrap().try_into().unwrap();
+        ret.push(n);
+    }
+    let token = rows.detach().unwrap().to_string();
+
+    // continued by another connection
+    let (conn, _) = prepare("select_resume").await;
+    let mut rows = conn.resume(&token.parse().unwrap()).await.unwrap();
+    while let Some(row) = rows.next().await {
+        let (n,): (u64,) = row.unwrap().try_into().unwrap();
+        ret.push(n);
+    }
+    assert!(rows.resume_token().is_none());
+    assert_eq!(ret, (0..10).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn select_iter_struct_from_str() {
+    let (conn, _) = prepare("select_iter_struct_from_str").await;
+
+    use databend_driver::TryFromRow;
+    #[derive(Debug, PartialEq)]
+    enum Color {
+        Red,
+        Green,
+    }
+    impl std::str::FromStr for Color {
+        type Err = String;
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "red" => Ok(Color::Red),
+                "green" => Ok(Color::Green),
+                _ => Err(format!("unknown color {}", s)),
+            }
+        }
+    }
+    #[derive(TryFromRow)]
+    struct RowResult {
+        id: u8,
+        #[databend(from_str)]
+        color: Color,
+    }
+
+    let rows = conn
+        .query_iter("select * from (values (1, 'red'), (2, 'green')) order by 1")
+        .await
+        .unwrap();
+    let results = rows.try_collect::<RowResult>().await.unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!((results[0].id, &results[0].color), (1, &Color::Red));
+    assert_eq!((results[1].id, &results[1].color), (2, &Color::Green));
+
+    let rows = conn
+        .query_iter("select * from (values (1, 'red'), (2, 'purple')) order by 1")
+        .await
+        .unwrap();
+    let err = match rows.try_collect::<RowResult>().await {
+        Ok(_) => panic!("expected parsing error"),
+        Err(e) => e.to_string(),
+    };
+    assert!(err.contains("row 1"), "{}", err);
+    assert!(err.contains("color"), "{}", err);
+    assert!(err.contains("unknown color purple"), "{}", err);
+}
+
+#[tokio::test]
+async fn select_iter_struct_named() {
+    let (conn, _) = prepare("select_iter_struct_named").await;
+
+    use databend_driver::TryFromRow;
+    #[derive(TryFromRow)]
+    struct RowResult {
+        name: String,
+        #[databend(rename = "number")]
+        id: u64,
+    }
+
+    let rows = conn
+        .query_iter("select number, 'x' as extra, to_string(number) as name from NUMBERS(2)")
+        .await
+        .unwrap();
+    let results = rows.try_collect_named::<RowResult>().await.unwrap();
+    let results: Vec<_> = results.iter().map(|r| (r.id, r.name.as_str())).collect();
+    assert_eq!(results, vec![(0, "0"), (1, "1")]);
+
+    let rows = conn.query_iter("select 1 as id").await.unwrap();
+    assert!(rows.try_collect_named::<RowResult>().await.is_err());
+}
+
+#[tokio::test]
+async fn select_iter_quarantine() {
+    let (conn, _) = prepare("select_iter_quarantine").await;
+
+    use databend_driver::TryFromRow;
+    #[derive(TryFromRow)]
+    struct RowResult {
+        #[databend(from_str)]
+        n: u32,
+    }
+
+    let rows = conn
+        .query_iter("select * from (values ('1'), ('x'), ('3')) order by 1")
+        .await
+        .unwrap();
+    let mut sink = Vec::new();
+    let results = rows
+        .try_collect_quarantine::<RowResult, _>(&mut sink)
+        .await
+        .unwrap();
+    assert_eq!(results.iter().map(|r| r.n).collect::<Vec<_>>(), vec![1, 3]);
+    let sink = String::from_utf8(sink).unwrap();
+    let lines: Vec<serde_json::Value> = sink
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["row"], 2);
+    assert_eq!(lines[0]["values"], serde_json::json!(["x"]));
+}
@@ -15,7 +15,9 @@
 use std::assert_eq;
 
 use chrono::{DateTime, NaiveDate, NaiveDateTime};
-use databend_driver::{Client, Connection, DecimalSize, NumberValue, Value};
+use databend_driver::{
+    Client, Connection, DataType, DecimalSize, NumberDataType, NumberValue, Value,
+};
 
 use crate::common::DEFAULT_DSN;
 
@@ -151,6 +153,25 @@ async fn select_decimal() {
     );
 }
 
+#[tokio::test]
+async fn select_decimal_fast() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    if dsn.starts_with("databend+flight://") {
+        return;
+    }
+    let client = Client::new(format!("{}&numeric_mode=fast", dsn));
+    let conn = client.get_conn().await.unwrap();
+    let row = conn
+        .query_row("select 1.25::Decimal(15,2)")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        row.values().to_owned(),
+        vec![Value::Number(NumberValue::Float64(1.25))]
+    );
+}
+
 #[tokio::test]
 async fn select_nullable() {
     let conn = prepare().await;
@@ -222,3 +243,56 @@ async fn select_sleep() {
     let (val,): (u8,) = row.try_into().unwrap();
     assert_eq!(val, 0);
 }
+
+#[tokio::test]
+async fn select_prepared() {
+    let conn = prepare().await;
+    let stmt = conn.prepare("select ?, ? -- ?").await.unwrap();
+    assert_eq!(stmt.param_count(), 2);
+    let row = stmt
+        .query_row(&[Value::from("it's ?"), Value::from(1u8)])
+        .await
+        .unwrap()
+        .unwrap();
+    let (s, n): (String, u8) = row.try_into().unwrap();
+    assert_eq!(s, "it's ?");
+    assert_eq!(n, 1);
+    assert!(stmt.query_row(&[Value::Null]).await.is_err());
+}
+
+#[tokio::test]
+async fn select_schema() {
+    let conn = prepare().await;
+    let (schema, _) = conn
+        .query_iter_ext("select 1::UInt8 as a, null::Nullable(String) as b, [1, 2] as c")
+        .await
+        .unwrap();
+    let fields = schema.fields();
+    assert_eq!(fields.len(), 3);
+    assert_eq!(fields[0].name, "a");
+    assert_eq!(fields[0].data_type, DataType::Number(NumberDataType::UInt8));
+    assert!(!fields[0].is_nullable());
+    assert!(fields[1].is_nullable());
+    assert_eq!(fields[1].data_type.remove_nullable(), &DataType::String);
+    assert_eq!(schema.index_of("c"), Some(2));
+}
+
+#[tokio::test]
+async fn select_schema_repeated() {
+    let conn = prepare().await;
+    conn.exec("CREATE OR REPLACE TABLE schema_repeated (a Int32)")
+        .await
+        .unwrap();
+    let sql = "SELECT * FROM schema_repeated";
+    for _ in 0..2 {
+        let (schema, _) = conn.query_iter_ext(sql).await.unwrap();
+        assert_eq!(schema.fields().len(), 1);
+    }
+    // cached schema is not reused once the table is altered
+    conn.exec("ALTER TABLE schema_repeated ADD COLUMN b String")
+        .await
+        .unwrap();
+    let (schema, _) = conn.query_iter_ext(sql).await.unwrap();
+    assert_eq!(schema.index_of("b"), Some(1));
+    conn.exec("DROP TABLE schema_repeated").await.unwrap();
+}
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use databend_driver::Client;
+use databend_driver::{Client, ConnectionExt, QueryComment};
 
 use crate::common::DEFAULT_DSN;
 
@@ -52,3 +52,37 @@ async fn set_timezone_with_dsn() {
     let (val,): (String,) = row.try_into().unwrap();
     assert_eq!(val, "Europe/London");
 }
+
+#[tokio::test]
+async fn set_setting() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let client = Client::new(dsn.to_string());
+    let conn = client.get_conn().await.unwrap();
+
+    conn.set_setting("max_threads", "3").await.unwrap();
+    let settings = conn.settings().await.unwrap();
+    let setting = settings.iter().find(|s| s.name == "max_threads").unwrap();
+    assert_eq!(setting.value, "3");
+    assert_eq!(setting.level, "SESSION");
+
+    conn.set_setting("timezone", "Asia/Shanghai").await.unwrap();
+    let row = conn.query_row("select timezone()").await.unwrap().unwrap();
+    let (val,): (String,) = row.try_into().unwrap();
+    assert_eq!(val, "Asia/Shanghai");
+
+    assert!(conn.set_setting("no such setting", "1").await.is_err());
+}
+
+#[tokio::test]
+async fn query_comment() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let client = Client::new(dsn.to_string());
+    let conn = client.get_conn().await.unwrap();
+
+    let comment = QueryComment::new().app("driver-test").trace("trace-1");
+    conn.set_query_comment(comment).await.unwrap();
+    let row = conn.query_row("select 1").await.unwrap().unwrap();
+    let (val,): (u8,) = row.try_into().unwrap();
+    assert_eq!(val, 1);
+    assert_eq!(conn.exec("set max_threads = 4").await.unwrap(), 0);
+}
@@ -0,0 +1,45 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use databend_driver::{Client, ConnectionExt, SpillOptions};
+
+use crate::common::DEFAULT_DSN;
+
+#[tokio::test]
+async fn query_all_spilled() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let client = Client::new(dsn.to_string());
+    let conn = client.get_conn().await.unwrap();
+    let sql = "select number, to_string(number) from NUMBERS(100)";
+
+    let options = SpillOptions::default();
+    let rows = conn.query_all(sql, &options).await.unwrap();
+    assert!(!rows.is_spilled());
+    assert_eq!(rows.len(), 100);
+
+    let options = SpillOptions {
+        threshold_rows: 10,
+        ..Default::default()
+    };
+    let rows = conn.query_all(sql, &options).await.unwrap();
+    assert!(rows.is_spilled());
+    assert_eq!(rows.len(), 100);
+    assert_eq!(rows.schema().fields().len(), 2);
+    for (i, row) in rows.iter().enumerate() {
+        let (n, s): (u64, String) = row.unwrap().try_into().unwrap();
+        assert_eq!(n, i as u64);
+        assert_eq!(s, i.to_string());
+    }
+    assert!(rows.get(100).is_err());
+}
@@ -15,7 +15,7 @@
 use std::vec;
 
 use chrono::{NaiveDateTime, Utc};
-use databend_driver::Client;
+use databend_driver::{Client, ConnectionExt, CopyOptions, CsvOptions, FileFormatOptions};
 use tokio::fs::File;
 use tokio_stream::StreamExt;
 
@@ -113,3 +113,195 @@ async fn stream_load_with_presigned() {
 async fn stream_load_without_presigned() {
     stream_load(false, "csv").await;
 }
+
+#[tokio::test]
+async fn stream_load_with_options() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let conn = Client::new(dsn.to_string()).get_conn().await.unwrap();
+    let info = conn.info().await;
+    if info.handler == "FlightSQL" {
+        // NOTE: FlightSQL does not support stream load
+        return;
+    }
+
+    let table = format!(
+        "books_stream_load_options_{}",
+        Utc::now().format("%Y%m%d%H%M%S%9f")
+    );
+    conn.exec(&format!(
+        "CREATE TABLE `{}` (title VARCHAR, author VARCHAR, date VARCHAR)",
+        table
+    ))
+    .await
+    .unwrap();
+    let sql = format!("INSERT INTO `{}` VALUES", table);
+
+    // rejected before uploading
+    let data = Box::new(std::io::Cursor::new(Vec::new()));
+    let format = FileFormatOptions::parquet().skip_header(1);
+    assert!(conn
+        .stream_load_with_options(&sql, data, 0, &format, &CopyOptions::new())
+        .await
+        .is_err());
+
+    let csv = "title|author|date\nThree Body|Liu Cixin|2008\nDune|Frank Herbert|1965\n";
+    let data = Box::new(std::io::Cursor::new(csv.as_bytes().to_vec()));
+    let format = FileFormatOptions::csv()
+        .field_delimiter("|")
+        .record_delimiter("\n")
+        .skip_header(1);
+    let progress = conn
+        .stream_load_with_options(&sql, data, csv.len() as u64, &format, &CopyOptions::new())
+        .await
+        .unwrap();
+    assert_eq!(progress.write_rows, 2);
+
+    conn.exec(&format!("DROP TABLE `{}`", table)).await.unwrap();
+}
+
+#[tokio::test]
+async fn load_data() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let conn = Client::new(dsn.to_string()).get_conn().await.unwrap();
+    let info = conn.info().await;
+    if info.handler == "FlightSQL" {
+        // NOTE: FlightSQL does not support stream load
+        return;
+    }
+
+    let file = File::open("tests/driver/data/books.csv").await.unwrap();
+    let size = file.metadata().await.unwrap().len();
+    let table = format!("books_load_data_{}", Utc::now().format("%Y%m%d%H%M%S%9f"));
+    let sql = format!(
+        "CREATE TABLE `{}` (
+            title VARCHAR NULL,
+            author VARCHAR NULL,
+            date VARCHAR NULL,
+            publish_time TIMESTAMP NULL)",
+        table
+    );
+    conn.exec(&sql).await.unwrap();
+
+    let sql = format!("INSERT INTO `{}` VALUES", table);
+    let stats = conn
+        .load_data(&sql, Box::new(file), size, None)
+        .await
+        .unwrap();
+    assert_eq!(stats.rows_loaded, 3);
+    assert_eq!(stats.bytes_uploaded, size);
+    assert_eq!(stats.error_rows, 0);
+
+    let sql = format!("DROP TABLE `{}`;", table);
+    conn.exec(&sql).await.unwrap();
+}
+
+#[tokio::test]
+async fn insert_csv() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let conn = Client::new(dsn.to_string()).get_conn().await.unwrap();
+    let info = conn.info().await;
+    if info.handler == "FlightSQL" {
+        // NOTE: FlightSQL does not support stream load
+        return;
+    }
+
+    let table = format!("books_insert_csv_{}", Utc::now().format("%Y%m%d%H%M%S%9f"));
+    let sql = format!("CREATE TABLE `{}` (id INT, title VARCHAR NULL)", table);
+    conn.exec(&sql).await.unwrap();
+
+    let data = "\u{feff}title,id\r\n\"Dune\r\nMessiah\",1\r\nbroken\r\nEmma,2\r\n";
+    let options = CsvOptions {
+        max_rejected: 1,
+        ..Default::default()
+    };
+    let load = conn
+        .insert_csv(&table, Box::new(std::io::Cursor::new(data)), &options)
+        .await
+        .unwrap();
+    assert_eq!(load.stats.rows_loaded, 2);
+    assert_eq!(load.rejected.len(), 1);
+    assert_eq!(load.rejected[0].line, 4);
+
+    let err = conn
+        .insert_csv(
+            &table,
+            Box::new(std::io::Cursor::new("id,title\n3\n")),
+            &CsvOptions::default(),
+        )
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("line 2: expected 2 fields, found 1"));
+
+    let sql = format!("DROP TABLE `{}`;", table);
+    conn.exec(&sql).await.unwrap();
+}
+
+#[tokio::test]
+async fn stage_files_with_pattern() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let conn = Client::new(dsn.to_string()).get_conn().await.unwrap();
+    let prefix = format!("@~/stage_files_{}", Utc::now().timestamp());
+    for name in ["a.csv", "b.csv", "c.txt", "c.txt.bak"] {
+        let data = Box::new(std::io::Cursor::new(b"1,2\n".to_vec()));
+        conn.upload_to_stage(&format!("{}/{}", prefix, name), data, 4)
+            .await
+            .unwrap();
+    }
+    let files = conn
+        .list_stage_files(&format!("{}/*.csv", prefix))
+        .await
+        .unwrap();
+    assert_eq!(files.len(), 2);
+    assert!(files
+        .iter()
+        .all(|f| f.size == 4 && f.name.ends_with(".csv")));
+
+    let removed = conn
+        .remove_stage_files(&format!("{}/*.csv", prefix))
+        .await
+        .unwrap();
+    assert_eq!(removed.len(), 2);
+    let files = conn
+        .list_stage_files(&format!("{}/", prefix))
+        .await
+        .unwrap();
+    assert_eq!(files.len(), 2);
+
+    // the name is not taken as a prefix of the other file
+    let removed = conn
+        .remove_stage_files(&format!("{}/c.txt", prefix))
+        .await
+        .unwrap();
+    assert_eq!(removed.len(), 1);
+    let files = conn
+        .list_stage_files(&format!("{}/", prefix))
+        .await
+        .unwrap();
+    assert_eq!(files.len(), 1);
+    assert!(files[0].name.ends_with("c.txt.bak"));
+    conn.remove_stage_files(&format!("{}/", prefix))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn put_local_files_with_same_name() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let conn = Client::new(dsn.to_string()).get_conn().await.unwrap();
+    let dir = std::env::temp_dir().join(format!("put_same_name_{}", Utc::now().timestamp()));
+    let mut files = Vec::new();
+    for sub in ["a", "b"] {
+        let path = dir.join(sub).join("x.csv");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "1,2\n").unwrap();
+        files.push(path);
+    }
+    let prefix = format!("@~/put_same_name_{}/", Utc::now().timestamp());
+    let err = conn.put_local_files(&files, &prefix).await.err().unwrap();
+    assert!(err.to_string().contains("would both be uploaded as x.csv"));
+    let uploaded = conn.list_stage_files(&prefix).await.unwrap();
+    assert!(uploaded.is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,59 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use databend_driver::{Client, ConnectionExt, UserFunction, UserFunctionKind};
+
+use crate::common::DEFAULT_DSN;
+
+#[tokio::test]
+async fn manage_functions() {
+    let dsn = option_env!("TEST_DATABEND_DSN").unwrap_or(DEFAULT_DSN);
+    let conn = Client::new(dsn.to_string()).get_conn().await.unwrap();
+    let name = format!("add_{}", chrono::Utc::now().timestamp());
+    let function = UserFunction::lambda(&name, &["x", "y"], "x + y").with_description("add");
+    conn.create_function(&function).await.unwrap();
+
+    let row = conn
+        .query_row(&format!("select {}(1, 2)", name))
+        .await
+        .unwrap()
+        .unwrap();
+    let (sum,): (i64,) = row.try_into().unwrap();
+    assert_eq!(sum, 3);
+
+    let functions = conn.list_functions().await.unwrap();
+    let listed = functions.iter().find(|f| f.name == name).unwrap();
+    assert_eq!(listed.description.as_deref(), Some("add"));
+    match &listed.kind {
+        UserFunctionKind::Lambda { parameters, .. } => assert_eq!(parameters, &["x", "y"]),
+        kind => panic!("unexpected function kind: {:?}", kind),
+    }
+
+    conn.drop_function(&name).await.unwrap();
+    let functions = conn.list_functions().await.unwrap();
+    assert!(functions.iter().all(|f| f.name != name));
+
+    let external = UserFunction::external(
+        "gcd",
+        &["INT", "Decimal(10, 2)"],
+        "INT",
+        "python",
+        "gcd",
+        "http://localhost:8815",
+    );
+    assert_eq!(
+        external.create_sql().unwrap(),
+        "CREATE FUNCTION IF NOT EXISTS `gcd` (INT, Decimal(10, 2)) RETURNS INT LANGUAGE python HANDLER = 'gcd' ADDRESS = 'http://localhost:8815'"
+    );
+}
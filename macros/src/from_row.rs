@@ -26,10 +26,42 @@ pub fn from_row_derive(tokens_input: TokenStream) -> TokenStream {
 
     let path = quote!(databend_driver::_macro_internal);
 
-    let set_fields_code = struct_fields.named.iter().map(|field| {
+    let fields = struct_fields
+        .named
+        .iter()
+        .map(|field| {
+            let attrs = crate::parser::parse_field_attrs(field);
+            let column = match attrs.rename {
+                Some(ref name) => name.clone(),
+                None => field
+                    .ident
+                    .as_ref()
+                    .map(|i| i.to_string())
+                    .unwrap_or_default(),
+            };
+            (field, attrs, column)
+        })
+        .collect::<Vec<_>>();
+    let columns = fields.iter().map(|(_, _, column)| column);
+
+    let set_fields_code = fields.iter().map(|(field, attrs, column)| {
         let field_name = &field.ident;
         let field_type = &field.ty;
 
+        let convert = if attrs.from_str {
+            quote! {
+                let s = String::try_from(col_value)
+                    .map_err(|_| format!("failed converting column {} ({}) from type({:?}) to String", col_ix, #column, t))?;
+                <#field_type as std::str::FromStr>::from_str(&s)
+                    .map_err(|e| format!("failed parsing column {} ({}) from {:?} to type({}): {}", col_ix, #column, s, std::any::type_name::<#field_type>(), e))?
+            }
+        } else {
+            quote! {
+                <#field_type>::try_from(col_value)
+                    .map_err(|_| format!("failed converting column {} ({}) from type({:?}) to type({})", col_ix, #column, t, std::any::type_name::<#field_type>()))?
+            }
+        };
+
         quote_spanned! {field.span() =>
             #field_name: {
                 let (col_ix, col_value) = vals_iter
@@ -37,8 +69,7 @@ pub fn from_row_derive(tokens_input: TokenStream) -> TokenStream {
                     .unwrap(); // vals_iter size is checked before this code is reached, so
                                // it is safe to unwrap
                 let t = col_value.get_type();
-                <#field_type>::try_from(col_value)
-                    .map_err(|_| format!("failed converting column {} from type({:?}) to type({})", col_ix, t, std::any::type_name::<#field_type>()))?
+                #convert
             },
         }
     });
@@ -57,6 +88,12 @@ pub fn from_row_derive(tokens_input: TokenStream) -> TokenStream {
                 })
             }
         }
+
+        impl #impl_generics #path::NamedColumns for #struct_name #ty_generics #where_clause {
+            fn column_names() -> &'static [&'static str] {
+                &[#(#columns),*]
+            }
+        }
     };
 
     TokenStream::from(generated)
@@ -17,7 +17,7 @@ use proc_macro::TokenStream;
 mod from_row;
 mod parser;
 
-#[proc_macro_derive(TryFromRow)]
+#[proc_macro_derive(TryFromRow, attributes(databend))]
 pub fn from_row_derive(tokens_input: TokenStream) -> TokenStream {
     from_row::from_row_derive(tokens_input)
 }
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use syn::{Data, DeriveInput, Fields, FieldsNamed};
+use syn::{Data, DeriveInput, Field, Fields, FieldsNamed, LitStr};
 
 /// Parses the tokens_input to a DeriveInput and returns the struct name from which it derives and
 /// the named fields
@@ -31,3 +31,32 @@ pub(crate) fn parse_named_fields<'a>(
         _ => panic!("derive({}) works only on structs!", current_derive),
     }
 }
+
+/// Options of a field from `#[databend(...)]` attributes
+#[derive(Default)]
+pub(crate) struct FieldAttrs {
+    /// Convert from a string column with `FromStr`
+    pub(crate) from_str: bool,
+    /// Column name if different from the field name
+    pub(crate) rename: Option<String>,
+}
+
+pub(crate) fn parse_field_attrs(field: &Field) -> FieldAttrs {
+    let mut attrs = FieldAttrs::default();
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("databend")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("from_str") {
+                attrs.from_str = true;
+                Ok(())
+            } else if meta.path.is_ident("rename") {
+                let name: LitStr = meta.value()?.parse()?;
+                attrs.rename = Some(name.value());
+                Ok(())
+            } else {
+                Err(meta.error("unsupported databend attribute"))
+            }
+        })
+        .unwrap_or_else(|e| panic!("{}", e));
+    }
+    attrs
+}
@@ -0,0 +1,57 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod protocol;
+mod session;
+
+use anyhow::Result;
+use clap::Parser;
+use tokio::net::TcpListener;
+
+use crate::session::Session;
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Experimental PostgreSQL wire protocol shim for Databend"
+)]
+struct Args {
+    #[clap(long, default_value = "127.0.0.1:5433", help = "Address to listen on")]
+    listen: String,
+
+    #[clap(
+        long,
+        env = "BENDSQL_DSN",
+        help = "Databend dsn, user and password are taken from clients"
+    )]
+    dsn: String,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    let listener = TcpListener::bind(&args.listen).await?;
+    eprintln!("listening on {}", args.listen);
+    let mut pid = 0;
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        pid += 1;
+        let session = Session::new(args.dsn.clone(), pid);
+        tokio::spawn(async move {
+            if let Err(e) = session.run(stream).await {
+                eprintln!("connection from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Framing of the PostgreSQL wire protocol v3, only the messages needed
+//! by the simple query flow.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+pub const SSL_REQUEST: i32 = 80877103;
+pub const CANCEL_REQUEST: i32 = 80877102;
+pub const PROTOCOL_V3: i32 = 196608;
+
+// messages are small except queries, anything bigger is not from a sane client
+const MAX_MESSAGE_LEN: usize = 64 << 20;
+
+pub enum Startup {
+    Ssl,
+    Cancel,
+    Params(HashMap<String, String>),
+}
+
+pub struct Message {
+    pub tag: u8,
+    pub body: Vec<u8>,
+}
+
+impl Message {
+    /// The body as a nul terminated string, like `Query` and `PasswordMessage`.
+    pub fn cstr(&self) -> Result<String> {
+        let end = self
+            .body
+            .iter()
+            .position(|b| *b == 0)
+            .unwrap_or(self.body.len());
+        Ok(String::from_utf8(self.body[..end].to_vec())?)
+    }
+}
+
+async fn read_body<R: AsyncRead + Unpin>(r: &mut R) -> Result<Vec<u8>> {
+    let len = r.read_i32().await? as usize;
+    if !(4..=MAX_MESSAGE_LEN).contains(&len) {
+        return Err(anyhow!("invalid message length: {}", len));
+    }
+    let mut body = vec![0; len - 4];
+    r.read_exact(&mut body).await?;
+    Ok(body)
+}
+
+/// The first message of a connection, without a tag.
+pub async fn read_startup<R: AsyncRead + Unpin>(r: &mut R) -> Result<Startup> {
+    let body = read_body(r).await?;
+    if body.len() < 4 {
+        return Err(anyhow!("startup message too short"));
+    }
+    let code = i32::from_be_bytes([body[0], body[1], body[2], body[3]]);
+    match code {
+        SSL_REQUEST => Ok(Startup::Ssl),
+        CANCEL_REQUEST => Ok(Startup::Cancel),
+        PROTOCOL_V3 => {
+            let mut params = HashMap::new();
+            let mut parts = body[4..].split(|b| *b == 0).map(String::from_utf8_lossy);
+            while let (Some(k), Some(v)) = (parts.next(), parts.next()) {
+                if k.is_empty() {
+                    break;
+                }
+                params.insert(k.to_string(), v.to_string());
+            }
+            Ok(Startup::Params(params))
+        }
+        _ => Err(anyhow!("unsupported protocol version: {}", code)),
+    }
+}
+
+/// Returns `None` when the client closed the connection.
+pub async fn read_message<R: AsyncRead + Unpin>(r: &mut R) -> Result<Option<Message>> {
+    let tag = match r.read_u8().await {
+        Ok(tag) => tag,
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let body = read_body(r).await?;
+    Ok(Some(Message { tag, body }))
+}
+
+/// Backend messages are appended to a buffer, which is flushed to the
+/// client once a response is complete.
+#[derive(Default)]
+pub struct Buffer(Vec<u8>);
+
+impl Buffer {
+    fn message(&mut self, tag: u8, body: &[u8]) {
+        self.0.push(tag);
+        self.0.extend(((body.len() + 4) as i32).to_be_bytes());
+        self.0.extend(body);
+    }
+
+    pub fn take(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.0)
+    }
+
+    pub fn auth_request(&mut self, code: i32) {
+        self.message(b'R', &code.to_be_bytes());
+    }
+
+    pub fn parameter_status(&mut self, name: &str, value: &str) {
+        let mut body = Vec::new();
+        put_cstr(&mut body, name);
+        put_cstr(&mut body, value);
+        self.message(b'S', &body);
+    }
+
+    pub fn backend_key_data(&mut self, pid: i32, key: i32) {
+        let mut body = pid.to_be_bytes().to_vec();
+        body.extend(key.to_be_bytes());
+        self.message(b'K', &body);
+    }
+
+    /// `I` for idle, the only state without transactions.
+    pub fn ready_for_query(&mut self) {
+        self.message(b'Z', b"I");
+    }
+
+    /// Columns with type oids, all in text format.
+    pub fn row_description(&mut self, fields: &[(String, i32)]) {
+        let mut body = (fields.len() as i16).to_be_bytes().to_vec();
+        for (name, oid) in fields {
+            put_cstr(&mut body, name);
+            body.extend(0i32.to_be_bytes()); // table oid
+            body.extend(0i16.to_be_bytes()); // column number
+            body.extend(oid.to_be_bytes());
+            body.extend((-1i16).to_be_bytes()); // type size
+            body.extend((-1i32).to_be_bytes()); // type modifier
+            body.extend(0i16.to_be_bytes()); // text format
+        }
+        self.message(b'T', &body);
+    }
+
+    pub fn data_row(&mut self, values: &[Option<String>]) {
+        let mut body = (values.len() as i16).to_be_bytes().to_vec();
+        for value in values {
+            match value {
+                Some(v) => {
+                    body.extend((v.len() as i32).to_be_bytes());
+                    body.extend(v.as_bytes());
+                }
+                None => body.extend((-1i32).to_be_bytes()),
+            }
+        }
+        self.message(b'D', &body);
+    }
+
+    pub fn command_complete(&mut self, tag: &str) {
+        let mut body = Vec::new();
+        put_cstr(&mut body, tag);
+        self.message(b'C', &body);
+    }
+
+    pub fn empty_query_response(&mut self) {
+        self.message(b'I', &[]);
+    }
+
+    pub fn error_response(&mut self, severity: &str, code: &str, message: &str) {
+        let mut body = Vec::new();
+        for (field, value) in [
+            (b'S', severity),
+            (b'V', severity),
+            (b'C', code),
+            (b'M', message),
+        ] {
+            body.push(field);
+            put_cstr(&mut body, value);
+        }
+        body.push(0);
+        self.message(b'E', &body);
+    }
+}
+
+fn put_cstr(buf: &mut Vec<u8>, s: &str) {
+    // nul is the terminator, it could not be part of the string
+    buf.extend(s.bytes().filter(|b| *b != 0));
+    buf.push(0);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn startup_and_messages() -> Result<()> {
+        let mut startup = Vec::new();
+        let params = b"user\0root\0database\0default\0\0";
+        startup.extend(((8 + params.len()) as i32).to_be_bytes());
+        startup.extend(PROTOCOL_V3.to_be_bytes());
+        startup.extend(params);
+        let Startup::Params(params) = read_startup(&mut startup.as_slice()).await? else {
+            panic!("expected startup params");
+        };
+        assert_eq!(params["user"], "root");
+        assert_eq!(params["database"], "default");
+
+        let mut buf = Buffer::default();
+        buf.command_complete("SELECT 1");
+        buf.data_row(&[Some("a".to_string()), None]);
+        let bytes = buf.take();
+        let mut r = bytes.as_slice();
+        let msg = read_message(&mut r).await?.unwrap();
+        assert_eq!((msg.tag, msg.cstr()?.as_str()), (b'C', "SELECT 1"));
+        let msg = read_message(&mut r).await?.unwrap();
+        assert_eq!(msg.tag, b'D');
+        assert_eq!(msg.body, [0, 2, 0, 0, 0, 1, b'a', 255, 255, 255, 255]);
+        assert!(read_message(&mut r).await?.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{anyhow, Result};
+use databend_driver::sql::schema::NumberDataType;
+use databend_driver::{split_statements, Client, Connection, ConnectionExt, DataType, Value};
+use tokio::io::{AsyncWriteExt, BufReader, BufWriter};
+use tokio::net::TcpStream;
+use tokio_stream::StreamExt;
+use url::Url;
+
+use crate::protocol::{read_message, read_startup, Buffer, Startup};
+
+// type oids from pg_type
+const BOOL: i32 = 16;
+const INT8: i32 = 20;
+const INT4: i32 = 23;
+const TEXT: i32 = 25;
+const FLOAT8: i32 = 701;
+const DATE: i32 = 1082;
+const TIMESTAMP: i32 = 1114;
+const NUMERIC: i32 = 1700;
+
+const AUTH_OK: i32 = 0;
+const AUTH_CLEARTEXT_PASSWORD: i32 = 3;
+
+/// One client connection, relayed to a driver connection opened with the
+/// user and password of the client.
+pub struct Session {
+    dsn: String,
+    pid: i32,
+}
+
+impl Session {
+    pub fn new(dsn: String, pid: i32) -> Self {
+        Self { dsn, pid }
+    }
+
+    pub async fn run(self, stream: TcpStream) -> Result<()> {
+        let (reader, writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        let mut writer = BufWriter::new(writer);
+        let mut buf = Buffer::default();
+
+        let params = loop {
+            match read_startup(&mut reader).await? {
+                // no TLS, the client goes on in plain text or gives up
+                Startup::Ssl => writer.write_all(b"N").await?,
+                Startup::Cancel => return Ok(()),
+                Startup::Params(params) => break params,
+            }
+            writer.flush().await?;
+        };
+        let user = params.get("user").cloned().unwrap_or_default();
+
+        buf.auth_request(AUTH_CLEARTEXT_PASSWORD);
+        writer.write_all(&buf.take()).await?;
+        writer.flush().await?;
+        let password = match read_message(&mut reader).await? {
+            Some(msg) if msg.tag == b'p' => msg.cstr()?,
+            _ => return Err(anyhow!("expected password message")),
+        };
+
+        let conn = match self.connect(&user, &password, params.get("database")).await {
+            Ok(conn) => conn,
+            Err(e) => {
+                buf.error_response("FATAL", "28P01", &e.to_string());
+                writer.write_all(&buf.take()).await?;
+                writer.flush().await?;
+                return Ok(());
+            }
+        };
+        let version = conn.version().await.unwrap_or_default();
+
+        buf.auth_request(AUTH_OK);
+        // clients check the server version for features, claim a recent one
+        buf.parameter_status("server_version", "14.0");
+        buf.parameter_status("server_encoding", "UTF8");
+        buf.parameter_status("client_encoding", "UTF8");
+        buf.parameter_status("DateStyle", "ISO, YMD");
+        buf.parameter_status("application_name", &version);
+        buf.backend_key_data(self.pid, 0);
+        buf.ready_for_query();
+        writer.write_all(&buf.take()).await?;
+        writer.flush().await?;
+
+        // after an error of the extended protocol, messages are skipped until Sync
+        let mut skip_until_sync = false;
+        while let Some(msg) = read_message(&mut reader).await? {
+            match msg.tag {
+                b'Q' => {
+                    let query = msg.cstr()?;
+                    simple_query(conn.as_ref(), &query, &mut buf).await;
+                    buf.ready_for_query();
+                }
+                b'X' => break,
+                b'S' => {
+                    skip_until_sync = false;
+                    buf.ready_for_query();
+                }
+                _ if skip_until_sync => {}
+                b'P' | b'B' | b'D' | b'E' | b'C' | b'H' => {
+                    skip_until_sync = true;
+                    buf.error_response(
+                        "ERROR",
+                        "0A000",
+                        "extended query protocol is not supported",
+                    );
+                }
+                tag => {
+                    buf.error_response(
+                        "ERROR",
+                        "08P01",
+                        &format!("unsupported message: {}", tag as char),
+                    );
+                    buf.ready_for_query();
+                }
+            }
+            writer.write_all(&buf.take()).await?;
+            writer.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn connect(
+        &self,
+        user: &str,
+        password: &str,
+        database: Option<&String>,
+    ) -> Result<Box<dyn Connection>> {
+        let mut u = Url::parse(&self.dsn)?;
+        u.set_username(user)
+            .and_then(|_| u.set_password(Some(password)))
+            .map_err(|_| anyhow!("invalid user: {}", user))?;
+        if let Some(database) = database {
+            u.set_path(database);
+        }
+        let conn = Client::new(u.to_string()).get_conn().await?;
+        // authenticate before accepting the client
+        conn.version().await?;
+        Ok(conn)
+    }
+}
+
+/// Statements are run one by one, stopped at the first error.
+async fn simple_query(conn: &dyn Connection, query: &str, buf: &mut Buffer) {
+    let statements = match split_statements(query) {
+        Ok(statements) => statements,
+        Err(e) => return buf.error_response("ERROR", "42601", &e.to_string()),
+    };
+    if statements.is_empty() {
+        return buf.empty_query_response();
+    }
+    for statement in statements {
+        if let Err(e) = run_statement(conn, statement.text, buf).await {
+            return buf.error_response("ERROR", "XX000", &e.to_string());
+        }
+    }
+}
+
+async fn run_statement(conn: &dyn Connection, sql: &str, buf: &mut Buffer) -> Result<()> {
+    let mut rows = conn.query_iter(sql).await?;
+    let schema = rows.schema();
+    if schema.fields().is_empty() {
+        let verb = sql
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_uppercase();
+        let tag = match verb.as_str() {
+            "INSERT" => "INSERT 0 0".to_string(),
+            _ => verb,
+        };
+        buf.command_complete(&tag);
+        return Ok(());
+    }
+    let fields: Vec<(String, i32)> = schema
+        .fields()
+        .iter()
+        .map(|f| (f.name.clone(), type_oid(&f.data_type)))
+        .collect();
+    buf.row_description(&fields);
+    let mut count = 0;
+    while let Some(row) = rows.next().await {
+        let values: Vec<Option<String>> = row?.values().iter().map(text_value).collect();
+        buf.data_row(&values);
+        count += 1;
+    }
+    buf.command_complete(&format!("SELECT {}", count));
+    Ok(())
+}
+
+fn type_oid(data_type: &DataType) -> i32 {
+    match data_type {
+        DataType::Nullable(inner) => type_oid(inner),
+        DataType::Boolean => BOOL,
+        DataType::Number(NumberDataType::Float32 | NumberDataType::Float64) => FLOAT8,
+        // not fit in int8
+        DataType::Number(NumberDataType::UInt64) => NUMERIC,
+        DataType::Number(NumberDataType::Int64 | NumberDataType::UInt32) => INT8,
+        DataType::Number(_) => INT4,
+        DataType::Decimal(_) => NUMERIC,
+        DataType::Date => DATE,
+        DataType::Timestamp => TIMESTAMP,
+        _ => TEXT,
+    }
+}
+
+/// Values in the text format of their types, `None` for NULL.
+fn text_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::Boolean(b) => Some(if *b { "t" } else { "f" }.to_string()),
+        v => Some(v.to_string()),
+    }
+}
@@ -0,0 +1,769 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A minimal SQL builder for statements generated by the driver,
+//! identifiers are always quoted and values are bound as escaped literals.
+
+use std::collections::BTreeMap;
+
+use databend_client::stage::StageLocation;
+
+use crate::copy::{as_str_map, CopyOptions, FileFormatOptions, UnloadOptions};
+use crate::error::{Error, Result};
+use crate::quote::{escape_literal, escape_regex, quote_ident, quote_qualified_ident};
+use crate::schema::{DataType, Schema};
+use crate::split::find_placeholders;
+use crate::template::{render, Param};
+use crate::time_travel::TimeTravelPoint;
+use crate::value::{NumberValue, Value};
+
+const OPERATORS: [&str; 9] = ["=", "!=", "<>", "<", "<=", ">", ">=", "LIKE", "NOT LIKE"];
+
+pub fn select(columns: &[&str]) -> Select {
+    Select {
+        columns: columns.iter().map(|c| c.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Select {
+    columns: Vec<String>,
+    from: Option<String>,
+    at: Option<TimeTravelPoint>,
+    // combined with `AND`, conditions in each group with `OR`
+    conditions: Vec<Vec<(String, String, Value)>>,
+    order_by: Vec<(String, bool)>,
+    limit: Option<u64>,
+}
+
+impl Select {
+    /// Table name could be qualified with database as `db.table`.
+    pub fn from(mut self, table: &str) -> Self {
+        self.from = Some(table.to_string());
+        self
+    }
+
+    /// Query the table at a point of its history.
+    pub fn at(mut self, point: TimeTravelPoint) -> Self {
+        self.at = Some(point);
+        self
+    }
+
+    /// Add a condition combined with `AND`, supported operators:
+    /// `=`, `!=`, `<>`, `<`, `<=`, `>`, `>=`, `LIKE`, `NOT LIKE`.
+    pub fn where_(mut self, column: &str, op: &str, value: impl Into<Value>) -> Self {
+        self.conditions
+            .push(vec![(column.to_string(), op.to_string(), value.into())]);
+        self
+    }
+
+    /// Add conditions combined with `OR` as a whole, which is combined
+    /// with the others with `AND`.
+    pub fn where_any(mut self, conditions: &[(&str, &str, Value)]) -> Self {
+        let conditions = conditions
+            .iter()
+            .map(|(column, op, value)| (column.to_string(), op.to_string(), value.clone()))
+            .collect();
+        self.conditions.push(conditions);
+        self
+    }
+
+    pub fn order_by(mut self, column: &str, asc: bool) -> Self {
+        self.order_by.push((column.to_string(), asc));
+        self
+    }
+
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn build(&self) -> Result<String> {
+        let mut sql = String::from("SELECT ");
+        if self.columns.is_empty() {
+            sql.push('*');
+        } else {
+            let columns = self
+                .columns
+                .iter()
+                .map(|c| quote_ident(c))
+                .collect::<Vec<_>>();
+            sql.push_str(&columns.join(", "));
+        }
+        if let Some(ref table) = self.from {
+            sql.push_str(" FROM ");
+            sql.push_str(&quote_qualified_ident(table));
+            if let Some(ref point) = self.at {
+                sql.push(' ');
+                sql.push_str(&point.to_string());
+            }
+        }
+        for (i, group) in self.conditions.iter().enumerate() {
+            let mut conditions = Vec::with_capacity(group.len());
+            for (column, op, value) in group {
+                let op = op.to_uppercase();
+                if !OPERATORS.contains(&op.as_str()) {
+                    return Err(Error::BadArgument(format!("Unsupported operator: {}", op)));
+                }
+                conditions.push(format!(
+                    "{} {} {}",
+                    quote_ident(column),
+                    op,
+                    value_literal(value)
+                ));
+            }
+            sql.push_str(if i == 0 { " WHERE " } else { " AND " });
+            match conditions.len() {
+                0 => sql.push_str("FALSE"),
+                1 => sql.push_str(&conditions[0]),
+                _ => sql.push_str(&format!("({})", conditions.join(" OR "))),
+            }
+        }
+        if !self.order_by.is_empty() {
+            let order_by = self
+                .order_by
+                .iter()
+                .map(|(c, asc)| {
+                    let order = if *asc { "ASC" } else { "DESC" };
+                    format!("{} {}", quote_ident(c), order)
+                })
+                .collect::<Vec<_>>();
+            sql.push_str(" ORDER BY ");
+            sql.push_str(&order_by.join(", "));
+        }
+        if let Some(limit) = self.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+        Ok(sql)
+    }
+}
+
+/// The operation could be `UPLOAD` or `DOWNLOAD`.
+pub fn presign(operation: &str, stage: &str) -> Result<String> {
+    let operation = operation.to_uppercase();
+    if operation != "UPLOAD" && operation != "DOWNLOAD" {
+        return Err(Error::BadArgument(format!(
+            "Invalid presign operation: {}",
+            operation
+        )));
+    }
+    let location = StageLocation::try_from(stage)?;
+    render(
+        "PRESIGN ? ?",
+        &[Param::Keyword(&operation), Param::Location(&location)],
+    )
+}
+
+pub fn list_stage(location: &StageLocation) -> Result<String> {
+    render("LIST ?", &[Param::Location(location)])
+}
+
+pub fn copy_into_table(
+    table: &str,
+    location: &StageLocation,
+    file_format_options: &BTreeMap<&str, &str>,
+    copy_options: &BTreeMap<&str, &str>,
+) -> Result<String> {
+    let mut sql = render(
+        "COPY INTO ? FROM ?",
+        &[Param::QualifiedIdent(table), Param::Location(location)],
+    )?;
+    if !file_format_options.is_empty() {
+        sql.push_str(&format!(
+            " FILE_FORMAT = ({})",
+            format_options(file_format_options)?
+        ));
+    }
+    if !copy_options.is_empty() {
+        sql.push(' ');
+        sql.push_str(&format_options(copy_options)?);
+    }
+    Ok(sql)
+}
+
+/// Same as [`copy_into_table`] with typed options, which are validated
+/// before the statement is rendered.
+pub fn copy_into_table_with_options(
+    table: &str,
+    location: &StageLocation,
+    file_format_options: &FileFormatOptions,
+    copy_options: &CopyOptions,
+) -> Result<String> {
+    let file_format_options = file_format_options.to_map()?;
+    let copy_options = copy_options.to_map()?;
+    copy_into_table(
+        table,
+        location,
+        &as_str_map(&file_format_options),
+        &as_str_map(&copy_options),
+    )
+}
+
+/// Unload results of the query to files under the location, in format
+/// like `PARQUET`, `CSV` or `NDJSON`.
+pub fn copy_into_location(location: &StageLocation, query: &str, format: &str) -> Result<String> {
+    let query = query.trim().trim_end_matches(';');
+    if query.is_empty() {
+        return Err(Error::BadArgument("Empty query to unload".to_string()));
+    }
+    // the query is not rendered as template, `?` is valid in it
+    let location = render("?", &[Param::Location(location)])?;
+    let format = render("?", &[Param::Keyword(format)])?;
+    Ok(format!(
+        "COPY INTO {} FROM ({}) FILE_FORMAT = (TYPE = {})",
+        location, query, format
+    ))
+}
+
+/// Same as [`copy_into_location`] with typed options, to choose the codec
+/// of Parquet files or to limit the size of the files.
+pub fn copy_into_location_with_options(
+    location: &StageLocation,
+    query: &str,
+    file_format_options: &FileFormatOptions,
+    unload_options: &UnloadOptions,
+) -> Result<String> {
+    let query = query.trim().trim_end_matches(';');
+    if query.is_empty() {
+        return Err(Error::BadArgument("Empty query to unload".to_string()));
+    }
+    let file_format_options = file_format_options.to_unload_map()?;
+    let unload_options = unload_options.to_map()?;
+    let location = render("?", &[Param::Location(location)])?;
+    let mut sql = format!(
+        "COPY INTO {} FROM ({}) FILE_FORMAT = ({})",
+        location,
+        query,
+        format_options(&as_str_map(&file_format_options))?
+    );
+    if !unload_options.is_empty() {
+        sql.push(' ');
+        sql.push_str(&format_options(&as_str_map(&unload_options))?);
+    }
+    Ok(sql)
+}
+
+pub fn show_tables(database: Option<&str>) -> String {
+    match database {
+        Some(database) => format!("SHOW TABLES FROM {}", quote_ident(database)),
+        None => "SHOW TABLES".to_string(),
+    }
+}
+
+pub fn show_create_database(database: &str) -> String {
+    format!("SHOW CREATE DATABASE {}", quote_ident(database))
+}
+
+pub fn show_create_table(table: &str) -> String {
+    format!("SHOW CREATE TABLE {}", quote_qualified_ident(table))
+}
+
+// statements accepting `/*+ SET_VAR(...) */` hints right after the keyword
+const HINTED_KEYWORDS: [&str; 6] = ["SELECT", "INSERT", "REPLACE", "UPDATE", "DELETE", "COPY"];
+
+/// Add a `SET_VAR` hint to override a setting for this statement only,
+/// like `SELECT /*+ SET_VAR(enable_query_result_cache=0) */ 1`.
+/// Leading comments are kept before the statement, values other than
+/// numbers are quoted.
+pub fn set_var_hint(sql: &str, name: &str, value: &str) -> Result<String> {
+    check_setting_name(name)?;
+    // the hint is a comment, which could not be closed inside a literal
+    if value.contains("*/") {
+        return Err(Error::BadArgument(format!(
+            "Invalid setting value: {}",
+            value
+        )));
+    }
+    let value = if value.parse::<f64>().is_ok_and(|f| f.is_finite()) {
+        value.to_string()
+    } else {
+        escape_literal(value)
+    };
+    let mut start = 0;
+    loop {
+        let rest = sql[start..].trim_start();
+        start = sql.len() - rest.len();
+        if rest.starts_with("--") {
+            start += rest.find('\n').unwrap_or(rest.len());
+        } else if rest.starts_with("/*") {
+            match rest.find("*/") {
+                Some(end) => start += end + 2,
+                None => return Err(Error::BadArgument("Unterminated comment".to_string())),
+            }
+        } else {
+            break;
+        }
+    }
+    let rest = &sql[start..];
+    let end = rest
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(rest.len());
+    let keyword = &rest[..end];
+    if !HINTED_KEYWORDS
+        .iter()
+        .any(|k| k.eq_ignore_ascii_case(keyword))
+    {
+        return Err(Error::BadArgument(format!(
+            "Setting hints are not supported for statement: {}",
+            keyword
+        )));
+    }
+    Ok(format!(
+        "{} /*+ SET_VAR({}={}) */{}",
+        &sql[..start + end],
+        name,
+        value,
+        &rest[end..]
+    ))
+}
+
+/// `SET` of a session setting, the name is checked since it could not be quoted.
+pub fn set_setting(name: &str, value: &str) -> Result<String> {
+    check_setting_name(name)?;
+    Ok(format!("SET {} = {}", name, escape_literal(value)))
+}
+
+fn check_setting_name(name: &str) -> Result<()> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(Error::BadArgument(format!(
+            "Invalid setting name: {}",
+            name
+        )));
+    }
+    Ok(())
+}
+
+pub fn kill_query(query_id: &str) -> String {
+    format!("KILL QUERY {}", escape_literal(query_id))
+}
+
+/// Generate `CREATE TABLE` with columns of the schema, schema of arrow
+/// or local files could be converted with `Schema::try_from` first.
+pub fn create_table_sql(table: &str, schema: &Schema) -> Result<String> {
+    create_table_of("TABLE", table, schema)
+}
+
+/// Generate `CREATE TEMP TABLE`, which lives only in the session.
+pub fn create_temp_table_sql(table: &str, schema: &Schema) -> Result<String> {
+    create_table_of("TEMP TABLE", table, schema)
+}
+
+/// Generate `CREATE TRANSIENT TABLE`, for servers without temp tables.
+pub fn create_transient_table_sql(table: &str, schema: &Schema) -> Result<String> {
+    create_table_of("TRANSIENT TABLE", table, schema)
+}
+
+fn create_table_of(kind: &str, table: &str, schema: &Schema) -> Result<String> {
+    if schema.fields().is_empty() {
+        return Err(Error::BadArgument(format!(
+            "No columns to create table {}",
+            table
+        )));
+    }
+    let mut columns = Vec::with_capacity(schema.fields().len());
+    for field in schema.fields() {
+        columns.push(format!(
+            "{} {}",
+            quote_ident(&field.name),
+            column_type(&field.data_type)?
+        ));
+    }
+    Ok(format!(
+        "CREATE {} {} ({})",
+        kind,
+        quote_qualified_ident(table),
+        columns.join(", ")
+    ))
+}
+
+fn column_type(data_type: &DataType) -> Result<String> {
+    match data_type {
+        DataType::Null | DataType::Nothing | DataType::EmptyArray | DataType::EmptyMap => Err(
+            Error::BadArgument(format!("Unsupported column type: {}", data_type)),
+        ),
+        DataType::Nullable(inner) => Ok(format!("Nullable({})", column_type(inner)?)),
+        DataType::Array(inner) => Ok(format!("Array({})", column_type(inner)?)),
+        DataType::Map(inner) => match inner.as_ref() {
+            DataType::Tuple(kv) if kv.len() == 2 => Ok(format!(
+                "Map({}, {})",
+                column_type(&kv[0])?,
+                column_type(&kv[1])?
+            )),
+            _ => Err(Error::BadArgument(format!(
+                "Unsupported column type: {}",
+                data_type
+            ))),
+        },
+        DataType::Tuple(inner) => {
+            let inner = inner.iter().map(column_type).collect::<Result<Vec<_>>>()?;
+            Ok(format!("Tuple({})", inner.join(", ")))
+        }
+        _ => Ok(data_type.to_string()),
+    }
+}
+
+/// `INSERT INTO t (a, b) VALUES` for data attached from stage.
+pub fn insert_columns(table: &str, columns: &[&str]) -> String {
+    let columns = columns.iter().map(|c| quote_ident(c)).collect::<Vec<_>>();
+    format!(
+        "INSERT INTO {} ({}) VALUES",
+        quote_qualified_ident(table),
+        columns.join(", ")
+    )
+}
+
+pub fn drop_table(table: &str) -> String {
+    format!("DROP TABLE IF EXISTS {}", quote_qualified_ident(table))
+}
+
+pub fn drop_function(name: &str) -> String {
+    format!("DROP FUNCTION IF EXISTS {}", quote_ident(name))
+}
+
+/// Grants of a user, `host` is `%` for users allowed from anywhere.
+pub fn show_grants_for_user(user: &str, host: &str) -> String {
+    format!(
+        "SHOW GRANTS FOR {}@{}",
+        escape_literal(user),
+        escape_literal(host)
+    )
+}
+
+pub fn show_grants_for_role(role: &str) -> String {
+    format!("SHOW GRANTS FOR ROLE {}", escape_literal(role))
+}
+
+pub fn drop_stage(stage: &str) -> String {
+    format!("DROP STAGE IF EXISTS {}", quote_ident(stage))
+}
+
+pub fn remove_stage(location: &StageLocation) -> Result<String> {
+    render("REMOVE ?", &[Param::Location(location)])
+}
+
+/// Remove only the file of the path under the stage, `REMOVE` alone takes
+/// the path as a prefix and would remove `a.csv.bak` with `a.csv`.
+pub fn remove_stage_file(stage: &str, path: &str) -> Result<String> {
+    let dir = match path.rsplit_once('/') {
+        Some((dir, _)) => format!("{}/", dir),
+        None => String::new(),
+    };
+    let location = StageLocation {
+        name: stage.to_string(),
+        path: dir,
+    };
+    let pattern = format!("^{}$", escape_regex(path));
+    render(
+        "REMOVE ? PATTERN = ?",
+        &[Param::Location(&location), Param::Literal(&pattern)],
+    )
+}
+
+/// Read and consume the changes captured by a stream,
+/// the offset of the stream only advances when the query succeeds.
+pub fn consume_stream(stream: &str) -> String {
+    format!(
+        "SELECT * FROM {} WITH CONSUME",
+        quote_qualified_ident(stream)
+    )
+}
+
+/// Bind parameters to the `?` placeholders of a statement as escaped literals,
+/// the number of parameters must match the placeholders.
+pub fn bind(sql: &str, params: &[Value]) -> Result<String> {
+    let positions = find_placeholders(sql)?;
+    if positions.len() != params.len() {
+        return Err(Error::BadArgument(format!(
+            "Expected {} parameters, got {}",
+            positions.len(),
+            params.len()
+        )));
+    }
+    let mut bound = String::with_capacity(sql.len());
+    let mut last = 0;
+    for (pos, value) in positions.iter().zip(params) {
+        bound.push_str(&sql[last..*pos]);
+        bound.push_str(&value_literal(value));
+        last = pos + 1;
+    }
+    bound.push_str(&sql[last..]);
+    Ok(bound)
+}
+
+fn format_options(options: &BTreeMap<&str, &str>) -> Result<String> {
+    let mut items = Vec::with_capacity(options.len());
+    for (k, v) in options {
+        if k.is_empty() || !k.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(Error::BadArgument(format!("Invalid option name: {}", k)));
+        }
+        let is_plain = v.parse::<bool>().is_ok() || v.parse::<i64>().is_ok();
+        if is_plain {
+            items.push(format!("{} = {}", k, v));
+        } else {
+            items.push(format!("{} = {}", k, escape_literal(v)));
+        }
+    }
+    Ok(items.join(" "))
+}
+
+fn value_literal(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Boolean(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+        Value::Number(NumberValue::Float32(f)) if !f.is_finite() => {
+            non_finite_literal(*f as f64, "FLOAT32")
+        }
+        Value::Number(NumberValue::Float64(f)) if !f.is_finite() => {
+            non_finite_literal(*f, "FLOAT64")
+        }
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => escape_literal(s),
+        Value::Timestamp(_) | Value::Date(_) => escape_literal(&value.to_string()),
+        Value::Array(vals) => format!("[{}]", values_literal(vals)),
+        Value::Tuple(vals) => format!("({})", values_literal(vals)),
+        Value::Map(entries) => {
+            let entries = entries
+                .iter()
+                .map(|(k, v)| format!("{}: {}", value_literal(k), value_literal(v)))
+                .collect::<Vec<_>>();
+            format!("{{{}}}", entries.join(", "))
+        }
+        Value::Variant(s) => format!("PARSE_JSON({})", escape_literal(s)),
+        Value::Bitmap(s) => format!("TO_BITMAP({})", escape_literal(s)),
+        Value::Geometry(s) => format!("TO_GEOMETRY({})", escape_literal(s)),
+    }
+}
+
+// NaN and infinity have no numeric literal, cast them from strings
+fn non_finite_literal(f: f64, type_name: &str) -> String {
+    let text = if f.is_nan() {
+        "NaN"
+    } else if f > 0.0 {
+        "Infinity"
+    } else {
+        "-Infinity"
+    };
+    format!("'{}'::{}", text, type_name)
+}
+
+fn values_literal(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(value_literal)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::copy::Compression;
+    use crate::schema::{Field, NumberDataType};
+
+    #[test]
+    fn build_select() -> Result<()> {
+        let sql = select(&["name", "engine"])
+            .from("system.tables")
+            .where_("database", "=", "it's")
+            .where_("num_rows", ">", 10u64)
+            .order_by("name", true)
+            .limit(5)
+            .build()?;
+        assert_eq!(
+            sql,
+            r#"SELECT `name`, `engine` FROM `system`.`tables` WHERE `database` = 'it\'s' AND `num_rows` > 10 ORDER BY `name` ASC LIMIT 5"#
+        );
+
+        let sql = select(&[]).from("t`1").build()?;
+        assert_eq!(sql, "SELECT * FROM `t``1`");
+
+        let sql = select(&[])
+            .from("t1")
+            .at(TimeTravelPoint::Snapshot("s1".to_string()))
+            .build()?;
+        assert_eq!(sql, "SELECT * FROM `t1` AT (SNAPSHOT => 's1')");
+
+        let sql = select(&["a"]).where_("a", "; DROP", 1u8).build();
+        assert!(sql.is_err());
+
+        let sql = select(&["a"])
+            .from("t")
+            .where_("a", ">=", 1u8)
+            .where_any(&[("a", ">", Value::from(1u8)), ("b", ">", Value::from("x"))])
+            .build()?;
+        assert_eq!(
+            sql,
+            "SELECT `a` FROM `t` WHERE `a` >= 1 AND (`a` > 1 OR `b` > 'x')"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn set_var_hints() -> Result<()> {
+        assert_eq!(
+            set_var_hint("select * from t1", "enable_query_result_cache", "0")?,
+            "select /*+ SET_VAR(enable_query_result_cache=0) */ * from t1"
+        );
+        assert_eq!(
+            set_var_hint("-- report\n /* q1 */ SELECT 1", "max_threads", "1")?,
+            "-- report\n /* q1 */ SELECT /*+ SET_VAR(max_threads=1) */ 1"
+        );
+        assert!(set_var_hint("WITH t AS (SELECT 1) SELECT * FROM t", "a", "0").is_err());
+        assert!(set_var_hint("/* open", "a", "0").is_err());
+        assert_eq!(
+            set_var_hint("SELECT 1", "timezone", "Asia/Shanghai")?,
+            "SELECT /*+ SET_VAR(timezone='Asia/Shanghai') */ 1"
+        );
+        assert!(set_var_hint("SELECT 1", "a=1) */ DROP", "0").is_err());
+        assert!(set_var_hint("SELECT 1", "timezone", "x */ DROP").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bind_params() -> Result<()> {
+        let sql = bind(
+            "INSERT INTO t VALUES (?, ?, ?) -- ?",
+            &[Value::from("it's ?"), Value::from(1u8), Value::Null],
+        )?;
+        assert_eq!(sql, r"INSERT INTO t VALUES ('it\'s ?', 1, NULL) -- ?");
+        assert!(bind("SELECT ?", &[]).is_err());
+        assert!(bind("SELECT '?'", &[Value::Null]).is_err());
+
+        let sql = bind(
+            "SELECT ?, ?, ?",
+            &[
+                Value::Number(NumberValue::Float64(f64::NAN)),
+                Value::Number(NumberValue::Float32(f32::NEG_INFINITY)),
+                Value::Number(NumberValue::Float64(1.5)),
+            ],
+        )?;
+        assert_eq!(sql, "SELECT 'NaN'::FLOAT64, '-Infinity'::FLOAT32, 1.5");
+        Ok(())
+    }
+
+    #[test]
+    fn build_statements() -> Result<()> {
+        assert_eq!(
+            presign("upload", "@~/path/to/file")?,
+            "PRESIGN UPLOAD @~/path/to/file"
+        );
+        assert!(presign("DELETE", "@~/path/to/file").is_err());
+
+        let location = StageLocation::try_from("@s1/data/")?;
+        assert_eq!(list_stage(&location)?, "LIST @s1/data/");
+
+        assert_eq!(
+            insert_columns("db.t", &["id", "user name"]),
+            "INSERT INTO `db`.`t` (`id`, `user name`) VALUES"
+        );
+
+        let file_format_options = vec![("type", "CSV"), ("skip_header", "1")]
+            .into_iter()
+            .collect();
+        let copy_options = vec![("purge", "true")].into_iter().collect();
+        assert_eq!(
+            copy_into_table("db.t", &location, &file_format_options, &copy_options)?,
+            "COPY INTO `db`.`t` FROM @s1/data/ FILE_FORMAT = (skip_header = 1 type = 'CSV') purge = true"
+        );
+        assert_eq!(
+            copy_into_table_with_options(
+                "t",
+                &location,
+                &FileFormatOptions::tsv().skip_header(1),
+                &CopyOptions::new().force(true),
+            )?,
+            "COPY INTO `t` FROM @s1/data/ FILE_FORMAT = (skip_header = 1 type = 'TSV') force = true"
+        );
+        assert!(copy_into_table_with_options(
+            "t",
+            &location,
+            &FileFormatOptions::parquet().field_delimiter(","),
+            &CopyOptions::new(),
+        )
+        .is_err());
+
+        assert_eq!(
+            copy_into_location(&location, "SELECT * FROM t WHERE a = '?';", "parquet")?,
+            "COPY INTO @s1/data/ FROM (SELECT * FROM t WHERE a = '?') FILE_FORMAT = (TYPE = PARQUET)"
+        );
+        assert!(copy_into_location(&location, "SELECT 1", "csv; DROP").is_err());
+        assert_eq!(
+            copy_into_location_with_options(
+                &location,
+                "SELECT 1",
+                &FileFormatOptions::parquet().compression(Compression::Snappy),
+                &UnloadOptions::new().max_file_size(1024),
+            )?,
+            "COPY INTO @s1/data/ FROM (SELECT 1) FILE_FORMAT = (compression = 'SNAPPY' type = 'PARQUET') max_file_size = 1024"
+        );
+
+        assert_eq!(show_tables(Some("db")), "SHOW TABLES FROM `db`");
+        assert_eq!(kill_query("q1"), "KILL QUERY 'q1'");
+        assert_eq!(show_create_database("db"), "SHOW CREATE DATABASE `db`");
+        let schema = Schema::from_vec(vec![
+            Field {
+                name: "id".to_string(),
+                data_type: DataType::Number(NumberDataType::UInt64),
+            },
+            Field {
+                name: "tags".to_string(),
+                data_type: DataType::Nullable(Box::new(DataType::Array(Box::new(
+                    DataType::String,
+                )))),
+            },
+        ]);
+        assert_eq!(
+            create_table_sql("db.t", &schema)?,
+            "CREATE TABLE `db`.`t` (`id` UInt64, `tags` Nullable(Array(String)))"
+        );
+        assert_eq!(
+            create_temp_table_sql("t", &schema)?,
+            "CREATE TEMP TABLE `t` (`id` UInt64, `tags` Nullable(Array(String)))"
+        );
+        assert_eq!(
+            create_transient_table_sql("t", &schema)?,
+            "CREATE TRANSIENT TABLE `t` (`id` UInt64, `tags` Nullable(Array(String)))"
+        );
+        let schema = Schema::from_vec(vec![Field {
+            name: "n".to_string(),
+            data_type: DataType::Null,
+        }]);
+        assert!(create_table_sql("t", &schema).is_err());
+        assert!(create_table_sql("t", &Schema::default()).is_err());
+        assert_eq!(drop_table("db.t"), "DROP TABLE IF EXISTS `db`.`t`");
+        assert_eq!(drop_stage("s1"), "DROP STAGE IF EXISTS `s1`");
+        assert_eq!(drop_function("f1"), "DROP FUNCTION IF EXISTS `f1`");
+        assert_eq!(
+            set_setting("timezone", "Asia/Shanghai")?,
+            "SET timezone = 'Asia/Shanghai'"
+        );
+        assert!(set_setting("max_threads = 1; DROP", "1").is_err());
+        assert_eq!(show_grants_for_user("u1", "%"), "SHOW GRANTS FOR 'u1'@'%'");
+        assert_eq!(
+            show_grants_for_role("it's"),
+            "SHOW GRANTS FOR ROLE 'it\\'s'"
+        );
+        assert_eq!(remove_stage(&location)?, "REMOVE @s1/data/");
+        assert_eq!(
+            remove_stage_file("s1", "data/a.csv")?,
+            r"REMOVE @s1/data/ PATTERN = '^data/a\\.csv$'"
+        );
+        assert_eq!(
+            consume_stream("db.s1"),
+            "SELECT * FROM `db`.`s1` WITH CONSUME"
+        );
+        Ok(())
+    }
+}
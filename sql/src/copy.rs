@@ -0,0 +1,503 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed options of `FILE_FORMAT` and `COPY INTO`, checked before sending
+//! instead of failing on the server for misspelled or conflicting options.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::error::{Error, Result};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileType {
+    Csv,
+    Tsv,
+    NdJson,
+    Parquet,
+    Orc,
+    Xml,
+}
+
+impl FileType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileType::Csv => "CSV",
+            FileType::Tsv => "TSV",
+            FileType::NdJson => "NDJSON",
+            FileType::Parquet => "PARQUET",
+            FileType::Orc => "ORC",
+            FileType::Xml => "XML",
+        }
+    }
+
+    /// Formats of text lines, which take delimiters and headers.
+    fn is_delimited(&self) -> bool {
+        matches!(self, FileType::Csv | FileType::Tsv)
+    }
+
+    /// Formats compressed inside the files by themselves.
+    fn is_columnar(&self) -> bool {
+        matches!(self, FileType::Parquet | FileType::Orc)
+    }
+}
+
+impl fmt::Display for FileType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Compression of whole files, detected by extension with `Auto`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Auto,
+    None,
+    Gzip,
+    Bz2,
+    Brotli,
+    Zstd,
+    Deflate,
+    RawDeflate,
+    Xz,
+    /// Only as the codec inside Parquet files unloaded.
+    Snappy,
+}
+
+impl Compression {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Compression::Auto => "AUTO",
+            Compression::None => "NONE",
+            Compression::Gzip => "GZIP",
+            Compression::Bz2 => "BZ2",
+            Compression::Brotli => "BROTLI",
+            Compression::Zstd => "ZSTD",
+            Compression::Deflate => "DEFLATE",
+            Compression::RawDeflate => "RAW_DEFLATE",
+            Compression::Xz => "XZ",
+            Compression::Snappy => "SNAPPY",
+        }
+    }
+}
+
+/// What to do with files having rows which could not be loaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnError {
+    /// Skip the bad rows and load the others.
+    Continue,
+    /// Skip the files with bad rows.
+    SkipFile,
+    /// Fail the whole statement, the default of the server.
+    Abort,
+}
+
+impl OnError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OnError::Continue => "continue",
+            OnError::SkipFile => "skip_file",
+            OnError::Abort => "abort",
+        }
+    }
+}
+
+/// Options in `FILE_FORMAT = (...)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileFormatOptions {
+    file_type: FileType,
+    compression: Option<Compression>,
+    field_delimiter: Option<String>,
+    record_delimiter: Option<String>,
+    quote: Option<String>,
+    skip_header: Option<u64>,
+}
+
+impl FileFormatOptions {
+    pub fn new(file_type: FileType) -> Self {
+        Self {
+            file_type,
+            compression: None,
+            field_delimiter: None,
+            record_delimiter: None,
+            quote: None,
+            skip_header: None,
+        }
+    }
+
+    pub fn csv() -> Self {
+        Self::new(FileType::Csv)
+    }
+
+    pub fn tsv() -> Self {
+        Self::new(FileType::Tsv)
+    }
+
+    pub fn ndjson() -> Self {
+        Self::new(FileType::NdJson)
+    }
+
+    pub fn parquet() -> Self {
+        Self::new(FileType::Parquet)
+    }
+
+    pub fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    /// Compression of whole files. Parquet and ORC are compressed inside,
+    /// for them it is only taken by unloading, as the codec of Parquet
+    /// which could be `None`, `Snappy` or `Zstd`.
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// CSV and TSV only.
+    pub fn field_delimiter(mut self, delimiter: &str) -> Self {
+        self.field_delimiter = Some(delimiter.to_string());
+        self
+    }
+
+    /// CSV and TSV only.
+    pub fn record_delimiter(mut self, delimiter: &str) -> Self {
+        self.record_delimiter = Some(delimiter.to_string());
+        self
+    }
+
+    /// CSV only, a single character.
+    pub fn quote(mut self, quote: &str) -> Self {
+        self.quote = Some(quote.to_string());
+        self
+    }
+
+    /// CSV and TSV only, lines skipped at the beginning of each file.
+    pub fn skip_header(mut self, lines: u64) -> Self {
+        self.skip_header = Some(lines);
+        self
+    }
+
+    /// Validate the options for loading files.
+    pub fn validate(&self) -> Result<()> {
+        self.check(false)
+    }
+
+    fn check(&self, unload: bool) -> Result<()> {
+        let file_type = self.file_type;
+        let unsupported = |option: &str| {
+            Err(Error::BadArgument(format!(
+                "File format option {} is not supported by {}",
+                option, file_type
+            )))
+        };
+        if !file_type.is_delimited() {
+            if self.field_delimiter.is_some() {
+                return unsupported("field_delimiter");
+            }
+            if self.record_delimiter.is_some() {
+                return unsupported("record_delimiter");
+            }
+            if self.skip_header.is_some() {
+                return unsupported("skip_header");
+            }
+        }
+        if self.quote.is_some() && file_type != FileType::Csv {
+            return unsupported("quote");
+        }
+        match self.compression {
+            Some(Compression::None | Compression::Snappy | Compression::Zstd)
+                if unload && file_type == FileType::Parquet => {}
+            Some(Compression::Snappy) => return unsupported("compression SNAPPY"),
+            Some(_) if file_type.is_columnar() => return unsupported("compression"),
+            _ => {}
+        }
+        for (name, delimiter) in [
+            ("field_delimiter", &self.field_delimiter),
+            ("record_delimiter", &self.record_delimiter),
+        ] {
+            if delimiter.as_deref() == Some("") {
+                return Err(Error::BadArgument(format!("Empty {}", name)));
+            }
+        }
+        if let Some(ref quote) = self.quote {
+            if quote.chars().count() != 1 {
+                return Err(Error::BadArgument(format!(
+                    "Quote must be a single character: {}",
+                    quote
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Validated options as taken by `stream_load` and `copy_into_table`.
+    pub fn to_map(&self) -> Result<BTreeMap<&'static str, String>> {
+        self.validate()?;
+        Ok(self.to_map_unchecked())
+    }
+
+    /// Validated options for unloading with `COPY INTO <location>`.
+    pub fn to_unload_map(&self) -> Result<BTreeMap<&'static str, String>> {
+        self.check(true)?;
+        Ok(self.to_map_unchecked())
+    }
+
+    fn to_map_unchecked(&self) -> BTreeMap<&'static str, String> {
+        let mut options = BTreeMap::new();
+        options.insert("type", self.file_type.as_str().to_string());
+        if let Some(compression) = self.compression {
+            options.insert("compression", compression.as_str().to_string());
+        }
+        if let Some(ref delimiter) = self.field_delimiter {
+            options.insert("field_delimiter", delimiter.clone());
+        }
+        if let Some(ref delimiter) = self.record_delimiter {
+            options.insert("record_delimiter", delimiter.clone());
+        }
+        if let Some(ref quote) = self.quote {
+            options.insert("quote", quote.clone());
+        }
+        if let Some(lines) = self.skip_header {
+            options.insert("skip_header", lines.to_string());
+        }
+        options
+    }
+}
+
+/// Options of `COPY INTO <table>`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CopyOptions {
+    size_limit: Option<u64>,
+    max_files: Option<u64>,
+    purge: Option<bool>,
+    force: Option<bool>,
+    on_error: Option<OnError>,
+}
+
+impl CopyOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop loading more files once about this many rows are loaded.
+    pub fn size_limit(mut self, rows: u64) -> Self {
+        self.size_limit = Some(rows);
+        self
+    }
+
+    pub fn max_files(mut self, files: u64) -> Self {
+        self.max_files = Some(files);
+        self
+    }
+
+    /// Remove the files from stage once loaded.
+    pub fn purge(mut self, purge: bool) -> Self {
+        self.purge = Some(purge);
+        self
+    }
+
+    /// Load files again even if they were loaded before.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = Some(force);
+        self
+    }
+
+    pub fn on_error(mut self, on_error: OnError) -> Self {
+        self.on_error = Some(on_error);
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.size_limit == Some(0) {
+            return Err(Error::BadArgument(
+                "size_limit must be positive, leave it unset for no limit".to_string(),
+            ));
+        }
+        if self.max_files == Some(0) {
+            return Err(Error::BadArgument(
+                "max_files must be positive, leave it unset for no limit".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Validated options as taken by `stream_load` and `copy_into_table`.
+    pub fn to_map(&self) -> Result<BTreeMap<&'static str, String>> {
+        self.validate()?;
+        let mut options = BTreeMap::new();
+        if let Some(rows) = self.size_limit {
+            options.insert("size_limit", rows.to_string());
+        }
+        if let Some(files) = self.max_files {
+            options.insert("max_files", files.to_string());
+        }
+        if let Some(purge) = self.purge {
+            options.insert("purge", purge.to_string());
+        }
+        if let Some(force) = self.force {
+            options.insert("force", force.to_string());
+        }
+        if let Some(on_error) = self.on_error {
+            options.insert("on_error", on_error.as_str().to_string());
+        }
+        Ok(options)
+    }
+}
+
+/// Options of `COPY INTO <location>`. Row groups of Parquet files are
+/// sized by the server, only the size of the files could be limited.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UnloadOptions {
+    max_file_size: Option<u64>,
+    single: Option<bool>,
+}
+
+impl UnloadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a new file once about this many bytes are written.
+    pub fn max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = Some(bytes);
+        self
+    }
+
+    /// Unload into a single file instead of many written in parallel.
+    pub fn single(mut self, single: bool) -> Self {
+        self.single = Some(single);
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.max_file_size == Some(0) {
+            return Err(Error::BadArgument(
+                "max_file_size must be positive, leave it unset for no limit".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Validated options as taken by `copy_into_location_with_options`.
+    pub fn to_map(&self) -> Result<BTreeMap<&'static str, String>> {
+        self.validate()?;
+        let mut options = BTreeMap::new();
+        if let Some(bytes) = self.max_file_size {
+            options.insert("max_file_size", bytes.to_string());
+        }
+        if let Some(single) = self.single {
+            options.insert("single", single.to_string());
+        }
+        Ok(options)
+    }
+}
+
+/// Borrow the options in the form of the untyped APIs.
+pub fn as_str_map<'a>(options: &'a BTreeMap<&'static str, String>) -> BTreeMap<&'a str, &'a str> {
+    options.iter().map(|(k, v)| (*k, v.as_str())).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn file_format_options() -> Result<()> {
+        let options = FileFormatOptions::csv()
+            .field_delimiter("|")
+            .quote("'")
+            .skip_header(1)
+            .compression(Compression::Gzip)
+            .to_map()?;
+        assert_eq!(
+            as_str_map(&options),
+            vec![
+                ("compression", "GZIP"),
+                ("field_delimiter", "|"),
+                ("quote", "'"),
+                ("skip_header", "1"),
+                ("type", "CSV"),
+            ]
+            .into_iter()
+            .collect()
+        );
+
+        assert!(FileFormatOptions::parquet()
+            .skip_header(1)
+            .validate()
+            .is_err());
+        assert!(FileFormatOptions::parquet()
+            .compression(Compression::Zstd)
+            .to_unload_map()
+            .is_ok());
+        assert!(FileFormatOptions::csv()
+            .compression(Compression::Snappy)
+            .to_unload_map()
+            .is_err());
+        assert!(FileFormatOptions::parquet()
+            .compression(Compression::Zstd)
+            .validate()
+            .is_err());
+        assert!(FileFormatOptions::tsv().quote("'").validate().is_err());
+        assert!(FileFormatOptions::csv().quote("''").validate().is_err());
+        assert!(FileFormatOptions::ndjson()
+            .compression(Compression::Zstd)
+            .validate()
+            .is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn unload_options() -> Result<()> {
+        let options = UnloadOptions::new()
+            .max_file_size(64 << 20)
+            .single(false)
+            .to_map()?;
+        assert_eq!(
+            as_str_map(&options),
+            vec![("max_file_size", "67108864"), ("single", "false")]
+                .into_iter()
+                .collect()
+        );
+        assert!(UnloadOptions::new().max_file_size(0).validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn copy_options() -> Result<()> {
+        let options = CopyOptions::new()
+            .purge(true)
+            .size_limit(1000)
+            .on_error(OnError::Continue)
+            .to_map()?;
+        assert_eq!(
+            as_str_map(&options),
+            vec![
+                ("on_error", "continue"),
+                ("purge", "true"),
+                ("size_limit", "1000"),
+            ]
+            .into_iter()
+            .collect()
+        );
+        assert!(CopyOptions::new().max_files(0).validate().is_err());
+        assert!(CopyOptions::new()
+            .purge(true)
+            .force(true)
+            .max_files(2)
+            .validate()
+            .is_ok());
+        Ok(())
+    }
+}
@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub use databend_client::error::ErrorKind;
+
 #[derive(Debug)]
 pub struct ConvertError {
     target: &'static str,
@@ -43,7 +45,7 @@ pub enum Error {
     BadArgument(String),
     InvalidResponse(String),
     Api(databend_client::error::Error),
-    #[cfg(feature = "flight-sql")]
+    #[cfg(feature = "arrow-ipc")]
     Arrow(arrow_schema::ArrowError),
     Convert(ConvertError),
 }
@@ -58,7 +60,7 @@ impl std::fmt::Display for Error {
 
             Error::BadArgument(msg) => write!(f, "BadArgument: {}", msg),
             Error::InvalidResponse(msg) => write!(f, "ResponseError: {}", msg),
-            #[cfg(feature = "flight-sql")]
+            #[cfg(feature = "arrow-ipc")]
             Error::Arrow(e) => {
                 let msg = match e {
                     arrow_schema::ArrowError::IoError(err) => {
@@ -86,6 +88,36 @@ impl std::fmt::Display for Error {
 
 impl std::error::Error for Error {}
 
+impl Error {
+    /// Kind of the failure for branching and retries, errors of FlightSQL
+    /// are classified by the code in the message like `Code: 1025`.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Api(e) => e.kind(),
+            Error::Transport(_) => ErrorKind::Transport,
+            // local files, not the connection
+            Error::IO(_) => ErrorKind::Other,
+            Error::Protocol(msg) => code_in_message(msg)
+                .map(ErrorKind::from_code)
+                .unwrap_or(ErrorKind::Other),
+            #[cfg(feature = "arrow-ipc")]
+            Error::Arrow(arrow_schema::ArrowError::IoError(msg)) => code_in_message(msg)
+                .map(ErrorKind::from_code)
+                .unwrap_or(ErrorKind::Other),
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+fn code_in_message(msg: &str) -> Option<u16> {
+    let start = msg.find("Code: ")? + "Code: ".len();
+    let digits: String = msg[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
 pub type Result<T, E = Error> = core::result::Result<T, E>;
 
 impl From<url::ParseError> for Error {
@@ -150,7 +182,7 @@ impl From<tonic::transport::Error> for Error {
     }
 }
 
-#[cfg(feature = "flight-sql")]
+#[cfg(feature = "arrow-ipc")]
 impl From<arrow_schema::ArrowError> for Error {
     fn from(e: arrow_schema::ArrowError) -> Self {
         Error::Arrow(e)
@@ -180,3 +212,20 @@ impl From<ConvertError> for Error {
         Error::Convert(e)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn flight_error_kind() {
+        let err = Error::Protocol(
+            "status: Internal, message: \"Code: 1025, Text = Unknown table 't'.\"".to_string(),
+        );
+        assert_eq!(err.kind(), ErrorKind::UnknownTable);
+        assert_eq!(
+            Error::Protocol("Code: x".to_string()).kind(),
+            ErrorKind::Other
+        );
+    }
+}
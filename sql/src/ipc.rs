@@ -0,0 +1,424 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Results in the arrow IPC stream format, to ship them between processes
+//! or cache them on disk with types preserved.
+
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use arrow::ipc::reader::StreamReader;
+use arrow::ipc::writer::StreamWriter;
+use arrow::record_batch::RecordBatch;
+use arrow_array::builder::{
+    BooleanBuilder, Date32Builder, Decimal128Builder, Decimal256Builder, Float32Builder,
+    Float64Builder, Int16Builder, Int32Builder, Int64Builder, Int8Builder, StringBuilder,
+    TimestampMicrosecondBuilder, UInt16Builder, UInt32Builder, UInt64Builder, UInt8Builder,
+};
+use arrow_array::{ArrayRef, NullArray};
+use arrow_schema::{
+    DataType as ArrowDataType, Field as ArrowField, Schema as ArrowSchema,
+    SchemaRef as ArrowSchemaRef, TimeUnit,
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::error::{ConvertError, Error, Result};
+use crate::rows::{Row, RowIterator, Rows};
+use crate::schema::{DataType, DecimalDataType, Field, NumberDataType, Schema};
+use crate::value::{NumberValue, Value};
+
+/// Rows per batch when converted from rows.
+pub const BATCH_SIZE: usize = 8192;
+
+/// Results as arrow record batches, with the schema known before the
+/// first batch arrives. `next()` is cancellation safe, rows of a partial
+/// batch are kept.
+pub struct RecordBatchIterator {
+    schema: ArrowSchemaRef,
+    it: Pin<Box<dyn Stream<Item = Result<RecordBatch>> + Send>>,
+}
+
+impl RecordBatchIterator {
+    pub fn new(
+        schema: ArrowSchemaRef,
+        it: Pin<Box<dyn Stream<Item = Result<RecordBatch>> + Send>>,
+    ) -> Self {
+        Self { schema, it }
+    }
+
+    /// Convert rows to batches of `batch_size` as they arrive, the last one
+    /// could be smaller.
+    pub fn from_rows(rows: RowIterator, batch_size: usize) -> Result<Self> {
+        let schema = Arc::new(ArrowSchema::try_from(rows.schema().as_ref())?);
+        let batches = RowBatches {
+            rows,
+            schema: schema.clone(),
+            batch: Vec::with_capacity(batch_size),
+            batch_size: batch_size.max(1),
+            finished: false,
+        };
+        Ok(Self::new(schema, Box::pin(batches)))
+    }
+
+    pub fn schema(&self) -> ArrowSchemaRef {
+        self.schema.clone()
+    }
+}
+
+impl Stream for RecordBatchIterator {
+    type Item = Result<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.it).poll_next(cx)
+    }
+}
+
+struct RowBatches {
+    rows: RowIterator,
+    schema: ArrowSchemaRef,
+    batch: Vec<Row>,
+    batch_size: usize,
+    finished: bool,
+}
+
+impl RowBatches {
+    fn take_batch(&mut self) -> Result<RecordBatch> {
+        let batch = std::mem::replace(&mut self.batch, Vec::with_capacity(self.batch_size));
+        rows_to_batch(&self.schema, &batch)
+    }
+}
+
+impl Stream for RowBatches {
+    type Item = Result<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.finished {
+            return Poll::Ready(None);
+        }
+        loop {
+            match Pin::new(&mut self.rows).poll_next(cx) {
+                Poll::Ready(Some(Ok(row))) => {
+                    self.batch.push(row);
+                    if self.batch.len() >= self.batch_size {
+                        return Poll::Ready(Some(self.take_batch()));
+                    }
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => {
+                    self.finished = true;
+                    if self.batch.is_empty() {
+                        return Poll::Ready(None);
+                    }
+                    return Poll::Ready(Some(self.take_batch()));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Write all rows to the writer in arrow IPC stream format,
+/// returns the number of rows written.
+pub async fn write_ipc<W>(rows: RowIterator, writer: &mut W) -> Result<usize>
+where
+    W: AsyncWrite + Unpin,
+{
+    let mut batches = RecordBatchIterator::from_rows(rows, BATCH_SIZE)?;
+    // encoded to memory batch by batch, since the arrow writer is blocking
+    let mut ipc = StreamWriter::try_new(Vec::new(), &batches.schema())?;
+    let mut total = 0;
+    while let Some(batch) = batches.next().await {
+        let batch = batch?;
+        total += batch.num_rows();
+        ipc.write(&batch)?;
+        let buf = std::mem::take(ipc.get_mut());
+        writer.write_all(&buf).await?;
+    }
+    ipc.finish()?;
+    let buf = std::mem::take(ipc.get_mut());
+    writer.write_all(&buf).await?;
+    writer.flush().await?;
+    Ok(total)
+}
+
+/// Read rows written by `write_ipc`, the whole stream is buffered
+/// in memory and decoded lazily batch by batch.
+pub async fn read_ipc<R>(reader: &mut R) -> Result<RowIterator>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await?;
+    let ipc = StreamReader::try_new(std::io::Cursor::new(buf), None)?;
+    let schema = Arc::new(Schema::try_from(ipc.schema())?);
+    let rows = ipc.flat_map(
+        |batch| match batch.map_err(Error::from).and_then(Rows::try_from) {
+            Ok(rows) => rows.into_iter().map(Ok).collect::<Vec<_>>(),
+            Err(e) => vec![Err(e)],
+        },
+    );
+    Ok(RowIterator::new(schema, Box::pin(tokio_stream::iter(rows))))
+}
+
+impl TryFrom<&Schema> for ArrowSchema {
+    type Error = Error;
+
+    fn try_from(schema: &Schema) -> Result<Self> {
+        let fields = schema
+            .fields()
+            .iter()
+            .map(ArrowField::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(ArrowSchema::new(fields))
+    }
+}
+
+impl TryFrom<&Field> for ArrowField {
+    type Error = Error;
+
+    fn try_from(field: &Field) -> Result<Self> {
+        let (data_type, nullable) = match field.data_type {
+            DataType::Nullable(ref inner) => (inner.as_ref(), true),
+            DataType::Null => (&field.data_type, true),
+            ref dt => (dt, false),
+        };
+        let data_type = match data_type {
+            DataType::Null => ArrowDataType::Null,
+            DataType::Boolean => ArrowDataType::Boolean,
+            DataType::Number(n) => match n {
+                NumberDataType::UInt8 => ArrowDataType::UInt8,
+                NumberDataType::UInt16 => ArrowDataType::UInt16,
+                NumberDataType::UInt32 => ArrowDataType::UInt32,
+                NumberDataType::UInt64 => ArrowDataType::UInt64,
+                NumberDataType::Int8 => ArrowDataType::Int8,
+                NumberDataType::Int16 => ArrowDataType::Int16,
+                NumberDataType::Int32 => ArrowDataType::Int32,
+                NumberDataType::Int64 => ArrowDataType::Int64,
+                NumberDataType::Float32 => ArrowDataType::Float32,
+                NumberDataType::Float64 => ArrowDataType::Float64,
+            },
+            DataType::Decimal(DecimalDataType::Decimal128(size)) => {
+                ArrowDataType::Decimal128(size.precision, size.scale as i8)
+            }
+            DataType::Decimal(DecimalDataType::Decimal256(size)) => {
+                ArrowDataType::Decimal256(size.precision, size.scale as i8)
+            }
+            DataType::Timestamp => ArrowDataType::Timestamp(TimeUnit::Microsecond, None),
+            DataType::Date => ArrowDataType::Date32,
+            DataType::Nullable(_) => {
+                return Err(Error::Parsing(format!(
+                    "Unsupported nested nullable type: {}",
+                    field.data_type
+                )))
+            }
+            // values of the other types are kept as strings
+            _ => ArrowDataType::Utf8,
+        };
+        Ok(ArrowField::new(&field.name, data_type, nullable))
+    }
+}
+
+macro_rules! build_column {
+    ($builder:expr, $rows:expr, $idx:expr, $target:literal, $pat:pat => $v:expr) => {{
+        let mut builder = $builder;
+        for row in $rows {
+            match &row.values()[$idx] {
+                Value::Null => builder.append_null(),
+                $pat => builder.append_value($v),
+                v => return Err(ConvertError::new($target, format!("{:?}", v)).into()),
+            }
+        }
+        Arc::new(builder.finish()) as ArrayRef
+    }};
+}
+
+/// Convert rows to a record batch of the arrow schema converted from theirs.
+pub fn rows_to_batch(schema: &ArrowSchemaRef, rows: &[Row]) -> Result<RecordBatch> {
+    let width = schema.fields().len();
+    if let Some((n, row)) = rows.iter().enumerate().find(|(_, row)| row.len() != width) {
+        return Err(Error::BadArgument(format!(
+            "row {} has {} columns, but the schema has {}",
+            n,
+            row.len(),
+            width
+        )));
+    }
+    let mut columns = Vec::with_capacity(schema.fields().len());
+    for (i, field) in schema.fields().iter().enumerate() {
+        let len = rows.len();
+        let column = match field.data_type() {
+            ArrowDataType::Null => Arc::new(NullArray::new(len)) as ArrayRef,
+            ArrowDataType::Boolean => build_column!(
+                BooleanBuilder::with_capacity(len), rows, i, "bool",
+                Value::Boolean(v) => *v
+            ),
+            ArrowDataType::UInt8 => build_column!(
+                UInt8Builder::with_capacity(len), rows, i, "uint8",
+                Value::Number(NumberValue::UInt8(v)) => *v
+            ),
+            ArrowDataType::UInt16 => build_column!(
+                UInt16Builder::with_capacity(len), rows, i, "uint16",
+                Value::Number(NumberValue::UInt16(v)) => *v
+            ),
+            ArrowDataType::UInt32 => build_column!(
+                UInt32Builder::with_capacity(len), rows, i, "uint32",
+                Value::Number(NumberValue::UInt32(v)) => *v
+            ),
+            ArrowDataType::UInt64 => build_column!(
+                UInt64Builder::with_capacity(len), rows, i, "uint64",
+                Value::Number(NumberValue::UInt64(v)) => *v
+            ),
+            ArrowDataType::Int8 => build_column!(
+                Int8Builder::with_capacity(len), rows, i, "int8",
+                Value::Number(NumberValue::Int8(v)) => *v
+            ),
+            ArrowDataType::Int16 => build_column!(
+                Int16Builder::with_capacity(len), rows, i, "int16",
+                Value::Number(NumberValue::Int16(v)) => *v
+            ),
+            ArrowDataType::Int32 => build_column!(
+                Int32Builder::with_capacity(len), rows, i, "int32",
+                Value::Number(NumberValue::Int32(v)) => *v
+            ),
+            ArrowDataType::Int64 => build_column!(
+                Int64Builder::with_capacity(len), rows, i, "int64",
+                Value::Number(NumberValue::Int64(v)) => *v
+            ),
+            ArrowDataType::Float32 => build_column!(
+                Float32Builder::with_capacity(len), rows, i, "float32",
+                Value::Number(NumberValue::Float32(v)) => *v
+            ),
+            ArrowDataType::Float64 => build_column!(
+                Float64Builder::with_capacity(len), rows, i, "float64",
+                Value::Number(NumberValue::Float64(v)) => *v
+            ),
+            ArrowDataType::Decimal128(p, s) => build_column!(
+                Decimal128Builder::with_capacity(len).with_precision_and_scale(*p, *s)?,
+                rows, i, "Decimal128",
+                Value::Number(NumberValue::Decimal128(v, _)) => *v
+            ),
+            ArrowDataType::Decimal256(p, s) => build_column!(
+                Decimal256Builder::with_capacity(len).with_precision_and_scale(*p, *s)?,
+                rows, i, "Decimal256",
+                Value::Number(NumberValue::Decimal256(v, _)) => *v
+            ),
+            ArrowDataType::Timestamp(_, _) => build_column!(
+                TimestampMicrosecondBuilder::with_capacity(len), rows, i, "timestamp",
+                Value::Timestamp(v) => *v
+            ),
+            ArrowDataType::Date32 => build_column!(
+                Date32Builder::with_capacity(len), rows, i, "date",
+                Value::Date(v) => *v
+            ),
+            // nested values are written as their text
+            _ => {
+                let mut builder = StringBuilder::with_capacity(len, len * 16);
+                for row in rows {
+                    match &row.values()[i] {
+                        Value::Null => builder.append_null(),
+                        Value::String(v) => builder.append_value(v),
+                        v => builder.append_value(v.to_string()),
+                    }
+                }
+                Arc::new(builder.finish()) as ArrayRef
+            }
+        };
+        columns.push(column);
+    }
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::DecimalSize;
+
+    #[test]
+    fn batch_roundtrip() -> Result<()> {
+        let schema = Schema::from_vec(vec![
+            Field {
+                name: "id".to_string(),
+                data_type: DataType::Number(NumberDataType::UInt64),
+            },
+            Field {
+                name: "name".to_string(),
+                data_type: DataType::Nullable(Box::new(DataType::String)),
+            },
+            Field {
+                name: "price".to_string(),
+                data_type: DataType::Decimal(DecimalDataType::Decimal128(DecimalSize {
+                    precision: 10,
+                    scale: 2,
+                })),
+            },
+            Field {
+                name: "d".to_string(),
+                data_type: DataType::Date,
+            },
+        ]);
+        let size = DecimalSize {
+            precision: 10,
+            scale: 2,
+        };
+        let rows = vec![
+            Row::from_vec(vec![
+                Value::Number(NumberValue::UInt64(1)),
+                Value::String("a".to_string()),
+                Value::Number(NumberValue::Decimal128(314, size)),
+                Value::Date(19000),
+            ]),
+            Row::from_vec(vec![
+                Value::Number(NumberValue::UInt64(2)),
+                Value::Null,
+                Value::Number(NumberValue::Decimal128(-1, size)),
+                Value::Date(0),
+            ]),
+        ];
+        let arrow_schema = Arc::new(ArrowSchema::try_from(&schema)?);
+        let batch = rows_to_batch(&arrow_schema, &rows)?;
+
+        let mut ipc = StreamWriter::try_new(Vec::new(), &arrow_schema)?;
+        ipc.write(&batch)?;
+        ipc.finish()?;
+        let buf = ipc.into_inner()?;
+        let mut reader = StreamReader::try_new(std::io::Cursor::new(buf), None)?;
+        let batch = reader.next().unwrap()?;
+        let decoded = Rows::try_from(batch)?;
+        assert_eq!(
+            decoded
+                .rows()
+                .iter()
+                .map(|r| r.values().to_vec())
+                .collect::<Vec<_>>(),
+            rows.iter().map(|r| r.values().to_vec()).collect::<Vec<_>>()
+        );
+
+        let bad = vec![Row::from_vec(vec![
+            Value::String("x".to_string()),
+            Value::Null,
+            Value::Null,
+            Value::Null,
+        ])];
+        assert!(rows_to_batch(&arrow_schema, &bad).is_err());
+
+        let short = vec![Row::from_vec(vec![Value::Number(NumberValue::UInt64(1))])];
+        assert!(matches!(
+            rows_to_batch(&arrow_schema, &short),
+            Err(Error::BadArgument(_))
+        ));
+        Ok(())
+    }
+}
@@ -12,16 +12,28 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod builder;
+pub mod copy;
 pub mod error;
 pub mod from_row;
+#[cfg(feature = "arrow-ipc")]
+pub mod ipc;
+mod ndjson;
+pub mod quote;
 pub mod rows;
 pub mod schema;
+pub mod ser;
+mod split;
+pub mod template;
+pub mod time_travel;
 pub mod value;
 
+pub use split::{find_placeholders, split_statements, Statement};
+
 #[doc(hidden)]
 pub mod _macro_internal {
     pub use crate::error::{Error, Result};
-    pub use crate::rows::{Row, RowIterator};
+    pub use crate::rows::{NamedColumns, Row, RowIterator};
     pub use crate::schema::Schema;
     pub use crate::value::Value;
 }
@@ -0,0 +1,348 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Convert rows from and to JSON Lines, one object keyed by field names per line.
+
+use std::io::{BufRead, Write};
+
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+use tokio_stream::StreamExt;
+
+use crate::error::{ConvertError, Error, Result};
+use crate::rows::{Row, RowIterator, Rows};
+use crate::schema::{DataType, Schema, SchemaRef};
+use crate::ser::{NonFinitePolicy, SerializeOptions};
+use crate::value::Value;
+
+impl Rows {
+    /// Read rows of the schema, missing fields are read as null.
+    /// NaN and infinite floats written as strings are accepted.
+    pub fn from_ndjson<R: BufRead>(reader: R, schema: SchemaRef) -> Result<Self> {
+        Self::from_ndjson_with_policy(reader, schema, NonFinitePolicy::String)
+    }
+
+    /// Read rows with the policy applied to NaN and infinite floats.
+    pub fn from_ndjson_with_policy<R: BufRead>(
+        reader: R,
+        schema: SchemaRef,
+        policy: NonFinitePolicy,
+    ) -> Result<Self> {
+        let mut rows = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut object = match serde_json::from_str(&line)? {
+                serde_json::Value::Object(object) => object,
+                other => {
+                    return Err(ConvertError::new("ndjson object", other.to_string()).into());
+                }
+            };
+            let mut values = Vec::with_capacity(schema.fields().len());
+            for field in schema.fields() {
+                let value = object
+                    .remove(&field.name)
+                    .unwrap_or(serde_json::Value::Null);
+                let value = json_to_value(&field.data_type, value)?;
+                values.push(policy.apply(value)?);
+            }
+            rows.push(Row::from_vec(values));
+        }
+        Ok(Self::new(schema, rows))
+    }
+
+    /// Write rows with default `SerializeOptions` except NaN and infinite floats
+    /// are written as strings, so that they could be read back with `from_ndjson`
+    /// without losing precision.
+    pub fn to_ndjson<W: Write>(&self, writer: W) -> Result<()> {
+        let options = SerializeOptions {
+            non_finite: NonFinitePolicy::String,
+            ..Default::default()
+        };
+        self.to_ndjson_with_options(writer, options)
+    }
+
+    pub fn to_ndjson_with_options<W: Write>(
+        &self,
+        mut writer: W,
+        options: SerializeOptions,
+    ) -> Result<()> {
+        let fields = self.schema().fields().to_vec();
+        for row in self.rows() {
+            let mut serializer = serde_json::Serializer::new(&mut writer);
+            let mut map = serializer.serialize_map(Some(fields.len()))?;
+            for (field, value) in fields.iter().zip(row.values()) {
+                map.serialize_entry(&field.name, &value.with_options(options))?;
+            }
+            map.end()?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Row {
+    /// Read a row from a JSON array of its values, as written by `to_json_array`.
+    pub fn from_json_array(schema: &Schema, line: &str) -> Result<Self> {
+        let values = match serde_json::from_str(line)? {
+            serde_json::Value::Array(values) => values,
+            other => return Err(ConvertError::new("json array", other.to_string()).into()),
+        };
+        if values.len() != schema.fields().len() {
+            return Err(ConvertError::new("json array", line.to_string())
+                .with_message(format!(
+                    "expect {} values, got {}",
+                    schema.fields().len(),
+                    values.len()
+                ))
+                .into());
+        }
+        let values = schema
+            .fields()
+            .iter()
+            .zip(values)
+            .map(|(field, value)| json_to_value(&field.data_type, value))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Row::from_vec(values))
+    }
+
+    /// Write the row as a JSON array in one line, without losing precision.
+    pub fn to_json_array(&self) -> Result<String> {
+        let options = SerializeOptions {
+            non_finite: NonFinitePolicy::String,
+            ..Default::default()
+        };
+        Ok(serde_json::to_string(&self.with_options(options))?)
+    }
+}
+
+impl RowIterator {
+    /// Convert all rows, the ones failed to convert are written to the
+    /// quarantine sink instead, one JSON object per line like
+    /// `{"row":3,"error":"...","values":[...]}`, so that a bad record does
+    /// not stall the whole pipeline. Errors of the query itself are returned.
+    pub async fn try_collect_quarantine<T, W>(mut self, mut sink: W) -> Result<Vec<T>>
+    where
+        T: TryFrom<Row>,
+        T::Error: std::fmt::Display,
+        W: Write,
+    {
+        #[derive(Serialize)]
+        struct Quarantined<V> {
+            row: usize,
+            error: String,
+            values: V,
+        }
+
+        let options = SerializeOptions {
+            non_finite: NonFinitePolicy::String,
+            ..Default::default()
+        };
+        let mut ret = Vec::new();
+        let mut idx = 0;
+        while let Some(row) = self.next().await {
+            let row = row?;
+            match T::try_from(row.clone()) {
+                Ok(v) => ret.push(v),
+                Err(e) => {
+                    let quarantined = Quarantined {
+                        row: idx,
+                        error: e.to_string(),
+                        values: row.with_options(options),
+                    };
+                    serde_json::to_writer(&mut sink, &quarantined)?;
+                    sink.write_all(b"\n")?;
+                }
+            }
+            idx += 1;
+        }
+        sink.flush()?;
+        Ok(ret)
+    }
+}
+
+fn json_to_value(data_type: &DataType, value: serde_json::Value) -> Result<Value> {
+    match (data_type, value) {
+        (_, serde_json::Value::Null) => Ok(Value::Null),
+        (DataType::Nullable(inner), value) => json_to_value(inner, value),
+        (DataType::Boolean, serde_json::Value::Bool(b)) => Ok(Value::Boolean(b)),
+        (DataType::Boolean, serde_json::Value::String(s)) => Ok(Value::Boolean(s.parse()?)),
+        (DataType::Timestamp, serde_json::Value::Number(n)) => match n.as_i64() {
+            Some(ts) => Ok(Value::Timestamp(ts)),
+            None => Err(ConvertError::new("timestamp", n.to_string()).into()),
+        },
+        (DataType::Date, serde_json::Value::Number(n)) => match n.as_i64() {
+            Some(d) => Ok(Value::Date(d as i32)),
+            None => Err(ConvertError::new("date", n.to_string()).into()),
+        },
+        (DataType::Timestamp, serde_json::Value::String(s)) => {
+            let ts = chrono::NaiveDateTime::parse_from_str(&s, "%Y-%m-%d %H:%M:%S%.f")?;
+            Ok(Value::Timestamp(ts.timestamp_micros()))
+        }
+        (
+            DataType::Number(_) | DataType::Decimal(_) | DataType::Date,
+            serde_json::Value::String(s),
+        ) => Value::try_from((data_type, s.as_str())),
+        (DataType::Number(_) | DataType::Decimal(_), serde_json::Value::Number(n)) => {
+            Value::try_from((data_type, n.to_string().as_str()))
+        }
+        (DataType::String, serde_json::Value::String(s)) => Ok(Value::String(s)),
+        (DataType::Boolean | DataType::Number(_) | DataType::Decimal(_), value) => {
+            Err(Error::from(ConvertError::new("value", value.to_string())))
+        }
+        (DataType::Variant, value) => Ok(Value::Variant(value.to_string())),
+        (DataType::Bitmap, serde_json::Value::String(s)) => Ok(Value::Bitmap(s)),
+        (DataType::Geometry, serde_json::Value::String(s)) => Ok(Value::Geometry(s)),
+        (DataType::EmptyArray, serde_json::Value::Array(_)) => Ok(Value::Array(vec![])),
+        (DataType::Array(inner), serde_json::Value::Array(vals)) => Ok(Value::Array(
+            vals.into_iter()
+                .map(|v| json_to_value(inner, v))
+                .collect::<Result<_>>()?,
+        )),
+        (DataType::Tuple(inner), serde_json::Value::Array(vals)) if inner.len() == vals.len() => {
+            Ok(Value::Tuple(
+                inner
+                    .iter()
+                    .zip(vals)
+                    .map(|(t, v)| json_to_value(t, v))
+                    .collect::<Result<_>>()?,
+            ))
+        }
+        (DataType::EmptyMap, serde_json::Value::Object(_)) => Ok(Value::Map(vec![])),
+        (DataType::Map(inner), serde_json::Value::Object(entries)) => match inner.as_ref() {
+            DataType::Tuple(kv) if kv.len() == 2 => Ok(Value::Map(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| {
+                        Ok((
+                            Value::try_from((&kv[0], k.as_str()))?,
+                            json_to_value(&kv[1], v)?,
+                        ))
+                    })
+                    .collect::<Result<_>>()?,
+            )),
+            _ => Err(ConvertError::new("map", format!("{:?}", entries)).into()),
+        },
+        // nested values written as text are parsed as returned by the server
+        (
+            DataType::Array(_)
+            | DataType::EmptyArray
+            | DataType::Map(_)
+            | DataType::EmptyMap
+            | DataType::Tuple(_),
+            serde_json::Value::String(s),
+        ) => Value::try_from((data_type, s.as_str())),
+        (_, serde_json::Value::String(s)) => Ok(Value::String(s)),
+        (_, value) => Ok(Value::String(value.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::schema::{DecimalDataType, DecimalSize, Field, NumberDataType, Schema};
+    use crate::value::NumberValue;
+
+    #[test]
+    fn ndjson_round_trip() -> Result<()> {
+        let field = |name: &str, data_type| Field {
+            name: name.to_string(),
+            data_type,
+        };
+        let schema = Arc::new(Schema::from_vec(vec![
+            field("id", DataType::Number(NumberDataType::UInt64)),
+            field("name", DataType::Nullable(Box::new(DataType::String))),
+            field("ok", DataType::Boolean),
+            field(
+                "amount",
+                DataType::Decimal(DecimalDataType::Decimal128(DecimalSize {
+                    precision: 10,
+                    scale: 2,
+                })),
+            ),
+            field("ts", DataType::Timestamp),
+            field("d", DataType::Date),
+        ]));
+        let input = r#"{"id":1,"name":"a","ok":true,"amount":"1.50","ts":"2023-01-02 03:04:05.000006","d":"2023-01-02"}
+{"id":2,"ok":false,"amount":3,"ts":"2023-01-02 03:04:05","d":19000}
+"#;
+        let rows = Rows::from_ndjson(input.as_bytes(), schema.clone())?;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows.rows()[1].values()[1], Value::Null);
+        assert_eq!(
+            rows.rows()[1].values()[3],
+            Value::Number(NumberValue::Decimal128(
+                300,
+                DecimalSize {
+                    precision: 10,
+                    scale: 2
+                }
+            ))
+        );
+
+        let mut output = Vec::new();
+        rows.to_ndjson(&mut output)?;
+        let again = Rows::from_ndjson(output.as_slice(), schema.clone())?;
+        assert_eq!(
+            rows.rows().iter().map(|r| r.values()).collect::<Vec<_>>(),
+            again.rows().iter().map(|r| r.values()).collect::<Vec<_>>()
+        );
+        let float_schema = Arc::new(Schema::from_vec(vec![field(
+            "f",
+            DataType::Number(NumberDataType::Float64),
+        )]));
+        let floats = Rows::new(
+            float_schema.clone(),
+            vec![
+                Row::from_vec(vec![Value::Number(NumberValue::Float64(f64::INFINITY))]),
+                Row::from_vec(vec![Value::Number(NumberValue::Float64(-0.5))]),
+            ],
+        );
+        let mut buf = Vec::new();
+        floats.to_ndjson(&mut buf)?;
+        assert_eq!(buf, b"{\"f\":\"Infinity\"}\n{\"f\":-0.5}\n");
+        let again = Rows::from_ndjson(buf.as_slice(), float_schema.clone())?;
+        assert_eq!(
+            again.rows()[0].values()[0],
+            Value::Number(NumberValue::Float64(f64::INFINITY))
+        );
+        let nulls = Rows::from_ndjson_with_policy(
+            buf.as_slice(),
+            float_schema.clone(),
+            NonFinitePolicy::Null,
+        )?;
+        assert_eq!(nulls.rows()[0].values()[0], Value::Null);
+        assert!(Rows::from_ndjson_with_policy(
+            buf.as_slice(),
+            float_schema,
+            NonFinitePolicy::Error
+        )
+        .is_err());
+
+        let row = &rows.rows()[0];
+        let line = row.to_json_array()?;
+        assert_eq!(Row::from_json_array(&schema, &line)?.values(), row.values());
+        assert!(Row::from_json_array(&schema, "[1]").is_err());
+
+        assert!(String::from_utf8(output).unwrap().starts_with(
+            r#"{"id":1,"name":"a","ok":true,"amount":"1.50","ts":"2023-01-02 03:04:05.000006""#
+        ));
+        Ok(())
+    }
+}
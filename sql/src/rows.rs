@@ -12,24 +12,33 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeMap;
+use std::future::Future;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::task::Context;
 use std::task::Poll;
+use std::time::Duration;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tokio_stream::{Stream, StreamExt};
 
-#[cfg(feature = "flight-sql")]
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "arrow-ipc")]
 use arrow::record_batch::RecordBatch;
 
 use crate::error::{Error, Result};
-use crate::schema::SchemaRef;
-use crate::value::Value;
+use crate::schema::{Schema, SchemaRef};
+use crate::value::{NumericMode, Value};
 
 #[derive(Clone, Debug)]
+#[non_exhaustive]
 pub enum RowWithProgress {
     Row(Row),
     Progress(QueryProgress),
+    /// The last item once all rows are returned.
+    Stats(QueryStats),
 }
 
 #[derive(Deserialize, Clone, Debug, Default)]
@@ -79,6 +88,35 @@ impl From<databend_client::response::Progresses> for QueryProgress {
     }
 }
 
+/// Resource usage of a finished query, for logging per query.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QueryStats {
+    pub scan_rows: usize,
+    pub scan_bytes: usize,
+    pub write_rows: usize,
+    pub write_bytes: usize,
+    pub result_rows: usize,
+    pub result_bytes: usize,
+    /// Reported by the server with REST API, or measured by the client
+    /// since the query was sent with FlightSQL.
+    pub running_time: Duration,
+}
+
+impl From<&databend_client::response::QueryStats> for QueryStats {
+    fn from(stats: &databend_client::response::QueryStats) -> Self {
+        let progresses = &stats.progresses;
+        Self {
+            scan_rows: progresses.scan_progress.rows,
+            scan_bytes: progresses.scan_progress.bytes,
+            write_rows: progresses.write_progress.rows,
+            write_bytes: progresses.write_progress.bytes,
+            result_rows: progresses.result_progress.rows,
+            result_bytes: progresses.result_progress.bytes,
+            running_time: Duration::from_secs_f64(stats.running_time_ms.max(0.0) / 1000.0),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct Row(Vec<Value>);
 
@@ -86,9 +124,17 @@ impl TryFrom<(SchemaRef, &Vec<String>)> for Row {
     type Error = Error;
 
     fn try_from((schema, data): (SchemaRef, &Vec<String>)) -> Result<Self> {
+        Self::try_from((schema, data, NumericMode::default()))
+    }
+}
+
+impl TryFrom<(SchemaRef, &Vec<String>, NumericMode)> for Row {
+    type Error = Error;
+
+    fn try_from((schema, data, mode): (SchemaRef, &Vec<String>, NumericMode)) -> Result<Self> {
         let mut values: Vec<Value> = Vec::new();
         for (i, field) in schema.fields().iter().enumerate() {
-            values.push(Value::try_from((&field.data_type, data[i].as_str()))?);
+            values.push(Value::try_from((&field.data_type, data[i].as_str(), mode))?);
         }
         Ok(Self(values))
     }
@@ -122,9 +168,56 @@ impl IntoIterator for Row {
 }
 
 #[derive(Clone, Debug, Default)]
-pub struct Rows(Vec<Row>);
+pub struct Rows {
+    schema: SchemaRef,
+    rows: Vec<Row>,
+}
+
+impl Rows {
+    pub fn new(schema: SchemaRef, rows: Vec<Row>) -> Self {
+        Self { schema, rows }
+    }
+
+    /// Rows with an empty schema, e.g. built by hand.
+    pub fn from_vec(rows: Vec<Row>) -> Self {
+        Self {
+            schema: SchemaRef::default(),
+            rows,
+        }
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    /// Convert all rows, collecting conversion errors instead of failing
+    /// on the first one, see [`PartialRows`].
+    pub fn try_collect_partial<T>(self, max_errors: usize) -> PartialRows<T>
+    where
+        T: TryFrom<Row>,
+        T::Error: std::fmt::Display,
+    {
+        let mut ret = PartialRows::new(max_errors);
+        for row in self.rows {
+            ret.push(T::try_from(row));
+        }
+        ret
+    }
+}
 
-#[cfg(feature = "flight-sql")]
+#[cfg(feature = "arrow-ipc")]
 impl TryFrom<RecordBatch> for Rows {
     type Error = Error;
     fn try_from(batch: RecordBatch) -> Result<Self> {
@@ -140,7 +233,10 @@ impl TryFrom<RecordBatch> for Rows {
             }
             rows.push(Row(values));
         }
-        Ok(Self(rows))
+        Ok(Self {
+            schema: Arc::new(schema.try_into()?),
+            rows,
+        })
     }
 }
 
@@ -149,15 +245,206 @@ impl IntoIterator for Rows {
     type IntoIter = std::vec::IntoIter<Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+        self.rows.into_iter()
+    }
+}
+
+/// Position of a result being consumed, to continue fetching the rest
+/// in another process, formatted as JSON by `to_string` and parsed back
+/// with `parse`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResumeToken {
+    /// Uri of the page being consumed, rows consumed in the page, and
+    /// routing hints of the gateway for the query.
+    RestApi {
+        page_uri: String,
+        offset: usize,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        route_hints: BTreeMap<String, String>,
+    },
+    /// Ticket of the flight, and rows consumed in it.
+    FlightSql { ticket: Vec<u8>, offset: usize },
+}
+
+impl std::fmt::Display for ResumeToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = serde_json::to_string(self).map_err(|_| std::fmt::Error)?;
+        write!(f, "{}", s)
+    }
+}
+
+impl std::str::FromStr for ResumeToken {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        serde_json::from_str(s)
+            .map_err(|e| Error::BadArgument(format!("Invalid resume token: {}", e)))
+    }
+}
+
+/// Resume token shared between a row stream and the iterators wrapping it,
+/// updated by the stream for every row returned, and cleared once all rows
+/// are returned.
+#[derive(Clone, Debug, Default)]
+pub struct ResumeState(Arc<Mutex<Option<ResumeToken>>>);
+
+impl ResumeState {
+    pub fn set(&self, token: Option<ResumeToken>) {
+        *self.0.lock().unwrap() = token;
+    }
+
+    pub fn get(&self) -> Option<ResumeToken> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+pub type CancelFuture = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
+/// Handle of the query behind a row iterator, to cancel it on the server.
+///
+/// The query is also killed when the iterator is dropped before all rows are
+/// returned, unless it is kept alive to be resumed later, which is implied
+/// by taking a resume token of the iterator.
+#[derive(Clone)]
+pub struct QueryHandle {
+    query_id: String,
+    cancel: Arc<dyn Fn() -> CancelFuture + Send + Sync>,
+    keep_alive: Arc<AtomicBool>,
+}
+
+impl QueryHandle {
+    pub fn new(query_id: String, cancel: Arc<dyn Fn() -> CancelFuture + Send + Sync>) -> Self {
+        Self {
+            query_id,
+            cancel,
+            keep_alive: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn query_id(&self) -> &str {
+        &self.query_id
+    }
+
+    pub async fn cancel(&self) -> Result<()> {
+        (self.cancel)().await
+    }
+
+    /// Do not kill the query when the iterator is dropped.
+    pub fn keep_alive(&self) {
+        self.keep_alive.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_kept_alive(&self) -> bool {
+        self.keep_alive.load(Ordering::Relaxed)
     }
 }
 
-pub struct RowIterator(Pin<Box<dyn Stream<Item = Result<Row>> + Send>>);
+impl std::fmt::Debug for QueryHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueryHandle")
+            .field("query_id", &self.query_id)
+            .finish()
+    }
+}
+
+/// Rows of a query, fetched as they are consumed.
+///
+/// `next()` is cancellation safe: dropping a pending `next()`, e.g. in a
+/// branch of `tokio::select!` not taken, loses no rows. The page being
+/// fetched is kept in the iterator and returned by the next call. Methods
+/// consuming the iterator like `try_collect` are not, rows collected so far
+/// are dropped with the future.
+pub struct RowIterator {
+    schema: SchemaRef,
+    it: Pin<Box<dyn Stream<Item = Result<Row>> + Send>>,
+    resume: Option<ResumeState>,
+    handle: Option<QueryHandle>,
+}
 
 impl RowIterator {
-    pub fn new(it: Pin<Box<dyn Stream<Item = Result<Row>> + Send>>) -> Self {
-        Self(it)
+    pub fn new(schema: SchemaRef, it: Pin<Box<dyn Stream<Item = Result<Row>> + Send>>) -> Self {
+        Self {
+            schema,
+            it,
+            resume: None,
+            handle: None,
+        }
+    }
+
+    pub fn with_resume(mut self, resume: Option<ResumeState>) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    pub fn with_handle(mut self, handle: Option<QueryHandle>) -> Self {
+        self.handle = handle;
+        self
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    pub fn resume_state(&self) -> Option<ResumeState> {
+        self.resume.clone()
+    }
+
+    /// Token to continue with the rows not returned yet, `None` if the
+    /// connection does not support resuming or all rows are returned.
+    /// The query is still killed when the iterator is dropped, use
+    /// [`RowIterator::detach`] to continue it elsewhere.
+    pub fn resume_token(&self) -> Option<ResumeToken> {
+        self.resume.as_ref().and_then(|r| r.get())
+    }
+
+    /// Keep the query alive when the iterator is dropped, and return the
+    /// token to continue with the rows not returned yet.
+    pub fn detach(self) -> Option<ResumeToken> {
+        if let Some(ref handle) = self.handle {
+            handle.keep_alive();
+        }
+        self.resume_token()
+    }
+
+    /// Handle to cancel the query, `None` if the connection does not
+    /// support it.
+    pub fn handle(&self) -> Option<QueryHandle> {
+        self.handle.clone()
+    }
+
+    /// Keep only the given columns in the given order, both the schema
+    /// and the rows are adjusted.
+    pub fn project(self, columns: &[&str]) -> Result<Self> {
+        let fields = self.schema.fields();
+        let mut indices = Vec::with_capacity(columns.len());
+        for column in columns {
+            let idx = fields
+                .iter()
+                .position(|f| f.name == *column)
+                .ok_or_else(|| Error::BadArgument(format!("Column not found: {}", column)))?;
+            indices.push(idx);
+        }
+        let schema = Schema::from_vec(indices.iter().map(|i| fields[*i].clone()).collect());
+        let rows = self.it.map(move |row| {
+            let row = row?;
+            let values = indices
+                .iter()
+                .map(|i| {
+                    row.0.get(*i).cloned().ok_or_else(|| {
+                        Error::BadArgument(format!(
+                            "Row has {} columns, column {} not found",
+                            row.len(),
+                            i
+                        ))
+                    })
+                })
+                .collect::<Result<_>>()?;
+            Ok(Row(values))
+        });
+        Ok(Self::new(Arc::new(schema), Box::pin(rows))
+            .with_resume(self.resume)
+            .with_handle(self.handle))
     }
 
     pub async fn try_collect<T>(mut self) -> Result<Vec<T>>
@@ -166,36 +453,156 @@ impl RowIterator {
         T::Error: std::fmt::Display,
     {
         let mut ret = Vec::new();
-        while let Some(row) = self.0.next().await {
-            let v = T::try_from(row?).map_err(|e| Error::Parsing(e.to_string()))?;
+        while let Some(row) = self.it.next().await {
+            let v = T::try_from(row?)
+                .map_err(|e| Error::Parsing(format!("row {}: {}", ret.len(), e)))?;
             ret.push(v)
         }
         Ok(ret)
     }
+
+    /// Like `try_collect`, but columns are matched to the fields by name,
+    /// so the order of the columns does not matter and others are ignored.
+    pub async fn try_collect_named<T>(self) -> Result<Vec<T>>
+    where
+        T: TryFrom<Row> + NamedColumns,
+        T::Error: std::fmt::Display,
+    {
+        self.project(T::column_names())?.try_collect().await
+    }
+
+    /// Like `try_collect`, but rows failed to convert are reported in
+    /// [`PartialRows`] instead, errors of the query itself are still returned.
+    pub async fn try_collect_partial<T>(mut self, max_errors: usize) -> Result<PartialRows<T>>
+    where
+        T: TryFrom<Row>,
+        T::Error: std::fmt::Display,
+    {
+        let mut ret = PartialRows::new(max_errors);
+        while let Some(row) = self.it.next().await {
+            ret.push(T::try_from(row?));
+        }
+        Ok(ret)
+    }
+}
+
+/// Structs converted from rows by column names, implemented by
+/// `#[derive(TryFromRow)]` along with the conversion by position.
+pub trait NamedColumns {
+    /// Columns of the fields in order, the field names unless renamed with
+    /// `#[databend(rename = "...")]`.
+    fn column_names() -> &'static [&'static str];
+}
+
+/// A row failed to convert, `row` is the index in the result.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RowError {
+    pub row: usize,
+    pub message: String,
+}
+
+/// Rows converted with `try_collect_partial`, with errors of the failed ones,
+/// only the first `max_errors` errors are kept but all of them are counted.
+#[derive(Debug)]
+pub struct PartialRows<T> {
+    pub rows: Vec<T>,
+    pub errors: Vec<RowError>,
+    pub error_count: usize,
+    max_errors: usize,
+}
+
+impl<T> PartialRows<T> {
+    fn new(max_errors: usize) -> Self {
+        Self {
+            rows: Vec::new(),
+            errors: Vec::new(),
+            error_count: 0,
+            max_errors,
+        }
+    }
+
+    fn push<E: std::fmt::Display>(&mut self, result: std::result::Result<T, E>) {
+        let row = self.rows.len() + self.error_count;
+        match result {
+            Ok(v) => self.rows.push(v),
+            Err(e) => {
+                if self.errors.len() < self.max_errors {
+                    self.errors.push(RowError {
+                        row,
+                        message: e.to_string(),
+                    });
+                }
+                self.error_count += 1;
+            }
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.error_count == 0
+    }
 }
 
 impl Stream for RowIterator {
     type Item = Result<Row>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        Pin::new(&mut self.0).poll_next(cx)
+        Pin::new(&mut self.it).poll_next(cx)
     }
 }
 
-pub struct RowProgressIterator(Pin<Box<dyn Stream<Item = Result<RowWithProgress>> + Send>>);
+/// Rows with progress of a query, `next()` is cancellation safe as that of
+/// [`RowIterator`].
+pub struct RowProgressIterator {
+    it: Pin<Box<dyn Stream<Item = Result<RowWithProgress>> + Send>>,
+    resume: Option<ResumeState>,
+    handle: Option<QueryHandle>,
+}
 
 impl RowProgressIterator {
     pub fn new(it: Pin<Box<dyn Stream<Item = Result<RowWithProgress>> + Send>>) -> Self {
-        Self(it)
+        Self {
+            it,
+            resume: None,
+            handle: None,
+        }
+    }
+
+    pub fn with_resume(mut self, resume: Option<ResumeState>) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    pub fn with_handle(mut self, handle: Option<QueryHandle>) -> Self {
+        self.handle = handle;
+        self
     }
 
-    pub async fn filter_rows(self) -> RowIterator {
-        let rows = self.0.filter_map(|r| match r {
+    /// Same as [`RowIterator::resume_token`].
+    pub fn resume_token(&self) -> Option<ResumeToken> {
+        self.resume.as_ref().and_then(|r| r.get())
+    }
+
+    /// Same as [`RowIterator::detach`].
+    pub fn detach(self) -> Option<ResumeToken> {
+        if let Some(ref handle) = self.handle {
+            handle.keep_alive();
+        }
+        self.resume_token()
+    }
+
+    pub fn handle(&self) -> Option<QueryHandle> {
+        self.handle.clone()
+    }
+
+    pub async fn filter_rows(self, schema: SchemaRef) -> RowIterator {
+        let rows = self.it.filter_map(|r| match r {
             Ok(RowWithProgress::Row(r)) => Some(Ok(r)),
             Ok(_) => None,
             Err(err) => Some(Err(err)),
         });
-        RowIterator(Box::pin(rows))
+        RowIterator::new(schema, Box::pin(rows))
+            .with_resume(self.resume)
+            .with_handle(self.handle)
     }
 }
 
@@ -203,6 +610,144 @@ impl Stream for RowProgressIterator {
     type Item = Result<RowWithProgress>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        Pin::new(&mut self.0).poll_next(cx)
+        Pin::new(&mut self.it).poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::value::NumberValue;
+
+    #[test]
+    fn resume_token_roundtrip() -> Result<()> {
+        let tokens = vec![
+            ResumeToken::RestApi {
+                page_uri: "/v1/query/q1/page/3".to_string(),
+                offset: 10,
+                route_hints: BTreeMap::new(),
+            },
+            ResumeToken::RestApi {
+                page_uri: "/v1/query/q1/page/3".to_string(),
+                offset: 10,
+                route_hints: BTreeMap::from([(
+                    "x-databend-route-hint".to_string(),
+                    "worker-1".to_string(),
+                )]),
+            },
+            ResumeToken::FlightSql {
+                ticket: vec![1, 2, 3],
+                offset: 0,
+            },
+        ];
+        for token in tokens {
+            let s = token.to_string();
+            assert_eq!(s.parse::<ResumeToken>()?, token);
+        }
+        assert!("not a token".parse::<ResumeToken>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn try_collect_partial() {
+        let schema = Arc::new(Schema::default());
+        let rows = (0..5)
+            .map(|i| Row::from_vec(vec![Value::Number(NumberValue::Int32(i))]))
+            .chain(std::iter::once(Row::from_vec(vec![Value::Null])))
+            .collect();
+        let rows = Rows::new(schema, rows);
+        let ret = rows.try_collect_partial::<(i32,)>(1);
+        assert_eq!(ret.rows.len(), 5);
+        assert_eq!(ret.error_count, 1);
+        assert_eq!(ret.errors[0].row, 5);
+        assert!(!ret.is_complete());
+    }
+
+    #[test]
+    fn query_stats_from_response() -> Result<()> {
+        let stats: databend_client::response::QueryStats = serde_json::from_str(
+            r#"{
+                "scan_progress": {"rows": 10, "bytes": 80},
+                "write_progress": {"rows": 0, "bytes": 0},
+                "result_progress": {"rows": 2, "bytes": 16},
+                "running_time_ms": 1500.0
+            }"#,
+        )?;
+        let stats = QueryStats::from(&stats);
+        assert_eq!(stats.scan_rows, 10);
+        assert_eq!(stats.scan_bytes, 80);
+        assert_eq!(stats.result_rows, 2);
+        assert_eq!(stats.running_time, Duration::from_millis(1500));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn project_short_row() -> Result<()> {
+        use crate::schema::{DataType, Field, NumberDataType};
+        let schema = Schema::from_vec(vec![
+            Field::new("a", DataType::Number(NumberDataType::Int32)),
+            Field::new("b", DataType::Number(NumberDataType::Int32)),
+        ]);
+        let rows = vec![
+            Row::from_vec(vec![
+                Value::Number(NumberValue::Int32(1)),
+                Value::Number(NumberValue::Int32(2)),
+            ]),
+            Row::from_vec(vec![Value::Number(NumberValue::Int32(3))]),
+        ];
+        let rows = tokio_stream::iter(rows.into_iter().map(Ok));
+        let mut rows = RowIterator::new(Arc::new(schema), Box::pin(rows)).project(&["b"])?;
+        let row = rows.next().await.unwrap()?;
+        assert_eq!(row.values(), &[Value::Number(NumberValue::Int32(2))]);
+        assert!(matches!(
+            rows.next().await,
+            Some(Err(Error::BadArgument(_)))
+        ));
+        Ok(())
+    }
+
+    /// Ready every other poll, like rows of a page arriving later.
+    struct SlowRows {
+        rows: std::collections::VecDeque<Row>,
+        ready: bool,
+    }
+
+    impl Stream for SlowRows {
+        type Item = Result<Row>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            if !self.ready {
+                self.ready = true;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            self.ready = false;
+            Poll::Ready(self.rows.pop_front().map(Ok))
+        }
+    }
+
+    #[tokio::test]
+    async fn next_cancellation_safe() -> Result<()> {
+        let rows = (0..5)
+            .map(|i| Row::from_vec(vec![Value::Number(NumberValue::Int32(i))]))
+            .collect();
+        let rows = SlowRows { rows, ready: false };
+        let mut rows = RowIterator::new(Arc::new(Schema::default()), Box::pin(rows));
+        let mut ret = Vec::new();
+        let mut cancelled = 0;
+        loop {
+            tokio::select! {
+                biased;
+                // ready on the second poll, dropping the pending `next()`
+                _ = tokio::task::yield_now() => cancelled += 1,
+                row = rows.next() => match row {
+                    Some(row) => ret.push(<(i32,)>::try_from(row?).map_err(Error::Parsing)?.0),
+                    None => break,
+                },
+            }
+        }
+        assert!(cancelled > 0);
+        assert_eq!(ret, vec![0, 1, 2, 3, 4]);
+        Ok(())
     }
 }
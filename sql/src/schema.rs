@@ -14,13 +14,28 @@
 
 use std::sync::Arc;
 
-#[cfg(feature = "flight-sql")]
+#[cfg(feature = "arrow-ipc")]
 use arrow_schema::{DataType as ArrowDataType, Field as ArrowField, SchemaRef as ArrowSchemaRef};
 
 use databend_client::response::SchemaField as APISchemaField;
 
 use crate::error::{Error, Result};
 
+/// Key of the arrow field metadata naming the databend type of the field,
+/// for types without a native arrow counterpart.
+#[cfg(feature = "arrow-ipc")]
+pub(crate) const EXTENSION_KEY: &str = "Extension";
+#[cfg(feature = "arrow-ipc")]
+pub(crate) const ARROW_EXT_TYPE_EMPTY_ARRAY: &str = "EmptyArray";
+#[cfg(feature = "arrow-ipc")]
+pub(crate) const ARROW_EXT_TYPE_EMPTY_MAP: &str = "EmptyMap";
+#[cfg(feature = "arrow-ipc")]
+pub(crate) const ARROW_EXT_TYPE_VARIANT: &str = "Variant";
+#[cfg(feature = "arrow-ipc")]
+pub(crate) const ARROW_EXT_TYPE_BITMAP: &str = "Bitmap";
+#[cfg(feature = "arrow-ipc")]
+pub(crate) const ARROW_EXT_TYPE_GEOMETRY: &str = "Geometry";
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum NumberDataType {
     UInt8,
@@ -56,7 +71,7 @@ impl DecimalDataType {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DataType {
     Null,
     Nothing,
@@ -74,6 +89,8 @@ pub enum DataType {
     Tuple(Vec<DataType>),
     Variant,
     Bitmap,
+    Binary,
+    Geometry,
     // Generic(usize),
 }
 
@@ -85,6 +102,18 @@ impl DataType {
             _ => false,
         }
     }
+
+    pub fn is_nullable(&self) -> bool {
+        matches!(self, DataType::Nullable(_) | DataType::Null)
+    }
+
+    /// Type of the values without `Nullable`.
+    pub fn remove_nullable(&self) -> &DataType {
+        match self {
+            DataType::Nullable(inner) => inner.as_ref(),
+            _ => self,
+        }
+    }
 }
 
 impl std::fmt::Display for DataType {
@@ -127,6 +156,8 @@ impl std::fmt::Display for DataType {
             }
             DataType::Variant => write!(f, "Variant"),
             DataType::Bitmap => write!(f, "Bitmap"),
+            DataType::Binary => write!(f, "Binary"),
+            DataType::Geometry => write!(f, "Geometry"),
         }
     }
 }
@@ -137,7 +168,20 @@ pub struct Field {
     pub data_type: DataType,
 }
 
-#[derive(Debug, Clone)]
+impl Field {
+    pub fn new(name: impl Into<String>, data_type: DataType) -> Self {
+        Self {
+            name: name.into(),
+            data_type,
+        }
+    }
+
+    pub fn is_nullable(&self) -> bool {
+        self.data_type.is_nullable()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct Schema(Vec<Field>);
 
 pub type SchemaRef = Arc<Schema>;
@@ -150,6 +194,15 @@ impl Schema {
     pub fn from_vec(fields: Vec<Field>) -> Self {
         Self(fields)
     }
+
+    /// Position of the first field with the name.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.0.iter().position(|f| f.name == name)
+    }
+
+    pub fn field_with_name(&self, name: &str) -> Option<&Field> {
+        self.0.iter().find(|f| f.name == name)
+    }
 }
 
 impl TryFrom<&TypeDesc<'_>> for DataType {
@@ -172,6 +225,11 @@ impl TryFrom<&TypeDesc<'_>> for DataType {
             "Float32" => DataType::Number(NumberDataType::Float32),
             "Float64" => DataType::Number(NumberDataType::Float64),
             "Decimal" => {
+                if desc.args.len() != 2 {
+                    return Err(Error::Parsing(
+                        "Decimal type must have two arguments".to_string(),
+                    ));
+                }
                 let precision = desc.args[0].name.parse::<u8>()?;
                 let scale = desc.args[1].name.parse::<u8>()?;
 
@@ -224,8 +282,12 @@ impl TryFrom<&TypeDesc<'_>> for DataType {
                 }
                 DataType::Tuple(inner)
             }
+            "EmptyArray" => DataType::EmptyArray,
+            "EmptyMap" => DataType::EmptyMap,
             "Variant" => DataType::Variant,
             "Bitmap" => DataType::Bitmap,
+            "Binary" => DataType::Binary,
+            "Geometry" => DataType::Geometry,
             _ => return Err(Error::Parsing(format!("Unknown type: {:?}", desc))),
         };
         Ok(dt)
@@ -236,8 +298,19 @@ impl TryFrom<APISchemaField> for Field {
     type Error = Error;
 
     fn try_from(f: APISchemaField) -> Result<Self> {
-        let type_desc = parse_type_desc(&f.data_type)?;
-        let dt = DataType::try_from(&type_desc)?;
+        // the SQL style `Int32 NULL` is used by some servers besides `Nullable(Int32)`
+        let (ty, nullable) = match f.data_type.strip_suffix(" NOT NULL") {
+            Some(ty) => (ty, false),
+            None => match f.data_type.strip_suffix(" NULL") {
+                Some(ty) => (ty, true),
+                None => (f.data_type.as_str(), false),
+            },
+        };
+        let type_desc = parse_type_desc(ty)?;
+        let mut dt = DataType::try_from(&type_desc)?;
+        if nullable && !dt.is_nullable() {
+            dt = DataType::Nullable(Box::new(dt));
+        }
         let field = Self {
             name: f.name,
             data_type: dt,
@@ -258,49 +331,69 @@ impl TryFrom<Vec<APISchemaField>> for Schema {
     }
 }
 
-#[cfg(feature = "flight-sql")]
+#[cfg(feature = "arrow-ipc")]
 impl TryFrom<&Arc<ArrowField>> for Field {
     type Error = Error;
 
     fn try_from(f: &Arc<ArrowField>) -> Result<Self> {
-        let mut dt = match f.data_type() {
-            ArrowDataType::Null => DataType::Null,
-            ArrowDataType::Boolean => DataType::Boolean,
-            ArrowDataType::Int8 => DataType::Number(NumberDataType::Int8),
-            ArrowDataType::Int16 => DataType::Number(NumberDataType::Int16),
-            ArrowDataType::Int32 => DataType::Number(NumberDataType::Int32),
-            ArrowDataType::Int64 => DataType::Number(NumberDataType::Int64),
-            ArrowDataType::UInt8 => DataType::Number(NumberDataType::UInt8),
-            ArrowDataType::UInt16 => DataType::Number(NumberDataType::UInt16),
-            ArrowDataType::UInt32 => DataType::Number(NumberDataType::UInt32),
-            ArrowDataType::UInt64 => DataType::Number(NumberDataType::UInt64),
-            ArrowDataType::Float32 => DataType::Number(NumberDataType::Float32),
-            ArrowDataType::Float64 => DataType::Number(NumberDataType::Float64),
-            ArrowDataType::Utf8
-            | ArrowDataType::Binary
-            | ArrowDataType::LargeUtf8
-            | ArrowDataType::LargeBinary
-            | ArrowDataType::FixedSizeBinary(_) => DataType::String,
-            ArrowDataType::Timestamp(_, _) => DataType::Timestamp,
-            ArrowDataType::Date32 => DataType::Date,
-            ArrowDataType::Decimal128(p, s) => {
-                DataType::Decimal(DecimalDataType::Decimal128(DecimalSize {
-                    precision: *p,
-                    scale: *s as u8,
-                }))
+        let extension = f.metadata().get(EXTENSION_KEY).map(|s| s.as_str());
+        let mut dt = match (extension, f.data_type()) {
+            (Some(ARROW_EXT_TYPE_EMPTY_ARRAY), _) => DataType::EmptyArray,
+            (Some(ARROW_EXT_TYPE_EMPTY_MAP), _) => DataType::EmptyMap,
+            (Some(ARROW_EXT_TYPE_VARIANT), _) => DataType::Variant,
+            (Some(ARROW_EXT_TYPE_BITMAP), _) => DataType::Bitmap,
+            (Some(ARROW_EXT_TYPE_GEOMETRY), _) => DataType::Geometry,
+            (_, ArrowDataType::List(inner)) | (_, ArrowDataType::LargeList(inner)) => {
+                DataType::Array(Box::new(Field::try_from(inner)?.data_type))
             }
-            ArrowDataType::Decimal256(p, s) => {
-                DataType::Decimal(DecimalDataType::Decimal256(DecimalSize {
-                    precision: *p,
-                    scale: *s as u8,
-                }))
-            }
-            _ => {
-                return Err(Error::Parsing(format!(
-                    "Unsupported datatype for arrow field: {:?}",
-                    f
-                )))
+            (_, ArrowDataType::Struct(fields)) => DataType::Tuple(
+                fields
+                    .iter()
+                    .map(|f| Field::try_from(f).map(|f| f.data_type))
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            (_, ArrowDataType::Map(entries, _)) => {
+                DataType::Map(Box::new(Field::try_from(entries)?.data_type))
             }
+            (_, dt) => match dt {
+                ArrowDataType::Null => DataType::Null,
+                ArrowDataType::Boolean => DataType::Boolean,
+                ArrowDataType::Int8 => DataType::Number(NumberDataType::Int8),
+                ArrowDataType::Int16 => DataType::Number(NumberDataType::Int16),
+                ArrowDataType::Int32 => DataType::Number(NumberDataType::Int32),
+                ArrowDataType::Int64 => DataType::Number(NumberDataType::Int64),
+                ArrowDataType::UInt8 => DataType::Number(NumberDataType::UInt8),
+                ArrowDataType::UInt16 => DataType::Number(NumberDataType::UInt16),
+                ArrowDataType::UInt32 => DataType::Number(NumberDataType::UInt32),
+                ArrowDataType::UInt64 => DataType::Number(NumberDataType::UInt64),
+                ArrowDataType::Float32 => DataType::Number(NumberDataType::Float32),
+                ArrowDataType::Float64 => DataType::Number(NumberDataType::Float64),
+                ArrowDataType::Utf8
+                | ArrowDataType::Binary
+                | ArrowDataType::LargeUtf8
+                | ArrowDataType::LargeBinary
+                | ArrowDataType::FixedSizeBinary(_) => DataType::String,
+                ArrowDataType::Timestamp(_, _) => DataType::Timestamp,
+                ArrowDataType::Date32 => DataType::Date,
+                ArrowDataType::Decimal128(p, s) => {
+                    DataType::Decimal(DecimalDataType::Decimal128(DecimalSize {
+                        precision: *p,
+                        scale: *s as u8,
+                    }))
+                }
+                ArrowDataType::Decimal256(p, s) => {
+                    DataType::Decimal(DecimalDataType::Decimal256(DecimalSize {
+                        precision: *p,
+                        scale: *s as u8,
+                    }))
+                }
+                _ => {
+                    return Err(Error::Parsing(format!(
+                        "Unsupported datatype for arrow field: {:?}",
+                        f
+                    )))
+                }
+            },
         };
         if f.is_nullable() && !matches!(dt, DataType::Null) {
             dt = DataType::Nullable(Box::new(dt));
@@ -312,7 +405,7 @@ impl TryFrom<&Arc<ArrowField>> for Field {
     }
 }
 
-#[cfg(feature = "flight-sql")]
+#[cfg(feature = "arrow-ipc")]
 impl TryFrom<ArrowSchemaRef> for Schema {
     type Error = Error;
 
@@ -332,12 +425,12 @@ struct TypeDesc<'t> {
     args: Vec<TypeDesc<'t>>,
 }
 
-fn parse_type_desc(s: &str) -> Result<TypeDesc> {
+fn parse_type_desc(s: &str) -> Result<TypeDesc<'_>> {
     let mut name = "";
     let mut args = vec![];
     let mut depth = 0;
     let mut start = 0;
-    for (i, c) in s.chars().enumerate() {
+    for (i, c) in s.char_indices() {
         match c {
             '(' => {
                 if depth == 0 {
@@ -356,16 +449,12 @@ fn parse_type_desc(s: &str) -> Result<TypeDesc> {
                     start = i + 1;
                 }
             }
-            ',' => {
-                if depth == 1 {
-                    args.push(parse_type_desc(&s[start..i])?);
-                    start = i + 1;
-                }
+            ',' if depth == 1 => {
+                args.push(parse_type_desc(&s[start..i])?);
+                start = i + 1;
             }
-            ' ' => {
-                if depth == 0 {
-                    start = i + 1;
-                }
+            ' ' if depth == 0 => {
+                start = i + 1;
             }
             _ => {}
         }
@@ -513,4 +602,51 @@ mod test {
             assert_eq!(output, case.output, "{}", case.desc);
         }
     }
+
+    #[test]
+    fn test_schema_from_api_fields() {
+        let fields = [
+            ("id", "UInt64"),
+            ("name", "Nullable(String)"),
+            ("score", "Decimal(15, 2) NULL"),
+            ("tags", "Array(String) NOT NULL"),
+            ("raw", "Binary"),
+            ("empty", "EmptyArray"),
+        ];
+        let schema = Schema::try_from(
+            fields
+                .iter()
+                .map(|(name, ty)| APISchemaField {
+                    name: name.to_string(),
+                    data_type: ty.to_string(),
+                })
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+        let types = schema
+            .fields()
+            .iter()
+            .map(|f| (f.name.as_str(), f.data_type.to_string(), f.is_nullable()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            vec![
+                ("id", "UInt64".to_string(), false),
+                ("name", "Nullable(String)".to_string(), true),
+                ("score", "Nullable(Decimal(15, 2))".to_string(), true),
+                ("tags", "Array(String)".to_string(), false),
+                ("raw", "Binary".to_string(), false),
+                ("empty", "EmptyArray".to_string(), false),
+            ]
+        );
+        assert_eq!(schema.index_of("score"), Some(2));
+        let name = schema.field_with_name("name").unwrap();
+        assert_eq!(name.data_type.remove_nullable(), &DataType::String);
+
+        let err = Schema::try_from(vec![APISchemaField {
+            name: "d".to_string(),
+            data_type: "Decimal(15)".to_string(),
+        }]);
+        assert!(err.is_err());
+    }
 }
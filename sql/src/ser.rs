@@ -0,0 +1,346 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Serialize values with serde, nulls become unit (`null` in JSON), rows
+//! become sequences of their values, arrays and tuples sequences, maps
+//! maps with keys as strings, and variants their json.
+//!
+//! By default decimals and timestamps are serialized as strings to avoid
+//! losing precision, which could be changed with `SerializeOptions`.
+
+use serde::ser::{Error as _, Serialize, SerializeMap, SerializeSeq, Serializer};
+
+use crate::error::{ConvertError, Error, Result};
+use crate::rows::{Row, Rows};
+use crate::value::{NumberValue, Value};
+
+/// How NaN and infinite floats are handled, since JSON has no representation for them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonFinitePolicy {
+    /// Written as `null`, and read as null.
+    #[default]
+    Null,
+    /// Written as strings `"NaN"`, `"Infinity"` and `"-Infinity"`, and read back as floats.
+    String,
+    /// Fail the serialization or parsing.
+    Error,
+}
+
+impl NonFinitePolicy {
+    /// Apply the policy to a float value parsed from text or JSON, values
+    /// of the other types are returned as is.
+    pub fn apply(self, value: Value) -> Result<Value> {
+        let f = match value {
+            Value::Number(NumberValue::Float32(f)) => f as f64,
+            Value::Number(NumberValue::Float64(f)) => f,
+            _ => return Ok(value),
+        };
+        if f.is_finite() {
+            return Ok(value);
+        }
+        match self {
+            NonFinitePolicy::String => Ok(value),
+            NonFinitePolicy::Null => Ok(Value::Null),
+            NonFinitePolicy::Error => Err(ConvertError::new("finite float", f.to_string()).into()),
+        }
+    }
+
+    /// Apply the policy to all values of the row.
+    pub fn apply_row(self, row: Row) -> Result<Row> {
+        if self == NonFinitePolicy::String {
+            return Ok(row);
+        }
+        let values = row
+            .into_iter()
+            .map(|v| self.apply(v))
+            .collect::<Result<_>>()?;
+        Ok(Row::from_vec(values))
+    }
+}
+
+impl std::str::FromStr for NonFinitePolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "null" => Ok(NonFinitePolicy::Null),
+            "string" => Ok(NonFinitePolicy::String),
+            "error" => Ok(NonFinitePolicy::Error),
+            _ => Err(Error::BadArgument(format!(
+                "Invalid non-finite float policy: {}",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SerializeOptions {
+    /// Serialize decimals as floats instead of strings, precision may be lost.
+    pub decimal_as_number: bool,
+    /// Serialize timestamps as microseconds and dates as days since epoch
+    /// instead of strings.
+    pub timestamp_as_number: bool,
+    pub non_finite: NonFinitePolicy,
+}
+
+fn serialize_float<S: Serializer>(
+    f: f64,
+    policy: NonFinitePolicy,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if f.is_finite() {
+        return serializer.serialize_f64(f);
+    }
+    match policy {
+        NonFinitePolicy::Null => serializer.serialize_unit(),
+        NonFinitePolicy::String if f.is_nan() => serializer.serialize_str("NaN"),
+        NonFinitePolicy::String if f > 0.0 => serializer.serialize_str("Infinity"),
+        NonFinitePolicy::String => serializer.serialize_str("-Infinity"),
+        NonFinitePolicy::Error => Err(S::Error::custom(format!(
+            "non-finite float {} could not be serialized",
+            f
+        ))),
+    }
+}
+
+/// Wrapper to serialize a value, row or rows with options.
+pub struct WithOptions<'a, T: ?Sized> {
+    inner: &'a T,
+    options: SerializeOptions,
+}
+
+impl Value {
+    pub fn with_options(&self, options: SerializeOptions) -> WithOptions<'_, Self> {
+        WithOptions {
+            inner: self,
+            options,
+        }
+    }
+}
+
+impl Row {
+    pub fn with_options(&self, options: SerializeOptions) -> WithOptions<'_, Self> {
+        WithOptions {
+            inner: self,
+            options,
+        }
+    }
+}
+
+impl Rows {
+    pub fn with_options(&self, options: SerializeOptions) -> WithOptions<'_, Self> {
+        WithOptions {
+            inner: self,
+            options,
+        }
+    }
+}
+
+impl Serialize for WithOptions<'_, Value> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.inner {
+            Value::Null => serializer.serialize_unit(),
+            Value::Boolean(b) => serializer.serialize_bool(*b),
+            Value::String(s) => serializer.serialize_str(s),
+            Value::Number(n) => match n {
+                NumberValue::Int8(i) => serializer.serialize_i8(*i),
+                NumberValue::Int16(i) => serializer.serialize_i16(*i),
+                NumberValue::Int32(i) => serializer.serialize_i32(*i),
+                NumberValue::Int64(i) => serializer.serialize_i64(*i),
+                NumberValue::UInt8(i) => serializer.serialize_u8(*i),
+                NumberValue::UInt16(i) => serializer.serialize_u16(*i),
+                NumberValue::UInt32(i) => serializer.serialize_u32(*i),
+                NumberValue::UInt64(i) => serializer.serialize_u64(*i),
+                NumberValue::Float32(f) if f.is_finite() => serializer.serialize_f32(*f),
+                NumberValue::Float32(f) => {
+                    serialize_float(*f as f64, self.options.non_finite, serializer)
+                }
+                NumberValue::Float64(f) => serialize_float(*f, self.options.non_finite, serializer),
+                NumberValue::Decimal128(..) | NumberValue::Decimal256(..) => {
+                    let s = n.to_string();
+                    if self.options.decimal_as_number {
+                        match s.parse::<f64>() {
+                            Ok(f) => serializer.serialize_f64(f),
+                            Err(_) => serializer.serialize_str(&s),
+                        }
+                    } else {
+                        serializer.serialize_str(&s)
+                    }
+                }
+            },
+            Value::Timestamp(ts) if self.options.timestamp_as_number => {
+                serializer.serialize_i64(*ts)
+            }
+            Value::Date(d) if self.options.timestamp_as_number => serializer.serialize_i32(*d),
+            Value::Timestamp(_) | Value::Date(_) => {
+                serializer.serialize_str(&self.inner.to_string())
+            }
+            Value::Array(vals) | Value::Tuple(vals) => {
+                let mut seq = serializer.serialize_seq(Some(vals.len()))?;
+                for v in vals {
+                    seq.serialize_element(&v.with_options(self.options))?;
+                }
+                seq.end()
+            }
+            // keys are written as strings, as required by formats like JSON
+            Value::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (k, v) in entries {
+                    map.serialize_entry(&k.to_string(), &v.with_options(self.options))?;
+                }
+                map.end()
+            }
+            // the text of the server, which is written as a string if not json
+            Value::Variant(s) => match serde_json::value::RawValue::from_string(s.clone()) {
+                Ok(raw) => raw.serialize(serializer),
+                Err(_) => serializer.serialize_str(s),
+            },
+            Value::Bitmap(s) | Value::Geometry(s) => serializer.serialize_str(s),
+        }
+    }
+}
+
+impl Serialize for WithOptions<'_, Row> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let values = self.inner.values();
+        let mut seq = serializer.serialize_seq(Some(values.len()))?;
+        for value in values {
+            seq.serialize_element(&value.with_options(self.options))?;
+        }
+        seq.end()
+    }
+}
+
+impl Serialize for WithOptions<'_, Rows> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let rows = self.inner.rows();
+        let mut seq = serializer.serialize_seq(Some(rows.len()))?;
+        for row in rows {
+            seq.serialize_element(&row.with_options(self.options))?;
+        }
+        seq.end()
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.with_options(SerializeOptions::default())
+            .serialize(serializer)
+    }
+}
+
+impl Serialize for Row {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.with_options(SerializeOptions::default())
+            .serialize(serializer)
+    }
+}
+
+impl Serialize for Rows {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.with_options(SerializeOptions::default())
+            .serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::DecimalSize;
+
+    #[test]
+    fn non_finite_row() -> Result<()> {
+        let row = Row::from_vec(vec![
+            Value::Number(NumberValue::Float64(f64::NAN)),
+            Value::Number(NumberValue::Float32(1.5)),
+        ]);
+        let policy: NonFinitePolicy = "null".parse()?;
+        let row = policy.apply_row(row)?;
+        assert_eq!(
+            row.values(),
+            &[Value::Null, Value::Number(NumberValue::Float32(1.5))]
+        );
+        let inf = Row::from_vec(vec![Value::Number(NumberValue::Float64(f64::INFINITY))]);
+        assert!(NonFinitePolicy::Error.apply_row(inf.clone()).is_err());
+        assert!(NonFinitePolicy::String.apply_row(inf).is_ok());
+        assert!("nan".parse::<NonFinitePolicy>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn serialize_row() {
+        let row = Row::from_vec(vec![
+            Value::Null,
+            Value::Boolean(true),
+            Value::String("a\"b".to_string()),
+            Value::Number(NumberValue::Int32(-1)),
+            Value::Number(NumberValue::Decimal128(
+                12345,
+                DecimalSize {
+                    precision: 10,
+                    scale: 2,
+                },
+            )),
+            Value::Timestamp(1_000_000),
+            Value::Date(1),
+        ]);
+        assert_eq!(
+            serde_json::to_string(&row).unwrap(),
+            r#"[null,true,"a\"b",-1,"123.45","1970-01-01 00:00:01","1970-01-02"]"#
+        );
+
+        let options = SerializeOptions {
+            decimal_as_number: true,
+            timestamp_as_number: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_string(&row.with_options(options)).unwrap(),
+            r#"[null,true,"a\"b",-1,123.45,1000000,1]"#
+        );
+
+        let rows = Rows::from_vec(vec![row.clone(), Row::default()]);
+        assert_eq!(
+            serde_json::to_string(&rows).unwrap(),
+            r#"[[null,true,"a\"b",-1,"123.45","1970-01-01 00:00:01","1970-01-02"],[]]"#
+        );
+    }
+
+    #[test]
+    fn serialize_non_finite() {
+        let row = Row::from_vec(vec![
+            Value::Number(NumberValue::Float64(f64::NAN)),
+            Value::Number(NumberValue::Float32(f32::INFINITY)),
+            Value::Number(NumberValue::Float64(f64::NEG_INFINITY)),
+            Value::Number(NumberValue::Float64(1.5)),
+        ]);
+        assert_eq!(serde_json::to_string(&row).unwrap(), "[null,null,null,1.5]");
+
+        let options = SerializeOptions {
+            non_finite: NonFinitePolicy::String,
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_string(&row.with_options(options)).unwrap(),
+            r#"["NaN","Infinity","-Infinity",1.5]"#
+        );
+
+        let options = SerializeOptions {
+            non_finite: NonFinitePolicy::Error,
+            ..Default::default()
+        };
+        assert!(serde_json::to_string(&row.with_options(options)).is_err());
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Range;
+
+use crate::error::{Error, Result};
+
+/// A statement in the source, without the trailing `;` and surrounding whitespace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Statement<'a> {
+    pub text: &'a str,
+    /// Byte range of `text` in the source.
+    pub span: Range<usize>,
+}
+
+/// Split statements separated by `;`.
+///
+/// Semicolons in comments (`--`, `/* */`), quoted strings and identifiers
+/// (`'`, `"`, `` ` ``) and `$$` bodies of scripting blocks are not separators.
+/// Empty statements, or those with only comments, are skipped.
+pub fn split_statements(sql: &str) -> Result<Vec<Statement<'_>>> {
+    let bytes = sql.as_bytes();
+    let mut statements = Vec::new();
+    let mut start = 0;
+    // whether the current statement has anything other than comments
+    let mut has_content = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some((end, is_comment)) = skip_opaque(sql, i)? {
+            i = end;
+            has_content |= !is_comment;
+            continue;
+        }
+        match bytes[i] {
+            b';' => {
+                if has_content {
+                    statements.push(statement(sql, start, i));
+                }
+                has_content = false;
+                start = i + 1;
+            }
+            b if b.is_ascii_whitespace() => {}
+            _ => has_content = true,
+        }
+        i += 1;
+    }
+    if has_content {
+        statements.push(statement(sql, start, bytes.len()));
+    }
+    Ok(statements)
+}
+
+/// Byte positions of `?` placeholders, the ones in comments, quoted strings
+/// and identifiers and `$$` bodies are not placeholders.
+pub fn find_placeholders(sql: &str) -> Result<Vec<usize>> {
+    let mut positions = Vec::new();
+    let mut i = 0;
+    while i < sql.len() {
+        if let Some((end, _)) = skip_opaque(sql, i)? {
+            i = end;
+            continue;
+        }
+        if sql.as_bytes()[i] == b'?' {
+            positions.push(i);
+        }
+        i += 1;
+    }
+    Ok(positions)
+}
+
+/// If a comment, a quoted string or identifier, or a `$$` body starts at `i`,
+/// returns where it ends and whether it is a comment.
+fn skip_opaque(sql: &str, i: usize) -> Result<Option<(usize, bool)>> {
+    let bytes = sql.as_bytes();
+    let end = match bytes[i] {
+        b'-' if bytes.get(i + 1) == Some(&b'-') => {
+            let end = match sql[i..].find('\n') {
+                Some(pos) => i + pos + 1,
+                None => bytes.len(),
+            };
+            return Ok(Some((end, true)));
+        }
+        b'/' if bytes.get(i + 1) == Some(&b'*') => {
+            let end = match sql[i + 2..].find("*/") {
+                Some(pos) => i + 2 + pos + 2,
+                None => return Err(unterminated("comment", i)),
+            };
+            return Ok(Some((end, true)));
+        }
+        b'$' if bytes.get(i + 1) == Some(&b'$') => match sql[i + 2..].find("$$") {
+            Some(pos) => i + 2 + pos + 2,
+            None => return Err(unterminated("$$ body", i)),
+        },
+        quote @ (b'\'' | b'"' | b'`') => {
+            let mut end = i + 1;
+            loop {
+                match bytes.get(end) {
+                    None => return Err(unterminated("quote", i)),
+                    Some(b'\\') if quote != b'`' => end += 2,
+                    Some(b) if *b == quote => {
+                        end += 1;
+                        // doubled quote is an escaped quote
+                        if bytes.get(end) != Some(&quote) {
+                            break;
+                        }
+                        end += 1;
+                    }
+                    Some(_) => end += 1,
+                }
+            }
+            end
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some((end, false)))
+}
+
+fn statement(sql: &str, start: usize, end: usize) -> Statement<'_> {
+    let raw = &sql[start..end];
+    let text = raw.trim();
+    let start = start + (raw.len() - raw.trim_start().len());
+    Statement {
+        text,
+        span: start..start + text.len(),
+    }
+}
+
+fn unterminated(what: &str, pos: usize) -> Error {
+    Error::BadArgument(format!("Unterminated {} at position {}", what, pos))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn split() -> Result<()> {
+        let sql = r#"select 1; select ';' -- comment;
+, "a;b", `c;d`;
+/* block; comment */
+-- only comment;
+EXECUTE IMMEDIATE $$
+BEGIN
+    RETURN 1;
+END;
+$$;
+select 'it\'s;', 'it''s;'"#;
+        let statements = split_statements(sql)?;
+        let texts = statements.iter().map(|s| s.text).collect::<Vec<_>>();
+        assert_eq!(
+            texts,
+            vec![
+                "select 1",
+                "select ';' -- comment;\n, \"a;b\", `c;d`",
+                "/* block; comment */\n-- only comment;\nEXECUTE IMMEDIATE $$\nBEGIN\n    RETURN 1;\nEND;\n$$",
+                r#"select 'it\'s;', 'it''s;'"#,
+            ]
+        );
+        for s in &statements {
+            assert_eq!(&sql[s.span.clone()], s.text);
+        }
+
+        assert!(split_statements("select 'a").is_err());
+        assert!(split_statements("select 1 /* a").is_err());
+        assert!(split_statements(" ; -- nothing").unwrap().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn placeholders() -> Result<()> {
+        let sql = "select ?, '?', `a?` -- ?\nfrom t where a = ? /* ? */";
+        let positions = find_placeholders(sql)?;
+        assert_eq!(positions.len(), 2);
+        assert!(positions.iter().all(|i| &sql[*i..*i + 1] == "?"));
+        assert!(find_placeholders("select '?").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Templates for statements generated by the driver, see
+//! [`databend_client::template`].
+
+pub use databend_client::template::Param;
+
+use crate::error::{Error, Result};
+
+/// Render the template, the number of parameters must match the placeholders.
+pub fn render(template: &str, params: &[Param]) -> Result<String> {
+    databend_client::template::render(template, params).map_err(|e| match e {
+        databend_client::error::Error::BadArgument(msg) => Error::BadArgument(msg),
+        e => e.into(),
+    })
+}
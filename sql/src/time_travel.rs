@@ -0,0 +1,270 @@
+// Copyright 2021 Datafuse Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Time travel with the `AT` clause, see
+//! <https://docs.databend.com/sql/sql-commands/query-syntax/query-at>.
+
+use std::fmt;
+
+use chrono::NaiveDateTime;
+
+use crate::error::{Error, Result};
+use crate::quote::escape_literal;
+
+/// Point of a fuse table history to query from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TimeTravelPoint {
+    Snapshot(String),
+    Timestamp(NaiveDateTime),
+}
+
+impl fmt::Display for TimeTravelPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeTravelPoint::Snapshot(id) => {
+                write!(f, "AT (SNAPSHOT => {})", escape_literal(id))
+            }
+            TimeTravelPoint::Timestamp(ts) => {
+                let ts = ts.format("%Y-%m-%d %H:%M:%S%.6f").to_string();
+                write!(f, "AT (TIMESTAMP => {}::TIMESTAMP)", escape_literal(&ts))
+            }
+        }
+    }
+}
+
+// keywords ending the table reference of a FROM clause
+const CLAUSE_KEYWORDS: [&str; 9] = [
+    "WHERE", "GROUP", "HAVING", "QUALIFY", "WINDOW", "ORDER", "LIMIT", "OFFSET", "FORMAT",
+];
+
+/// Add the `AT` clause right after the table name of a `SELECT` statement.
+///
+/// Only statements reading from a single table are supported, statements with
+/// joins, subqueries in `FROM`, or set operations are rejected instead of
+/// guessing which table the clause belongs to.
+pub fn rewrite_at(sql: &str, point: &TimeTravelPoint) -> Result<String> {
+    let tokens = tokenize(sql)?;
+    let top = tokens.iter().filter(|t| t.depth == 0).collect::<Vec<_>>();
+    match top.first() {
+        Some(t) if t.is_keyword(sql, "SELECT") => {}
+        _ => {
+            return Err(unsupported("only SELECT statement is supported"));
+        }
+    }
+    if top.iter().any(|t| t.is_keyword(sql, "JOIN")) {
+        return Err(unsupported("JOIN is not supported"));
+    }
+    let froms = top
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| t.is_keyword(sql, "FROM"))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let from = match froms.as_slice() {
+        [from] => *from,
+        [] => return Err(unsupported("no table to travel")),
+        _ => return Err(unsupported("multiple FROM clauses")),
+    };
+
+    // table reference as `[catalog.][database.]table`
+    let mut end = None;
+    let mut i = from + 1;
+    while let Some(t) = top.get(i) {
+        match t.kind {
+            TokenKind::Ident if end.is_none() || top[i - 1].kind == TokenKind::Dot => {
+                end = Some(t.end);
+            }
+            TokenKind::Dot if end.is_some() => {}
+            _ => break,
+        }
+        i += 1;
+    }
+    let end = match end {
+        Some(end) if top[i - 1].kind == TokenKind::Ident => end,
+        _ => return Err(unsupported("FROM must be followed by a table name")),
+    };
+    for t in &top[i..] {
+        if CLAUSE_KEYWORDS.iter().any(|k| t.is_keyword(sql, k)) {
+            break;
+        }
+        if t.is_keyword(sql, "AT") {
+            return Err(unsupported("AT clause already exists"));
+        }
+        if t.kind == TokenKind::Comma || t.kind == TokenKind::Open {
+            return Err(unsupported("only one table is supported"));
+        }
+    }
+    Ok(format!("{} {}{}", &sql[..end], point, &sql[end..]))
+}
+
+fn unsupported(reason: &str) -> Error {
+    Error::BadArgument(format!("Unsupported statement for time travel: {}", reason))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum TokenKind {
+    Ident,
+    Literal,
+    Dot,
+    Comma,
+    Open,
+    Close,
+    Other,
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+    depth: usize,
+}
+
+impl Token {
+    fn is_keyword(&self, sql: &str, keyword: &str) -> bool {
+        self.kind == TokenKind::Ident && sql[self.start..self.end].eq_ignore_ascii_case(keyword)
+    }
+}
+
+/// A lexer only good enough to find the top level tokens,
+/// strings, quoted identifiers and comments are skipped as a whole.
+fn tokenize(sql: &str) -> Result<Vec<Token>> {
+    let bytes = sql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let kind = match bytes[i] {
+            b if b.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                match sql[i + 2..].find("*/") {
+                    Some(pos) => i += pos + 4,
+                    None => return Err(unsupported("unterminated comment")),
+                }
+                continue;
+            }
+            quote @ (b'\'' | b'"' | b'`') => {
+                i += 1;
+                loop {
+                    match bytes.get(i) {
+                        None => return Err(unsupported("unterminated quote")),
+                        Some(b'\\') if quote != b'`' => i += 2,
+                        Some(b) if *b == quote => {
+                            i += 1;
+                            // doubled quote is an escaped quote
+                            if bytes.get(i) != Some(&quote) {
+                                break;
+                            }
+                            i += 1;
+                        }
+                        Some(_) => i += 1,
+                    }
+                }
+                if quote == b'`' {
+                    TokenKind::Ident
+                } else {
+                    TokenKind::Literal
+                }
+            }
+            b if b.is_ascii_alphanumeric() || b == b'_' || b >= 0x80 => {
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] >= 0x80)
+                {
+                    i += 1;
+                }
+                TokenKind::Ident
+            }
+            b'.' => {
+                i += 1;
+                TokenKind::Dot
+            }
+            b',' => {
+                i += 1;
+                TokenKind::Comma
+            }
+            b'(' => {
+                i += 1;
+                depth += 1;
+                tokens.push(Token {
+                    kind: TokenKind::Open,
+                    start,
+                    end: i,
+                    depth: depth - 1,
+                });
+                continue;
+            }
+            b')' => {
+                i += 1;
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| unsupported("unbalanced parentheses"))?;
+                TokenKind::Close
+            }
+            _ => {
+                i += 1;
+                TokenKind::Other
+            }
+        };
+        tokens.push(Token {
+            kind,
+            start,
+            end: i,
+            depth,
+        });
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rewrite() -> Result<()> {
+        let snapshot = TimeTravelPoint::Snapshot("c5c538d6b8bc42f483eefbddd000af7d".to_string());
+        assert_eq!(
+            rewrite_at("SELECT * FROM t1", &snapshot)?,
+            "SELECT * FROM t1 AT (SNAPSHOT => 'c5c538d6b8bc42f483eefbddd000af7d')"
+        );
+
+        let ts = NaiveDateTime::parse_from_str("2023-06-26 09:49:02", "%Y-%m-%d %H:%M:%S").unwrap();
+        let ts = TimeTravelPoint::Timestamp(ts);
+        assert_eq!(
+            rewrite_at(
+                "select count(*), max(a) from db.`from` t where b = 'from x' order by 1, 2;",
+                &ts
+            )?,
+            "select count(*), max(a) from db.`from` AT (TIMESTAMP => '2023-06-26 09:49:02.000000'::TIMESTAMP) t where b = 'from x' order by 1, 2;"
+        );
+
+        assert!(rewrite_at("SELECT 1", &ts).is_err());
+        assert!(rewrite_at("INSERT INTO t SELECT * FROM t2", &ts).is_err());
+        assert!(rewrite_at("SELECT * FROM t1 JOIN t2 ON t1.a = t2.a", &ts).is_err());
+        assert!(rewrite_at("SELECT * FROM t1, t2", &ts).is_err());
+        assert!(rewrite_at("SELECT * FROM (SELECT * FROM t1)", &ts).is_err());
+        assert!(rewrite_at("SELECT * FROM t1 UNION ALL SELECT * FROM t2", &ts).is_err());
+        assert!(rewrite_at("SELECT * FROM t1 AT (SNAPSHOT => 'x')", &ts).is_err());
+        Ok(())
+    }
+}
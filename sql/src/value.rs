@@ -12,11 +12,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use arrow::datatypes::{i256, ArrowNativeTypeOp};
+use arrow_buffer::i256;
 use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
 
+#[cfg(feature = "arrow-ipc")]
+use crate::schema::{
+    ARROW_EXT_TYPE_BITMAP, ARROW_EXT_TYPE_EMPTY_ARRAY, ARROW_EXT_TYPE_EMPTY_MAP,
+    ARROW_EXT_TYPE_GEOMETRY, ARROW_EXT_TYPE_VARIANT, EXTENSION_KEY,
+};
 use crate::{
     error::{ConvertError, Error, Result},
+    quote::escape_literal,
     schema::{DecimalDataType, DecimalSize},
 };
 use std::fmt::Write;
@@ -25,13 +33,13 @@ use std::fmt::Write;
 const DAYS_FROM_CE: i32 = 719_163;
 const NULL_VALUE: &str = "NULL";
 
-#[cfg(feature = "flight-sql")]
+#[cfg(feature = "arrow-ipc")]
 use {
     arrow_array::{
         Array as ArrowArray, BinaryArray, BooleanArray, Date32Array, Decimal128Array,
         Decimal256Array, Float32Array, Float64Array, Int16Array, Int32Array, Int64Array, Int8Array,
-        LargeBinaryArray, LargeStringArray, StringArray, TimestampMicrosecondArray, UInt16Array,
-        UInt32Array, UInt64Array, UInt8Array,
+        LargeBinaryArray, LargeListArray, LargeStringArray, ListArray, MapArray, StringArray,
+        StructArray, TimestampMicrosecondArray, UInt16Array, UInt32Array, UInt64Array, UInt8Array,
     },
     arrow_schema::{DataType as ArrowDataType, Field as ArrowField, TimeUnit},
     std::sync::Arc,
@@ -64,11 +72,19 @@ pub enum Value {
     /// Microseconds from 1970-01-01 00:00:00 UTC
     Timestamp(i64),
     Date(i32),
-    // Array(Vec<Value>),
-    // Map(Vec<(Value, Value)>),
-    // Tuple(Vec<Value>),
-    // Variant,
-    // Generic(usize, Vec<u8>),
+    Array(Vec<Value>),
+    /// Entries in the order returned by the server
+    Map(Vec<(Value, Value)>),
+    Tuple(Vec<Value>),
+    /// Variant in the JSON text returned by the server, kept as is to not
+    /// reorder keys or lose the precision of numbers, JSONB not in text is
+    /// in hex. Use `serde_json::Value::try_from` to parse it.
+    Variant(String),
+    /// Bitmap in the text form returned by the server, e.g. `1,3,5`, or in
+    /// hex if sent in binary
+    Bitmap(String),
+    /// Geometry in the text form returned by the server, WKT or EWKB in hex
+    Geometry(String),
 }
 
 impl Value {
@@ -94,11 +110,72 @@ impl Value {
             Self::Timestamp(_) => DataType::Timestamp,
 
             Self::Date(_) => DataType::Date,
-            // TODO:(everpcpc) fix nested type
-            // Self::Array(v) => DataType::Array(Box::new(v[0].get_type())),
-            // Self::Map(_) => DataType::Map(Box::new(DataType::Null)),
-            // Self::Tuple(_) => DataType::Tuple(vec![]),
-            // Self::Variant => DataType::Variant,
+            // the type of nested values is guessed from their first element
+            Self::Array(v) => match v.first() {
+                Some(v) => DataType::Array(Box::new(v.get_type())),
+                None => DataType::EmptyArray,
+            },
+            Self::Map(v) => match v.first() {
+                Some((k, v)) => {
+                    DataType::Map(Box::new(DataType::Tuple(vec![k.get_type(), v.get_type()])))
+                }
+                None => DataType::EmptyMap,
+            },
+            Self::Tuple(v) => DataType::Tuple(v.iter().map(|v| v.get_type()).collect()),
+            Self::Variant(_) => DataType::Variant,
+            Self::Bitmap(_) => DataType::Bitmap,
+            Self::Geometry(_) => DataType::Geometry,
+        }
+    }
+}
+
+/// How numbers in text, as returned by the REST API, are parsed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NumericMode {
+    /// Decimals are parsed into `Decimal128`/`Decimal256` without precision loss.
+    #[default]
+    Lossless,
+    /// Decimals are parsed into `Float64`, faster and easier to consume,
+    /// but may lose precision.
+    Fast,
+}
+
+impl std::str::FromStr for NumericMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "lossless" => Ok(NumericMode::Lossless),
+            "fast" => Ok(NumericMode::Fast),
+            _ => Err(Error::BadArgument(format!("Invalid numeric mode: {}", s))),
+        }
+    }
+}
+
+impl TryFrom<(&DataType, &str, NumericMode)> for Value {
+    type Error = Error;
+
+    fn try_from((t, v, mode): (&DataType, &str, NumericMode)) -> Result<Self> {
+        match (t, mode) {
+            (DataType::Decimal(_), NumericMode::Fast) => {
+                Ok(Self::Number(NumberValue::Float64(v.parse()?)))
+            }
+            (DataType::Nullable(inner), _) => {
+                if v == NULL_VALUE {
+                    Ok(Self::Null)
+                } else {
+                    Self::try_from((inner.as_ref(), v, mode))
+                }
+            }
+            (
+                DataType::Array(_)
+                | DataType::EmptyArray
+                | DataType::Map(_)
+                | DataType::EmptyMap
+                | DataType::Tuple(_),
+                _,
+            ) => NestedParser::new(v, mode).parse_all(t),
+            _ => Self::try_from((t, v)),
         }
     }
 }
@@ -108,8 +185,8 @@ impl TryFrom<(&DataType, &str)> for Value {
 
     fn try_from((t, v): (&DataType, &str)) -> Result<Self> {
         match t {
-            DataType::Null => Ok(Self::Null),
-            DataType::Boolean => Ok(Self::Boolean(v == "1")),
+            DataType::Null | DataType::Nothing => Ok(Self::Null),
+            DataType::Boolean => Ok(Self::Boolean(v == "1" || v == "true")),
             DataType::String => Ok(Self::String(v.to_string())),
 
             DataType::Number(NumberDataType::Int8) => {
@@ -168,13 +245,20 @@ impl TryFrom<(&DataType, &str)> for Value {
                 }
             }
 
-            // TODO:(everpcpc) handle complex types
-            _ => Ok(Self::String(v.to_string())),
+            DataType::Array(_)
+            | DataType::EmptyArray
+            | DataType::Map(_)
+            | DataType::EmptyMap
+            | DataType::Tuple(_) => NestedParser::new(v, NumericMode::Lossless).parse_all(t),
+            DataType::Variant => Ok(Self::Variant(v.to_string())),
+            DataType::Bitmap => Ok(Self::Bitmap(v.to_string())),
+            DataType::Geometry => Ok(Self::Geometry(v.to_string())),
+            DataType::Binary => Ok(Self::String(v.to_string())),
         }
     }
 }
 
-#[cfg(feature = "flight-sql")]
+#[cfg(feature = "arrow-ipc")]
 impl TryFrom<(&ArrowField, &Arc<dyn ArrowArray>, usize)> for Value {
     type Error = Error;
     fn try_from(
@@ -183,6 +267,23 @@ impl TryFrom<(&ArrowField, &Arc<dyn ArrowArray>, usize)> for Value {
         if field.is_nullable() && array.is_null(seq) {
             return Ok(Value::Null);
         }
+        match field.metadata().get(EXTENSION_KEY).map(|s| s.as_str()) {
+            Some(ARROW_EXT_TYPE_EMPTY_ARRAY) => return Ok(Value::Array(vec![])),
+            Some(ARROW_EXT_TYPE_EMPTY_MAP) => return Ok(Value::Map(vec![])),
+            Some(ARROW_EXT_TYPE_VARIANT) => {
+                let data = binary_value(array, seq)?;
+                return Ok(Value::Variant(text_or_hex(data)));
+            }
+            Some(ARROW_EXT_TYPE_BITMAP) => {
+                let data = binary_value(array, seq)?;
+                return Ok(Value::Bitmap(text_or_hex(data)));
+            }
+            Some(ARROW_EXT_TYPE_GEOMETRY) => {
+                let data = binary_value(array, seq)?;
+                return Ok(Value::Geometry(hex(data)));
+            }
+            _ => {}
+        }
         match field.data_type() {
             ArrowDataType::Null => Ok(Value::Null),
             ArrowDataType::Boolean => match array.as_any().downcast_ref::<BooleanArray>() {
@@ -308,23 +409,153 @@ impl TryFrom<(&ArrowField, &Arc<dyn ArrowArray>, usize)> for Value {
             | ArrowDataType::Duration(_) => {
                 Err(ConvertError::new("unsupported data type", format!("{:?}", array)).into())
             }
-            // ArrowDataType::List(_) | ArrowDataType::LargeList(_) => {
-            //     let v = array.as_list_opt::<i64>().unwrap().value(seq);
-            //     Ok(Value::String(format!("{:?}", v)))
-            // }
-            // Struct(Vec<Field>),
-            // Map(Box<Field>, bool),
-            // RunEndEncoded(Box<Field>, Box<Field>),
+            ArrowDataType::List(f) => match array.as_any().downcast_ref::<ListArray>() {
+                Some(array) => {
+                    let values = array.value(seq);
+                    let values = (0..values.len())
+                        .map(|i| Value::try_from((f.as_ref(), &values, i)))
+                        .collect::<Result<Vec<_>>>()?;
+                    Ok(Value::Array(values))
+                }
+                None => Err(ConvertError::new("list", format!("{:?}", array)).into()),
+            },
+            ArrowDataType::LargeList(f) => match array.as_any().downcast_ref::<LargeListArray>() {
+                Some(array) => {
+                    let values = array.value(seq);
+                    let values = (0..values.len())
+                        .map(|i| Value::try_from((f.as_ref(), &values, i)))
+                        .collect::<Result<Vec<_>>>()?;
+                    Ok(Value::Array(values))
+                }
+                None => Err(ConvertError::new("large list", format!("{:?}", array)).into()),
+            },
+            ArrowDataType::Struct(fs) => match array.as_any().downcast_ref::<StructArray>() {
+                Some(array) => {
+                    let values = fs
+                        .iter()
+                        .zip(array.columns())
+                        .map(|(f, column)| Value::try_from((f.as_ref(), column, seq)))
+                        .collect::<Result<Vec<_>>>()?;
+                    Ok(Value::Tuple(values))
+                }
+                None => Err(ConvertError::new("struct", format!("{:?}", array)).into()),
+            },
+            ArrowDataType::Map(entries, _) => {
+                let fs = match entries.data_type() {
+                    ArrowDataType::Struct(fs) if fs.len() == 2 => fs,
+                    _ => return Err(ConvertError::new("map", format!("{:?}", array)).into()),
+                };
+                match array.as_any().downcast_ref::<MapArray>() {
+                    Some(array) => {
+                        let entries = array.value(seq);
+                        let (keys, values) = (entries.column(0), entries.column(1));
+                        let values = (0..entries.len())
+                            .map(|i| {
+                                Ok((
+                                    Value::try_from((fs[0].as_ref(), keys, i))?,
+                                    Value::try_from((fs[1].as_ref(), values, i))?,
+                                ))
+                            })
+                            .collect::<Result<Vec<_>>>()?;
+                        Ok(Value::Map(values))
+                    }
+                    None => Err(ConvertError::new("map", format!("{:?}", array)).into()),
+                }
+            }
             _ => Err(ConvertError::new("unsupported data type", format!("{:?}", array)).into()),
         }
     }
 }
 
+#[cfg(feature = "arrow-ipc")]
+fn binary_value(array: &Arc<dyn ArrowArray>, seq: usize) -> Result<&[u8]> {
+    if let Some(array) = array.as_any().downcast_ref::<LargeBinaryArray>() {
+        return Ok(array.value(seq));
+    }
+    match array.as_any().downcast_ref::<BinaryArray>() {
+        Some(array) => Ok(array.value(seq)),
+        None => Err(ConvertError::new("binary", format!("{:?}", array)).into()),
+    }
+}
+
+/// The text if the data is in UTF-8, or the raw bytes in hex otherwise,
+/// e.g. variants in JSONB and bitmaps in their binary form.
+#[cfg(feature = "arrow-ipc")]
+fn text_or_hex(data: &[u8]) -> String {
+    match std::str::from_utf8(data) {
+        Ok(s) => s.to_string(),
+        Err(_) => hex(data),
+    }
+}
+
+#[cfg(feature = "arrow-ipc")]
+fn hex(data: &[u8]) -> String {
+    let mut hex = String::with_capacity(data.len() * 2);
+    for b in data {
+        write!(hex, "{:02X}", b).unwrap();
+    }
+    hex
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Boolean(b)
+    }
+}
+
+// This macro implements From for NumberValue
+macro_rules! impl_from_number {
+    ($t:ty, $variant:ident) => {
+        impl From<$t> for Value {
+            fn from(n: $t) -> Self {
+                Value::Number(NumberValue::$variant(n))
+            }
+        }
+    };
+}
+
+impl_from_number!(i8, Int8);
+impl_from_number!(i16, Int16);
+impl_from_number!(i32, Int32);
+impl_from_number!(i64, Int64);
+impl_from_number!(u8, UInt8);
+impl_from_number!(u16, UInt16);
+impl_from_number!(u32, UInt32);
+impl_from_number!(u64, UInt64);
+impl_from_number!(f32, Float32);
+impl_from_number!(f64, Float64);
+
+impl From<serde_json::Value> for Value {
+    fn from(v: serde_json::Value) -> Self {
+        Value::Variant(v.to_string())
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(v: Vec<T>) -> Self {
+        Value::Array(v.into_iter().map(Into::into).collect())
+    }
+}
+
 impl TryFrom<Value> for String {
     type Error = Error;
     fn try_from(val: Value) -> Result<Self> {
         match val {
-            Value::String(s) => Ok(s),
+            Value::String(s) | Value::Variant(s) | Value::Bitmap(s) | Value::Geometry(s) => Ok(s),
+            // nested values in the same text as displayed
+            Value::Array(_) | Value::Map(_) | Value::Tuple(_) => Ok(val.to_string()),
             _ => Err(ConvertError::new("string", format!("{:?}", val)).into()),
         }
     }
@@ -415,6 +646,113 @@ impl TryFrom<Value> for NaiveDate {
     }
 }
 
+impl TryFrom<Value> for serde_json::Value {
+    type Error = Error;
+    fn try_from(val: Value) -> Result<Self> {
+        match val {
+            Value::Variant(s) | Value::String(s) => Ok(serde_json::from_str(&s)?),
+            _ => Err(ConvertError::new("variant", format!("{:?}", val)).into()),
+        }
+    }
+}
+
+fn convert_nested<T>(target: &'static str, val: Value) -> Result<T>
+where
+    T: TryFrom<Value>,
+    T::Error: std::fmt::Display,
+{
+    let data = format!("{:?}", val);
+    T::try_from(val).map_err(|e| {
+        ConvertError::new(target, data)
+            .with_message(e.to_string())
+            .into()
+    })
+}
+
+impl<T> TryFrom<Value> for Vec<T>
+where
+    T: TryFrom<Value>,
+    T::Error: std::fmt::Display,
+{
+    type Error = Error;
+    fn try_from(val: Value) -> Result<Self> {
+        match val {
+            Value::Array(vals) | Value::Tuple(vals) => vals
+                .into_iter()
+                .map(|v| convert_nested("array", v))
+                .collect(),
+            _ => Err(ConvertError::new("array", format!("{:?}", val)).into()),
+        }
+    }
+}
+
+impl<K, V> TryFrom<Value> for HashMap<K, V>
+where
+    K: TryFrom<Value> + Eq + Hash,
+    K::Error: std::fmt::Display,
+    V: TryFrom<Value>,
+    V::Error: std::fmt::Display,
+{
+    type Error = Error;
+    fn try_from(val: Value) -> Result<Self> {
+        match val {
+            Value::Map(entries) => entries
+                .into_iter()
+                .map(|(k, v)| Ok((convert_nested("map", k)?, convert_nested("map", v)?)))
+                .collect(),
+            _ => Err(ConvertError::new("map", format!("{:?}", val)).into()),
+        }
+    }
+}
+
+impl<K, V> TryFrom<Value> for BTreeMap<K, V>
+where
+    K: TryFrom<Value> + Ord,
+    K::Error: std::fmt::Display,
+    V: TryFrom<Value>,
+    V::Error: std::fmt::Display,
+{
+    type Error = Error;
+    fn try_from(val: Value) -> Result<Self> {
+        match val {
+            Value::Map(entries) => entries
+                .into_iter()
+                .map(|(k, v)| Ok((convert_nested("map", k)?, convert_nested("map", v)?)))
+                .collect(),
+            _ => Err(ConvertError::new("map", format!("{:?}", val)).into()),
+        }
+    }
+}
+
+// This macro implements TryFrom for tuple of types from Tuple value
+macro_rules! impl_try_from_tuple_value {
+    ( $len:literal, $($Ti:tt),+ ) => {
+        impl<$($Ti),+> TryFrom<Value> for ($($Ti,)+)
+        where
+            $($Ti: TryFrom<Value>, $Ti::Error: std::fmt::Display),+
+        {
+            type Error = Error;
+            fn try_from(val: Value) -> Result<Self> {
+                match val {
+                    Value::Tuple(vals) if vals.len() == $len => {
+                        let mut vals = vals.into_iter();
+                        // the length is checked above, so it is safe to unwrap
+                        Ok(($(convert_nested::<$Ti>("tuple", vals.next().unwrap())?,)+))
+                    }
+                    _ => Err(ConvertError::new("tuple", format!("{:?}", val)).into()),
+                }
+            }
+        }
+    };
+}
+
+impl_try_from_tuple_value!(1, T1);
+impl_try_from_tuple_value!(2, T1, T2);
+impl_try_from_tuple_value!(3, T1, T2, T3);
+impl_try_from_tuple_value!(4, T1, T2, T3, T4);
+impl_try_from_tuple_value!(5, T1, T2, T3, T4, T5);
+impl_try_from_tuple_value!(6, T1, T2, T3, T4, T5, T6);
+
 // This macro implements TryFrom to Option for Nullable column
 macro_rules! impl_try_from_to_option {
     ($($t:ty),*) => {
@@ -450,6 +788,53 @@ impl_try_from_to_option!(f32);
 impl_try_from_to_option!(f64);
 impl_try_from_to_option!(NaiveDateTime);
 impl_try_from_to_option!(NaiveDate);
+impl_try_from_to_option!(serde_json::Value);
+
+impl<T> TryFrom<Value> for Option<Vec<T>>
+where
+    T: TryFrom<Value>,
+    T::Error: std::fmt::Display,
+{
+    type Error = Error;
+    fn try_from(val: Value) -> Result<Self> {
+        match val {
+            Value::Null => Ok(None),
+            _ => Ok(Some(val.try_into()?)),
+        }
+    }
+}
+
+impl<K, V> TryFrom<Value> for Option<HashMap<K, V>>
+where
+    K: TryFrom<Value> + Eq + Hash,
+    K::Error: std::fmt::Display,
+    V: TryFrom<Value>,
+    V::Error: std::fmt::Display,
+{
+    type Error = Error;
+    fn try_from(val: Value) -> Result<Self> {
+        match val {
+            Value::Null => Ok(None),
+            _ => Ok(Some(val.try_into()?)),
+        }
+    }
+}
+
+impl<K, V> TryFrom<Value> for Option<BTreeMap<K, V>>
+where
+    K: TryFrom<Value> + Ord,
+    K::Error: std::fmt::Display,
+    V: TryFrom<Value>,
+    V::Error: std::fmt::Display,
+{
+    type Error = Error;
+    fn try_from(val: Value) -> Result<Self> {
+        match val {
+            Value::Null => Ok(None),
+            _ => Ok(Some(val.try_into()?)),
+        }
+    }
+}
 
 impl std::fmt::Display for NumberValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -488,10 +873,236 @@ impl std::fmt::Display for Value {
                 let d = NaiveDate::from_num_days_from_ce_opt(days).unwrap_or_default();
                 write!(f, "{}", d)
             }
+            Value::Array(vals) => {
+                write!(f, "[")?;
+                for (i, v) in vals.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    fmt_nested(v, f)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    fmt_nested(k, f)?;
+                    write!(f, ":")?;
+                    fmt_nested(v, f)?;
+                }
+                write!(f, "}}")
+            }
+            Value::Tuple(vals) => {
+                write!(f, "(")?;
+                for (i, v) in vals.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    fmt_nested(v, f)?;
+                }
+                write!(f, ")")
+            }
+            Value::Variant(s) | Value::Bitmap(s) | Value::Geometry(s) => write!(f, "{}", s),
         }
     }
 }
 
+/// Values inside nested values are written as the server does,
+/// with strings and temporal values quoted.
+fn fmt_nested(v: &Value, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match v {
+        Value::Null
+        | Value::Boolean(_)
+        | Value::Number(_)
+        | Value::Array(_)
+        | Value::Map(_)
+        | Value::Tuple(_) => write!(f, "{}", v),
+        _ => write!(f, "{}", escape_literal(&v.to_string())),
+    }
+}
+
+/// Parser of nested values in text, as returned by the REST API,
+/// e.g. `[1,2]`, `{'k':[NULL]}` and `(1,'a')`.
+struct NestedParser<'a> {
+    text: &'a str,
+    pos: usize,
+    mode: NumericMode,
+}
+
+impl<'a> NestedParser<'a> {
+    fn new(text: &'a str, mode: NumericMode) -> Self {
+        Self { text, pos: 0, mode }
+    }
+
+    fn parse_all(mut self, t: &DataType) -> Result<Value> {
+        let v = self.parse(t)?;
+        self.skip_whitespace();
+        if self.pos != self.text.len() {
+            return Err(self.error("unexpected trailing characters"));
+        }
+        Ok(v)
+    }
+
+    fn parse(&mut self, t: &DataType) -> Result<Value> {
+        self.skip_whitespace();
+        match t {
+            DataType::Nullable(inner) => {
+                if self.eat_null() {
+                    Ok(Value::Null)
+                } else {
+                    self.parse(inner)
+                }
+            }
+            DataType::Null => {
+                if self.eat_null() {
+                    Ok(Value::Null)
+                } else {
+                    Err(self.error("expected NULL"))
+                }
+            }
+            DataType::EmptyArray => {
+                self.expect('[')?;
+                self.expect(']')?;
+                Ok(Value::Array(vec![]))
+            }
+            DataType::EmptyMap => {
+                self.expect('{')?;
+                self.expect('}')?;
+                Ok(Value::Map(vec![]))
+            }
+            DataType::Array(inner) => {
+                let vals = self.parse_list('[', ']', |p, _| p.parse(inner))?;
+                Ok(Value::Array(vals))
+            }
+            DataType::Tuple(inner) => {
+                let vals = self.parse_list('(', ')', |p, i| match inner.get(i) {
+                    Some(t) => p.parse(t),
+                    None => Err(p.error("too many tuple fields")),
+                })?;
+                if vals.len() != inner.len() {
+                    return Err(self.error("too few tuple fields"));
+                }
+                Ok(Value::Tuple(vals))
+            }
+            DataType::Map(inner) => {
+                let (kt, vt) = match inner.as_ref() {
+                    DataType::Tuple(kv) if kv.len() == 2 => (&kv[0], &kv[1]),
+                    _ => return Err(self.error("invalid map type")),
+                };
+                let entries = self.parse_list('{', '}', |p, _| {
+                    let k = p.parse(kt)?;
+                    p.expect(':')?;
+                    let v = p.parse(vt)?;
+                    Ok((k, v))
+                })?;
+                Ok(Value::Map(entries))
+            }
+            _ => {
+                let token = self.token()?;
+                match t {
+                    DataType::Boolean => match token.as_str() {
+                        "1" | "true" => Ok(Value::Boolean(true)),
+                        "0" | "false" => Ok(Value::Boolean(false)),
+                        _ => Err(self.error("invalid boolean")),
+                    },
+                    _ => Value::try_from((t, token.as_str(), self.mode)),
+                }
+            }
+        }
+    }
+
+    fn parse_list<T, F>(&mut self, open: char, close: char, mut f: F) -> Result<Vec<T>>
+    where
+        F: FnMut(&mut Self, usize) -> Result<T>,
+    {
+        self.expect(open)?;
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.eat(close) {
+            return Ok(items);
+        }
+        loop {
+            items.push(f(self, items.len())?);
+            self.skip_whitespace();
+            if self.eat(close) {
+                return Ok(items);
+            }
+            self.expect(',')?;
+        }
+    }
+
+    /// A quoted string with escapes, or the text until the next delimiter.
+    fn token(&mut self) -> Result<String> {
+        if !self.eat('\'') {
+            let rest = &self.text[self.pos..];
+            let end = rest.find([',', ']', '}', ')', ':']).unwrap_or(rest.len());
+            self.pos += end;
+            return Ok(rest[..end].trim().to_string());
+        }
+        let mut s = String::new();
+        let mut chars = self.text[self.pos..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\'' => {
+                    self.pos += i + 1;
+                    return Ok(s);
+                }
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => s.push('\n'),
+                    Some((_, 't')) => s.push('\t'),
+                    Some((_, 'r')) => s.push('\r'),
+                    Some((_, '0')) => s.push('\0'),
+                    Some((_, c)) => s.push(c),
+                    None => break,
+                },
+                c => s.push(c),
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.text[self.pos..].starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_null(&mut self) -> bool {
+        if self.text[self.pos..].starts_with(NULL_VALUE) {
+            self.pos += NULL_VALUE.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        self.skip_whitespace();
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c)))
+        }
+    }
+
+    fn error(&self, message: &str) -> Error {
+        ConvertError::new("nested value", self.text.to_string())
+            .with_message(format!("{} at position {}", message, self.pos))
+            .into()
+    }
+}
+
 pub fn display_decimal_128(num: i128, scale: u8) -> String {
     let mut buf = String::new();
     if scale == 0 {
@@ -526,7 +1137,7 @@ pub fn display_decimal_256(num: i256, scale: u8) -> String {
     if scale == 0 {
         write!(buf, "{}", num).unwrap();
     } else {
-        let pow_scale = i256::from_i128(10i128).pow_wrapping(scale as u32);
+        let pow_scale = i256::from_i128(10i128).wrapping_pow(scale as u32);
         // -1/10 = 0
         if num >= i256::ZERO {
             write!(
@@ -561,7 +1172,7 @@ pub fn parse_decimal(text: &str, size: DecimalSize) -> Result<NumberValue> {
     }
     let text = &text[start..];
     let point_pos = text.find('.');
-    let e_pos = text.find(|c| c == 'e' || c == 'E');
+    let e_pos = text.find(['e', 'E']);
     let (i_part, f_part, e_part) = match (point_pos, e_pos) {
         (Some(p1), Some(p2)) => (&text[..p1], &text[(p1 + 1)..p2], Some(&text[(p2 + 1)..])),
         (Some(p), None) => (&text[..p], &text[(p + 1)..], None),
@@ -581,10 +1192,17 @@ pub fn parse_decimal(text: &str, size: DecimalSize) -> Result<NumberValue> {
         if digits.is_empty() {
             digits.push(b'0')
         }
+        // align to the scale of the type, e.g. `3` as `3.00` for scale 2, `123.1e3` for scale 0
         let scale = f_part.len() as i32 - exp;
-        if scale < 0 {
-            // e.g 123.1e3
-            for _ in 0..(-scale) {
+        let target = size.scale as i32;
+        if scale < target {
+            for _ in 0..(target - scale) {
+                digits.push(b'0')
+            }
+        } else if scale > target {
+            let len = digits.len().saturating_sub((scale - target) as usize);
+            digits.truncate(len);
+            if digits.is_empty() || digits == b"-" {
                 digits.push(b'0')
             }
         };
@@ -594,7 +1212,8 @@ pub fn parse_decimal(text: &str, size: DecimalSize) -> Result<NumberValue> {
 
         if size.precision > 38 {
             Ok(NumberValue::Decimal256(
-                i256::from_string(digits).unwrap(),
+                i256::from_string(digits)
+                    .ok_or_else(|| ConvertError::new("decimal", text.to_string()))?,
                 size,
             ))
         } else {
@@ -602,3 +1221,183 @@ pub fn parse_decimal(text: &str, size: DecimalSize) -> Result<NumberValue> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::Field;
+    use databend_client::response::SchemaField as APISchemaField;
+
+    fn parse(desc: &str, text: &str) -> Result<Value> {
+        let field = Field::try_from(APISchemaField {
+            name: "v".to_string(),
+            data_type: desc.to_string(),
+        })?;
+        Value::try_from((&field.data_type, text))
+    }
+
+    #[test]
+    fn parse_nested() -> Result<()> {
+        assert_eq!(
+            parse("Array(Nullable(Int32))", "[1,NULL, 3]")?,
+            Value::Array(vec![Value::from(1i32), Value::Null, Value::from(3i32)])
+        );
+        assert_eq!(parse("Array(Int32)", "[]")?, Value::Array(vec![]));
+        assert_eq!(
+            parse("Map(String, Array(String))", r"{'a':['x','y\'z'],'b':[]}")?,
+            Value::Map(vec![
+                (
+                    Value::from("a"),
+                    Value::Array(vec![Value::from("x"), Value::from("y'z")])
+                ),
+                (Value::from("b"), Value::Array(vec![])),
+            ])
+        );
+        assert_eq!(
+            parse(
+                "Tuple(Boolean, Date, Decimal(4, 2))",
+                "(true,'1970-01-02',1.5)"
+            )?,
+            Value::Tuple(vec![
+                Value::Boolean(true),
+                Value::Date(1),
+                Value::Number(NumberValue::Decimal128(
+                    150,
+                    DecimalSize {
+                        precision: 4,
+                        scale: 2
+                    }
+                )),
+            ])
+        );
+        // the text of variants is kept as is, not reordered or rounded
+        let text = r#"{"b":[1,null],"a":12345678901234567890.123}"#;
+        assert_eq!(parse("Variant", text)?, Value::Variant(text.to_string()));
+        assert_eq!(
+            parse("Geometry", "POINT(1 2)")?,
+            Value::Geometry("POINT(1 2)".to_string())
+        );
+        assert!(parse("Array(Int32)", "[1,2").is_err());
+        assert!(parse("Tuple(Int32, Int32)", "(1)").is_err());
+        assert!(parse("Array(Int32)", "[1] 2").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn display_nested() -> Result<()> {
+        for (desc, text) in [
+            ("Array(Nullable(String))", r"['a\'b',NULL]"),
+            ("Map(String, Int64)", "{'a':1,'b':2}"),
+            ("Tuple(Int64, Timestamp)", "(1,'2023-01-02 03:04:05')"),
+            ("Array(Array(Int8))", "[[1],[]]"),
+        ] {
+            assert_eq!(parse(desc, text)?.to_string(), text);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn convert_nested() -> Result<()> {
+        let v = parse("Array(Nullable(Int64))", "[1,NULL]")?;
+        assert_eq!(Vec::<Option<i64>>::try_from(v)?, vec![Some(1), None]);
+
+        let v = parse("Map(String, Int64)", "{'a':1,'b':2}")?;
+        let m = HashMap::<String, i64>::try_from(v.clone())?;
+        assert_eq!(m["b"], 2);
+        let m = BTreeMap::<String, i64>::try_from(v)?;
+        assert_eq!(
+            m.into_iter().collect::<Vec<_>>(),
+            [("a".into(), 1), ("b".into(), 2)]
+        );
+
+        let v = parse("Tuple(String, Boolean)", "('x',0)")?;
+        assert_eq!(<(String, bool)>::try_from(v)?, ("x".to_string(), false));
+
+        let v = parse("Variant", "[1]")?;
+        assert_eq!(
+            serde_json::Value::try_from(v.clone())?,
+            serde_json::json!([1])
+        );
+        assert_eq!(String::try_from(v)?, "[1]");
+        let v = parse("Map(String, Int64)", "{'a':1}")?;
+        assert_eq!(String::try_from(v)?, "{'a':1}");
+
+        assert!(Vec::<i64>::try_from(Value::from("x")).is_err());
+        assert!(<(String, bool)>::try_from(Value::Tuple(vec![Value::from("x")])).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "arrow-ipc")]
+    #[test]
+    fn arrow_nested() -> Result<()> {
+        use arrow_array::{types::Int32Type, Int32Array};
+
+        let list: Arc<dyn ArrowArray> =
+            Arc::new(ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+                Some(vec![Some(1), None]),
+                Some(vec![]),
+            ]));
+        let field = ArrowField::new(
+            "a",
+            ArrowDataType::List(Arc::new(ArrowField::new(
+                "item",
+                ArrowDataType::Int32,
+                true,
+            ))),
+            false,
+        );
+        assert_eq!(
+            Value::try_from((&field, &list, 0))?,
+            Value::Array(vec![Value::from(1i32), Value::Null])
+        );
+        assert_eq!(Value::try_from((&field, &list, 1))?, Value::Array(vec![]));
+
+        let ints: Arc<dyn ArrowArray> = Arc::new(Int32Array::from(vec![7]));
+        let strs: Arc<dyn ArrowArray> = Arc::new(StringArray::from(vec!["x"]));
+        let fields = vec![
+            Arc::new(ArrowField::new("0", ArrowDataType::Int32, false)),
+            Arc::new(ArrowField::new("1", ArrowDataType::Utf8, false)),
+        ];
+        let tuple: Arc<dyn ArrowArray> = Arc::new(StructArray::from(vec![
+            (fields[0].clone(), ints),
+            (fields[1].clone(), strs),
+        ]));
+        let field = ArrowField::new("t", ArrowDataType::Struct(fields.into()), false);
+        assert_eq!(
+            Value::try_from((&field, &tuple, 0))?,
+            Value::Tuple(vec![Value::from(7i32), Value::from("x")])
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "arrow-ipc")]
+    #[test]
+    fn arrow_extension() -> Result<()> {
+        let field = |ext: &str| {
+            ArrowField::new("v", ArrowDataType::LargeBinary, false).with_metadata(
+                [(EXTENSION_KEY.to_string(), ext.to_string())]
+                    .into_iter()
+                    .collect(),
+            )
+        };
+        let data: Arc<dyn ArrowArray> = Arc::new(LargeBinaryArray::from(vec![
+            br#"{"b":1,"a":2}"#.as_ref(),
+            &[0x80, 0x00, 0x00, 0x01][..],
+        ]));
+        let variant = field(ARROW_EXT_TYPE_VARIANT);
+        assert_eq!(
+            Value::try_from((&variant, &data, 0))?,
+            Value::Variant(r#"{"b":1,"a":2}"#.to_string())
+        );
+        // JSONB and bitmaps in binary degrade to hex instead of failing
+        assert_eq!(
+            Value::try_from((&variant, &data, 1))?,
+            Value::Variant("80000001".to_string())
+        );
+        assert_eq!(
+            Value::try_from((&field(ARROW_EXT_TYPE_BITMAP), &data, 1))?,
+            Value::Bitmap("80000001".to_string())
+        );
+        Ok(())
+    }
+}